@@ -48,7 +48,7 @@ fn insert_many_hash_map(map: &mut HashMap<BenchKey, usize>, n: usize) {
 
 fn read_many_one_way(
     slot_map: &OneWay<BenchKey, (), usize>,
-    keys: &Vec<BenchKey>,
+    keys: &[BenchKey],
     k: usize,
 ) {
     for _ in 0..k {
@@ -60,7 +60,7 @@ fn read_many_one_way(
 
 fn delete_many_one_way(
     slot_map: &mut OneWay<BenchKey, (), usize>,
-    keys: &Vec<BenchKey>,
+    keys: &[BenchKey],
 ) {
     for key in keys.iter() {
         let _ = slot_map.remove(key).unwrap();
@@ -69,7 +69,7 @@ fn delete_many_one_way(
 
 fn delete_many_slotmap(
     slot_map: &mut SlotMap<DefaultKey, usize>,
-    keys: &Vec<DefaultKey>,
+    keys: &[DefaultKey],
 ) {
     for key in keys.iter() {
         let _ = slot_map.remove(*key).unwrap();
@@ -78,7 +78,7 @@ fn delete_many_slotmap(
 
 fn read_many_slotmap(
     slot_map: &SlotMap<DefaultKey, usize>,
-    keys: &Vec<DefaultKey>,
+    keys: &[DefaultKey],
     k: usize,
 ) {
     for _ in 0..k {
@@ -91,7 +91,7 @@ fn read_many_slotmap(
 #[allow(dead_code)]
 fn read_many_hash_map(
     map: &HashMap<BenchKey, usize>,
-    keys: &Vec<BenchKey>,
+    keys: &[BenchKey],
     k: usize,
 ) {
     for _ in 0..k {
@@ -166,6 +166,38 @@ fn read_benchmark(c: &mut Criterion) {
     // });
 }
 
+/// Read from maps too large for the cache, where every lookup misses and the
+/// fetch of the chunk holding the item comes before the fetch of the item
+fn large_map_read_benchmark(c: &mut Criterion) {
+    let mut one_way: OneWay<BenchKey, (), usize> = OneWay::new();
+    let mut slot_map: SlotMap<DefaultKey, usize> = SlotMap::new();
+
+    let mut one_way_keys = (0..4_000_000)
+        .map(|i| one_way.insert((), i))
+        .collect::<Vec<_>>();
+    let mut slotmap_keys = (0..4_000_000)
+        .map(|i| slot_map.insert(i))
+        .collect::<Vec<_>>();
+
+    let mut rng = thread_rng();
+
+    one_way_keys.shuffle(&mut rng);
+    slotmap_keys.shuffle(&mut rng);
+    one_way_keys.truncate(1_000_000);
+    slotmap_keys.truncate(1_000_000);
+
+    c.bench_function("one-way reading 1m from 4m", |b| {
+        b.iter(|| {
+            read_many_one_way(&one_way, &one_way_keys, 1);
+        })
+    });
+    c.bench_function("slotmap reading 1m from 4m", |b| {
+        b.iter(|| {
+            read_many_slotmap(&slot_map, &slotmap_keys, 1);
+        })
+    });
+}
+
 fn deletion_benchmark(c: &mut Criterion) {
     c.bench_function("one-way deleting 1M", |b| {
         b.iter_custom(|_| {
@@ -225,10 +257,135 @@ fn deletion_benchmark(c: &mut Criterion) {
     // });
 }
 
+/// Values large enough that scanning them along with the key data would
+/// touch far more memory than the key data alone
+type LargeValue = [u64; 32];
+
+fn large_value_benchmark(c: &mut Criterion) {
+    let mut one_way_keys: Vec<BenchKey> = Vec::new();
+    let mut slotmap_keys: Vec<DefaultKey> = Vec::new();
+
+    let mut one_way: OneWay<BenchKey, (), LargeValue> = OneWay::new();
+    let mut slot_map: SlotMap<DefaultKey, LargeValue> = SlotMap::new();
+
+    for i in 0..10_000u64 {
+        one_way_keys.push(one_way.insert((), [i; 32]));
+        slotmap_keys.push(slot_map.insert([i; 32]));
+    }
+
+    // Remove every other item, so half the lookups miss and iteration has
+    // to skip vacant slots
+    for (one_way_key, slotmap_key) in
+        one_way_keys.iter().zip(&slotmap_keys).step_by(2)
+    {
+        let _ = one_way.remove(one_way_key);
+        let _ = slot_map.remove(*slotmap_key);
+    }
+
+    let mut rng = thread_rng();
+
+    one_way_keys.shuffle(&mut rng);
+    slotmap_keys.shuffle(&mut rng);
+
+    c.bench_function("one-way contains_key 1m large values", |b| {
+        b.iter(|| {
+            for _ in 0..100 {
+                for key in &one_way_keys {
+                    black_box(one_way.contains_key(key));
+                }
+            }
+        })
+    });
+    c.bench_function("slotmap contains_key 1m large values", |b| {
+        b.iter(|| {
+            for _ in 0..100 {
+                for key in &slotmap_keys {
+                    black_box(slot_map.contains_key(*key));
+                }
+            }
+        })
+    });
+
+    c.bench_function("one-way iterating 10k large values", |b| {
+        b.iter(|| black_box(one_way.values().map(|v| v[0]).sum::<u64>()))
+    });
+    c.bench_function("slotmap iterating 10k large values", |b| {
+        b.iter(|| black_box(slot_map.values().map(|v| v[0]).sum::<u64>()))
+    });
+}
+
+/// Iterate over a map where most slots have been vacated at random, as in a
+/// map that has seen a lot of churn
+fn sparse_iteration_benchmark(c: &mut Criterion) {
+    let mut one_way: OneWay<BenchKey, (), usize> = OneWay::new();
+    let mut slot_map: SlotMap<DefaultKey, usize> = SlotMap::new();
+
+    let keys = (0..100_000)
+        .map(|i| (one_way.insert((), i), slot_map.insert(i)))
+        .collect::<Vec<_>>();
+
+    let mut rng = thread_rng();
+
+    for (one_way_key, slotmap_key) in keys {
+        if rng.gen_ratio(15, 16) {
+            let _ = one_way.remove(&one_way_key);
+            let _ = slot_map.remove(slotmap_key);
+        }
+    }
+
+    c.bench_function("one-way iterating sparse 100k", |b| {
+        b.iter(|| black_box(one_way.values().sum::<usize>()))
+    });
+    c.bench_function("slotmap iterating sparse 100k", |b| {
+        b.iter(|| black_box(slot_map.values().sum::<usize>()))
+    });
+}
+
+/// Remove and reinsert items in random order, so every insertion reuses a
+/// slot from the free list
+fn churn_benchmark(c: &mut Criterion) {
+    let mut one_way_keys: Vec<BenchKey> = Vec::new();
+    let mut slotmap_keys: Vec<DefaultKey> = Vec::new();
+
+    let mut one_way: OneWay<BenchKey, (), usize> = OneWay::new();
+    let mut slot_map: SlotMap<DefaultKey, usize> = SlotMap::new();
+
+    for i in 0..10_000 {
+        one_way_keys.push(one_way.insert((), i));
+        slotmap_keys.push(slot_map.insert(i));
+    }
+
+    let mut rng = thread_rng();
+
+    one_way_keys.shuffle(&mut rng);
+    slotmap_keys.shuffle(&mut rng);
+
+    c.bench_function("one-way churning 10k", |b| {
+        b.iter(|| {
+            for key in one_way_keys.iter_mut() {
+                let value = *one_way.remove(key).unwrap();
+                *key = one_way.insert((), black_box(value));
+            }
+        })
+    });
+    c.bench_function("slotmap churning 10k", |b| {
+        b.iter(|| {
+            for key in slotmap_keys.iter_mut() {
+                let value = slot_map.remove(*key).unwrap();
+                *key = slot_map.insert(black_box(value));
+            }
+        })
+    });
+}
+
 criterion_group!(
     benches,
+    churn_benchmark,
     deletion_benchmark,
     insertion_benchmark,
     read_benchmark,
+    large_map_read_benchmark,
+    large_value_benchmark,
+    sparse_iteration_benchmark,
 );
 criterion_main!(benches);
@@ -0,0 +1,4 @@
+//! Operations for driving a [`SlotMap`](crate::SlotMap) through arbitrary
+//! sequences from fuzz targets and property tests
+
+pub use super::slot_map_fuzz::{apply_ops, Op};
@@ -47,6 +47,10 @@
 #[cfg(test)]
 extern crate static_assertions;
 
+// Allows the exported macros to be used in this crate's own tests
+#[cfg(test)]
+extern crate self as one_way_slot_map;
+
 /// Macro for creating a simple Key type for one-way slot maps. Key types can be
 /// created from scratch, but for most cases, this will produce what you want
 #[macro_export]
@@ -81,11 +85,206 @@ macro_rules! define_key_type (
 pub const SLOT_MAP_CHUNK_SIZE: usize = 256;
 
 pub use slot_map::SlotMap;
+pub use slot_map_any::{AnySlotMap, TypedKey};
+#[cfg(feature = "concurrent")]
+pub use slot_map_append_only::AppendOnlySlotMap;
+#[cfg(feature = "concurrent")]
+pub use slot_map_async::{AsyncRwLock, AsyncSlotMap};
+#[cfg(feature = "base62")]
+pub use slot_map_base62::Base62Error;
+pub use slot_map_bi::{BiSlotMap, BiValueMut};
+pub use slot_map_component::ComponentSlotMap;
+#[cfg(feature = "concurrent")]
+pub use slot_map_concurrent::ConcurrentSlotMap;
+#[cfg(feature = "concurrent")]
+pub use slot_map_concurrent_stats::{ConcurrentSlotMapStats, ShardStats};
+pub use slot_map_cow::CowSlotMap;
+pub use slot_map_cow_snapshot::SlotMapSnapshot;
+#[cfg(feature = "debug-keys")]
+pub use slot_map_debug_keys::StaleKeyPolicy;
+pub use slot_map_delta::SlotMapDelta;
+pub use slot_map_dense::DenseOneWaySlotMap;
+#[cfg(feature = "drop-report")]
+pub use slot_map_drop_report::{DropReport, LeakedItems};
+#[cfg(feature = "concurrent")]
+pub use slot_map_epoch::{EpochGuard, EpochSlotMap};
+pub use slot_map_exhaustion::GenerationExhaustion;
+pub use slot_map_export::SlotMapExport;
+#[cfg(feature = "ffi")]
+pub use slot_map_ffi::{
+    owsm_map_clear, owsm_map_for_each, owsm_map_free, owsm_map_get,
+    owsm_map_insert, owsm_map_len, owsm_map_new, owsm_map_remove, OwsmMap,
+    OwsmVisitor,
+};
+pub use slot_map_fixed::FixedSlotMap;
+pub use slot_map_frozen::FrozenSlotMap;
+pub use slot_map_graph::GraphSlotMap;
+#[cfg(feature = "heap-size-trait")]
+pub use slot_map_heap_size::HeapSize;
+#[cfg(feature = "profiling")]
+pub use slot_map_heatmap::SlotAccess;
+pub use slot_map_hop::HopOneWaySlotMap;
+#[cfg(feature = "serde")]
+pub use slot_map_human_readable::HumanReadableSlotMap;
+pub use slot_map_indexed::{IndexId, IndexedSlotMap};
+pub use slot_map_insert_error::InsertError;
+pub use slot_map_interning::InterningSlotMap;
 pub use slot_map_key::SlotMapKey;
 pub use slot_map_key_data::SlotMapKeyData;
+#[cfg(feature = "concurrent")]
+pub use slot_map_left_right::{left_right, ReadGuard, ReadHandle, WriteHandle};
+pub use slot_map_linked::LinkedSlotMap;
+#[cfg(feature = "concurrent")]
+pub use slot_map_locked::{LockedSlotMap, SlotReadGuard, SlotWriteGuard};
+pub use slot_map_lru::LruSlotMap;
+pub use slot_map_metadata::MetadataSlotMap;
+#[cfg(feature = "mmap")]
+pub use slot_map_mmap::MmapSlotMap;
+pub use slot_map_observed::{ObservedSlotMap, SlotMapEvent, SubscriptionId};
+pub use slot_map_op_log::{LoggedSlotMap, OpLogValue};
+pub use slot_map_packed_key::PackedKeyData;
+pub use slot_map_pairs::FromPairsError;
+pub use slot_map_partition::SlotMapPartition;
+pub use slot_map_persistent::PersistentSlotMap;
+pub use slot_map_pinnable::{PinGuard, PinnableSlotMap, PinnedError};
+pub use slot_map_pointer_indexed::PointerIndexedSlotMap;
+pub use slot_map_priority::PrioritySlotMap;
+#[cfg(feature = "proptest")]
+pub use slot_map_proptest::{churned_slot_map, ChurnedSlotMap};
+pub use slot_map_raw_parts::SlotMapRawParts;
+pub use slot_map_recording::{OpRecording, RecordedOp, RecordingSlotMap};
+#[cfg(feature = "serde")]
+pub use slot_map_serde::{SlotMapMigration, VersionedSlotMap};
+pub use slot_map_set::SlotSet;
+pub use slot_map_slab::SlabSlotMap;
+#[cfg(feature = "serde")]
+pub use slot_map_slotmap_compat::{SlotmapKeyData, SlotmapKeyTranslation};
+pub use slot_map_snapshot::{
+    SnapshotBuilder, SnapshotChunk, SnapshotHeader, SnapshotWriter,
+};
+pub use slot_map_snapshot_error::SnapshotError;
+pub use slot_map_sparse_secondary::SparseSecondaryMap;
+#[cfg(feature = "concurrent")]
+pub use slot_map_staging::{InsertBuffer, InsertStaging};
+pub use slot_map_stats::SlotMapStats;
+pub use slot_map_subset::SubsetSnapshot;
+pub use slot_map_ticked::TickedSlotMap;
+#[cfg(feature = "concurrent")]
+pub use slot_map_tracked::{LivenessView, TrackedSlotMap};
+pub use slot_map_tree::TreeSlotMap;
+pub use slot_map_ttl::TtlSlotMap;
+pub use slot_map_two_way::TwoWaySlotMap;
+#[cfg(feature = "wasm")]
+pub use slot_map_wasm::JsKey;
 // pub use slot_map_value_iterator::SlotMapValueIterator;
 
+#[cfg(feature = "testing")]
+pub mod fuzz;
+#[cfg(feature = "serde")]
+pub mod packed_key_map;
 mod slot_map;
+mod slot_map_any;
+#[cfg(feature = "concurrent")]
+mod slot_map_append_only;
+#[cfg(feature = "concurrent")]
+mod slot_map_async;
+#[cfg(feature = "base62")]
+mod slot_map_base62;
+mod slot_map_bi;
+mod slot_map_component;
+#[cfg(feature = "concurrent")]
+mod slot_map_concurrent;
+#[cfg(feature = "concurrent")]
+mod slot_map_concurrent_stats;
+#[cfg(feature = "testing")]
+mod slot_map_consistency;
+mod slot_map_cow;
+mod slot_map_cow_snapshot;
+#[cfg(feature = "debug-keys")]
+mod slot_map_debug_keys;
+mod slot_map_delta;
+mod slot_map_dense;
+mod slot_map_digest;
+mod slot_map_dot;
+#[cfg(feature = "drop-report")]
+mod slot_map_drop_report;
+mod slot_map_dump;
+#[cfg(feature = "concurrent")]
+mod slot_map_epoch;
+mod slot_map_exhaustion;
+mod slot_map_export;
+#[cfg(feature = "ffi")]
+mod slot_map_ffi;
+mod slot_map_fixed;
+mod slot_map_frozen;
+#[cfg(feature = "testing")]
+mod slot_map_fuzz;
+mod slot_map_graph;
+mod slot_map_heap_size;
+#[cfg(feature = "profiling")]
+mod slot_map_heatmap;
+mod slot_map_hop;
+#[cfg(feature = "serde")]
+mod slot_map_human_readable;
+mod slot_map_indexed;
+mod slot_map_insert_error;
+mod slot_map_interning;
 mod slot_map_key;
 mod slot_map_key_data;
+#[cfg(feature = "concurrent")]
+mod slot_map_left_right;
+mod slot_map_linked;
+#[cfg(feature = "concurrent")]
+mod slot_map_locked;
+mod slot_map_lru;
+mod slot_map_metadata;
+#[cfg(feature = "mmap")]
+mod slot_map_mmap;
+mod slot_map_observed;
+mod slot_map_op_log;
+#[cfg(feature = "testing")]
+mod slot_map_oracle;
+mod slot_map_packed_key;
+#[cfg(feature = "serde")]
+mod slot_map_packed_key_map;
+mod slot_map_pairs;
+mod slot_map_partition;
+mod slot_map_persistent;
+mod slot_map_pinnable;
+mod slot_map_pointer_indexed;
+mod slot_map_priority;
+#[cfg(feature = "proptest")]
+mod slot_map_proptest;
+mod slot_map_raw_parts;
+#[cfg(feature = "rayon")]
+mod slot_map_rayon;
+mod slot_map_recording;
+#[cfg(feature = "serde")]
+mod slot_map_serde;
+mod slot_map_set;
+mod slot_map_shared_chunk;
+mod slot_map_slab;
+#[cfg(feature = "serde")]
+mod slot_map_slotmap_compat;
+#[cfg(feature = "slotmap")]
+mod slot_map_slotmap_interop;
+mod slot_map_snapshot;
+mod slot_map_snapshot_error;
+mod slot_map_sparse_secondary;
+#[cfg(feature = "concurrent")]
+mod slot_map_staging;
+mod slot_map_stats;
+mod slot_map_subset;
+mod slot_map_ticked;
+#[cfg(feature = "concurrent")]
+mod slot_map_tracked;
+mod slot_map_tree;
+mod slot_map_ttl;
+mod slot_map_two_way;
+#[cfg(feature = "wasm")]
+mod slot_map_wasm;
+#[cfg(test)]
+mod test_support;
+#[cfg(feature = "testing")]
+pub mod testing;
 // mod slot_map_value_iterator;
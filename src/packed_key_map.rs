@@ -0,0 +1,25 @@
+//! Serialization of a [`SlotMap`](crate::SlotMap) as an ordinary map from
+//! packed `u64` keys to items, for use with `#[serde(with = ...)]` where a
+//! map has to fit a schema that expects plain maps
+//!
+//! ```
+//! # use one_way_slot_map::*;
+//! # use serde::{Deserialize, Serialize};
+//! # define_key_type!(TestKey<()>);
+//! #[derive(Serialize, Deserialize)]
+//! struct Scene {
+//!     #[serde(with = "one_way_slot_map::packed_key_map")]
+//!     names: SlotMap<TestKey, (), String>,
+//! }
+//!
+//! let mut scene = Scene { names: SlotMap::new() };
+//! let key = scene.names.insert((), "camera".to_owned());
+//!
+//! let json = serde_json::to_string(&scene).unwrap();
+//! assert_eq!(r#"{"names":{"0":"camera"}}"#, json);
+//!
+//! let loaded: Scene = serde_json::from_str(&json).unwrap();
+//! assert_eq!(Some(&"camera".to_owned()), loaded.names.get(&key));
+//! ```
+
+pub use super::slot_map_packed_key_map::{deserialize, serialize};
@@ -1,135 +1,677 @@
-use super::{SlotMapKey, SlotMapKeyData};
+use super::slot_map_key_data::MAX_GENERATION;
+use super::slot_map_shared_chunk::SharedChunk;
+use super::{
+    GenerationExhaustion, InsertError, SlotMapExport, SlotMapKey,
+    SlotMapKeyData, SnapshotHeader, SnapshotWriter,
+};
 use std::borrow::Borrow;
+#[cfg(feature = "wrap-stats")]
+use std::collections::BTreeMap;
 use std::marker::PhantomData;
-use std::mem::{swap, transmute, MaybeUninit};
+use std::mem::{swap, MaybeUninit};
+use std::sync::OnceLock;
 
 /// Size of the individual array chunks in the slot map
 pub const SLOT_MAP_CHUNK_SIZE: usize = 256;
 
-type FilledChunk<T> = Box<[(SlotMapKeyData, T); SLOT_MAP_CHUNK_SIZE]>;
+/// The slots of a chunk, with the key data of every slot stored apart from
+/// the values. Generation checks and scans for filled slots only touch the
+/// densely packed key data, however large the values are. With the
+/// `cache-align` feature, chunks start on a cache line, and the key data of a
+/// whole chunk fills a whole number of lines, so the values start on one too
+#[cfg_attr(feature = "cache-align", repr(C, align(64)))]
+#[cfg_attr(not(feature = "cache-align"), repr(C))]
+pub(crate) struct ChunkSlots<K, V> {
+    keys: [K; SLOT_MAP_CHUNK_SIZE],
+    values: [V; SLOT_MAP_CHUNK_SIZE],
+}
+
+/// A chunk whose slots have all been written. Filled chunks can be shared
+/// with snapshots of the map, and are copied before being modified if they are
+pub(crate) type FilledChunk<T> = SharedChunk<ChunkSlots<SlotMapKeyData, T>>;
+
+/// The chunk currently being written. It is allocated the same way as a filled
+/// chunk so it can become one without being moved, but it is never shared
 type UnfilledChunk<T> =
-    Box<[MaybeUninit<(SlotMapKeyData, T)>; SLOT_MAP_CHUNK_SIZE]>;
+    SharedChunk<ChunkSlots<MaybeUninit<SlotMapKeyData>, MaybeUninit<T>>>;
+
+/// Function that copies a filled chunk so a shared chunk can be modified
+type ChunkCloner<T> = fn(&ChunkSlots<SlotMapKeyData, T>) -> FilledChunk<T>;
+
+impl<T> ChunkSlots<SlotMapKeyData, T> {
+    /// Get the slot at the given index in the chunk
+    #[inline]
+    pub(crate) fn get(
+        &self,
+        index_in_chunk: usize,
+    ) -> Option<(&SlotMapKeyData, &T)> {
+        Some((self.keys.get(index_in_chunk)?, &self.values[index_in_chunk]))
+    }
+
+    /// Get mutable access to the slot at the given index in the chunk
+    #[inline]
+    fn get_mut(
+        &mut self,
+        index_in_chunk: usize,
+    ) -> Option<(&mut SlotMapKeyData, &mut T)> {
+        Some((
+            self.keys.get_mut(index_in_chunk)?,
+            &mut self.values[index_in_chunk],
+        ))
+    }
+
+    /// Borrow every slot in the chunk
+    pub(crate) fn as_chunk_ref(&self) -> ChunkRef<'_, T> {
+        ChunkRef {
+            keys: &self.keys,
+            values: &self.values,
+        }
+    }
+
+    /// Borrow every slot in the chunk mutably
+    fn as_chunk_mut(&mut self) -> ChunkMut<'_, T> {
+        ChunkMut {
+            keys: &mut self.keys,
+            values: &mut self.values,
+        }
+    }
+
+    /// Create an iterator over the slots of the chunk in order
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&SlotMapKeyData, &T)> {
+        self.keys.iter().zip(self.values.iter())
+    }
+
+    /// Create an iterator over mutable references to the slots of the chunk
+    /// in order
+    fn iter_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (&mut SlotMapKeyData, &mut T)> {
+        self.keys.iter_mut().zip(self.values.iter_mut())
+    }
+}
+
+impl<T> ChunkSlots<MaybeUninit<SlotMapKeyData>, MaybeUninit<T>> {
+    /// Write the given slot at the given index in the chunk. Whatever was
+    /// written there before is not dropped
+    fn write(&mut self, index_in_chunk: usize, slot: (SlotMapKeyData, T)) {
+        self.keys[index_in_chunk] = MaybeUninit::new(slot.0);
+        self.values[index_in_chunk] = MaybeUninit::new(slot.1);
+    }
+
+    /// Get the key data and values of the first slots of the chunk
+    ///
+    /// # Safety
+    /// The given number of slots must have been written
+    unsafe fn written(&self, count: usize) -> (&[SlotMapKeyData], &[T]) {
+        // MaybeUninit<X> has the same layout as X
+        (
+            std::slice::from_raw_parts(self.keys.as_ptr() as *const _, count),
+            std::slice::from_raw_parts(self.values.as_ptr() as *const _, count),
+        )
+    }
+
+    /// Get mutable access to the key data and values of the first slots of
+    /// the chunk
+    ///
+    /// # Safety
+    /// The given number of slots must have been written
+    unsafe fn written_mut(
+        &mut self,
+        count: usize,
+    ) -> (&mut [SlotMapKeyData], &mut [T]) {
+        // MaybeUninit<X> has the same layout as X
+        (
+            std::slice::from_raw_parts_mut(
+                self.keys.as_mut_ptr() as *mut _,
+                count,
+            ),
+            std::slice::from_raw_parts_mut(
+                self.values.as_mut_ptr() as *mut _,
+                count,
+            ),
+        )
+    }
+}
+
+/// The initialized slots of a chunk, borrowed as the parallel arrays of key
+/// data and values they are stored in
+pub(crate) struct ChunkRef<'a, T> {
+    keys: &'a [SlotMapKeyData],
+    values: &'a [T],
+}
+
+impl<T> Clone for ChunkRef<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ChunkRef<'_, T> {}
+
+impl<T> Default for ChunkRef<'_, T> {
+    fn default() -> Self {
+        ChunkRef {
+            keys: &[],
+            values: &[],
+        }
+    }
+}
+
+impl<'a, T> ChunkRef<'a, T> {
+    /// Get the number of initialized slots in the chunk
+    pub(crate) fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Get the key data of every initialized slot in the chunk
+    pub(crate) fn keys(&self) -> &'a [SlotMapKeyData] {
+        self.keys
+    }
+
+    /// Get the values of every initialized slot in the chunk
+    pub(crate) fn values(&self) -> &'a [T] {
+        self.values
+    }
+
+    /// Get the slot at the given index in the chunk
+    pub(crate) fn get(
+        &self,
+        index_in_chunk: usize,
+    ) -> Option<(&'a SlotMapKeyData, &'a T)> {
+        Some((self.keys.get(index_in_chunk)?, &self.values[index_in_chunk]))
+    }
+
+    /// Create an iterator over the slots of the chunk in order
+    pub(crate) fn iter(
+        &self,
+    ) -> impl Iterator<Item = (&'a SlotMapKeyData, &'a T)> + Clone {
+        self.keys.iter().zip(self.values.iter())
+    }
+}
+
+impl<'a, T> IntoIterator for ChunkRef<'a, T> {
+    type Item = (&'a SlotMapKeyData, &'a T);
+    type IntoIter = std::iter::Zip<
+        std::slice::Iter<'a, SlotMapKeyData>,
+        std::slice::Iter<'a, T>,
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.keys.iter().zip(self.values.iter())
+    }
+}
+
+/// The initialized slots of a chunk, mutably borrowed as the parallel arrays
+/// of key data and values they are stored in
+pub(crate) struct ChunkMut<'a, T> {
+    keys: &'a mut [SlotMapKeyData],
+    values: &'a mut [T],
+}
+
+impl<'a, T> ChunkMut<'a, T> {
+    /// Get the slot at the given index in the chunk
+    pub(crate) fn get(
+        &self,
+        index_in_chunk: usize,
+    ) -> Option<(&SlotMapKeyData, &T)> {
+        Some((self.keys.get(index_in_chunk)?, &self.values[index_in_chunk]))
+    }
+
+    /// Get mutable access to the slot at the given index in the chunk
+    pub(crate) fn get_mut(
+        &mut self,
+        index_in_chunk: usize,
+    ) -> Option<(&mut SlotMapKeyData, &mut T)> {
+        Some((
+            self.keys.get_mut(index_in_chunk)?,
+            &mut self.values[index_in_chunk],
+        ))
+    }
+
+    /// Borrow the slots of the chunk again for a shorter lifetime
+    pub(crate) fn reborrow(&mut self) -> ChunkMut<'_, T> {
+        ChunkMut {
+            keys: self.keys,
+            values: self.values,
+        }
+    }
+
+    /// Split the borrow into the key data and values of the chunk
+    pub(crate) fn into_parts(self) -> (&'a mut [SlotMapKeyData], &'a mut [T]) {
+        (self.keys, self.values)
+    }
+}
+
+impl<'a, T> IntoIterator for ChunkMut<'a, T> {
+    type Item = (&'a mut SlotMapKeyData, &'a mut T);
+    type IntoIter = std::iter::Zip<
+        std::slice::IterMut<'a, SlotMapKeyData>,
+        std::slice::IterMut<'a, T>,
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.keys.iter_mut().zip(self.values.iter_mut())
+    }
+}
+
+/// Allocate a chunk with no slots written
+fn new_unfilled_chunk<T>() -> UnfilledChunk<T> {
+    // Safety - Arrays of MaybeUninit don't need to be initialized
+    unsafe { SharedChunk::new_uninit().assume_init() }
+}
+
+/// Allocate a chunk with no slots written, or return None if the memory isn't
+/// available
+fn try_new_unfilled_chunk<T>() -> Option<UnfilledChunk<T>> {
+    // Safety - Arrays of MaybeUninit don't need to be initialized
+    SharedChunk::try_new_uninit().map(|chunk| unsafe { chunk.assume_init() })
+}
+
+/// Get mutable access to the chunk currently being written
+fn unfilled_chunk_mut<T>(
+    chunk: &mut UnfilledChunk<T>,
+) -> &mut ChunkSlots<MaybeUninit<SlotMapKeyData>, MaybeUninit<T>> {
+    // Safety - The unfilled chunk is never shared, so this is the only
+    // reference to it
+    unsafe { &mut *(SharedChunk::as_ptr(chunk) as *mut _) }
+}
+
+/// Convert a chunk whose slots have all been written into a filled chunk
+/// without moving it
+///
+/// # Safety
+/// Every slot in the given chunk must have been written
+unsafe fn assume_filled<T>(chunk: UnfilledChunk<T>) -> FilledChunk<T> {
+    // MaybeUninit<X> has the same layout as X, and the chunk is repr(C), so
+    // its arrays are in the same place either way
+    chunk.cast::<ChunkSlots<SlotMapKeyData, T>>()
+}
+
+/// Drop the values in the given written slots. Like when a slice is dropped,
+/// every value is dropped even if dropping one of them panics
+///
+/// # Safety
+/// Every given value must have been written, and none of them may be read or
+/// dropped again
+unsafe fn drop_written<T>(values: &mut [MaybeUninit<T>]) {
+    // MaybeUninit<X> has the same layout as X
+    std::ptr::drop_in_place(values as *mut [_] as *mut [T])
+}
+
+/// Move the slot at the given index out of the given chunk
+///
+/// # Safety
+/// The slot must have been written, and its value may not be read or dropped
+/// again
+unsafe fn read_slot<T>(
+    chunk: &ChunkSlots<MaybeUninit<SlotMapKeyData>, MaybeUninit<T>>,
+    index_in_chunk: usize,
+) -> (SlotMapKeyData, T) {
+    (
+        chunk.keys[index_in_chunk].assume_init_read(),
+        chunk.values[index_in_chunk].assume_init_read(),
+    )
+}
+
+/// A new chunk being written in order from its first slot. If writing is cut
+/// short by a panic, the slots written so far are dropped with the writer
+struct ChunkWriter<T> {
+    chunk: UnfilledChunk<T>,
+    written: usize,
+}
+
+impl<T> ChunkWriter<T> {
+    fn new() -> ChunkWriter<T> {
+        ChunkWriter {
+            chunk: new_unfilled_chunk(),
+            written: 0,
+        }
+    }
+
+    /// Write the next slot
+    fn push(&mut self, slot: (SlotMapKeyData, T)) {
+        unfilled_chunk_mut(&mut self.chunk).write(self.written, slot);
+        self.written += 1;
+    }
+
+    /// Get the chunk once every slot has been written
+    fn finish(self) -> FilledChunk<T> {
+        assert_eq!(SLOT_MAP_CHUNK_SIZE, self.written, "Chunk must be full");
+
+        let writer = std::mem::ManuallyDrop::new(self);
+
+        // Safety - The chunk is moved out of a writer that won't be dropped,
+        // and every slot was written
+        unsafe { assume_filled(std::ptr::read(&writer.chunk)) }
+    }
+}
+
+impl<T> Drop for ChunkWriter<T> {
+    fn drop(&mut self) {
+        let written = self.written;
+
+        // Safety - Only the slots that were written are dropped
+        unsafe {
+            drop_written(
+                &mut unfilled_chunk_mut(&mut self.chunk).values[..written],
+            )
+        }
+    }
+}
+
+/// Build the filled chunk at the given chunk index from exactly a chunk's
+/// worth of values, all of which are stored in filled slots of the first
+/// generation
+#[cfg(feature = "rayon")]
+pub(crate) fn new_filled_chunk<T>(
+    chunk_index: u32,
+    values: Vec<T>,
+) -> FilledChunk<T> {
+    let mut writer = ChunkWriter::new();
+
+    for (index_in_chunk, value) in values.into_iter().enumerate() {
+        let key_data = SlotMapKeyData {
+            chunk_index,
+            index_in_chunk: index_in_chunk as u16,
+            generation: 0,
+        };
+
+        writer.push((key_data, value));
+    }
+
+    writer.finish()
+}
+
+/// Get mutable access to a filled chunk, first replacing it with a copy if it
+/// is shared with a snapshot
+#[inline]
+fn filled_chunk_mut<'a, T>(
+    chunk: &'a mut FilledChunk<T>,
+    cloner: Option<&ChunkCloner<T>>,
+) -> &'a mut ChunkSlots<SlotMapKeyData, T> {
+    match cloner {
+        // Safety - Chunks are only shared after a cloner has been recorded, so
+        // without one this is the only reference to the chunk
+        None => unsafe { &mut *(SharedChunk::as_ptr(chunk) as *mut _) },
+        Some(cloner) => {
+            if SharedChunk::get_mut(chunk).is_none() {
+                *chunk = cloner(chunk);
+            }
+
+            SharedChunk::get_mut(chunk).expect("copied chunk is not shared")
+        }
+    }
+}
+
+/// Copy a filled chunk
+fn clone_filled_chunk<T: Clone>(
+    chunk: &ChunkSlots<SlotMapKeyData, T>,
+) -> FilledChunk<T> {
+    map_filled_chunk(chunk, &mut T::clone)
+}
 
 // Require the chunk size to be a power of 2
 #[cfg(test)]
 mod sanity_checks {
+    use crate::test_support::TestKey;
+    use crate::SlotMap;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
     const_assert_eq!(super::SLOT_MAP_CHUNK_SIZE.count_ones(), 1u32);
+
+    // The key data of a chunk ends on a cache line, so its values start on one
+    const_assert_eq!(
+        std::mem::size_of::<[super::SlotMapKeyData; super::SLOT_MAP_CHUNK_SIZE]>(
+        ) % 64,
+        0
+    );
+    #[cfg(feature = "cache-align")]
+    const_assert_eq!(
+        std::mem::align_of::<super::ChunkSlots<super::SlotMapKeyData, u8>>(),
+        64
+    );
+
+    // Maps are as thread safe as the values they hold
+    assert_impl_all!(SlotMap<TestKey, usize, String>: Send, Sync);
+    assert_impl_all!(SlotMap<TestKey, usize, Cell<usize>>: Send);
+    assert_not_impl_any!(SlotMap<TestKey, usize, Cell<usize>>: Sync);
+    assert_not_impl_any!(SlotMap<TestKey, usize, Rc<usize>>: Send, Sync);
+    assert_impl_all!(std::sync::RwLock<SlotMap<TestKey, usize, String>>: Sync);
+    assert_impl_all!(crate::FrozenSlotMap<TestKey, usize, String>: Send, Sync);
 }
 
 /// Generate a new filled chunk based on the given filled chunk by performing
 /// the given mapping operation on the input chunk and storing the result in
 /// the newly generated chunk in the corresponding slot
 fn map_filled_chunk<T, U, F>(
-    filled_chunk: &FilledChunk<T>,
+    filled_chunk: &ChunkSlots<SlotMapKeyData, T>,
     mapper: &mut F,
 ) -> FilledChunk<U>
 where
     F: FnMut(&T) -> U,
 {
-    // Safety - This is safe because we are initializing a chunk of memory,
-    // but we are still treating that chunk as uninitialized. This uninitialized
-    // memory will be initialized by this function, but if there is a panic, it
-    // be unwound and not read
-    let mut result_chunk: UnfilledChunk<U> =
-        unsafe { Box::new(MaybeUninit::uninit().assume_init()) };
-
-    result_chunk.iter_mut().zip(filled_chunk.iter()).for_each(
-        |(target_slot, (slot_info, val))| {
-            *target_slot = MaybeUninit::new((*slot_info, mapper(val)))
-        },
-    );
+    // If the mapper panics, the values mapped so far are dropped with the
+    // writer
+    let mut writer = ChunkWriter::new();
 
-    // Safety - This is safe because we are only converting the MaybeUninits
-    // to the regular values because we just initialized them
-    unsafe { std::mem::transmute(result_chunk) }
+    for (slot_info, val) in filled_chunk.iter() {
+        writer.push((*slot_info, mapper(val)));
+    }
+
+    writer.finish()
+}
+
+/// Iterator over shared references to filled chunks. An iterator over the
+/// shared chunks themselves would only be `Send` when the values are both
+/// `Send` and `Sync`, but the chunks are never cloned or dropped through it,
+/// so it is as
+/// thread safe as an iterator over references to the values
+struct FilledChunks<'a, T>(std::slice::Iter<'a, FilledChunk<T>>);
+
+impl<'a, T> Iterator for FilledChunks<'a, T> {
+    type Item = &'a ChunkSlots<SlotMapKeyData, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|chunk| &**chunk)
+    }
+}
+
+// Safety - Only shared references to values are handed out
+unsafe impl<T: Sync> Send for FilledChunks<'_, T> {}
+
+/// Iterator over mutable references to filled chunks, copying shared chunks
+/// first
+struct FilledChunksMut<'a, T> {
+    chunks: std::slice::IterMut<'a, FilledChunk<T>>,
+    cloner: Option<ChunkCloner<T>>,
+}
+
+impl<'a, T> Iterator for FilledChunksMut<'a, T> {
+    type Item = &'a mut ChunkSlots<SlotMapKeyData, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk = self.chunks.next()?;
+        Some(filled_chunk_mut(chunk, self.cloner.as_ref()))
+    }
+}
+
+// Safety - Without a cloner, chunks are not shared, so the iterator is like
+// one over mutable references to the values. A cloner is only recorded when
+// the values are Sync, which makes copying a shared chunk, or dropping the
+// last reference to one, from another thread sound
+unsafe impl<T: Send> Send for FilledChunksMut<'_, T> {}
+
+/// Raw access to the initialized slots of a map being compacted, so that
+/// items can be moved into open slots from several threads at once
+pub(crate) struct CompactionSlots<'a, T> {
+    chunks: Vec<(*mut SlotMapKeyData, *mut T)>,
+
+    _phantom: PhantomData<&'a mut T>,
+}
+
+// Safety - Moving items between slots from another thread only needs the
+// values to be Send, and callers keep moves from sharing slots
+unsafe impl<T: Send> Send for CompactionSlots<'_, T> {}
+unsafe impl<T: Send> Sync for CompactionSlots<'_, T> {}
+
+impl<T> CompactionSlots<'_, T> {
+    /// Move the item in the slot at the first key data into the open slot at
+    /// the second, which takes the second key data. The value left behind in
+    /// the open slot goes to the first slot
+    ///
+    /// # Safety
+    /// Both slots must be initialized, and neither may be part of another
+    /// move made at the same time
+    pub(crate) unsafe fn move_item(
+        &self,
+        from: &SlotMapKeyData,
+        to: &SlotMapKeyData,
+    ) {
+        let slot = |key_data: &SlotMapKeyData| {
+            let (keys, values) = self.chunks[key_data.chunk_index as usize];
+            let index_in_chunk = key_data.index_in_chunk as usize;
+
+            (keys.add(index_in_chunk), values.add(index_in_chunk))
+        };
+
+        let ((_, from_value), (to_key, to_value)) = (slot(from), slot(to));
+
+        std::ptr::swap(from_value, to_value);
+        *to_key = *to;
+    }
+}
+
+/// Reasons key data given to `SlotMap::from_filled_slots` couldn't be used,
+/// with the key data at fault
+#[derive(Debug)]
+pub(crate) enum FilledSlotsError {
+    /// The key data names the same slot as other key data
+    Collision(SlotMapKeyData),
+
+    /// The key data has the generation of a vacant slot
+    VacantGeneration(SlotMapKeyData),
+
+    /// The key data names a slot further along than the number of items
+    /// allows
+    OutOfRange(SlotMapKeyData),
 }
 
 /// Encapsulation of the slot storage objects to make the borrow checker happy
-struct Slots<T> {
+pub(crate) struct Slots<T> {
     current_chunk: UnfilledChunk<T>,
 
-    #[allow(clippy::vec_box)]
     filled_chunks: Vec<FilledChunk<T>>,
 
     current_chunk_index: u32,
     current_chunk_cursor: u16,
+
+    /// Chunk allocated ahead of time by a fallible insert, used as the next
+    /// current chunk
+    spare_chunk: Option<UnfilledChunk<T>>,
+
+    /// Set the first time filled chunks are shared, which can only happen
+    /// when the values can be cloned
+    chunk_cloner: OnceLock<ChunkCloner<T>>,
 }
 
 impl<T> Slots<T> {
     pub fn new() -> Slots<T> {
-        // Safety - This is safe because we are initializing a chunk of memory,
-        // but we are still treating that chunk as uninitialized
-        let first_chunk =
-            unsafe { Box::new(MaybeUninit::uninit().assume_init()) };
         Slots {
-            current_chunk: first_chunk,
+            current_chunk: new_unfilled_chunk(),
             filled_chunks: Vec::new(),
             current_chunk_index: Default::default(),
             current_chunk_cursor: Default::default(),
+            spare_chunk: None,
+            chunk_cloner: OnceLock::new(),
         }
     }
 
-    fn get_slot(&self, key: &SlotMapKeyData) -> Option<&(SlotMapKeyData, T)> {
-        if key.chunk_index < self.current_chunk_index {
-            self.filled_chunks
-                .get(key.chunk_index as usize)
-                .unwrap()
-                .get(key.index_in_chunk as usize)
-        } else if key.index_in_chunk < self.current_chunk_cursor {
-            // Safety - The index_in_chunk corresponds to a slot that was
-            // already written. This is only true if the key was generated
-            // by this map.
-            self.current_chunk
-                .get(key.index_in_chunk as usize)
-                .map(|s| unsafe { &*s.as_ptr() })
+    /// Get the slot at the coordinates in the given key. Filled chunks are
+    /// looked in first because they hold nearly every slot, so most lookups
+    /// only take the bounds check on the list of filled chunks
+    #[inline]
+    fn get_slot(&self, key: &SlotMapKeyData) -> Option<(&SlotMapKeyData, &T)> {
+        let index_in_chunk = key.index_in_chunk as usize;
+
+        if let Some(chunk) = self.filled_chunks.get(key.chunk_index as usize) {
+            chunk.get(index_in_chunk)
+        } else if key.chunk_index == self.current_chunk_index
+            && key.index_in_chunk < self.current_chunk_cursor
+        {
+            // Safety - The slots before the cursor were already written
+            let (keys, values) = unsafe {
+                self.current_chunk
+                    .written(self.current_chunk_cursor as usize)
+            };
+
+            Some((&keys[index_in_chunk], &values[index_in_chunk]))
         } else {
             None
         }
     }
 
-    /// Get the slot at the coordinates in the given key.  This method does not
-    /// check to ensure that the given key's chunk index is within in the range
-    /// of the existing storage vec, but there are also no explicit unwraps here
-    /// either, so you get what you get
-    fn get_storage_slot_mut(
-        &mut self,
-        key: &SlotMapKeyData,
-    ) -> Option<&mut (SlotMapKeyData, T)> {
-        self.filled_chunks
-            .get_mut(key.chunk_index as usize)
-            .and_then(|chunk| chunk.get_mut(key.index_in_chunk as usize))
+    /// Get the value of the item with exactly the given key data. Filled
+    /// slots never have odd generations, so checking that the given key data
+    /// is filled and comparing generations once covers both the slot being
+    /// filled and it holding the same generation
+    #[inline]
+    fn get_value(&self, key: &SlotMapKeyData) -> Option<&T> {
+        let (slot_key, value) = self.get_slot(key)?;
+
+        (slot_key.generation == key.generation && key.is_filled())
+            .then_some(value)
     }
 
-    /// Get the slot in the current chunk indicated by the given key. This
-    /// method does not check to make sure that the chunk index in the given key
-    /// matches the current chunk's index. The index within the chunk is
-    /// validated on creation of the key
-    fn get_current_chunk_slot_mut(
+    /// Mutable version of get_value
+    #[inline]
+    fn get_value_mut(&mut self, key: &SlotMapKeyData) -> Option<&mut T> {
+        let (slot_key, value) = self.get_existing_slot_mut(key)?;
+
+        (slot_key.generation == key.generation && key.is_filled())
+            .then_some(value)
+    }
+
+    /// Write the given slot into the slot in the current chunk indicated by
+    /// the given key. This method does not check to make sure that the chunk
+    /// index in the given key matches the current chunk's index. The index
+    /// within the chunk is validated on creation of the key
+    fn write_current_chunk_slot(
         &mut self,
         key: &SlotMapKeyData,
-    ) -> &mut MaybeUninit<(SlotMapKeyData, T)> {
-        self.current_chunk
-            .get_mut(key.index_in_chunk as usize)
-            .expect("Invalid index in chunk")
+        slot: (SlotMapKeyData, T),
+    ) {
+        unfilled_chunk_mut(&mut self.current_chunk)
+            .write(key.index_in_chunk as usize, slot)
     }
 
     /// Get a mutable reference to the slot indicated by the coordinates in the
     /// given key. The reason this is get "existing" slot is because it will
     /// return None if a non-initialized slot in the current chunk is requested
     /// rather than a mutable reference to the uninitialized slot
+    #[inline]
     fn get_existing_slot_mut(
         &mut self,
         key: &SlotMapKeyData,
-    ) -> Option<&mut (SlotMapKeyData, T)> {
-        if key.chunk_index < self.current_chunk_index {
-            self.get_storage_slot_mut(key)
-        } else if key.index_in_chunk < self.current_chunk_cursor {
-            // Safety - since the index in the chunk is less than the cursor
-            // and we assume the given key was generated by this map, we know
-            // the returned MaybeUninit will have been initialized
-            unsafe {
-                Some(self.get_current_chunk_slot_mut(key).assume_init_mut())
-            }
+    ) -> Option<(&mut SlotMapKeyData, &mut T)> {
+        let index_in_chunk = key.index_in_chunk as usize;
+
+        if let Some(chunk) =
+            self.filled_chunks.get_mut(key.chunk_index as usize)
+        {
+            filled_chunk_mut(chunk, self.chunk_cloner.get())
+                .get_mut(index_in_chunk)
+        } else if key.chunk_index == self.current_chunk_index
+            && key.index_in_chunk < self.current_chunk_cursor
+        {
+            // Safety - since the index in the chunk is less than the cursor,
+            // we know the slot will have been initialized
+            let (keys, values) = unsafe {
+                unfilled_chunk_mut(&mut self.current_chunk)
+                    .written_mut(self.current_chunk_cursor as usize)
+            };
+
+            Some((&mut keys[index_in_chunk], &mut values[index_in_chunk]))
         } else {
             None
         }
@@ -137,54 +679,199 @@ impl<T> Slots<T> {
 
     /// Move the current chunk into filled chunks
     fn move_current_chunk_to_filled_chunk(&mut self) {
-        // Safety - This is safe because we are initializing a chunk of memory,
-        // but we are still treating that chunk as uninitialized
         let mut new_storage_chunk: UnfilledChunk<T> =
-            unsafe { Box::new(MaybeUninit::uninit().assume_init()) };
+            self.spare_chunk.take().unwrap_or_else(new_unfilled_chunk);
 
         swap(&mut new_storage_chunk, &mut self.current_chunk);
 
         // Safety - this function is only called when the current_chunk is full
         // which means all the elements have been written, so we can assume
         // all the memory is initialized
-        let new_filled_chunk = unsafe { transmute(new_storage_chunk) };
+        let new_filled_chunk = unsafe { assume_filled(new_storage_chunk) };
         self.filled_chunks.push(new_filled_chunk);
         self.current_chunk_index = self.filled_chunks.len() as u32;
         self.current_chunk_cursor = 0;
     }
 
+    /// Write the given slot into the next uninitialized position in the
+    /// current chunk, and move the current chunk into the filled chunks if
+    /// that write filled it
+    pub(crate) fn push_slot(&mut self, slot: (SlotMapKeyData, T)) {
+        let cursor = self.current_chunk_cursor as usize;
+
+        unfilled_chunk_mut(&mut self.current_chunk).write(cursor, slot);
+
+        if cursor + 1 == SLOT_MAP_CHUNK_SIZE {
+            self.move_current_chunk_to_filled_chunk();
+        } else {
+            self.current_chunk_cursor += 1;
+        }
+    }
+
+    /// Allocate the memory needed to move the current chunk into the filled
+    /// chunks ahead of time. The list of filled chunks is grown, and the next
+    /// current chunk is kept as the spare chunk until the current one fills
+    fn try_reserve_chunk(&mut self) -> Result<(), InsertError<()>> {
+        self.filled_chunks
+            .try_reserve(1)
+            .map_err(|_| InsertError::AllocationFailed(()))?;
+
+        if self.spare_chunk.is_none() {
+            self.spare_chunk = Some(
+                try_new_unfilled_chunk()
+                    .ok_or(InsertError::AllocationFailed(()))?,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Add a filled chunk after the existing ones. No slots of the current
+    /// chunk may have been written
+    #[cfg(feature = "rayon")]
+    pub(crate) fn push_filled_chunk(&mut self, chunk: FilledChunk<T>) {
+        assert_eq!(0, self.current_chunk_cursor, "Current chunk is not empty");
+
+        self.filled_chunks.push(chunk);
+        self.current_chunk_index = self.filled_chunks.len() as u32;
+    }
+
+    /// Get the initialized slots of the chunk at the given index. The current
+    /// chunk is only returned if at least one of its slots has been written
+    pub(crate) fn chunk(&self, chunk_index: usize) -> Option<ChunkRef<'_, T>> {
+        if let Some(chunk) = self.filled_chunks.get(chunk_index) {
+            Some(chunk.as_chunk_ref())
+        } else if chunk_index == self.current_chunk_index as usize
+            && self.current_chunk_cursor > 0
+        {
+            // Safety - The slices are limited to the range of the current
+            // chunk that has been initialized
+            let (keys, values) = unsafe {
+                self.current_chunk
+                    .written(self.current_chunk_cursor as usize)
+            };
+
+            Some(ChunkRef { keys, values })
+        } else {
+            None
+        }
+    }
+
+    /// Consume these slots, producing every initialized slot in order of its
+    /// coordinates
+    pub(crate) fn into_slots(
+        mut self,
+    ) -> impl Iterator<Item = (SlotMapKeyData, T)> {
+        // Safety - Only the initialized slots of the current chunk are read,
+        // and the cursor is reset before the slots are dropped, so no value is
+        // read or dropped twice
+        let current_slots = (0..self.current_chunk_cursor as usize)
+            .map(|i| unsafe { read_slot(&self.current_chunk, i) })
+            .collect::<Vec<_>>();
+
+        self.current_chunk_cursor = 0;
+
+        let cloner = self.chunk_cloner.get().copied();
+
+        std::mem::take(&mut self.filled_chunks)
+            .into_iter()
+            .flat_map(move |mut chunk| {
+                let _ = filled_chunk_mut(&mut chunk, cloner.as_ref());
+
+                // Safety - The chunk is no longer shared, so its values can be
+                // moved out. Viewing it as unwritten slots keeps the values
+                // from being dropped again when the chunk is freed
+                let chunk: UnfilledChunk<T> = unsafe { chunk.cast() };
+
+                (0..SLOT_MAP_CHUNK_SIZE)
+                    .map(|i| unsafe { read_slot(&chunk, i) })
+                    .collect::<Vec<_>>()
+            })
+            .chain(current_slots)
+    }
+
+    /// Get the number of chunks that contain at least one initialized slot
+    pub(crate) fn chunk_count(&self) -> usize {
+        self.filled_chunks.len() + (self.current_chunk_cursor > 0) as usize
+    }
+
+    /// Get the number of initialized slots, filled or vacant
+    pub(crate) fn slot_count(&self) -> usize {
+        self.filled_chunks.len() * SLOT_MAP_CHUNK_SIZE
+            + self.current_chunk_cursor as usize
+    }
+
+    /// Get the number of bytes allocated on the heap for the chunks and the
+    /// list of filled chunks. Chunks shared with other maps are counted in
+    /// full
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        // Each chunk's allocation holds its reference count in front of the
+        // slots, padded out to the slots' alignment
+        let chunk_bytes = FilledChunk::<T>::layout().size();
+        let spare_chunks = self.spare_chunk.is_some() as usize;
+
+        (self.filled_chunks.len() + 1 + spare_chunks) * chunk_bytes
+            + self.filled_chunks.capacity()
+                * std::mem::size_of::<FilledChunk<T>>()
+    }
+
     /// Construct an iterator over all initialized slots
-    pub fn values(&self) -> impl Iterator<Item = &(SlotMapKeyData, T)> {
+    pub fn values(&self) -> impl Iterator<Item = (&SlotMapKeyData, &T)> {
         let full_chunks_iter =
-            self.filled_chunks.iter().flat_map(|slc| slc.iter());
+            FilledChunks(self.filled_chunks.iter()).flat_map(|slc| slc.iter());
 
-        // Safety - This raw dereference is safe because it is limited to the
-        // range of the current chunk that has been initialized
-        let current_chunk_iter = self
-            .current_chunk
-            .iter()
-            .take(self.current_chunk_cursor as usize)
-            .map(|s| unsafe { &*s.as_ptr() });
+        // Safety - This is limited to the range of the current chunk that has
+        // been initialized
+        let (keys, values) = unsafe {
+            self.current_chunk
+                .written(self.current_chunk_cursor as usize)
+        };
 
-        full_chunks_iter.chain(current_chunk_iter)
+        full_chunks_iter.chain(keys.iter().zip(values))
     }
 
     /// Construct an iterator over all initialized slots as mutable references
     pub fn values_mut(
         &mut self,
-    ) -> impl Iterator<Item = &mut (SlotMapKeyData, T)> {
-        let full_chunks_iter =
-            self.filled_chunks.iter_mut().flat_map(|slc| slc.iter_mut());
+    ) -> impl Iterator<Item = (&mut SlotMapKeyData, &mut T)> {
+        let full_chunks_iter = FilledChunksMut {
+            chunks: self.filled_chunks.iter_mut(),
+            cloner: self.chunk_cloner.get().copied(),
+        }
+        .flat_map(|slc| slc.iter_mut());
 
-        // Safety - This raw dereference is safe because it is limited to the
-        // range of the current chunk that has been initialized
-        let current_chunk_iter = self
-            .current_chunk
-            .iter_mut()
-            .take(self.current_chunk_cursor as usize)
-            .map(|s| unsafe { s.assume_init_mut() });
+        // Safety - This is limited to the range of the current chunk that has
+        // been initialized
+        let (keys, values) = unsafe {
+            unfilled_chunk_mut(&mut self.current_chunk)
+                .written_mut(self.current_chunk_cursor as usize)
+        };
 
-        full_chunks_iter.chain(current_chunk_iter)
+        full_chunks_iter.chain(keys.iter_mut().zip(values))
+    }
+
+    /// Get mutable access to the initialized slots of every chunk, one slice
+    /// per chunk, so that chunks can be processed independently
+    pub(crate) fn chunks_mut(&mut self) -> Vec<ChunkMut<'_, T>> {
+        let cursor = self.current_chunk_cursor as usize;
+
+        let mut chunks = FilledChunksMut {
+            chunks: self.filled_chunks.iter_mut(),
+            cloner: self.chunk_cloner.get().copied(),
+        }
+        .map(|chunk| chunk.as_chunk_mut())
+        .collect::<Vec<_>>();
+
+        // Safety - Only the written part of the current chunk is included
+        let (keys, values) = unsafe {
+            unfilled_chunk_mut(&mut self.current_chunk).written_mut(cursor)
+        };
+
+        if cursor > 0 {
+            chunks.push(ChunkMut { keys, values });
+        }
+
+        chunks
     }
 
     /// Construct an iterator over all initialized slots where each item is a
@@ -192,9 +879,10 @@ impl<T> Slots<T> {
     /// stored at the slot
     pub fn iter_raw(
         &self,
-    ) -> impl Iterator<Item = (SlotMapKeyData, &(SlotMapKeyData, T))> {
-        let full_chunks_iter = self.filled_chunks.iter().enumerate().flat_map(
-            |(chunk_index, slc)| {
+    ) -> impl Iterator<Item = (SlotMapKeyData, (&SlotMapKeyData, &T))> {
+        let full_chunks_iter = FilledChunks(self.filled_chunks.iter())
+            .enumerate()
+            .flat_map(|(chunk_index, slc)| {
                 slc.iter().enumerate().map(move |(index_in_chunk, slot)| {
                     let key_data = SlotMapKeyData {
                         chunk_index: chunk_index as u32,
@@ -204,26 +892,28 @@ impl<T> Slots<T> {
 
                     (key_data, slot)
                 })
-            },
-        );
+            });
 
-        // Safety - This raw dereference is safe because it is limited to the
-        // range of the current chunk that has been initialized
-        let current_chunk_iter = self
-            .current_chunk
-            .iter()
-            .take(self.current_chunk_cursor as usize)
-            .map(|s| unsafe { &*s.as_ptr() })
-            .enumerate()
-            .map(move |(index_in_chunk, slot)| {
+        let current_chunk_index = self.current_chunk_index;
+
+        // Safety - This is limited to the range of the current chunk that has
+        // been initialized
+        let (keys, values) = unsafe {
+            self.current_chunk
+                .written(self.current_chunk_cursor as usize)
+        };
+
+        let current_chunk_iter = keys.iter().zip(values).enumerate().map(
+            move |(index_in_chunk, slot)| {
                 let key_data = SlotMapKeyData {
-                    chunk_index: self.current_chunk_index as u32,
+                    chunk_index: current_chunk_index,
                     index_in_chunk: index_in_chunk as u16,
                     generation: slot.0.generation,
                 };
 
                 (key_data, slot)
-            });
+            },
+        );
 
         full_chunks_iter.chain(current_chunk_iter)
     }
@@ -233,43 +923,47 @@ impl<T> Slots<T> {
     /// to the information stored at the slot
     pub fn iter_mut_raw(
         &mut self,
-    ) -> impl Iterator<Item = (SlotMapKeyData, &mut (SlotMapKeyData, T))> {
-        let full_chunks_iter =
-            self.filled_chunks.iter_mut().enumerate().flat_map(
-                |(chunk_index, slc)| {
-                    slc.iter_mut().enumerate().map(
-                        move |(index_in_chunk, slot)| {
-                            let key_data = SlotMapKeyData {
-                                chunk_index: chunk_index as u32,
-                                index_in_chunk: index_in_chunk as u16,
-                                generation: slot.0.generation,
-                            };
-
-                            (key_data, slot)
-                        },
-                    )
-                },
-            );
+    ) -> impl Iterator<Item = (SlotMapKeyData, (&mut SlotMapKeyData, &mut T))>
+    {
+        let full_chunks_iter = FilledChunksMut {
+            chunks: self.filled_chunks.iter_mut(),
+            cloner: self.chunk_cloner.get().copied(),
+        }
+        .enumerate()
+        .flat_map(move |(chunk_index, slc)| {
+            slc.iter_mut()
+                .enumerate()
+                .map(move |(index_in_chunk, slot)| {
+                    let key_data = SlotMapKeyData {
+                        chunk_index: chunk_index as u32,
+                        index_in_chunk: index_in_chunk as u16,
+                        generation: slot.0.generation,
+                    };
+
+                    (key_data, slot)
+                })
+        });
 
         let current_chunk_index = self.current_chunk_index;
 
-        // Safety - This raw dereference is safe because it is limited to the
-        // range of the current chunk that has been initialized
-        let current_chunk_iter = self
-            .current_chunk
-            .iter_mut()
-            .take(self.current_chunk_cursor as usize)
-            .map(|s| unsafe { s.assume_init_mut() })
-            .enumerate()
-            .map(move |(index_in_chunk, slot)| {
+        // Safety - This is limited to the range of the current chunk that has
+        // been initialized
+        let (keys, values) = unsafe {
+            unfilled_chunk_mut(&mut self.current_chunk)
+                .written_mut(self.current_chunk_cursor as usize)
+        };
+
+        let current_chunk_iter = keys.iter_mut().zip(values).enumerate().map(
+            move |(index_in_chunk, slot)| {
                 let key_data = SlotMapKeyData {
-                    chunk_index: current_chunk_index as u32,
+                    chunk_index: current_chunk_index,
                     index_in_chunk: index_in_chunk as u16,
                     generation: slot.0.generation,
                 };
 
                 (key_data, slot)
-            });
+            },
+        );
 
         full_chunks_iter.chain(current_chunk_iter)
     }
@@ -277,45 +971,162 @@ impl<T> Slots<T> {
     /// Create new slots based on this one with the values mapped with the given
     /// function
     fn map<R>(&self, mut mapper: impl FnMut(&T) -> R) -> Slots<R> {
-        // Safety - This is safe because we are initializing a chunk of memory,
-        // but we are still treating that chunk as uninitialized
-        let mut current_chunk: UnfilledChunk<R> =
-            unsafe { Box::new(MaybeUninit::uninit().assume_init()) };
-
-        current_chunk
-            .iter_mut()
-            .zip(self.current_chunk.iter())
-            .take(self.current_chunk_cursor as usize)
-            .for_each(|(target, src)| {
-                // Safety - This operation is limited to the indexes of
-                // the current chunk that have been written
-                unsafe {
-                    let src_ptr = &*src.as_ptr();
-                    *target = MaybeUninit::new((src_ptr.0, mapper(&src_ptr.1)));
-                }
-            });
+        // The new slots are always consistent, so if the mapper panics, the
+        // values mapped so far are dropped with them
+        let mut slots = Slots::new();
 
-        Slots {
-            current_chunk,
-            filled_chunks: self
+        for chunk in &self.filled_chunks {
+            slots
                 .filled_chunks
-                .iter()
-                .map(|chunk| map_filled_chunk(chunk, &mut mapper))
-                .collect(),
-            current_chunk_index: self.current_chunk_index,
-            current_chunk_cursor: self.current_chunk_cursor,
+                .push(map_filled_chunk(chunk, &mut mapper));
+            slots.current_chunk_index += 1;
+        }
+
+        let current_slots = self
+            .chunk(self.current_chunk_index as usize)
+            .unwrap_or_default();
+
+        for (key_data, value) in current_slots.iter() {
+            slots.push_slot((*key_data, mapper(value)));
+        }
+
+        slots
+    }
+
+    /// Share the filled chunks of these slots, copying the written slots of
+    /// the current chunk. Shared chunks are copied before these slots next
+    /// modify them
+    pub(crate) fn share(
+        &self,
+    ) -> (Vec<FilledChunk<T>>, Vec<(SlotMapKeyData, T)>)
+    where
+        T: Clone + Sync,
+    {
+        let _ = self.chunk_cloner.get_or_init(|| clone_filled_chunk::<T>);
+
+        // Safety - Only the written slots of the current chunk are copied
+        let (keys, values) = unsafe {
+            self.current_chunk
+                .written(self.current_chunk_cursor as usize)
+        };
+
+        let current =
+            keys.iter().copied().zip(values.iter().cloned()).collect();
+
+        (self.filled_chunks.clone(), current)
+    }
+
+    /// Create slots that share the filled chunks of these slots and hold a
+    /// copy of the written slots of the current chunk. Either one copies a
+    /// shared chunk before modifying it
+    pub(crate) fn share_all(&self) -> Slots<T>
+    where
+        T: Clone + Sync,
+    {
+        let (filled_chunks, current) = self.share();
+
+        let mut slots = Slots {
+            current_chunk: new_unfilled_chunk(),
+            current_chunk_index: filled_chunks.len() as u32,
+            filled_chunks,
+            current_chunk_cursor: 0,
+            spare_chunk: None,
+            chunk_cloner: OnceLock::from(
+                clone_filled_chunk::<T> as ChunkCloner<T>,
+            ),
+        };
+
+        for slot in current {
+            slots.push_slot(slot);
         }
+
+        slots
     }
 }
 
+// Safety - Slots own their values like a Vec does. Full chunks are only
+// shared with snapshots when the values are Sync, so reading a shared chunk
+// from the thread the slots are moved to is sound, and the Send bound covers
+// values being dropped by whichever owner of a chunk is dropped last
+unsafe impl<T: Send> Send for Slots<T> {}
+
+// Safety - Shared access only hands out shared references to values, and
+// snapshots taken through a shared reference may drop values on another
+// thread, so values must be both Send and Sync
+unsafe impl<T: Send + Sync> Sync for Slots<T> {}
+
 impl<T> Drop for Slots<T> {
     /// Because the current slot is stored in `MaybeUninit`s, any written slots
     /// need to be dropped manually
     fn drop(&mut self) {
-        self.current_chunk
-            .iter_mut()
-            .take(self.current_chunk_cursor as usize)
-            .for_each(|s| unsafe { s.as_mut_ptr().drop_in_place() })
+        let cursor = self.current_chunk_cursor as usize;
+
+        // Safety - Only the written slots are dropped
+        unsafe {
+            drop_written(
+                &mut unfilled_chunk_mut(&mut self.current_chunk).values
+                    [..cursor],
+            )
+        }
+    }
+}
+
+/// Mark the filled slot with the given key data vacant and link it in at the
+/// head of the chain of open slots, unless its generations have run out and
+/// the given policy retires it instead
+fn vacate_slot(
+    slot_key: &mut SlotMapKeyData,
+    next_open_slot: &mut SlotMapKeyData,
+    exhaustion: GenerationExhaustion,
+) {
+    let removed = *slot_key;
+    slot_key.increment_generation();
+
+    if slot_key.generation == MAX_GENERATION {
+        match exhaustion {
+            GenerationExhaustion::Wrap => {}
+            GenerationExhaustion::Retire => return,
+            GenerationExhaustion::Notify(notify) => notify(removed),
+        }
+    }
+
+    slot_key.swap_coordinates(next_open_slot);
+}
+
+/// How values are overwritten once they have been removed
+#[cfg(feature = "poison-on-remove")]
+enum Poison<T> {
+    /// Overwrite values with the given function
+    With(fn(&mut T)),
+
+    /// Set every byte of values to the given byte. Only used for `Copy`
+    /// values, which have no drop glue to skip, and for which the caller has
+    /// promised the resulting bit pattern is valid
+    Bytes(u8),
+}
+
+#[cfg(feature = "poison-on-remove")]
+impl<T> Clone for Poison<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+#[cfg(feature = "poison-on-remove")]
+impl<T> Copy for Poison<T> {}
+
+#[cfg(feature = "poison-on-remove")]
+impl<T> Poison<T> {
+    fn apply(self, value: &mut T) {
+        match self {
+            Poison::With(poison) => poison(value),
+
+            // Safety - Bytes are only used for Copy values whose every byte
+            // may be set to the given byte
+            Poison::Bytes(byte) => unsafe {
+                std::ptr::write_bytes(value as *mut T, byte, 1)
+            },
+        }
     }
 }
 
@@ -326,6 +1137,52 @@ struct Inner<T> {
     slots: Slots<T>,
     next_open_slot: SlotMapKeyData,
     len: usize,
+
+    #[cfg(feature = "debug-keys")]
+    stale_key_policy: super::StaleKeyPolicy,
+
+    #[cfg(feature = "poison-on-remove")]
+    poison: Option<Poison<T>>,
+
+    /// Slots whose values were removed but not yet poisoned, with the
+    /// generations they were vacated at. Their values are poisoned the next
+    /// time the map inserts or removes, once the caller can no longer be
+    /// reading them
+    #[cfg(feature = "poison-on-remove")]
+    poison_pending: Vec<SlotMapKeyData>,
+
+    exhaustion: GenerationExhaustion,
+
+    /// Number of times a slot's generation has wrapped back to the start,
+    /// by chunk index. Wraps are rare, so only chunks that have had one are
+    /// stored
+    #[cfg(feature = "wrap-stats")]
+    wraps: BTreeMap<u32, u64>,
+
+    #[cfg(feature = "profiling")]
+    access_counters: Vec<super::slot_map_heatmap::AccessCounter>,
+
+    #[cfg(feature = "drop-report")]
+    drop_report: super::DropReport,
+
+    /// Number of items ever inserted, which with the length gives the number
+    /// of items removed
+    #[cfg(feature = "drop-report")]
+    inserts: u64,
+}
+
+#[cfg(feature = "drop-report")]
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        super::slot_map_drop_report::report_dropped::<T>(
+            self.drop_report,
+            self.inserts,
+            self.slots
+                .values()
+                .filter(|(key_data, _)| key_data.is_filled())
+                .map(|(key_data, _)| *key_data),
+        );
+    }
 }
 
 /// Implementation of a slot map that limits the restrictions on slotted keys
@@ -371,12 +1228,254 @@ where
                 slots: Slots::new(),
                 next_open_slot: Default::default(),
                 len: Default::default(),
+
+                #[cfg(feature = "debug-keys")]
+                stale_key_policy: super::StaleKeyPolicy::current_default(),
+
+                #[cfg(feature = "poison-on-remove")]
+                poison: None,
+
+                #[cfg(feature = "poison-on-remove")]
+                poison_pending: Vec::new(),
+
+                exhaustion: GenerationExhaustion::default(),
+                #[cfg(feature = "wrap-stats")]
+                wraps: BTreeMap::new(),
+
+                #[cfg(feature = "profiling")]
+                access_counters: Vec::new(),
+
+                #[cfg(feature = "drop-report")]
+                drop_report: super::DropReport::default(),
+
+                #[cfg(feature = "drop-report")]
+                inserts: 0,
+            },
+
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Assemble a slot map from already-populated storage and bookkeeping.
+    /// The caller is responsible for the consistency of the given parts
+    pub(crate) fn from_raw_state(
+        slots: Slots<T>,
+        next_open_slot: SlotMapKeyData,
+        len: usize,
+    ) -> SlotMap<K, P, T> {
+        SlotMap {
+            inner: Inner {
+                slots,
+                next_open_slot,
+                len,
+
+                #[cfg(feature = "debug-keys")]
+                stale_key_policy: super::StaleKeyPolicy::current_default(),
+
+                #[cfg(feature = "poison-on-remove")]
+                poison: None,
+
+                #[cfg(feature = "poison-on-remove")]
+                poison_pending: Vec::new(),
+
+                exhaustion: GenerationExhaustion::default(),
+                #[cfg(feature = "wrap-stats")]
+                wraps: BTreeMap::new(),
+
+                #[cfg(feature = "profiling")]
+                access_counters: Vec::new(),
+
+                #[cfg(feature = "drop-report")]
+                drop_report: super::DropReport::default(),
+
+                #[cfg(feature = "drop-report")]
+                inserts: len as u64,
             },
 
-            _phantom: PhantomData::default(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Create a copy of this map that shares its full chunks with this map
+    /// until either one modifies them
+    pub(crate) fn share_all(&self) -> SlotMap<K, P, T>
+    where
+        T: Clone + Sync,
+    {
+        SlotMap::from_raw_state(
+            self.inner.slots.share_all(),
+            self.inner.next_open_slot,
+            self.inner.len,
+        )
+    }
+
+    /// Get what this map does when a stale key is used
+    #[cfg(feature = "debug-keys")]
+    pub fn stale_key_policy(&self) -> super::StaleKeyPolicy {
+        self.inner.stale_key_policy
+    }
+
+    /// Set what this map does when a stale key is used
+    #[cfg(feature = "debug-keys")]
+    pub fn set_stale_key_policy(&mut self, policy: super::StaleKeyPolicy) {
+        self.inner.stale_key_policy = policy;
+    }
+
+    /// Overwrite every removed value with the given function, so anything
+    /// that still reads a removed value, like a forged key passed to a raw
+    /// method, sees an obviously wrong value rather than the old one. The
+    /// references returned by [`SlotMap::remove`] and [`SlotMap::drain`]
+    /// still see the removed values, which are overwritten the next time the
+    /// map inserts or removes an item, once those references are gone.
+    /// Clearing the map overwrites values right away. `None` turns poisoning
+    /// off
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey, (), u32>::new();
+    /// map.set_poison_on_remove(Some(|value| *value = 0xDEADBEEF));
+    ///
+    /// let key = map.insert((), 5);
+    /// assert_eq!(Some(&mut 5), map.remove(&key));
+    /// ```
+    #[cfg(feature = "poison-on-remove")]
+    pub fn set_poison_on_remove(&mut self, poison: Option<fn(&mut T)>) {
+        self.inner.poison = poison.map(Poison::With);
+    }
+
+    /// Overwrite every byte of every removed value with the given byte, like
+    /// [`SlotMap::set_poison_on_remove`]. A pattern like `0xA5` stands out in
+    /// integers, floats, and debugger memory views alike
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey, (), [u16; 2]>::new();
+    ///
+    /// // Safety - Every bit pattern is a valid array of integers
+    /// unsafe { map.poison_on_remove_with_byte(0xA5) };
+    ///
+    /// let key = map.insert((), [1, 2]);
+    /// assert_eq!(Some(&mut [1, 2]), map.remove(&key));
+    /// ```
+    ///
+    /// # Safety
+    /// A value with every byte set to the given byte must be a valid `T`.
+    /// That holds for integers, floats, and arrays and plain structs of them,
+    /// but not for types like `bool`, `char`, references, or enums
+    #[cfg(feature = "poison-on-remove")]
+    pub unsafe fn poison_on_remove_with_byte(&mut self, byte: u8)
+    where
+        T: Copy,
+    {
+        self.inner.poison = Some(Poison::Bytes(byte));
+    }
+
+    /// Poison the values of slots vacated since this was last called, which
+    /// the caller may have been reading until now. Slots that were filled
+    /// again since are left alone
+    #[cfg(feature = "poison-on-remove")]
+    fn poison_vacated(&mut self) {
+        let pending = std::mem::take(&mut self.inner.poison_pending);
+
+        let Some(poison) = self.inner.poison else {
+            return;
+        };
+
+        for vacated in pending {
+            if let Some((key_data, value)) =
+                self.inner.slots.get_existing_slot_mut(&vacated)
+            {
+                if key_data.generation == vacated.generation {
+                    poison.apply(value);
+                }
+            }
         }
     }
 
+    /// Overwrite every value with the default value as it's removed. See
+    /// [`SlotMap::set_poison_on_remove`]
+    #[cfg(feature = "poison-on-remove")]
+    pub fn poison_on_remove_with_default(&mut self)
+    where
+        T: Default,
+    {
+        self.set_poison_on_remove(Some(|value| *value = T::default()));
+    }
+
+    /// Get what this map does with slots whose generations run out
+    pub fn generation_exhaustion(&self) -> GenerationExhaustion {
+        self.inner.exhaustion
+    }
+
+    /// Set what this map does with slots whose generations run out. This
+    /// only affects items removed from now on
+    pub fn set_generation_exhaustion(&mut self, policy: GenerationExhaustion) {
+        self.inner.exhaustion = policy;
+    }
+
+    /// Get the access counters of the slots that have been counted
+    #[cfg(feature = "profiling")]
+    pub(crate) fn access_counters(
+        &self,
+    ) -> &[super::slot_map_heatmap::AccessCounter] {
+        &self.inner.access_counters
+    }
+
+    /// Mutable version of access_counters
+    #[cfg(feature = "profiling")]
+    pub(crate) fn access_counters_mut(
+        &mut self,
+    ) -> &mut Vec<super::slot_map_heatmap::AccessCounter> {
+        &mut self.inner.access_counters
+    }
+
+    /// Get what this map reports if it's dropped while it still holds items
+    #[cfg(feature = "drop-report")]
+    pub fn drop_report(&self) -> super::DropReport {
+        self.inner.drop_report
+    }
+
+    /// Set what this map reports if it's dropped while it still holds items
+    #[cfg(feature = "drop-report")]
+    pub fn set_drop_report(&mut self, report: super::DropReport) {
+        self.inner.drop_report = report;
+    }
+
+    /// Get the number of items that were ever inserted into this map
+    #[cfg(feature = "drop-report")]
+    pub fn insert_count(&self) -> u64 {
+        self.inner.inserts
+    }
+
+    /// Get the number of generation wraps in each chunk that has had any
+    #[cfg(feature = "wrap-stats")]
+    pub(crate) fn wraps(&self) -> &BTreeMap<u32, u64> {
+        &self.inner.wraps
+    }
+
+    /// Get the storage of this map
+    pub(crate) fn slots(&self) -> &Slots<T> {
+        &self.inner.slots
+    }
+
+    /// Consume this map, producing every initialized slot in order of its
+    /// coordinates
+    pub(crate) fn into_slots(
+        mut self,
+    ) -> impl Iterator<Item = (SlotMapKeyData, T)> {
+        // The items are handed over rather than discarded, so the emptied
+        // map is left with nothing to report when it's dropped
+        self.inner.len = 0;
+        std::mem::replace(&mut self.inner.slots, Slots::new()).into_slots()
+    }
+
+    /// Get the head of the chain of open slots
+    pub(crate) fn next_open_slot(&self) -> SlotMapKeyData {
+        self.inner.next_open_slot
+    }
+
     /// Get the number of items in the slot map
     ///
     /// ```
@@ -424,6 +1523,66 @@ where
     /// assert_eq!(&SlotMapKeyData::from(0), key.borrow());
     /// ```
     pub fn insert(&mut self, pointer: P, value: T) -> K {
+        K::from((pointer, self.insert_raw(value)))
+    }
+
+    /// Insert the given item into the slot map and return its key, or give
+    /// the item back in an error if the map can't grow to hold it. Unlike
+    /// [`SlotMap::insert`], this doesn't panic when the map runs out of
+    /// coordinates for new slots, and it reports a failure to allocate a new
+    /// chunk instead of aborting. The chunk is allocated before the item is
+    /// written and kept until the current chunk fills
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey, (), usize>::new();
+    ///
+    /// let key = map.try_insert((), 10).unwrap();
+    /// assert_eq!(Some(&10), map.get(&key));
+    /// ```
+    pub fn try_insert(
+        &mut self,
+        pointer: P,
+        value: T,
+    ) -> Result<K, InsertError<T>> {
+        match self.prepare_insert() {
+            Ok(()) => Ok(self.insert(pointer, value)),
+            Err(e) => Err(e.with_value(value)),
+        }
+    }
+
+    /// Check that the next insertion can be made without running out of
+    /// coordinates or memory for a new chunk
+    fn prepare_insert(&mut self) -> Result<(), InsertError<()>> {
+        let next_slot = self.inner.next_open_slot;
+
+        if next_slot.chunk_index < self.inner.slots.current_chunk_index
+            || next_slot.index_in_chunk < self.inner.slots.current_chunk_cursor
+        {
+            // Open slots that were already written are reused without growing
+            return Ok(());
+        }
+
+        // The last slot can't be used, because there would be nothing for
+        // the next open slot to point to after it
+        if next_slot.is_last_coordinates() {
+            return Err(InsertError::ChunkIndexOverflow(()));
+        }
+
+        if next_slot.index_in_chunk as usize == SLOT_MAP_CHUNK_SIZE - 1 {
+            self.inner.slots.try_reserve_chunk()?;
+        }
+
+        Ok(())
+    }
+
+    /// Insert the given item into the slot map and return the raw key data
+    /// for the slot it was written to
+    pub(crate) fn insert_raw(&mut self, value: T) -> SlotMapKeyData {
+        #[cfg(feature = "poison-on-remove")]
+        self.poison_vacated();
+
         let next_slot = &mut self.inner.next_open_slot;
 
         let key_data = if next_slot.chunk_index
@@ -437,14 +1596,20 @@ where
                 .expect("invalid next slot pointer");
             *old_val = value;
             new_next_slot.increment_generation();
+
+            #[cfg(feature = "wrap-stats")]
+            if new_next_slot.generation == 0 {
+                *self.inner.wraps.entry(next_slot.chunk_index).or_default() +=
+                    1;
+            }
+
             new_next_slot.swap_coordinates(next_slot);
             *new_next_slot
         } else {
             let key_data = *next_slot;
-            let slot_opt =
-                self.inner.slots.get_current_chunk_slot_mut(next_slot);
-
-            *slot_opt = MaybeUninit::new((*next_slot, value));
+            self.inner
+                .slots
+                .write_current_chunk_slot(next_slot, (key_data, value));
 
             if self.inner.next_open_slot.increment_coordinates() {
                 self.inner.slots.move_current_chunk_to_filled_chunk()
@@ -456,7 +1621,160 @@ where
 
         self.inner.len += 1;
 
-        K::from((pointer, key_data))
+        #[cfg(feature = "drop-report")]
+        {
+            self.inner.inserts += 1;
+        }
+
+        #[cfg(feature = "profiling")]
+        super::slot_map_heatmap::record_write(
+            &mut self.inner.access_counters,
+            &key_data,
+        );
+
+        key_data
+    }
+
+    /// Tells if the next insertion will start a new chunk, which is when there
+    /// are no open slots to reuse and the current chunk hasn't been written
+    #[cfg(feature = "rayon")]
+    pub(crate) fn is_at_chunk_boundary(&self) -> bool {
+        self.inner.slots.current_chunk_cursor == 0
+            && self.inner.next_open_slot
+                == SlotMapKeyData::from(self.inner.slots.slot_count() as u64)
+    }
+
+    /// Add a chunk of filled slots (see [`new_filled_chunk`]) at the end of
+    /// this map. The map must be at a chunk boundary, and the chunk must have
+    /// been built for the next chunk index
+    #[cfg(feature = "rayon")]
+    pub(crate) fn push_filled_chunk(&mut self, chunk: FilledChunk<T>) {
+        assert!(
+            self.is_at_chunk_boundary(),
+            "Map is not at a chunk boundary"
+        );
+        debug_assert_eq!(
+            self.inner.slots.current_chunk_index,
+            chunk.as_chunk_ref().keys()[0].chunk_index
+        );
+
+        self.inner.slots.push_filled_chunk(chunk);
+        self.inner.len += SLOT_MAP_CHUNK_SIZE;
+
+        #[cfg(feature = "drop-report")]
+        {
+            self.inner.inserts += SLOT_MAP_CHUNK_SIZE as u64;
+        }
+        self.inner.next_open_slot =
+            SlotMapKeyData::from(self.inner.slots.slot_count() as u64);
+    }
+
+    /// Get mutable access to the initialized slots of every chunk of this map
+    pub(crate) fn chunks_mut(&mut self) -> Vec<ChunkMut<'_, T>> {
+        self.inner.slots.chunks_mut()
+    }
+
+    /// Get the key data that the next insertion into this map will produce
+    pub(crate) fn next_key_data(&self) -> SlotMapKeyData {
+        let next_slot = self.inner.next_open_slot;
+
+        match self.inner.slots.get_slot(&next_slot) {
+            Some((open_slot, _)) => {
+                let mut key_data = *open_slot;
+                key_data.increment_generation();
+
+                SlotMapKeyData {
+                    generation: key_data.generation,
+                    ..next_slot
+                }
+            }
+            None => next_slot,
+        }
+    }
+
+    /// Find key data among the given items naming a slot too far past the end
+    /// of this map for [`SlotMap::insert_all_at`] to extend it to, which is
+    /// more than a chunk's worth of slots per item
+    pub(crate) fn find_out_of_reach(
+        &self,
+        items: &[(SlotMapKeyData, T)],
+    ) -> Option<SlotMapKeyData> {
+        let max_slot_count =
+            self.inner.slots.slot_count() + items.len() * SLOT_MAP_CHUNK_SIZE;
+
+        items
+            .iter()
+            .map(|(key_data, _)| *key_data)
+            .find(|key_data| key_data.position() >= max_slot_count)
+    }
+
+    /// Write each of the given values into the slot named by its key data,
+    /// which must have a filled generation and be within reach according to
+    /// [`SlotMap::find_out_of_reach`], so that the value can be found with
+    /// exactly that key data. Storage is extended with vacant slots holding
+    /// default values if the coordinates are past the end of the map, and
+    /// filled target slots have their values and generations overwritten.
+    /// The chain of open slots is rebuilt in order of position once, after
+    /// every value is written
+    pub(crate) fn insert_all_at(&mut self, items: Vec<(SlotMapKeyData, T)>)
+    where
+        T: Default,
+    {
+        debug_assert!(items.iter().all(|(key_data, _)| key_data.is_filled()));
+        debug_assert_eq!(None, self.find_out_of_reach(&items));
+
+        let slot_count = items
+            .iter()
+            .map(|(key_data, _)| key_data.position() + 1)
+            .max()
+            .unwrap_or(0);
+
+        let mut open_slots_changed = false;
+
+        // New vacant slots each link to the slot after them, which continues
+        // the chain of open slots that previously ended at the frontier
+        while self.inner.slots.slot_count() < slot_count {
+            let mut link =
+                SlotMapKeyData::from(self.inner.slots.slot_count() as u64);
+            let _ = link.increment_coordinates();
+            link.generation = 1;
+
+            self.inner.slots.push_slot((link, T::default()));
+            open_slots_changed = true;
+        }
+
+        // Replaced values are dropped once the chain of open slots is valid
+        // again, in case dropping one of them panics
+        let mut replaced = Vec::with_capacity(items.len());
+
+        for (key_data, value) in items {
+            let (slot_key, slot_value) = self
+                .inner
+                .slots
+                .get_existing_slot_mut(&key_data)
+                .expect("target slot was just initialized");
+
+            if !slot_key.is_filled() {
+                open_slots_changed = true;
+                self.inner.len += 1;
+
+                #[cfg(feature = "drop-report")]
+                {
+                    self.inner.inserts += 1;
+                }
+            }
+
+            *slot_key = key_data;
+            replaced.push(std::mem::replace(slot_value, value));
+        }
+
+        if open_slots_changed {
+            self.relink_open_slots(|coordinates, key_data, _| {
+                !key_data.is_filled() && !key_data.is_retired_at(coordinates)
+            });
+        }
+
+        drop(replaced);
     }
 
     /// Get a reference to the item in the map that corresponds to the given key
@@ -478,7 +1796,11 @@ where
     /// assert_eq!(None, map.get(&fake_key));
     /// ```
     #[inline]
+    #[cfg_attr(feature = "debug-keys", track_caller)]
     pub fn get(&self, key: &K) -> Option<&T> {
+        #[cfg(feature = "debug-keys")]
+        self.check_stale_key("get", key.borrow());
+
         self.get_unbounded(key)
     }
 
@@ -527,13 +1849,17 @@ where
     ///
     /// assert_eq!(None, map.get_raw(&fake_key_data));
     /// ```
+    #[inline]
     pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
-        self.inner
-            .slots
-            .get_slot(key_data)
-            .filter(|slot| slot.0.is_filled())
-            .filter(|slot| slot.0.generation == key_data.generation)
-            .map(|slot| &slot.1)
+        let value = self.inner.slots.get_value(key_data)?;
+
+        #[cfg(feature = "profiling")]
+        super::slot_map_heatmap::record_read(
+            &self.inner.access_counters,
+            key_data,
+        );
+
+        Some(value)
     }
 
     /// Get a mutable reference to the item in the map that corresponds to the
@@ -559,7 +1885,12 @@ where
     ///
     /// assert_eq!(None, map.get_mut(&fake_key));
     /// ```
+    #[inline]
+    #[cfg_attr(feature = "debug-keys", track_caller)]
     pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        #[cfg(feature = "debug-keys")]
+        self.check_stale_key("get_mut", key.borrow());
+
         self.get_mut_unbounded(key)
     }
 
@@ -588,18 +1919,12 @@ where
     ///
     /// assert_eq!(None, map.get_mut(&fake_key));
     /// ```
+    #[inline]
     pub fn get_mut_unbounded(
         &mut self,
         key: &impl Borrow<SlotMapKeyData>,
     ) -> Option<&mut T> {
-        let key_data = key.borrow();
-
-        self.inner
-            .slots
-            .get_existing_slot_mut(key_data)
-            .filter(|slot| slot.0.is_filled())
-            .filter(|slot| slot.0.generation == key_data.generation)
-            .map(|slot| &mut slot.1)
+        self.get_mut_raw(key.borrow())
     }
 
     /// Similar to get_unbounded_mut, but only requires to slotmap key data
@@ -624,13 +1949,17 @@ where
     ///
     /// assert_eq!(None, map.get_mut_raw(&fake_key_data));
     /// ```
+    #[inline]
     pub fn get_mut_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
-        self.inner
-            .slots
-            .get_existing_slot_mut(key_data)
-            .filter(|slot| slot.0.is_filled())
-            .filter(|slot| slot.0.generation == key_data.generation)
-            .map(|slot| &mut slot.1)
+        let value = self.inner.slots.get_value_mut(key_data)?;
+
+        #[cfg(feature = "profiling")]
+        super::slot_map_heatmap::record_write(
+            &mut self.inner.access_counters,
+            key_data,
+        );
+
+        Some(value)
     }
 
     /// Remove the item at the given index and return a mutable ref to the
@@ -649,7 +1978,12 @@ where
     ///
     /// assert_eq!(None, map.get(&key));
     /// ```
+    #[inline]
+    #[cfg_attr(feature = "debug-keys", track_caller)]
     pub fn remove(&mut self, key: &K) -> Option<&mut T> {
+        #[cfg(feature = "debug-keys")]
+        self.check_stale_key("remove", key.borrow());
+
         self.remove_unbounded(key)
     }
 
@@ -671,6 +2005,7 @@ where
     ///
     /// assert_eq!(None, map.get(&key));
     /// ```
+    #[inline]
     pub fn remove_unbounded(
         &mut self,
         key: &impl Borrow<SlotMapKeyData>,
@@ -693,16 +2028,113 @@ where
     ///
     /// assert_eq!(None, map.get(&key));
     /// ```
+    #[inline]
     pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        #[cfg(feature = "poison-on-remove")]
+        self.poison_vacated();
+
+        self.inner
+            .slots
+            .get_existing_slot_mut(key_data)
+            .filter(|(key, _)| {
+                key.generation == key_data.generation && key_data.is_filled()
+            })
+            .map(|(key, value)| {
+                self.inner.len -= 1;
+
+                #[cfg(feature = "profiling")]
+                super::slot_map_heatmap::record_write(
+                    &mut self.inner.access_counters,
+                    key,
+                );
+
+                #[cfg(feature = "poison-on-remove")]
+                let coordinates = *key;
+
+                vacate_slot(
+                    key,
+                    &mut self.inner.next_open_slot,
+                    self.inner.exhaustion,
+                );
+
+                #[cfg(feature = "poison-on-remove")]
+                if self.inner.poison.is_some() {
+                    self.inner.poison_pending.push(SlotMapKeyData {
+                        generation: key.generation,
+                        ..coordinates
+                    });
+                }
+
+                value
+            })
+    }
+
+    /// Get the item in the slot at the given key data's coordinates if that
+    /// slot is filled, whatever generation it's in
+    #[inline]
+    pub(crate) fn get_ignoring_generation(
+        &self,
+        key_data: &SlotMapKeyData,
+    ) -> Option<&T> {
+        self.inner
+            .slots
+            .get_slot(key_data)
+            .filter(|slot| slot.0.is_filled())
+            .map(|slot| slot.1)
+    }
+
+    /// Mutable version of get_ignoring_generation
+    #[inline]
+    pub(crate) fn get_mut_ignoring_generation(
+        &mut self,
+        key_data: &SlotMapKeyData,
+    ) -> Option<&mut T> {
+        self.inner
+            .slots
+            .get_existing_slot_mut(key_data)
+            .filter(|slot| slot.0.is_filled())
+            .map(|slot| slot.1)
+    }
+
+    /// Remove the item in the slot at the given key data's coordinates if
+    /// that slot is filled, whatever generation it's in
+    pub(crate) fn remove_ignoring_generation(
+        &mut self,
+        key_data: &SlotMapKeyData,
+    ) -> Option<&mut T> {
+        #[cfg(feature = "poison-on-remove")]
+        self.poison_vacated();
+
         self.inner
             .slots
             .get_existing_slot_mut(key_data)
             .filter(|(key, _)| key.is_filled())
-            .filter(|(key, _)| key.generation == key_data.generation)
             .map(|(key, value)| {
                 self.inner.len -= 1;
-                key.increment_generation();
-                key.swap_coordinates(&mut self.inner.next_open_slot);
+
+                #[cfg(feature = "profiling")]
+                super::slot_map_heatmap::record_write(
+                    &mut self.inner.access_counters,
+                    key,
+                );
+
+                #[cfg(feature = "poison-on-remove")]
+                let coordinates = *key;
+
+                vacate_slot(
+                    key,
+                    &mut self.inner.next_open_slot,
+                    self.inner.exhaustion,
+                );
+
+                #[cfg(feature = "poison-on-remove")]
+                if self.inner.poison.is_some() {
+                    self.inner.poison_pending.push(SlotMapKeyData {
+                        generation: key.generation,
+                        ..coordinates
+                    });
+                }
+
                 value
             })
     }
@@ -776,20 +2208,25 @@ where
     ///
     /// assert!(!map.contains_key_raw(&fake_key_data));
     /// ```
+    #[inline]
     pub fn contains_key_raw(&self, key_data: &SlotMapKeyData) -> bool {
-        self.inner
-            .slots
-            .get_slot(key_data)
-            .filter(|(existing_key, _)| {
-                existing_key.generation == key_data.generation
-            })
-            .is_some()
+        self.inner.slots.get_value(key_data).is_some()
     }
 
     /// Remove all items from this map and process them one-by-one
     pub fn drain(&mut self) -> impl Iterator<Item = &mut T> {
+        #[cfg(feature = "poison-on-remove")]
+        self.poison_vacated();
+
         let len = &mut self.inner.len;
         let next_open_slot = &mut self.inner.next_open_slot;
+        let exhaustion = self.inner.exhaustion;
+        #[cfg(feature = "poison-on-remove")]
+        let mut poison_pending = self
+            .inner
+            .poison
+            .is_some()
+            .then_some(&mut self.inner.poison_pending);
 
         Drain {
             inner: self
@@ -800,8 +2237,18 @@ where
                 .map(move |(key, val)| {
                     *len -= 1;
 
-                    key.increment_generation();
-                    next_open_slot.swap_coordinates(key);
+                    #[cfg(feature = "poison-on-remove")]
+                    let coordinates = *key;
+
+                    vacate_slot(key, next_open_slot, exhaustion);
+
+                    #[cfg(feature = "poison-on-remove")]
+                    if let Some(pending) = &mut poison_pending {
+                        pending.push(SlotMapKeyData {
+                            generation: key.generation,
+                            ..coordinates
+                        });
+                    }
 
                     val
                 }),
@@ -809,12 +2256,270 @@ where
         }
     }
 
-    /// Clears all the values in the slot map.  This can be a memory intensive
-    /// operation because we will have to write information for every non-empty
-    /// slot into the queue of slots that can now be used
-    #[inline]
+    /// Clears all the values in the slot map. Like removal, this leaves the
+    /// values in their slots until the slots are reused. Every slot is
+    /// visited once in order of position, and the chain of open slots is
+    /// rebuilt in that order, so the slots nearest the start of the map are
+    /// reused first
     pub fn clear(&mut self) {
-        let _ = self.drain();
+        #[cfg(feature = "poison-on-remove")]
+        self.poison_vacated();
+
+        let exhaustion = self.inner.exhaustion;
+        #[cfg(feature = "poison-on-remove")]
+        let poison = self.inner.poison;
+
+        self.relink_open_slots(|coordinates, key_data, _value| {
+            if !key_data.is_filled() {
+                return !key_data.is_retired_at(coordinates);
+            }
+
+            let removed = *key_data;
+            key_data.increment_generation();
+
+            #[cfg(feature = "poison-on-remove")]
+            if let Some(poison) = poison {
+                poison.apply(_value);
+            }
+
+            if key_data.generation != MAX_GENERATION {
+                return true;
+            }
+
+            match exhaustion {
+                GenerationExhaustion::Wrap => true,
+                GenerationExhaustion::Retire => false,
+                GenerationExhaustion::Notify(notify) => {
+                    notify(removed);
+                    true
+                }
+            }
+        });
+
+        self.inner.len = 0;
+    }
+
+    /// Put this map into a canonical form without changing which keys are
+    /// valid or what they refer to. Values left behind in vacant slots are
+    /// replaced with default values, and the chain of open slots is relinked
+    /// in order of position. Two maps with the same slots, generations, and
+    /// live values are identical in every respect after canonicalization, so
+    /// they serialize to the same bytes and have the same digest regardless
+    /// of the order values were removed in
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut left = SlotMap::<TestKey,(),usize>::new();
+    /// let keys = (0..3).map(|i| left.insert((), i)).collect::<Vec<_>>();
+    /// let mut right = left.clone();
+    ///
+    /// let _ = left.remove(&keys[0]);
+    /// let _ = left.remove(&keys[2]);
+    /// let _ = right.remove(&keys[2]);
+    /// let _ = right.remove(&keys[0]);
+    ///
+    /// assert_ne!(left.state_digest(), right.state_digest());
+    ///
+    /// left.canonicalize();
+    /// right.canonicalize();
+    ///
+    /// assert_eq!(left.state_digest(), right.state_digest());
+    /// assert_eq!(Some(&1), left.get(&keys[1]));
+    /// ```
+    pub fn canonicalize(&mut self)
+    where
+        T: Default,
+    {
+        self.relink_open_slots(|coordinates, key_data, value| {
+            if key_data.is_filled() {
+                return false;
+            }
+
+            *value = T::default();
+
+            !key_data.is_retired_at(coordinates)
+        });
+    }
+
+    /// Relink the chain of open slots in order of position. The given
+    /// function is called first with the coordinates, key data, and value of
+    /// every slot, and tells if the slot is open. Slots that aren't are left
+    /// out of the chain
+    fn relink_open_slots(
+        &mut self,
+        mut visit: impl FnMut(&SlotMapKeyData, &mut SlotMapKeyData, &mut T) -> bool,
+    ) {
+        let frontier =
+            SlotMapKeyData::from(self.inner.slots.slot_count() as u64);
+
+        let mut head = None;
+        let mut previous: Option<&mut SlotMapKeyData> = None;
+
+        for (coordinates, (key_data, value)) in self.inner.slots.iter_mut_raw()
+        {
+            if !visit(&coordinates, key_data, value) {
+                continue;
+            }
+
+            let coordinates = SlotMapKeyData {
+                generation: 0,
+                ..coordinates
+            };
+
+            match previous.take() {
+                Some(link) => {
+                    *link = SlotMapKeyData {
+                        generation: link.generation,
+                        ..coordinates
+                    }
+                }
+                None => head = Some(coordinates),
+            }
+
+            previous = Some(key_data);
+        }
+
+        if let Some(link) = previous {
+            *link = SlotMapKeyData {
+                generation: link.generation,
+                ..frontier
+            };
+        }
+
+        self.inner.next_open_slot = head.unwrap_or(frontier);
+    }
+
+    /// Move items into the open slots nearest the start of the map until the
+    /// items fill the first slots without gaps, leaving every open slot after
+    /// them. The given function is called with the old and new key data of
+    /// every item that moved, in order of the items' old positions, and keys
+    /// of items that didn't move stay valid. Moved items take the next
+    /// generation of the open slot they move into, and the slots they leave
+    /// keep theirs, so no key from before compaction matches an item inserted
+    /// afterwards. The slots after the items are kept rather than freed, and
+    /// are the first to be reused, in order of position. Slots retired by
+    /// [`GenerationExhaustion::Retire`] are never filled, so items are packed
+    /// around them and they stay where they are
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # use std::borrow::Borrow;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey,(),&'static str>::new();
+    ///
+    /// let first = map.insert((), "first");
+    /// let second = map.insert((), "second");
+    /// let third = map.insert((), "third");
+    /// let _ = map.remove(&first);
+    ///
+    /// let mut moves = Vec::new();
+    /// map.compact(|old, new| moves.push((old, new)));
+    ///
+    /// assert_eq!(1, moves.len());
+    /// assert_eq!(Borrow::<SlotMapKeyData>::borrow(&third), &moves[0].0);
+    /// assert_eq!(Some(&"third"), map.get_raw(&moves[0].1));
+    /// assert_eq!(Some(&"second"), map.get(&second));
+    /// assert_eq!(None, map.get(&first));
+    /// ```
+    pub fn compact(
+        &mut self,
+        mut remap: impl FnMut(SlotMapKeyData, SlotMapKeyData),
+    ) {
+        let moves = self.plan_compaction();
+        let slots = self.compaction_slots();
+
+        for (from, to) in &moves {
+            // Safety - Moves are made one at a time
+            unsafe { slots.move_item(from, to) };
+        }
+
+        self.vacate_compacted(&moves);
+
+        for (from, to) in moves {
+            remap(from, to);
+        }
+    }
+
+    /// Plan the moves that compact this map, pairing the key data of each
+    /// item after the first `len` slots that aren't retired with the key data
+    /// it will have in the open slot among them that it moves to. Moves are in
+    /// order of the items' positions, and the open slots are filled in order
+    /// of position
+    pub(crate) fn plan_compaction(
+        &self,
+    ) -> Vec<(SlotMapKeyData, SlotMapKeyData)> {
+        let len = self.inner.len;
+        let mut usable = 0;
+        let mut open_slots = Vec::new();
+        let mut items = Vec::new();
+
+        for (coordinates, (key_data, _)) in self.inner.slots.iter_raw() {
+            // Retired slots can never take an item again
+            if key_data.is_retired_at(&coordinates) {
+                continue;
+            }
+
+            if usable < len && !key_data.is_filled() {
+                let mut new_key_data = coordinates;
+                new_key_data.increment_generation();
+                open_slots.push(new_key_data);
+            } else if usable >= len && key_data.is_filled() {
+                items.push(coordinates);
+            }
+
+            usable += 1;
+        }
+
+        debug_assert_eq!(open_slots.len(), items.len());
+
+        items.into_iter().zip(open_slots).collect()
+    }
+
+    /// Get raw access to the initialized slots of this map for making the
+    /// planned compaction moves
+    pub(crate) fn compaction_slots(&mut self) -> CompactionSlots<'_, T> {
+        CompactionSlots {
+            chunks: self
+                .inner
+                .slots
+                .chunks_mut()
+                .into_iter()
+                .map(|chunk| {
+                    let (keys, values) = chunk.into_parts();
+                    (keys.as_mut_ptr(), values.as_mut_ptr())
+                })
+                .collect(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Vacate the slots items were moved out of once the given planned
+    /// compaction moves have been made, then relink the open slots, which are
+    /// now all after the first `len`, in order of position. The vacated slots
+    /// keep their generations, so keys to the items' old positions never
+    /// match items inserted there later
+    pub(crate) fn vacate_compacted(
+        &mut self,
+        moves: &[(SlotMapKeyData, SlotMapKeyData)],
+    ) {
+        let exhaustion = self.inner.exhaustion;
+
+        // The chain of open slots is rebuilt afterwards, so the vacated slots
+        // are only linked to each other here
+        let mut scratch_chain = SlotMapKeyData::default();
+
+        for (from, _) in moves {
+            if let Some((key_data, _)) =
+                self.inner.slots.get_existing_slot_mut(from)
+            {
+                vacate_slot(key_data, &mut scratch_chain, exhaustion);
+            }
+        }
+
+        self.relink_open_slots(|coordinates, key_data, _| {
+            !key_data.is_filled() && !key_data.is_retired_at(coordinates)
+        });
     }
 
     /// Get an iterator over keys and values given a way to get the pointer from
@@ -828,7 +2533,7 @@ where
         F: FnMut(&T) -> P,
     {
         self.iter_raw().map(move |(key_data, v)| {
-            (K::from(((&mut pointer_finder)(v), key_data)), v)
+            (K::from((pointer_finder(v), key_data)), v)
         })
     }
 
@@ -843,13 +2548,17 @@ where
         F: FnMut(&T) -> P,
     {
         self.iter_mut_raw().map(move |(key_data, v)| {
-            (K::from(((&mut pointer_finder)(v), key_data)), v)
+            (K::from((pointer_finder(v), key_data)), v)
         })
     }
 
     /// Create an iterator over all raw key data and values for items present
     /// in the slot map
     pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        // Every slot is checked with a plain filter, which the compiler turns
+        // into a branch free loop. Finding the filled slots a block at a time
+        // with bit masks and visiting only those was measured to be slower at
+        // every fill rate, because walking the set bits is sequential
         self.inner
             .slots
             .iter_raw()
@@ -869,27 +2578,445 @@ where
             .map(|(key_data, (_, value))| (key_data, value))
     }
 
-    /// Create an iterator over all items in the items in the map
-    pub fn values(&self) -> impl Iterator<Item = &T> {
-        self.inner
-            .slots
-            .values()
-            .filter(|(key, _)| key.is_filled())
-            .map(|(_, value)| value)
+    /// Create an iterator over all items in the items in the map
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.inner
+            .slots
+            .values()
+            .filter(|(key, _)| key.is_filled())
+            .map(|(_, value)| value)
+    }
+
+    /// Construct an iterator over all the values in the slot map as mutable
+    /// references
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.inner
+            .slots
+            .values_mut()
+            .filter(|(key, _)| key.is_filled())
+            .map(|(_, value)| value)
+    }
+
+    /// Create a writer that exposes the complete state of this map one chunk
+    /// at a time. Each chunk can be encoded and written out on its own, so
+    /// even very large maps can be persisted without building the whole
+    /// encoded form in memory. The map can be rebuilt with a
+    /// [`SnapshotBuilder`](crate::SnapshotBuilder)
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey,(),usize>::new();
+    ///
+    /// let keys = (0..1000).map(|i| map.insert((), i)).collect::<Vec<_>>();
+    /// let _ = map.remove(&keys[10]);
+    ///
+    /// let writer = map.snapshot_writer();
+    /// let mut builder = SnapshotBuilder::new(writer.header());
+    ///
+    /// for chunk in writer {
+    ///     builder.push_chunk(chunk.slots().map(|(k, v)| (k, *v))).unwrap();
+    /// }
+    ///
+    /// let copy: SlotMap<TestKey,(),usize> = builder.build().unwrap();
+    ///
+    /// assert_eq!(999, copy.len());
+    /// assert_eq!(None, copy.get(&keys[10]));
+    /// assert_eq!(Some(&11), copy.get(&keys[11]));
+    /// ```
+    pub fn snapshot_writer(&self) -> SnapshotWriter<'_, T> {
+        SnapshotWriter::new(
+            &self.inner.slots,
+            SnapshotHeader {
+                len: self.inner.len,
+                next_open_slot: self.inner.next_open_slot,
+                chunk_count: self.inner.slots.chunk_count(),
+                value_version: 0,
+            },
+        )
+    }
+
+    /// Verify that the bookkeeping of this map is consistent: every filled
+    /// slot holds its own coordinates, every vacant slot is reachable from
+    /// the chain of open slots exactly once, the chain never enters a filled
+    /// slot, the next open slot is in bounds with a filled generation, and
+    /// the length matches the number of filled slots. The first violation
+    /// found is returned with the coordinates of the slot involved. This is
+    /// the same check a map rebuilt from a snapshot goes through
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey,(),usize>::new();
+    ///
+    /// let keys = (0..1000).map(|i| map.insert((), i)).collect::<Vec<_>>();
+    /// keys.iter().step_by(3).for_each(|k| { let _ = map.remove(k); });
+    ///
+    /// assert_eq!(Ok(()), map.check_invariants());
+    /// ```
+    #[cfg(any(test, feature = "invariants"))]
+    pub fn check_invariants(&self) -> Result<(), super::SnapshotError> {
+        super::slot_map_snapshot::validate(
+            &self.inner.slots,
+            &SnapshotHeader {
+                len: self.inner.len,
+                next_open_slot: self.inner.next_open_slot,
+                chunk_count: self.inner.slots.chunk_count(),
+                value_version: 0,
+            },
+        )
+    }
+
+    /// Consume this map, producing its values densely packed along with a table
+    /// recording the key each value had in this map and the key it will have
+    /// when the export is loaded with [`SlotMap::import`]. Values in vacant
+    /// slots are dropped
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # use std::borrow::Borrow;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey,(),&'static str>::new();
+    ///
+    /// let first = map.insert((), "first");
+    /// let second = map.insert((), "second");
+    /// let _ = map.remove(&first);
+    ///
+    /// let export = map.export();
+    /// assert_eq!(&["second"], export.values());
+    ///
+    /// let new_key_data = export.remap_key(second.borrow()).unwrap();
+    /// let new_map = SlotMap::<TestKey,(),&'static str>::import(export);
+    ///
+    /// assert_eq!(Some(&"second"), new_map.get_raw(&new_key_data));
+    /// ```
+    pub fn export(self) -> SlotMapExport<T> {
+        let mut values = Vec::with_capacity(self.inner.len);
+        let mut remap = Vec::with_capacity(self.inner.len);
+
+        for (key_data, value) in self.into_slots() {
+            if key_data.is_filled() {
+                remap.push((
+                    key_data,
+                    SlotMapKeyData::from(values.len() as u64),
+                ));
+                values.push(value);
+            }
+        }
+
+        SlotMapExport::new(values, remap)
+    }
+
+    /// Create a map holding each of the given items in the slot named by its
+    /// key data, so that exactly that key data finds it. Slots without an
+    /// item are left vacant holding default values, linked into the chain of
+    /// open slots in order of position. The map may have at most a chunk's
+    /// worth of slots for every item, so a single stray key can't make it
+    /// allocate an unbounded number of vacant slots
+    pub(crate) fn from_filled_slots(
+        mut items: Vec<(SlotMapKeyData, T)>,
+    ) -> Result<SlotMap<K, P, T>, FilledSlotsError>
+    where
+        T: Default,
+    {
+        items.sort_unstable_by_key(|(key_data, _)| key_data.position());
+
+        for pair in items.windows(2) {
+            if pair[0].0.position() == pair[1].0.position() {
+                return Err(FilledSlotsError::Collision(pair[1].0));
+            }
+        }
+
+        if let Some((key_data, _)) = items.iter().find(|(k, _)| !k.is_filled())
+        {
+            return Err(FilledSlotsError::VacantGeneration(*key_data));
+        }
+
+        let len = items.len();
+
+        if let Some((key_data, _)) = items
+            .last()
+            .filter(|(k, _)| k.position() >= len * SLOT_MAP_CHUNK_SIZE)
+        {
+            return Err(FilledSlotsError::OutOfRange(*key_data));
+        }
+        let slot_count = items
+            .last()
+            .map_or(0, |(key_data, _)| key_data.position() + 1);
+
+        // Every vacant slot links to the next vacant slot, and the last one to
+        // the end of the map
+        let mut vacant = Vec::with_capacity(slot_count - len);
+        let mut filled = items.iter().map(|(k, _)| k.position()).peekable();
+
+        for position in 0..slot_count {
+            if filled.next_if_eq(&position).is_none() {
+                vacant.push(position);
+            }
+        }
+
+        let link_to = |vacant_index: usize| {
+            SlotMapKeyData::from(
+                vacant.get(vacant_index).copied().unwrap_or(slot_count) as u64,
+            )
+        };
+
+        let mut slots = Slots::new();
+        let mut items = items.into_iter().peekable();
+        let mut vacant_index = 0;
+
+        for position in 0..slot_count {
+            match items.next_if(|(key_data, _)| key_data.position() == position)
+            {
+                Some(slot) => slots.push_slot(slot),
+                None => {
+                    vacant_index += 1;
+
+                    let link = SlotMapKeyData {
+                        generation: 1,
+                        ..link_to(vacant_index)
+                    };
+
+                    slots.push_slot((link, T::default()));
+                }
+            }
+        }
+
+        Ok(SlotMap::from_raw_state(slots, link_to(0), len))
+    }
+
+    /// Create a compact map from the values of an export. The value at each
+    /// position in the export is stored under the new key recorded for it in
+    /// the export's remap table
+    pub fn import(export: SlotMapExport<T>) -> SlotMap<K, P, T> {
+        let mut slots = Slots::new();
+        let values = export.into_values();
+        let len = values.len();
+
+        for (index, value) in values.into_iter().enumerate() {
+            slots.push_slot((SlotMapKeyData::from(index as u64), value));
+        }
+
+        SlotMap::from_raw_state(slots, SlotMapKeyData::from(len as u64), len)
+    }
+
+    /// Exchange the contents of this map with those of the given map without
+    /// moving any items. Only the slots, and what is recorded about them,
+    /// are exchanged, so each map keeps its own stale key, poisoning,
+    /// exhaustion, and drop report settings. Keys handed out by either map
+    /// are not tied to the map that produced them, so after the swap a key
+    /// from the old contents may refer to an unrelated item in the new
+    /// contents. [`SlotMap::swap_contents_bumped`] rules that out
+    pub fn swap_contents(&mut self, other: &mut SlotMap<K, P, T>) {
+        let (this, other) = (&mut self.inner, &mut other.inner);
+
+        swap(&mut this.slots, &mut other.slots);
+        swap(&mut this.next_open_slot, &mut other.next_open_slot);
+        swap(&mut this.len, &mut other.len);
+
+        #[cfg(feature = "wrap-stats")]
+        swap(&mut this.wraps, &mut other.wraps);
+
+        #[cfg(feature = "poison-on-remove")]
+        swap(&mut this.poison_pending, &mut other.poison_pending);
+
+        #[cfg(feature = "profiling")]
+        swap(&mut this.access_counters, &mut other.access_counters);
+
+        #[cfg(feature = "drop-report")]
+        swap(&mut this.inserts, &mut other.inserts);
+    }
+
+    /// Exchange the contents of this map with those of the given map like
+    /// [`SlotMap::swap_contents`], then raise the generations of each map's
+    /// new contents above those of its old contents, so keys into the old
+    /// contents of either map never match items in its new contents, except
+    /// in slots whose generations have run out. The given functions are
+    /// called with the old and new key data of every item whose key changed,
+    /// first for the items now in this map and then for the items now in the
+    /// given map. This takes time in proportion to the number of slots
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut left = SlotMap::<TestKey, (), &'static str>::new();
+    /// let mut right = SlotMap::<TestKey, (), &'static str>::new();
+    /// let left_key = left.insert((), "left");
+    /// let right_key = right.insert((), "right");
+    ///
+    /// let mut moved_left = Vec::new();
+    /// let mut moved_right = Vec::new();
+    /// left.swap_contents_bumped(
+    ///     &mut right,
+    ///     |old, new| moved_left.push((old, new)),
+    ///     |old, new| moved_right.push((old, new)),
+    /// );
+    ///
+    /// // Both keys named the same slot, so neither finds the other's item
+    /// assert_eq!(None, left.get(&left_key));
+    /// assert_eq!(None, right.get(&right_key));
+    /// assert_eq!(Some(&"right"), left.get_raw(&moved_left[0].1));
+    /// assert_eq!(Some(&"left"), right.get_raw(&moved_right[0].1));
+    /// ```
+    pub fn swap_contents_bumped(
+        &mut self,
+        other: &mut SlotMap<K, P, T>,
+        remap_self: impl FnMut(SlotMapKeyData, SlotMapKeyData),
+        remap_other: impl FnMut(SlotMapKeyData, SlotMapKeyData),
+    ) where
+        T: Default,
+    {
+        let generations = self.slot_generations();
+        let other_generations = other.slot_generations();
+
+        self.swap_contents(other);
+        self.raise_generations_above(&generations, remap_self);
+        other.raise_generations_above(&other_generations, remap_other);
+    }
+
+    /// Get the generation of every slot, filled or vacant, in order of
+    /// position
+    fn slot_generations(&self) -> Vec<u32> {
+        self.inner
+            .slots
+            .values()
+            .map(|(key_data, _)| key_data.generation)
+            .collect()
+    }
+
+    /// Raise the generation of every slot of this map above the given
+    /// generation for its position, keeping filled slots filled and vacant
+    /// slots vacant, and add vacant slots until there is a slot for every
+    /// given generation. Keys with the given generations or older then never
+    /// match items in this map, except in slots whose generations have run
+    /// out. The given function is called with the old and new key data of
+    /// every item whose generation was raised
+    fn raise_generations_above(
+        &mut self,
+        generations: &[u32],
+        mut remap: impl FnMut(SlotMapKeyData, SlotMapKeyData),
+    ) where
+        T: Default,
+    {
+        // The lowest generation past the other one with the parity of the
+        // given one, unless that would reach the last generation
+        let raise = |generation: u32, other: u32| {
+            let raised = other + 1 + ((other + 1 + generation) & 1);
+
+            if generation > other || raised >= MAX_GENERATION {
+                generation
+            } else {
+                raised
+            }
+        };
+
+        for ((_, (key_data, _)), other) in
+            self.inner.slots.iter_mut_raw().zip(generations)
+        {
+            let generation = raise(key_data.generation, *other);
+
+            if generation != key_data.generation {
+                let old = *key_data;
+                key_data.generation = generation;
+
+                if key_data.is_filled() {
+                    remap(old, *key_data);
+                }
+            }
+        }
+
+        // New vacant slots each link to the slot after them, which continues
+        // the chain of open slots that previously ended at the frontier
+        for other in generations.iter().skip(self.inner.slots.slot_count()) {
+            let mut link =
+                SlotMapKeyData::from(self.inner.slots.slot_count() as u64);
+            let _ = link.increment_coordinates();
+            link.generation = raise(1, *other);
+
+            self.inner.slots.push_slot((link, T::default()));
+        }
+    }
+
+    /// Replace the contents of this map with the given map without moving any
+    /// items, returning the old contents. This is useful for switching over
+    /// to a map that was rebuilt elsewhere. Like with
+    /// [`SlotMap::swap_contents`], each map keeps its own settings, and keys
+    /// into the old contents may refer to unrelated items in the new
+    /// contents. [`SlotMap::replace_all_bumped`] rules that out
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey,(),&'static str>::new();
+    /// let old_key = map.insert((), "old");
+    ///
+    /// let mut rebuilt = SlotMap::new();
+    /// let _ = rebuilt.insert((), "skipped");
+    /// let new_key = rebuilt.insert((), "new");
+    ///
+    /// let old = map.replace_all(rebuilt);
+    ///
+    /// assert_eq!(Some(&"new"), map.get(&new_key));
+    /// assert_eq!(Some(&"old"), old.get(&old_key));
+    /// ```
+    pub fn replace_all(
+        &mut self,
+        mut new: SlotMap<K, P, T>,
+    ) -> SlotMap<K, P, T> {
+        self.swap_contents(&mut new);
+        new
     }
 
-    /// Construct an iterator over all the values in the slot map as mutable
-    /// references
-    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
-        self.inner
-            .slots
-            .values_mut()
-            .filter(|(key, _)| key.is_filled())
-            .map(|(_, value)| value)
+    /// Replace the contents of this map like [`SlotMap::replace_all`], then
+    /// raise the generations of the new contents above those of the old
+    /// contents, so keys into the old contents never match items in the new
+    /// contents, except in slots whose generations have run out. The given
+    /// function is called with the old and new key data of every item of the
+    /// new contents whose key changed. This takes time in proportion to the
+    /// number of slots
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey,(),&'static str>::new();
+    /// let old_key = map.insert((), "old");
+    ///
+    /// let mut rebuilt = SlotMap::new();
+    /// let first_key = rebuilt.insert((), "first");
+    /// let second_key = rebuilt.insert((), "second");
+    ///
+    /// let mut moved = Vec::new();
+    /// let _ = map.replace_all_bumped(rebuilt, |old, new| moved.push((old, new)));
+    ///
+    /// // The old key and the rebuilt map's first key named the same slot
+    /// assert_eq!(None, map.get(&old_key));
+    /// assert_eq!(None, map.get(&first_key));
+    /// assert_eq!(1, moved.len());
+    /// assert_eq!(Some(&"first"), map.get_raw(&moved[0].1));
+    /// assert_eq!(Some(&"second"), map.get(&second_key));
+    /// ```
+    pub fn replace_all_bumped(
+        &mut self,
+        mut new: SlotMap<K, P, T>,
+        remap: impl FnMut(SlotMapKeyData, SlotMapKeyData),
+    ) -> SlotMap<K, P, T>
+    where
+        T: Default,
+    {
+        let generations = self.slot_generations();
+
+        self.swap_contents(&mut new);
+        self.raise_generations_above(&generations, remap);
+        new
     }
 
     /// Create a new map that has the same structure as this one, but with the
-    /// values mapped with the given closure
+    /// values mapped with the given closure. The new map keeps this map's
+    /// stale key and exhaustion policies, drop report setting, counts of
+    /// inserts, generation wraps, and slot accesses. Poisoning is set up for
+    /// values of one type, so it is the one setting the new map starts
+    /// without, and values removed from this map but not yet poisoned are
+    /// copied as they are
     pub fn map<F, R>(&self, mapper: F) -> SlotMap<K, P, R>
     where
         F: FnMut(&T) -> R,
@@ -899,6 +3026,21 @@ where
                 slots: self.inner.slots.map(mapper),
                 len: self.inner.len,
                 next_open_slot: self.inner.next_open_slot,
+                #[cfg(feature = "debug-keys")]
+                stale_key_policy: self.inner.stale_key_policy,
+                #[cfg(feature = "poison-on-remove")]
+                poison: None,
+                #[cfg(feature = "poison-on-remove")]
+                poison_pending: Vec::new(),
+                exhaustion: self.inner.exhaustion,
+                #[cfg(feature = "wrap-stats")]
+                wraps: self.inner.wraps.clone(),
+                #[cfg(feature = "profiling")]
+                access_counters: self.inner.access_counters.clone(),
+                #[cfg(feature = "drop-report")]
+                drop_report: self.inner.drop_report,
+                #[cfg(feature = "drop-report")]
+                inserts: self.inner.inserts,
             },
             _phantom: Default::default(),
         }
@@ -910,8 +3052,21 @@ where
     K: SlotMapKey<P>,
     T: Clone,
 {
+    /// Copy the map along with all of its settings. Unlike with
+    /// [`SlotMap::map`], the copy is poisoned the same way as this map,
+    /// including values removed from this map but not yet poisoned
     fn clone(&self) -> Self {
-        self.map(T::clone)
+        let clone = self.map(T::clone);
+
+        #[cfg(feature = "poison-on-remove")]
+        let clone = {
+            let mut clone = clone;
+            clone.inner.poison = self.inner.poison;
+            clone.inner.poison_pending = self.inner.poison_pending.clone();
+            clone
+        };
+
+        clone
     }
 }
 
@@ -950,11 +3105,16 @@ where
 #[cfg(test)]
 mod test {
 
+    use std::cell::Cell;
+    use std::collections::HashMap;
+    use std::panic::AssertUnwindSafe;
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
 
     use super::*;
     use rand::seq::SliceRandom;
-    use rand::thread_rng;
+    use rand::{thread_rng, Rng};
 
     #[derive(Debug, Hash, Clone, Copy)]
     struct TestKey(usize, SlotMapKeyData);
@@ -1125,6 +3285,26 @@ mod test {
         assert_eq!(insertions, counter);
     }
 
+    #[test]
+    fn test_filling_chunk_does_not_move_items() {
+        let mut map = create_test_map();
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE - 1)
+            .map(|i| map.insert(i, format!("{}", i)))
+            .collect::<Vec<_>>();
+        let addresses = keys
+            .iter()
+            .map(|k| map.get(k).unwrap() as *const String)
+            .collect::<Vec<_>>();
+
+        // This insertion fills the chunk, which then becomes a filled chunk
+        let _ = map.insert(0, "last".to_owned());
+
+        for (k, address) in keys.iter().zip(addresses) {
+            assert!(std::ptr::eq(address, map.get(k).unwrap()));
+        }
+    }
+
     #[test]
     fn test_clear() {
         let mut map = create_test_map();
@@ -1150,11 +3330,88 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_clear_reuses_slots_in_order() {
+        let mut map = create_test_map();
+        let mut rng = thread_rng();
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 3 + 10)
+            .map(|i| map.insert(i, format!("{}", i)))
+            .collect::<Vec<_>>();
+        for k in keys.iter().filter(|_| rng.gen_bool(0.3)) {
+            let _ = map.remove(k);
+        }
+
+        map.clear();
+
+        assert_eq!(Ok(()), map.check_invariants());
+        assert_eq!(0, map.len());
+        assert!(keys.iter().all(|k| map.get(k).is_none()));
+
+        // Every slot is open again, and they are handed out from the start
+        for (i, k) in keys.iter().enumerate() {
+            let reused = map.insert(i, format!("{}", i));
+            let (k, reused): (&SlotMapKeyData, &SlotMapKeyData) =
+                (k.borrow(), reused.borrow());
+            assert_coordinates_eq(k, reused);
+            assert_ne!(k, reused);
+        }
+
+        let next = map.insert_raw("new".to_owned());
+        assert_eq!(keys.len(), next.position());
+        assert_eq!(Ok(()), map.check_invariants());
+    }
+
     fn assert_coordinates_eq(k1: &SlotMapKeyData, k2: &SlotMapKeyData) {
         assert_eq!(k1.chunk_index, k2.chunk_index);
         assert_eq!(k1.index_in_chunk, k2.index_in_chunk);
     }
 
+    #[test]
+    fn test_canonicalize() {
+        let mut left = create_test_map();
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 2)
+            .map(|i| left.insert(i, format!("{}", i)))
+            .collect::<Vec<_>>();
+        let mut right = left.clone();
+
+        let mut removals = vec![3, SLOT_MAP_CHUNK_SIZE + 1, 0, 100];
+        removals.shuffle(&mut thread_rng());
+
+        for i in &removals {
+            let _ = left.remove(&keys[*i]);
+        }
+        for i in removals.iter().rev() {
+            let _ = right.remove(&keys[*i]);
+        }
+
+        left.canonicalize();
+        right.canonicalize();
+
+        assert_eq!(left.state_digest(), right.state_digest());
+        assert_eq!(left.next_open_slot(), SlotMapKeyData::from(0));
+
+        // Canonicalizing doesn't change keys and open slots are reused in
+        // order of position
+        for (i, k) in keys.iter().enumerate() {
+            assert_eq!(!removals.contains(&i), left.get(k).is_some());
+        }
+
+        let reused = removals
+            .iter()
+            .map(|_| {
+                let key = left.insert(0, String::new()).1;
+                key.chunk_index as usize * SLOT_MAP_CHUNK_SIZE
+                    + key.index_in_chunk as usize
+            })
+            .collect::<Vec<_>>();
+
+        let mut expected = removals.clone();
+        expected.sort_unstable();
+        assert_eq!(expected, reused);
+        assert_eq!(SLOT_MAP_CHUNK_SIZE * 2, left.slots().slot_count());
+    }
+
     #[test]
     fn test_embedded_empty_stack_consistency() {
         let mut map = create_test_map();
@@ -1179,7 +3436,7 @@ mod test {
                 keys.push(map.insert(i, format!("{}", i)));
                 assert_coordinates_eq(
                     &prev_next_slot,
-                    &map.inner
+                    map.inner
                         .slots
                         .get_slot(&keys.get(i).unwrap().1)
                         .unwrap()
@@ -1231,7 +3488,7 @@ mod test {
                     assert_coordinates_eq(&k.1, &map.inner.next_open_slot);
 
                     let cleared_slot =
-                        map.inner.slots.get_slot(&k.1).unwrap().0;
+                        *map.inner.slots.get_slot(&k.1).unwrap().0;
 
                     assert_coordinates_eq(&prev_next_slot, &cleared_slot);
 
@@ -1309,4 +3566,578 @@ mod test {
 
         assert_eq!(1, Arc::strong_count(&drop_counter));
     }
+
+    #[test]
+    fn test_swap_contents() {
+        let mut left = create_test_map();
+        let mut right = create_test_map();
+
+        let left_keys = (0..SLOT_MAP_CHUNK_SIZE + 3)
+            .map(|i| left.insert(i, format!("left {}", i)))
+            .collect::<Vec<_>>();
+        let right_key = right.insert(0, "right".to_owned());
+        let _ = left.remove(&left_keys[2]);
+
+        left.swap_contents(&mut right);
+
+        assert_eq!(1, left.len());
+        assert_eq!(Some(&"right".to_owned()), left.get(&right_key));
+        assert_eq!(left_keys.len() - 1, right.len());
+        assert_eq!(Some(&"left 3".to_owned()), right.get(&left_keys[3]));
+
+        // The open slot chain moves with the contents
+        assert_eq!(
+            right.insert(0, "reused".to_owned()).1,
+            SlotMapKeyData {
+                generation: 2,
+                ..left_keys[2].1
+            }
+        );
+
+        let old = right.replace_all(SlotMap::new());
+        assert!(right.is_empty());
+        assert_eq!(left_keys.len(), old.len());
+    }
+
+    #[test]
+    fn test_swap_contents_keeps_settings() {
+        let mut left = create_test_map();
+        let mut right = create_test_map();
+        left.set_generation_exhaustion(GenerationExhaustion::Retire);
+
+        let key = right.insert(0, "right".to_owned());
+        left.swap_contents(&mut right);
+
+        assert!(matches!(
+            left.generation_exhaustion(),
+            GenerationExhaustion::Retire
+        ));
+        assert!(matches!(
+            right.generation_exhaustion(),
+            GenerationExhaustion::Wrap
+        ));
+        assert_eq!(Some(&"right".to_owned()), left.get(&key));
+    }
+
+    #[test]
+    fn test_bumped_replace_never_matches_old_keys() {
+        let mut map = create_test_map();
+        let mut old_keys = (0..SLOT_MAP_CHUNK_SIZE * 2)
+            .map(|i| map.insert(i, format!("old {}", i)))
+            .collect::<Vec<_>>();
+
+        for i in (0..old_keys.len()).step_by(3) {
+            let _ = map.remove(&old_keys[i]);
+            old_keys.push(map.insert(0, "reinserted".to_owned()));
+        }
+
+        let mut rebuilt = create_test_map();
+        let mut new_keys = (0..SLOT_MAP_CHUNK_SIZE)
+            .map(|i| rebuilt.insert(i, format!("new {}", i)))
+            .collect::<Vec<_>>();
+        let _ = rebuilt.remove(&new_keys.swap_remove(5));
+
+        let mut moves = Vec::new();
+        let old = map.replace_all_bumped(rebuilt, |old, new| {
+            moves.push((old, new));
+        });
+
+        assert_eq!(new_keys.len(), map.len());
+        assert_eq!(new_keys.len(), moves.len());
+        assert_eq!(old.slots().slot_count(), map.slots().slot_count());
+        assert_eq!(Ok(()), map.check_invariants());
+
+        for (from, to) in &moves {
+            assert_eq!(None, map.get_raw(from));
+            assert!(map.get_raw(to).is_some());
+        }
+
+        // Old keys miss both the new contents and items inserted afterwards
+        let inserted = (0..SLOT_MAP_CHUNK_SIZE * 2)
+            .map(|i| map.insert(i, "inserted".to_owned()))
+            .collect::<Vec<_>>();
+
+        for key in &old_keys {
+            assert_eq!(None, map.get(key));
+        }
+        for key in &inserted {
+            assert_eq!(Some(&"inserted".to_owned()), map.get(key));
+        }
+    }
+
+    #[test]
+    fn test_bumped_swap_never_matches_old_keys() {
+        let mut left = create_test_map();
+        let mut right = create_test_map();
+
+        let left_keys = (0..SLOT_MAP_CHUNK_SIZE + 3)
+            .map(|i| left.insert(i, format!("left {}", i)))
+            .collect::<Vec<_>>();
+        let right_keys = (0..3)
+            .map(|i| right.insert(i, format!("right {}", i)))
+            .collect::<Vec<_>>();
+
+        let (mut into_left, mut into_right) = (0, 0);
+        left.swap_contents_bumped(
+            &mut right,
+            |_, _| into_left += 1,
+            |_, _| into_right += 1,
+        );
+
+        // Only the slots both maps had are bumped
+        assert_eq!((3, 3), (into_left, into_right));
+        assert_eq!(Ok(()), left.check_invariants());
+        assert_eq!(Ok(()), right.check_invariants());
+
+        for _ in 0..SLOT_MAP_CHUNK_SIZE * 2 {
+            let _ = left.insert(0, "inserted".to_owned());
+            let _ = right.insert(0, "inserted".to_owned());
+        }
+
+        for key in &left_keys {
+            assert_eq!(None, left.get(key));
+        }
+        for key in &right_keys {
+            assert_eq!(None, right.get(key));
+        }
+    }
+
+    // Key whose pointer can't be sent to another thread
+    define_key_type!(RcKey<Rc<usize>>);
+
+    fn assert_send<I: Send>(iter: I) -> I {
+        iter
+    }
+
+    #[test]
+    fn test_iterators_are_send() {
+        // Values that can be sent but not shared, behind keys that can't be
+        // sent at all
+        let mut map = SlotMap::<RcKey, Rc<usize>, Cell<usize>>::new();
+        let key = map.insert(Rc::new(7), Cell::new(1));
+        assert_eq!(7, *key.pointer);
+
+        let moved = std::thread::scope(|s| {
+            let values = assert_send(map.values_mut());
+            let sum = s.spawn(move || values.map(|v| v.get()).sum::<usize>());
+            sum.join().unwrap()
+        });
+        assert_eq!(1, moved);
+
+        let _ = assert_send(map.iter_mut_raw());
+        let _ = assert_send(map.iter_mut(|_| Rc::new(0)).map(|(_, v)| v));
+        let _ = assert_send(map.drain());
+        assert!(map.is_empty());
+
+        let mut shared = SlotMap::<RcKey, Rc<usize>, usize>::new();
+        let _ = shared.insert(Rc::new(0), 1);
+
+        let _ = assert_send(shared.values());
+        let _ = assert_send(shared.iter_raw());
+        let _ = assert_send(shared.values_mut());
+    }
+
+    #[test]
+    fn test_compact() {
+        for (count, removed) in [
+            (0, 0),
+            (10, 0),
+            (SLOT_MAP_CHUNK_SIZE * 3 + 20, 2),
+            (SLOT_MAP_CHUNK_SIZE * 4, 3),
+            (SLOT_MAP_CHUNK_SIZE * 2 + 1, 1),
+        ] {
+            let mut map = create_test_map();
+            let mut keys = (0..count)
+                .map(|i| map.insert(i, format!("{}", i)))
+                .collect::<Vec<_>>();
+
+            // Remove every n-th item, and everything in the last chunk
+            let stale = (0..count)
+                .rev()
+                .filter(|i| {
+                    removed > 0
+                        && (i % removed == 0
+                            || i / SLOT_MAP_CHUNK_SIZE
+                                == (count - 1) / SLOT_MAP_CHUNK_SIZE)
+                })
+                .map(|i| keys.swap_remove(i))
+                .collect::<Vec<_>>();
+
+            for key in &stale {
+                assert!(map.remove(key).is_some());
+            }
+
+            // Sharing chunks with a snapshot doesn't change the result
+            let snapshot = map.snapshot();
+
+            let mut moves = HashMap::new();
+            map.compact(|old, new| {
+                assert!(moves.insert(u64::from(old), new).is_none())
+            });
+
+            assert_eq!(keys.len(), map.len());
+            assert_eq!(count, map.slots().slot_count());
+            assert_eq!(Ok(()), map.check_invariants());
+
+            for key in &keys {
+                let new =
+                    moves.get(&u64::from(key.1)).copied().unwrap_or(key.1);
+                assert_eq!(Some(&format!("{}", key.0)), map.get_raw(&new));
+            }
+
+            for key in &stale {
+                assert_eq!(None, map.get(key));
+            }
+
+            assert_eq!(count - stale.len(), snapshot.len());
+
+            // The map keeps working, reusing the open slots after the items
+            let key = map.insert(0, "new".to_owned());
+            assert_eq!(
+                keys.len() as u64,
+                u64::from(SlotMapKeyData {
+                    generation: 0,
+                    ..key.1
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_keys_from_before_compaction_never_match() {
+        let mut map = create_test_map();
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 2)
+            .map(|i| map.insert(i, format!("{}", i)))
+            .collect::<Vec<_>>();
+
+        for key in keys.iter().step_by(2) {
+            let _ = map.remove(key);
+        }
+
+        let mut moves = Vec::new();
+        map.compact(|old, new| moves.push((old, new)));
+        assert!(!moves.is_empty());
+
+        let inserted = (0..SLOT_MAP_CHUNK_SIZE * 2)
+            .map(|i| map.insert(i, "new".to_owned()))
+            .collect::<Vec<_>>();
+        assert_eq!(Ok(()), map.check_invariants());
+
+        // Neither removed keys nor the old keys of moved items reach the
+        // items inserted into the slots they refer to
+        for key in keys.iter().step_by(2) {
+            assert_eq!(None, map.get(key));
+        }
+        for (old, new) in &moves {
+            assert_eq!(None, map.get_raw(old));
+            assert!(map.get_raw(new).is_some());
+        }
+        for key in &inserted {
+            assert_eq!(Some(&"new".to_owned()), map.get(key));
+        }
+    }
+
+    #[test]
+    fn test_check_invariants() {
+        let mut map = SlotMap::<TestKey, usize, usize>::new();
+        let mut keys = Vec::new();
+        let mut rng = thread_rng();
+
+        for i in 0..SLOT_MAP_CHUNK_SIZE * 5 {
+            keys.push(map.insert(i, i));
+
+            if i % 3 == 0 {
+                keys.shuffle(&mut rng);
+                let _ = map.remove(&keys.pop().unwrap());
+            }
+
+            assert_eq!(Ok(()), map.check_invariants());
+        }
+
+        map.compact(|_, _| {});
+        assert_eq!(Ok(()), map.check_invariants());
+
+        let corrupted = SlotMap::<TestKey, usize, usize>::from_raw_state(
+            map.inner.slots.share_all(),
+            map.inner.next_open_slot,
+            map.len() + 1,
+        );
+
+        assert_eq!(
+            Err(crate::SnapshotError::LengthMismatch {
+                declared: map.len() + 1,
+                found: map.len(),
+            }),
+            corrupted.check_invariants()
+        );
+    }
+
+    /// Value that counts the live instances of it and can be told to panic
+    /// when it's cloned or dropped
+    struct Fragile {
+        id: usize,
+        live: Arc<AtomicUsize>,
+        panic_on_drop: bool,
+    }
+
+    impl Fragile {
+        fn new(id: usize, live: &Arc<AtomicUsize>) -> Fragile {
+            let _ = live.fetch_add(1, Ordering::SeqCst);
+
+            Fragile {
+                id,
+                live: live.clone(),
+                panic_on_drop: false,
+            }
+        }
+    }
+
+    impl Clone for Fragile {
+        fn clone(&self) -> Self {
+            assert_ne!(FRAGILE_CLONE_PANIC_ID, self.id, "clone failed");
+            Fragile::new(self.id, &self.live)
+        }
+    }
+
+    impl Drop for Fragile {
+        fn drop(&mut self) {
+            let _ = self.live.fetch_sub(1, Ordering::SeqCst);
+            assert!(!self.panic_on_drop, "drop failed");
+        }
+    }
+
+    /// Id of the Fragile values that panic when cloned
+    const FRAGILE_CLONE_PANIC_ID: usize = 1_000_000;
+
+    fn create_fragile_map(
+        live: &Arc<AtomicUsize>,
+    ) -> SlotMap<TestKey, usize, Fragile> {
+        let mut map = SlotMap::new();
+
+        for i in 0..SLOT_MAP_CHUNK_SIZE * 2 + 50 {
+            let _ = map.insert(i, Fragile::new(i, live));
+        }
+
+        map
+    }
+
+    #[test]
+    fn test_panicking_map_and_clone_drop_partial_copies() {
+        // Panic in a filled chunk and in the current chunk
+        for panic_id in [SLOT_MAP_CHUNK_SIZE + 10, SLOT_MAP_CHUNK_SIZE * 2 + 20]
+        {
+            let live = Arc::new(AtomicUsize::new(0));
+            let mut map = create_fragile_map(&live);
+            let count = live.load(Ordering::SeqCst);
+
+            let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                map.map(|value| {
+                    assert_ne!(panic_id, value.id);
+                    Fragile::new(value.id, &live)
+                })
+            }));
+
+            assert!(result.is_err());
+            assert_eq!(count, live.load(Ordering::SeqCst));
+
+            map.values_mut()
+                .find(|value| value.id == panic_id)
+                .unwrap()
+                .id = FRAGILE_CLONE_PANIC_ID;
+
+            let result =
+                std::panic::catch_unwind(AssertUnwindSafe(|| map.clone()));
+
+            assert!(result.is_err());
+            assert_eq!(count, live.load(Ordering::SeqCst));
+            assert_eq!(count, map.len());
+            assert_eq!(Ok(()), map.check_invariants());
+
+            drop(map);
+            assert_eq!(0, live.load(Ordering::SeqCst));
+        }
+    }
+
+    #[test]
+    fn test_panicking_drop_drops_other_values() {
+        for panic_id in [10, SLOT_MAP_CHUNK_SIZE * 2 + 20] {
+            let live = Arc::new(AtomicUsize::new(0));
+            let mut map = create_fragile_map(&live);
+
+            map.values_mut()
+                .find(|value| value.id == panic_id)
+                .unwrap()
+                .panic_on_drop = true;
+
+            let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                drop(map);
+            }));
+
+            assert!(result.is_err());
+            assert_eq!(0, live.load(Ordering::SeqCst));
+        }
+    }
+
+    #[test]
+    fn test_panic_while_draining_leaves_map_empty() {
+        let live = Arc::new(AtomicUsize::new(0));
+        let mut map = create_fragile_map(&live);
+
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            for value in map.drain() {
+                assert_ne!(300, value.id);
+            }
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(0, map.len());
+        assert_eq!(0, map.iter_raw().count());
+        assert_eq!(Ok(()), map.check_invariants());
+
+        drop(map);
+        assert_eq!(0, live.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_try_insert_reports_exhausted_coordinates() {
+        let mut map = SlotMap::<TestKey, usize, usize>::new();
+
+        for i in 0..SLOT_MAP_CHUNK_SIZE * 3 {
+            let key = map.try_insert(i, i).unwrap();
+            assert_eq!(Some(&i), map.get(&key));
+        }
+
+        let mut empty = SlotMap::<TestKey, usize, usize>::new();
+        empty.inner.next_open_slot = SlotMapKeyData {
+            chunk_index: u32::MAX,
+            index_in_chunk: (SLOT_MAP_CHUNK_SIZE - 1) as u16,
+            generation: 0,
+        };
+
+        assert_eq!(
+            Err(InsertError::ChunkIndexOverflow(5)),
+            empty.try_insert(0, 5).map(|key| key.0)
+        );
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_try_insert_keeps_reserved_chunk() {
+        let mut map = SlotMap::<TestKey, usize, usize>::new();
+
+        for i in 0..SLOT_MAP_CHUNK_SIZE - 1 {
+            let _ = map.insert(i, i);
+        }
+
+        // The insert that fills the current chunk allocates the next one
+        // ahead of time, and that chunk then becomes the current chunk
+        // instead of another being allocated
+        map.prepare_insert().unwrap();
+        let spare = SharedChunk::as_ptr(
+            map.inner
+                .slots
+                .spare_chunk
+                .as_ref()
+                .expect("no spare chunk"),
+        );
+
+        let _ = map.try_insert(0, 0).unwrap();
+        assert!(map.inner.slots.spare_chunk.is_none());
+        assert_eq!(spare, SharedChunk::as_ptr(&map.inner.slots.current_chunk));
+
+        let key = map.try_insert(1, 1).unwrap();
+        assert_eq!(Some(&1), map.get(&key));
+        assert_eq!(Ok(()), map.check_invariants());
+    }
+
+    #[cfg(feature = "poison-on-remove")]
+    #[test]
+    fn test_poison_on_remove() {
+        let mut map = SlotMap::<TestKey, usize, usize>::new();
+        map.set_poison_on_remove(Some(|value| *value = usize::MAX));
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 2)
+            .map(|i| map.insert(i, i))
+            .collect::<Vec<_>>();
+        let slot_value =
+            |map: &SlotMap<TestKey, usize, usize>, i: usize| -> usize {
+                *map.inner.slots.values().nth(i).unwrap().1
+            };
+
+        // The caller sees removed values, which are poisoned on the next
+        // removal or insertion
+        for (i, key) in keys.iter().enumerate().step_by(3) {
+            assert_eq!(Some(i), map.remove(key).copied());
+            assert_eq!(i, slot_value(&map, i));
+        }
+
+        assert_eq!(Some(&mut 1), map.remove(&keys[1]));
+
+        for i in 0..keys.len() {
+            let expected = if i % 3 == 0 { usize::MAX } else { i };
+            assert_eq!(expected, slot_value(&map, i));
+        }
+
+        // The last removed slot is filled again before it would be poisoned,
+        // so its new value is left alone
+        let key = map.insert(1, 100);
+        assert_eq!(Some(&100), map.get(&key));
+
+        map.poison_on_remove_with_default();
+        map.clear();
+
+        assert!(map
+            .inner
+            .slots
+            .values()
+            .all(|(_, value)| *value == 0 || *value == usize::MAX));
+    }
+
+    #[cfg(feature = "poison-on-remove")]
+    #[test]
+    fn test_clone_keeps_poisoning() {
+        let mut map = SlotMap::<TestKey, usize, usize>::new();
+        map.set_poison_on_remove(Some(|value| *value = usize::MAX));
+
+        let keys = (0..3).map(|i| map.insert(i, i)).collect::<Vec<_>>();
+        let _ = map.remove(&keys[0]);
+
+        let mut clone = map.clone();
+        let _ = clone.remove(&keys[1]);
+
+        let values = clone
+            .inner
+            .slots
+            .values()
+            .map(|(_, value)| *value)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![usize::MAX, 1, 2], values);
+        assert!(map.map(|value| *value).inner.poison.is_none());
+    }
+
+    #[cfg(feature = "poison-on-remove")]
+    #[test]
+    fn test_poison_on_remove_with_byte() {
+        let mut map = SlotMap::<TestKey, usize, [u32; 3]>::new();
+
+        // Safety - Every bit pattern is a valid array of integers
+        unsafe { map.poison_on_remove_with_byte(0xA5) };
+
+        let keys = (0..3)
+            .map(|i| map.insert(i, [i as u32; 3]))
+            .collect::<Vec<_>>();
+
+        assert_eq!(Some(&mut [0; 3]), map.remove(&keys[0]));
+        let _ = map.drain().count();
+        let _ = map.insert(0, [7; 3]);
+
+        // The first slot was reused, and the others hold the pattern
+        let values = map
+            .inner
+            .slots
+            .values()
+            .map(|(_, value)| *value)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![[0xA5A5A5A5; 3], [0xA5A5A5A5; 3], [7; 3]], values);
+    }
 }
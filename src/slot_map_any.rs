@@ -0,0 +1,268 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+use std::any::Any;
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+
+/// Key for a value of type `V` in an [`AnySlotMap`]. The value type is part
+/// of the key's type, so lookups with it are statically typed
+pub struct TypedKey<V> {
+    key_data: SlotMapKeyData,
+    _phantom: PhantomData<fn() -> V>,
+}
+
+impl<V> std::fmt::Debug for TypedKey<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("TypedKey")
+            .field(&std::any::type_name::<V>())
+            .field(&self.key_data)
+            .finish()
+    }
+}
+
+impl<V> Clone for TypedKey<V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V> Copy for TypedKey<V> {}
+
+impl<V> PartialEq for TypedKey<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key_data == other.key_data
+    }
+}
+
+impl<V> Eq for TypedKey<V> {}
+
+impl<V> std::hash::Hash for TypedKey<V> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key_data.hash(state);
+    }
+}
+
+impl<V> Borrow<SlotMapKeyData> for TypedKey<V> {
+    fn borrow(&self) -> &SlotMapKeyData {
+        &self.key_data
+    }
+}
+
+impl<V> From<((), SlotMapKeyData)> for TypedKey<V> {
+    fn from((_, key_data): ((), SlotMapKeyData)) -> Self {
+        TypedKey {
+            key_data,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<V: 'static> SlotMapKey<()> for TypedKey<V> {}
+
+/// Slot map that holds values of any type, like a registry of resources of
+/// many kinds. Inserting a value gives back a [`TypedKey`] for the value's
+/// type, so getting it back needs no annotations or casts. The type is also
+/// checked when the value is read, so raw key data can be used to look up
+/// values whose type is only known at the call site
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # use std::borrow::Borrow;
+/// struct Texture {
+///     width: u32,
+/// }
+///
+/// let mut resources = AnySlotMap::new();
+///
+/// let texture = resources.insert(Texture { width: 64 });
+/// let name = resources.insert("player".to_owned());
+///
+/// assert_eq!(Some(64), resources.get(&texture).map(|t| t.width));
+/// assert_eq!(Some(&"player".to_owned()), resources.get(&name));
+///
+/// // Raw key data is checked against the requested type
+/// let key_data: &SlotMapKeyData = texture.borrow();
+/// assert!(resources.get_raw::<String>(key_data).is_none());
+/// assert!(resources.get_raw::<Texture>(key_data).is_some());
+/// ```
+pub struct AnySlotMap {
+    map: SlotMap<TypedKey<()>, (), Box<dyn Any + Send>>,
+}
+
+impl std::fmt::Debug for AnySlotMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AnySlotMap")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
+
+impl Default for AnySlotMap {
+    fn default() -> Self {
+        AnySlotMap::new()
+    }
+}
+
+impl AnySlotMap {
+    /// Create a new empty map
+    pub fn new() -> AnySlotMap {
+        AnySlotMap {
+            map: SlotMap::new(),
+        }
+    }
+
+    /// Get the number of values in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Insert the given value into the map and return its typed key
+    pub fn insert<V: Any + Send>(&mut self, value: V) -> TypedKey<V> {
+        TypedKey::from(((), self.map.insert_raw(Box::new(value))))
+    }
+
+    /// Get a reference to the value for the given key if it exists
+    pub fn get<V: Any>(&self, key: &TypedKey<V>) -> Option<&V> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Get a reference to the value for the given key data if it exists and
+    /// has the requested type
+    pub fn get_raw<V: Any>(&self, key_data: &SlotMapKeyData) -> Option<&V> {
+        self.map.get_raw(key_data)?.downcast_ref()
+    }
+
+    /// Get a mutable reference to the value for the given key if it exists
+    pub fn get_mut<V: Any>(&mut self, key: &TypedKey<V>) -> Option<&mut V> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Get a mutable reference to the value for the given key data if it
+    /// exists and has the requested type
+    pub fn get_mut_raw<V: Any>(
+        &mut self,
+        key_data: &SlotMapKeyData,
+    ) -> Option<&mut V> {
+        self.map.get_mut_raw(key_data)?.downcast_mut()
+    }
+
+    /// Get a type-erased reference to the value for the given key data if it
+    /// exists
+    pub fn get_any_raw(
+        &self,
+        key_data: &SlotMapKeyData,
+    ) -> Option<&(dyn Any + Send)> {
+        self.map.get_raw(key_data).map(|value| &**value)
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key<V: Any>(&self, key: &TypedKey<V>) -> bool {
+        self.map.contains_key_raw(key.borrow())
+    }
+
+    /// Remove the value for the given key from the map and return it if it
+    /// exists
+    pub fn remove<V: Any>(&mut self, key: &TypedKey<V>) -> Option<V> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Remove the value for the given key data from the map and return it if
+    /// it exists and has the requested type. Values of other types are left
+    /// in the map
+    pub fn remove_raw<V: Any>(
+        &mut self,
+        key_data: &SlotMapKeyData,
+    ) -> Option<V> {
+        if !self.map.get_raw(key_data)?.is::<V>() {
+            return None;
+        }
+
+        // Leave a zero-sized placeholder in the vacant slot so the value can
+        // be moved out
+        let slot = self.map.remove_raw(key_data)?;
+        let value = std::mem::replace(slot, Box::new(()));
+
+        value.downcast().ok().map(|value| *value)
+    }
+
+    /// Remove all values from the map
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Create an iterator over the keys and values of all values of the
+    /// given type in the map
+    pub fn iter<V: Any>(&self) -> impl Iterator<Item = (TypedKey<V>, &V)> {
+        self.map.iter_raw().filter_map(|(key_data, value)| {
+            Some((TypedKey::from(((), key_data)), value.downcast_ref()?))
+        })
+    }
+
+    /// Create an iterator over the raw key data and type-erased values of
+    /// all values in the map
+    pub fn iter_raw(
+        &self,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &(dyn Any + Send))> {
+        self.map
+            .iter_raw()
+            .map(|(key_data, value)| (key_data, &**value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_values_of_mixed_types() {
+        let mut map = AnySlotMap::new();
+
+        let numbers = (0..100usize).map(|i| map.insert(i)).collect::<Vec<_>>();
+        let strings = (0..100)
+            .map(|i| map.insert(format!("{}", i)))
+            .collect::<Vec<_>>();
+
+        let shared = Arc::new(());
+        let arc = map.insert(shared.clone());
+
+        assert_eq!(201, map.len());
+        assert_eq!(100, map.iter::<usize>().count());
+        assert_eq!(100, map.iter::<String>().count());
+        assert_eq!(0, map.iter::<u32>().count());
+
+        for (i, (number, string)) in numbers.iter().zip(&strings).enumerate() {
+            assert_eq!(Some(&i), map.get(number));
+            assert_eq!(Some(&format!("{}", i)), map.get(string));
+
+            // Looking up raw key data as the wrong type finds nothing
+            assert_eq!(None, map.get_raw::<String>(number.borrow()));
+            assert_eq!(None, map.remove_raw::<String>(number.borrow()));
+            assert!(map.get_any_raw(number.borrow()).unwrap().is::<usize>());
+        }
+
+        *map.get_mut(&numbers[3]).unwrap() += 1000;
+        assert_eq!(Some(1003), map.remove(&numbers[3]));
+        assert_eq!(None, map.remove(&numbers[3]));
+        assert!(!map.contains_key(&numbers[3]));
+
+        // Removed values are moved out rather than left in the slot
+        assert_eq!(2, Arc::strong_count(&shared));
+        let removed = map.remove(&arc);
+        assert_eq!(2, Arc::strong_count(&shared));
+        drop(removed);
+        assert_eq!(1, Arc::strong_count(&shared));
+
+        let reused = map.insert(7u8);
+        assert_eq!(Some(&7), map.get(&reused));
+        assert_eq!(None, map.get(&arc));
+
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(0, map.iter_raw().count());
+    }
+}
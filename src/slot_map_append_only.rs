@@ -0,0 +1,403 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData, SLOT_MAP_CHUNK_SIZE};
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+/// Number of buckets of slots. Bucket `b` holds `SLOT_MAP_CHUNK_SIZE << b`
+/// slots, so together the buckets cover every chunk index a key can hold
+pub(crate) const BUCKET_COUNT: usize = 33;
+
+/// A single slot that is written once
+struct Slot<T> {
+    ready: AtomicBool,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// Slot map that values can only be added to, where inserting never takes a
+/// lock and looking up a value is wait-free. Inserts reserve a slot by
+/// bumping an atomic cursor, and the storage for new slots is allocated on
+/// demand and installed with a compare-and-swap. Storage is never moved, so
+/// references to values stay valid for as long as the map is borrowed.
+/// Values can't be removed, but the map can be converted into a regular slot
+/// map once it no longer needs to be shared, and its keys remain valid
+pub struct AppendOnlySlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    buckets: [AtomicPtr<Slot<T>>; BUCKET_COUNT],
+    cursor: AtomicUsize,
+
+    _phantom: PhantomData<fn(P, K)>,
+}
+
+// Safety - Values are moved in by inserting threads and shared with reading
+// threads, and slots are only written by the thread that reserved them
+unsafe impl<K, P, T> Send for AppendOnlySlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Send,
+{
+}
+
+unsafe impl<K, P, T> Sync for AppendOnlySlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Send + Sync,
+{
+}
+
+impl<K, P, T> std::fmt::Debug for AppendOnlySlotMap<K, P, T>
+where
+    T: std::fmt::Debug,
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.values()).finish()
+    }
+}
+
+impl<K, P, T> Default for AppendOnlySlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        AppendOnlySlotMap::new()
+    }
+}
+
+/// Find the bucket containing the slot at the given linear index and the
+/// position of the slot in that bucket
+pub(crate) fn locate(linear_index: usize) -> (usize, usize) {
+    let chunk = linear_index / SLOT_MAP_CHUNK_SIZE + 1;
+    let bucket = (usize::BITS - 1 - chunk.leading_zeros()) as usize;
+    let bucket_start = ((1 << bucket) - 1) * SLOT_MAP_CHUNK_SIZE;
+
+    (bucket, linear_index - bucket_start)
+}
+
+/// Get the number of slots in the given bucket
+pub(crate) fn bucket_len(bucket: usize) -> usize {
+    SLOT_MAP_CHUNK_SIZE << bucket
+}
+
+impl<K, P, T> AppendOnlySlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> AppendOnlySlotMap<K, P, T> {
+        AppendOnlySlotMap {
+            buckets: std::array::from_fn(|_| AtomicPtr::new(null_mut())),
+            cursor: AtomicUsize::new(0),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Get the number of slots that have been reserved. Inserts that are
+    /// still in progress on other threads are included
+    pub fn len(&self) -> usize {
+        self.cursor.load(Ordering::Acquire)
+    }
+
+    /// Tells if nothing has been inserted into this map
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Insert the given item into the map and return its key. This never
+    /// blocks, though the first insert into each new bucket of slots
+    /// allocates it
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<usize>);
+    /// let map = AppendOnlySlotMap::<TestKey, usize, String>::new();
+    ///
+    /// let keys = std::thread::scope(|s| {
+    ///     let handles = (0..4)
+    ///         .map(|i| {
+    ///             let map = &map;
+    ///             s.spawn(move || map.insert(i, format!("{}", i)))
+    ///         })
+    ///         .collect::<Vec<_>>();
+    ///
+    ///     handles
+    ///         .into_iter()
+    ///         .map(|h| h.join().unwrap())
+    ///         .collect::<Vec<_>>()
+    /// });
+    ///
+    /// for key in &keys {
+    ///     assert_eq!(Some(&format!("{}", key.pointer)), map.get(key));
+    /// }
+    /// ```
+    pub fn insert(&self, pointer: P, value: T) -> K {
+        let linear_index = self.cursor.fetch_add(1, Ordering::AcqRel);
+        let (bucket, index) = locate(linear_index);
+
+        assert!(bucket < BUCKET_COUNT, "append-only slot map is full");
+
+        let slot = &self.bucket(bucket)[index];
+
+        // Safety - The slot was reserved by the cursor bump above, so no other
+        // thread writes it, and readers don't look at it until it is ready
+        unsafe { (*slot.value.get()).write(value) };
+        slot.ready.store(true, Ordering::Release);
+
+        K::from((pointer, SlotMapKeyData::from(linear_index as u64)))
+    }
+
+    /// Get a reference to the item in the map that corresponds to the given
+    /// key if it exists. This is wait-free
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        if key_data.generation != 0 {
+            return None;
+        }
+
+        let linear_index = key_data.chunk_index as usize * SLOT_MAP_CHUNK_SIZE
+            + key_data.index_in_chunk as usize;
+
+        self.slot(linear_index).and_then(read_slot)
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Create an iterator over the raw key data and values of the items in
+    /// the map. Items inserted while iterating may or may not be produced
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        (0..self.len()).filter_map(move |linear_index| {
+            self.slot(linear_index)
+                .and_then(read_slot)
+                .map(|value| (SlotMapKeyData::from(linear_index as u64), value))
+        })
+    }
+
+    /// Create an iterator over the values in the map
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.iter_raw().map(|(_, value)| value)
+    }
+
+    /// Convert this map into a regular slot map that values can be removed
+    /// from. Every key handed out by this map is valid in the new map
+    pub fn into_slot_map(mut self) -> SlotMap<K, P, T> {
+        let len = *self.cursor.get_mut();
+        let mut map = SlotMap::new();
+
+        for linear_index in 0..len {
+            let (bucket, index) = locate(linear_index);
+            let slot = &mut self.bucket_mut(bucket)[index];
+
+            assert!(*slot.ready.get_mut(), "insert into slot was interrupted");
+            *slot.ready.get_mut() = false;
+
+            // Safety - The slot was ready, and is marked as not ready so the
+            // value isn't dropped again
+            let value = unsafe { slot.value.get_mut().assume_init_read() };
+            let _ = map.insert_raw(value);
+        }
+
+        map
+    }
+
+    /// Get the slot at the given linear index if its bucket exists
+    fn slot(&self, linear_index: usize) -> Option<&Slot<T>> {
+        let (bucket, index) = locate(linear_index);
+        let ptr = self.buckets.get(bucket)?.load(Ordering::Acquire);
+
+        // Safety - Installed buckets are never freed or moved while the map
+        // is alive, and the index is within the bucket's length
+        (!ptr.is_null()).then(|| unsafe { &*ptr.add(index) })
+    }
+
+    /// Get the slots of the given bucket, allocating and installing them if
+    /// no other thread has yet
+    fn bucket(&self, bucket: usize) -> &[Slot<T>] {
+        let len = bucket_len(bucket);
+        let mut ptr = self.buckets[bucket].load(Ordering::Acquire);
+
+        if ptr.is_null() {
+            let new_slots = Box::into_raw(
+                (0..len)
+                    .map(|_| Slot {
+                        ready: AtomicBool::new(false),
+                        value: UnsafeCell::new(MaybeUninit::uninit()),
+                    })
+                    .collect::<Box<[Slot<T>]>>(),
+            ) as *mut Slot<T>;
+
+            ptr = match self.buckets[bucket].compare_exchange(
+                null_mut(),
+                new_slots,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => new_slots,
+                Err(installed) => {
+                    // Safety - Another thread installed its bucket first, so
+                    // these slots were never shared
+                    drop(unsafe { free_bucket(new_slots, len) });
+                    installed
+                }
+            };
+        }
+
+        // Safety - Installed buckets have the length for their position
+        unsafe { std::slice::from_raw_parts(ptr, len) }
+    }
+
+    /// Get mutable access to the slots of the given bucket, which must exist
+    fn bucket_mut(&mut self, bucket: usize) -> &mut [Slot<T>] {
+        let ptr = *self.buckets[bucket].get_mut();
+
+        // Safety - The bucket exists and this map is borrowed mutably
+        unsafe { std::slice::from_raw_parts_mut(ptr, bucket_len(bucket)) }
+    }
+}
+
+/// Get the value of the given slot if it has been written
+fn read_slot<T>(slot: &Slot<T>) -> Option<&T> {
+    // Safety - Ready slots are never written again
+    slot.ready
+        .load(Ordering::Acquire)
+        .then(|| unsafe { (*slot.value.get()).assume_init_ref() })
+}
+
+/// Take back ownership of the bucket at the given pointer
+///
+/// # Safety
+/// The pointer must have come from a boxed slice of slots with the given
+/// length that is not used anywhere else
+unsafe fn free_bucket<T>(ptr: *mut Slot<T>, len: usize) -> Box<[Slot<T>]> {
+    Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len))
+}
+
+impl<K, P, T> Drop for AppendOnlySlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn drop(&mut self) {
+        for (bucket, ptr) in self.buckets.iter_mut().enumerate() {
+            let ptr = *ptr.get_mut();
+
+            if ptr.is_null() {
+                continue;
+            }
+
+            // Safety - This map owns its buckets and nothing else can be
+            // using them during the drop
+            let mut slots = unsafe { free_bucket(ptr, bucket_len(bucket)) };
+
+            for slot in slots.iter_mut() {
+                if *slot.ready.get_mut() {
+                    // Safety - Ready slots hold initialized values
+                    unsafe { slot.value.get_mut().assume_init_drop() };
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_locate() {
+        assert_eq!((0, 0), locate(0));
+        assert_eq!(
+            (0, SLOT_MAP_CHUNK_SIZE - 1),
+            locate(SLOT_MAP_CHUNK_SIZE - 1)
+        );
+        assert_eq!((1, 0), locate(SLOT_MAP_CHUNK_SIZE));
+        assert_eq!((2, 0), locate(SLOT_MAP_CHUNK_SIZE * 3));
+        assert_eq!((2, 1), locate(SLOT_MAP_CHUNK_SIZE * 3 + 1));
+
+        let last_chunk = u32::MAX as usize;
+        let (bucket, index) = locate(last_chunk * SLOT_MAP_CHUNK_SIZE);
+        assert!(bucket < BUCKET_COUNT);
+        assert!(index < bucket_len(bucket));
+    }
+
+    #[test]
+    fn test_concurrent_inserts() {
+        let map = AppendOnlySlotMap::<TestKey, usize, String>::new();
+        let per_thread = 3000;
+
+        let keys = std::thread::scope(|s| {
+            let handles = (0..8)
+                .map(|t| {
+                    let map = &map;
+
+                    s.spawn(move || {
+                        (0..per_thread)
+                            .map(|i| {
+                                let p = t * per_thread + i;
+                                let key = map.insert(p, format!("{}", p));
+                                assert_eq!(
+                                    Some(&format!("{}", p)),
+                                    map.get(&key)
+                                );
+                                key
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(keys.len(), map.len());
+        assert_eq!(keys.len(), map.values().count());
+
+        let fake = SlotMapKeyData::from((keys.len() * 2) as u64);
+        assert_eq!(None, map.get_raw(&fake));
+
+        let slot_map = map.into_slot_map();
+        assert_eq!(keys.len(), slot_map.len());
+
+        for k in &keys {
+            assert_eq!(Some(&format!("{}", k.pointer)), slot_map.get(k));
+        }
+    }
+
+    #[test]
+    fn test_drop() {
+        let counter = Arc::new(());
+
+        let map = AppendOnlySlotMap::<TestKey, usize, Arc<()>>::new();
+        for i in 0..SLOT_MAP_CHUNK_SIZE * 5 {
+            let _ = map.insert(i, counter.clone());
+        }
+        assert_eq!(SLOT_MAP_CHUNK_SIZE * 5 + 1, Arc::strong_count(&counter));
+
+        drop(map);
+        assert_eq!(1, Arc::strong_count(&counter));
+
+        let map = AppendOnlySlotMap::<TestKey, usize, Arc<()>>::new();
+        for i in 0..SLOT_MAP_CHUNK_SIZE + 1 {
+            let _ = map.insert(i, counter.clone());
+        }
+
+        let slot_map = map.into_slot_map();
+        assert_eq!(SLOT_MAP_CHUNK_SIZE + 2, Arc::strong_count(&counter));
+
+        drop(slot_map);
+        assert_eq!(1, Arc::strong_count(&counter));
+    }
+}
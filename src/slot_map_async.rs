@@ -0,0 +1,324 @@
+use super::{SlotMap, SlotMapKey};
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+
+/// Reader-writer lock whose lock operations are awaited rather than blocking
+/// the thread. [`AsyncSlotMap`] is generic over this so it can be used with
+/// the lock of whichever async runtime an application uses. For example,
+/// tokio's lock can be used with
+///
+/// ```ignore
+/// struct TokioLock<T>(tokio::sync::RwLock<T>);
+///
+/// impl<T: Send + Sync> AsyncRwLock<T> for TokioLock<T> {
+///     type ReadGuard<'a> = tokio::sync::RwLockReadGuard<'a, T> where T: 'a;
+///     type WriteGuard<'a> = tokio::sync::RwLockWriteGuard<'a, T> where T: 'a;
+///
+///     fn new(value: T) -> Self {
+///         TokioLock(tokio::sync::RwLock::new(value))
+///     }
+///
+///     fn read(&self) -> impl Future<Output = Self::ReadGuard<'_>> + Send {
+///         self.0.read()
+///     }
+///
+///     fn write(&self) -> impl Future<Output = Self::WriteGuard<'_>> + Send {
+///         self.0.write()
+///     }
+///
+///     fn into_inner(self) -> T {
+///         self.0.into_inner()
+///     }
+/// }
+/// ```
+pub trait AsyncRwLock<T> {
+    /// Guard for shared access to the locked value
+    type ReadGuard<'a>: Deref<Target = T>
+    where
+        Self: 'a;
+
+    /// Guard for exclusive access to the locked value
+    type WriteGuard<'a>: DerefMut<Target = T>
+    where
+        Self: 'a;
+
+    /// Create a lock around the given value
+    fn new(value: T) -> Self;
+
+    /// Wait for shared access to the locked value
+    fn read(&self) -> impl Future<Output = Self::ReadGuard<'_>> + Send;
+
+    /// Wait for exclusive access to the locked value
+    fn write(&self) -> impl Future<Output = Self::WriteGuard<'_>> + Send;
+
+    /// Consume the lock, returning the locked value
+    fn into_inner(self) -> T;
+}
+
+/// Slot map shared between async tasks behind an async reader-writer lock,
+/// so tasks that contend on the map yield to the executor instead of
+/// blocking its thread. The lock is never held across an await point of the
+/// caller, because items are only accessed through closures
+pub struct AsyncSlotMap<K, P, T, L>
+where
+    K: SlotMapKey<P>,
+    L: AsyncRwLock<SlotMap<K, P, T>>,
+{
+    lock: L,
+
+    _phantom: std::marker::PhantomData<fn(P, K, T)>,
+}
+
+impl<K, P, T, L> std::fmt::Debug for AsyncSlotMap<K, P, T, L>
+where
+    K: SlotMapKey<P>,
+    L: AsyncRwLock<SlotMap<K, P, T>>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncSlotMap").finish_non_exhaustive()
+    }
+}
+
+impl<K, P, T, L> Default for AsyncSlotMap<K, P, T, L>
+where
+    K: SlotMapKey<P>,
+    L: AsyncRwLock<SlotMap<K, P, T>>,
+{
+    fn default() -> Self {
+        AsyncSlotMap::new()
+    }
+}
+
+impl<K, P, T, L> From<SlotMap<K, P, T>> for AsyncSlotMap<K, P, T, L>
+where
+    K: SlotMapKey<P>,
+    L: AsyncRwLock<SlotMap<K, P, T>>,
+{
+    fn from(map: SlotMap<K, P, T>) -> Self {
+        AsyncSlotMap {
+            lock: L::new(map),
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<K, P, T, L> AsyncSlotMap<K, P, T, L>
+where
+    K: SlotMapKey<P>,
+    L: AsyncRwLock<SlotMap<K, P, T>>,
+{
+    /// Create a new empty map
+    pub fn new() -> AsyncSlotMap<K, P, T, L> {
+        SlotMap::new().into()
+    }
+
+    /// Get the number of items in the map
+    pub async fn len(&self) -> usize {
+        self.lock.read().await.len()
+    }
+
+    /// Tells if the map is empty
+    pub async fn is_empty(&self) -> bool {
+        self.lock.read().await.is_empty()
+    }
+
+    /// Insert the given item into the map and return its key
+    pub async fn insert(&self, pointer: P, value: T) -> K {
+        self.lock.write().await.insert(pointer, value)
+    }
+
+    /// Call the given function with a reference to the item for the given
+    /// key if it exists. The map is read-locked during the call
+    pub async fn get_with<R>(
+        &self,
+        key: &K,
+        f: impl FnOnce(&T) -> R,
+    ) -> Option<R> {
+        self.lock.read().await.get(key).map(f)
+    }
+
+    /// Get a copy of the item for the given key if it exists
+    pub async fn get_cloned(&self, key: &K) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.get_with(key, T::clone).await
+    }
+
+    /// Call the given function with a mutable reference to the item for the
+    /// given key if it exists. The map is write-locked during the call
+    pub async fn get_mut_with<R>(
+        &self,
+        key: &K,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> Option<R> {
+        self.lock.write().await.get_mut(key).map(f)
+    }
+
+    /// Tells if the given key is in the map
+    pub async fn contains_key(&self, key: &K) -> bool {
+        self.lock.read().await.contains_key(key)
+    }
+
+    /// Remove the item for the given key, calling the given function with a
+    /// mutable reference to the removed item
+    pub async fn remove_with<R>(
+        &self,
+        key: &K,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> Option<R> {
+        self.lock.write().await.remove(key).map(f)
+    }
+
+    /// Remove the item for the given key, returning whether it was present
+    pub async fn remove(&self, key: &K) -> bool {
+        self.remove_with(key, |_| ()).await.is_some()
+    }
+
+    /// Consume this wrapper, returning the map
+    pub fn into_inner(self) -> SlotMap<K, P, T> {
+        self.lock.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use std::pin::pin;
+    use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+    use std::task::{Context, Poll, Wake, Waker};
+
+    /// Async lock that yields to the executor until the lock is available
+    struct YieldingLock<T>(RwLock<T>);
+
+    impl<T: Send + Sync> AsyncRwLock<T> for YieldingLock<T> {
+        type ReadGuard<'a>
+            = RwLockReadGuard<'a, T>
+        where
+            T: 'a;
+        type WriteGuard<'a>
+            = RwLockWriteGuard<'a, T>
+        where
+            T: 'a;
+
+        fn new(value: T) -> Self {
+            YieldingLock(RwLock::new(value))
+        }
+
+        fn read(&self) -> impl Future<Output = Self::ReadGuard<'_>> + Send {
+            std::future::poll_fn(|cx| match self.0.try_read() {
+                Ok(guard) => Poll::Ready(guard),
+                Err(_) => {
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+            })
+        }
+
+        fn write(&self) -> impl Future<Output = Self::WriteGuard<'_>> + Send {
+            std::future::poll_fn(|cx| match self.0.try_write() {
+                Ok(guard) => Poll::Ready(guard),
+                Err(_) => {
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+            })
+        }
+
+        fn into_inner(self) -> T {
+            self.0.into_inner().unwrap()
+        }
+    }
+
+    struct ThreadWaker(std::thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Run the given future to completion on the current thread
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+        let mut cx = Context::from_waker(&waker);
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+
+            std::thread::park();
+        }
+    }
+
+    type TestMap = AsyncSlotMap<
+        TestKey,
+        usize,
+        String,
+        YieldingLock<SlotMap<TestKey, usize, String>>,
+    >;
+
+    fn assert_send<F: Future + Send>(future: F) -> F {
+        future
+    }
+
+    #[test]
+    fn test_async_crud() {
+        let map = TestMap::new();
+
+        let keys = std::thread::scope(|s| {
+            let handles = (0..4)
+                .map(|t| {
+                    let map = &map;
+
+                    s.spawn(move || {
+                        block_on(assert_send(async move {
+                            let mut keys = Vec::new();
+
+                            for i in 0..100 {
+                                let p = t * 100 + i;
+                                let key = map.insert(p, format!("{}", p)).await;
+
+                                if i % 2 == 0 {
+                                    assert!(map.remove(&key).await);
+                                    assert!(!map.contains_key(&key).await);
+                                } else {
+                                    keys.push(key);
+                                }
+                            }
+
+                            keys
+                        }))
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        block_on(async {
+            assert_eq!(keys.len(), map.len().await);
+
+            for key in &keys {
+                assert_eq!(
+                    Some(format!("{}", key.pointer)),
+                    map.get_cloned(key).await
+                );
+            }
+
+            let _ = map.get_mut_with(&keys[0], |v| v.push('!')).await;
+            assert_eq!(
+                Some(true),
+                map.get_with(&keys[0], |v| v.ends_with('!')).await
+            );
+        });
+
+        assert_eq!(keys.len(), map.into_inner().len());
+    }
+}
@@ -0,0 +1,178 @@
+use super::SlotMapKeyData;
+use std::fmt::{Display, Formatter};
+
+const ALPHABET: &[u8; 62] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Odd multiplier used to scramble packed key data before encoding, so
+/// neighboring slots don't get similar looking codes
+const SCRAMBLE: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Multiplicative inverse of [`SCRAMBLE`] modulo 2^64
+const UNSCRAMBLE: u64 = {
+    // Each step of Newton's method doubles the number of correct low bits,
+    // starting from 3 bits that are correct for any odd number
+    let mut inverse = SCRAMBLE;
+    let mut step = 0;
+
+    while step < 5 {
+        inverse = inverse
+            .wrapping_mul(2u64.wrapping_sub(SCRAMBLE.wrapping_mul(inverse)));
+        step += 1;
+    }
+
+    inverse
+};
+
+/// Reasons a string isn't a key encoded with [`SlotMapKeyData::to_base62`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Base62Error {
+    /// The string was empty
+    Empty,
+
+    /// The string contained a character outside of `0-9`, `A-Z`, and `a-z`
+    InvalidCharacter(char),
+
+    /// The string had a leading zero or encoded a number too large to be key
+    /// data, so it can't have been produced by encoding a key
+    NotCanonical,
+}
+
+impl Display for Base62Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Base62Error::Empty => write!(f, "empty key code"),
+            Base62Error::InvalidCharacter(c) => {
+                write!(f, "invalid character {:?} in key code", c)
+            }
+            Base62Error::NotCanonical => {
+                write!(f, "key code is not in canonical form")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Base62Error {}
+
+impl SlotMapKeyData {
+    /// Encode this key data as a short string of letters and digits, at most
+    /// 11 characters long, that can be used in URLs and API payloads as is.
+    /// The packed key data is scrambled before encoding so the codes of
+    /// related keys don't look related and don't show the key layout, but
+    /// this is not encryption and anyone can decode a code. The encoding is
+    /// stable across versions of this crate
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// # use std::borrow::Borrow;
+    /// let mut map = SlotMap::<TestKey, (), &str>::new();
+    /// let key = map.insert((), "order #1234");
+    ///
+    /// let code = Borrow::<SlotMapKeyData>::borrow(&key).to_base62();
+    /// assert!(code.chars().all(|c| c.is_ascii_alphanumeric()));
+    ///
+    /// let key_data = SlotMapKeyData::from_base62(&code).unwrap();
+    /// assert_eq!(Some(&"order #1234"), map.get_raw(&key_data));
+    /// ```
+    pub fn to_base62(&self) -> String {
+        let mut remaining = scramble(u64::from(*self));
+        let mut digits = Vec::with_capacity(11);
+
+        loop {
+            digits.push(ALPHABET[(remaining % 62) as usize]);
+            remaining /= 62;
+
+            if remaining == 0 {
+                break;
+            }
+        }
+
+        digits.reverse();
+        String::from_utf8(digits).expect("alphabet is ascii")
+    }
+
+    /// Decode key data encoded with [`SlotMapKeyData::to_base62`]. Every key
+    /// has exactly one code, so codes with leading zeros are rejected
+    pub fn from_base62(code: &str) -> Result<SlotMapKeyData, Base62Error> {
+        if code.is_empty() {
+            return Err(Base62Error::Empty);
+        }
+
+        if code.len() > 1 && code.starts_with('0') {
+            return Err(Base62Error::NotCanonical);
+        }
+
+        let mut packed = 0u64;
+
+        for c in code.chars() {
+            let digit = match c {
+                '0'..='9' => c as u64 - '0' as u64,
+                'A'..='Z' => c as u64 - 'A' as u64 + 10,
+                'a'..='z' => c as u64 - 'a' as u64 + 36,
+                _ => return Err(Base62Error::InvalidCharacter(c)),
+            };
+
+            packed = packed
+                .checked_mul(62)
+                .and_then(|p| p.checked_add(digit))
+                .ok_or(Base62Error::NotCanonical)?;
+        }
+
+        Ok(SlotMapKeyData::from(unscramble(packed)))
+    }
+}
+
+/// Mix the bits of the given value reversibly
+fn scramble(value: u64) -> u64 {
+    let mixed = (value ^ (value >> 32)).wrapping_mul(SCRAMBLE);
+    mixed ^ (mixed >> 32)
+}
+
+/// Undo [`scramble`]. Xoring with the top half shifted down is its own
+/// inverse, because the top half is left unchanged
+fn unscramble(value: u64) -> u64 {
+    let mixed = (value ^ (value >> 32)).wrapping_mul(UNSCRAMBLE);
+    mixed ^ (mixed >> 32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn test_codes_round_trip() {
+        let mut rng = thread_rng();
+
+        assert_eq!(1, SCRAMBLE.wrapping_mul(UNSCRAMBLE));
+
+        for packed in (0..1000).chain((0..10000).map(|_| rng.gen::<u64>())) {
+            let key_data = SlotMapKeyData::from(packed);
+            let code = key_data.to_base62();
+
+            assert!(code.len() <= 11);
+            assert_eq!(Ok(key_data), SlotMapKeyData::from_base62(&code));
+        }
+
+        // Neighboring slots don't share a prefix
+        assert_ne!(
+            SlotMapKeyData::from(1).to_base62()[..3],
+            SlotMapKeyData::from(2).to_base62()[..3]
+        );
+
+        assert_eq!(Err(Base62Error::Empty), SlotMapKeyData::from_base62(""));
+        assert_eq!(
+            Err(Base62Error::InvalidCharacter('-')),
+            SlotMapKeyData::from_base62("ab-c")
+        );
+        assert_eq!(
+            Err(Base62Error::NotCanonical),
+            SlotMapKeyData::from_base62("0a")
+        );
+        assert_eq!(
+            Err(Base62Error::NotCanonical),
+            SlotMapKeyData::from_base62("zzzzzzzzzzzz")
+        );
+    }
+}
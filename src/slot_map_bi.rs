@@ -0,0 +1,335 @@
+use super::slot_map_interning::ValueIndex;
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::ops::{Deref, DerefMut};
+
+/// Slot map that can also find the key of a value. Inserting a value equal
+/// to one already in the map is refused, so every value has a single key.
+/// Values can still be changed through [`BiSlotMap::get_mut`], which hands
+/// out a guard that updates the reverse index when it's dropped. A value
+/// changed to equal another value is indexed like any other, but
+/// [`BiSlotMap::key_of`] will only find one of the two
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # use std::borrow::Borrow;
+/// # define_key_type!(TestKey<()>);
+/// let mut map = BiSlotMap::<TestKey, (), String>::new();
+///
+/// let key = map.insert((), "Hello".to_owned()).unwrap();
+/// assert!(map.insert((), "Hello".to_owned()).is_err());
+///
+/// map.get_mut(&key).unwrap().push_str(", World");
+///
+/// assert_eq!(None, map.key_of("Hello"));
+/// assert_eq!(Some(*key.borrow()), map.key_of("Hello, World"));
+/// ```
+pub struct BiSlotMap<K, P, T, S = RandomState>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, T>,
+    index: ValueIndex<S>,
+}
+
+/// Mutable access to a value in a [`BiSlotMap`] that updates the map's
+/// reverse index for the value when dropped
+pub struct BiValueMut<'a, T, S>
+where
+    T: Hash,
+    S: BuildHasher,
+{
+    value: &'a mut T,
+    key_data: SlotMapKeyData,
+    hash: u64,
+    index: &'a mut ValueIndex<S>,
+}
+
+impl<T, S> Deref for BiValueMut<'_, T, S>
+where
+    T: Hash,
+    S: BuildHasher,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T, S> DerefMut for BiValueMut<'_, T, S>
+where
+    T: Hash,
+    S: BuildHasher,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        self.value
+    }
+}
+
+impl<T, S> Drop for BiValueMut<'_, T, S>
+where
+    T: Hash,
+    S: BuildHasher,
+{
+    fn drop(&mut self) {
+        let hash = self.index.hash(&*self.value);
+
+        if hash != self.hash {
+            self.index.remove(self.hash, &self.key_data);
+            self.index.insert(hash, self.key_data);
+        }
+    }
+}
+
+impl<T, S> std::fmt::Debug for BiValueMut<'_, T, S>
+where
+    T: Hash + std::fmt::Debug,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl<K, P, T, S> std::fmt::Debug for BiSlotMap<K, P, T, S>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.map.fmt(f)
+    }
+}
+
+impl<K, P, T, S> Default for BiSlotMap<K, P, T, S>
+where
+    K: SlotMapKey<P>,
+    S: Default,
+{
+    fn default() -> Self {
+        BiSlotMap::with_hasher(S::default())
+    }
+}
+
+impl<K, P, T> BiSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> BiSlotMap<K, P, T> {
+        BiSlotMap::with_hasher(RandomState::new())
+    }
+}
+
+impl<K, P, T, S> BiSlotMap<K, P, T, S>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map that hashes values with the given hasher
+    pub fn with_hasher(hasher: S) -> BiSlotMap<K, P, T, S> {
+        BiSlotMap {
+            map: SlotMap::new(),
+            index: ValueIndex::new(hasher),
+        }
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Get a reference to the item for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.map.get_raw(key_data)
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Remove all items from the map
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.index.clear();
+    }
+
+    /// Create an iterator over the raw key data and values of all items in
+    /// the map
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.map.iter_raw()
+    }
+
+    /// Create an iterator over the values of all items in the map
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.map.values()
+    }
+}
+
+impl<K, P, T, S> BiSlotMap<K, P, T, S>
+where
+    K: SlotMapKey<P>,
+    T: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Insert the given item into the map and return its key, or give the
+    /// item back if an equal value is already in the map
+    pub fn insert(&mut self, pointer: P, value: T) -> Result<K, T> {
+        let hash = self.index.hash(&value);
+
+        if self.index.find(hash, &self.map, &value).is_some() {
+            return Err(value);
+        }
+
+        let key_data = self.map.insert_raw(value);
+        self.index.insert(hash, key_data);
+
+        Ok(K::from((pointer, key_data)))
+    }
+
+    /// Get the key data of the item equal to the given value if there is one.
+    /// The value can be anything the items can be borrowed as, like a `&str`
+    /// for a map of `String`s
+    pub fn key_of<Q>(&self, value: &Q) -> Option<SlotMapKeyData>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.index.find(self.index.hash(value), &self.map, value)
+    }
+
+    /// Get mutable access to the item for the given key if it exists. The
+    /// reverse index is updated when the returned guard is dropped
+    pub fn get_mut(&mut self, key: &K) -> Option<BiValueMut<'_, T, S>> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Same as get_mut, but only requires slot map key data
+    pub fn get_mut_raw(
+        &mut self,
+        key_data: &SlotMapKeyData,
+    ) -> Option<BiValueMut<'_, T, S>> {
+        let value = self.map.get_mut_raw(key_data)?;
+
+        Some(BiValueMut {
+            hash: self.index.hash(&*value),
+            value,
+            key_data: *key_data,
+            index: &mut self.index,
+        })
+    }
+
+    /// Replace the item for the given key with the given value and return
+    /// the old value. The new value is given back if the key isn't in the
+    /// map or another item already has an equal value
+    pub fn replace(&mut self, key: &K, value: T) -> Result<T, T> {
+        self.replace_raw(key.borrow(), value)
+    }
+
+    /// Same as replace, but only requires slot map key data
+    pub fn replace_raw(
+        &mut self,
+        key_data: &SlotMapKeyData,
+        value: T,
+    ) -> Result<T, T> {
+        let hash = self.index.hash(&value);
+
+        match self.index.find(hash, &self.map, &value) {
+            Some(existing) if existing != *key_data => return Err(value),
+            _ => {}
+        }
+
+        match self.get_mut_raw(key_data) {
+            Some(mut current) => Ok(std::mem::replace(&mut *current, value)),
+            None => Err(value),
+        }
+    }
+
+    /// Remove the item for the given key and return a mutable ref to it if
+    /// there was one
+    pub fn remove(&mut self, key: &K) -> Option<&mut T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        let hash = self.index.hash(self.map.get_raw(key_data)?);
+        self.index.remove(hash, key_data);
+
+        self.map.remove_raw(key_data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+
+    fn key_data(key: &TestKey) -> SlotMapKeyData {
+        *key.borrow()
+    }
+
+    #[test]
+    fn test_reverse_index_follows_changes() {
+        let mut map = BiSlotMap::<TestKey, usize, usize>::new();
+
+        let keys = (0..1000)
+            .map(|i| map.insert(i, i).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(Err(10), map.insert(0, 10));
+
+        // Move every value up by a million through each way of changing it
+        for key in &keys {
+            match key.pointer % 3 {
+                0 => *map.get_mut(key).unwrap() += 1_000_000,
+                1 => assert_eq!(
+                    Ok(key.pointer),
+                    map.replace(key, key.pointer + 1_000_000)
+                ),
+                _ => {
+                    assert!(map.remove(key).is_some());
+                    let new_key =
+                        map.insert(key.pointer, key.pointer + 1_000_000);
+                    assert_eq!(None, map.get(key));
+                    assert!(new_key.is_ok());
+                }
+            }
+        }
+
+        assert_eq!(1000, map.len());
+        assert_eq!(1000, map.index.len());
+
+        for key in &keys {
+            assert_eq!(None, map.key_of(&key.pointer));
+
+            let found = map.key_of(&(key.pointer + 1_000_000)).unwrap();
+            assert_eq!(Some(&(key.pointer + 1_000_000)), map.get_raw(&found));
+
+            if key.pointer % 3 != 2 {
+                assert_eq!(key_data(key), found);
+            }
+        }
+
+        // Replacing with a value another item has is refused
+        assert_eq!(Err(1_000_001), map.replace(&keys[0], 1_000_001));
+        assert_eq!(Ok(1_000_000), map.replace(&keys[0], 1_000_000));
+
+        map.clear();
+        assert_eq!(0, map.index.len());
+        assert_eq!(None, map.key_of(&1_000_000));
+    }
+}
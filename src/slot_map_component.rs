@@ -0,0 +1,394 @@
+use super::{SlotMapKey, SlotMapKeyData, SLOT_MAP_CHUNK_SIZE};
+use std::marker::PhantomData;
+
+/// Marks a position in the sparse table that has no component
+const EMPTY: u32 = u32::MAX;
+
+/// Dense indexes of the components for one chunk's worth of key positions
+type SparseChunk = Box<[u32; SLOT_MAP_CHUNK_SIZE]>;
+
+/// Storage for components of entities whose keys come from another map, like
+/// a [`SlotMap`](crate::SlotMap) or [`SlotSet`](crate::SlotSet) of entities.
+/// Components are packed densely in one array with no gaps, so iterating
+/// them is a straight walk over memory, and a sparse table with a chunk per
+/// chunk of the entity map finds a component by key. Keys are checked with
+/// their generation, so a component left behind by a removed entity is never
+/// found with the key of the entity that reuses its slot.
+///
+/// Systems that touch two components use [`ComponentSlotMap::join`], which
+/// walks the dense components of one map and looks up the other, so the map
+/// with fewer components should be the one it's called on
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(Entity<()>);
+/// let mut entities = SlotSet::<Entity, ()>::new();
+/// let mut positions = ComponentSlotMap::<Entity, (), f32>::new();
+/// let mut velocities = ComponentSlotMap::<Entity, (), f32>::new();
+///
+/// let moving = entities.insert(());
+/// let still = entities.insert(());
+///
+/// positions.insert(&moving, 0.0);
+/// positions.insert(&still, 5.0);
+/// velocities.insert(&moving, 2.0);
+///
+/// for (_, position, velocity) in positions.join_mut(&velocities) {
+///     *position += velocity;
+/// }
+///
+/// assert_eq!(Some(&2.0), positions.get(&moving));
+/// assert_eq!(Some(&5.0), positions.get(&still));
+/// ```
+pub struct ComponentSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Dense index of the component for each key position, by chunk
+    sparse: Vec<Option<SparseChunk>>,
+
+    /// Key data of each component, parallel to the values
+    keys: Vec<SlotMapKeyData>,
+    values: Vec<T>,
+
+    _phantom: PhantomData<fn(P, K)>,
+}
+
+impl<K, P, T> std::fmt::Debug for ComponentSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter_raw()).finish()
+    }
+}
+
+impl<K, P, T> Default for ComponentSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        ComponentSlotMap::new()
+    }
+}
+
+impl<K, P, T> Clone for ComponentSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        ComponentSlotMap {
+            sparse: self.sparse.clone(),
+            keys: self.keys.clone(),
+            values: self.values.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<K, P, T> ComponentSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> ComponentSlotMap<K, P, T> {
+        ComponentSlotMap {
+            sparse: Vec::new(),
+            keys: Vec::new(),
+            values: Vec::new(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Get the number of components in the map
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Set the component for the given key, returning the component it
+    /// replaced if the key already had one. A component left in the key's
+    /// slot by an older key is dropped
+    pub fn insert(&mut self, key: &K, value: T) -> Option<T> {
+        self.insert_raw(key.borrow(), value)
+    }
+
+    /// Same as insert, but only requires slot map key data
+    pub fn insert_raw(
+        &mut self,
+        key_data: &SlotMapKeyData,
+        value: T,
+    ) -> Option<T> {
+        let position = key_data.position();
+        let chunk_index = position / SLOT_MAP_CHUNK_SIZE;
+
+        if self.sparse.len() <= chunk_index {
+            self.sparse.resize_with(chunk_index + 1, || None);
+        }
+
+        let dense_index = &mut self.sparse[chunk_index]
+            .get_or_insert_with(|| Box::new([EMPTY; SLOT_MAP_CHUNK_SIZE]))
+            [position % SLOT_MAP_CHUNK_SIZE];
+
+        if *dense_index == EMPTY {
+            *dense_index = self.values.len() as u32;
+            self.keys.push(*key_data);
+            self.values.push(value);
+
+            return None;
+        }
+
+        let dense_index = *dense_index as usize;
+        let previous_key =
+            std::mem::replace(&mut self.keys[dense_index], *key_data);
+        let previous = std::mem::replace(&mut self.values[dense_index], value);
+
+        (previous_key == *key_data).then_some(previous)
+    }
+
+    /// Get a reference to the component for the given key if it has one
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.dense_index(key_data).map(|index| &self.values[index])
+    }
+
+    /// Get a mutable reference to the component for the given key if it has
+    /// one
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Same as get_mut, but only requires slot map key data
+    pub fn get_mut_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        self.dense_index(key_data)
+            .map(|index| &mut self.values[index])
+    }
+
+    /// Tells if the given key has a component
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.dense_index(key.borrow()).is_some()
+    }
+
+    /// Remove the component for the given key and return it if there was
+    /// one. The last component is moved into its place
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<T> {
+        let index = self.dense_index(key_data)?;
+
+        self.set_dense_index(key_data, EMPTY);
+        let _ = self.keys.swap_remove(index);
+        let value = self.values.swap_remove(index);
+
+        if let Some(moved) = self.keys.get(index).copied() {
+            self.set_dense_index(&moved, index as u32);
+        }
+
+        Some(value)
+    }
+
+    /// Remove all components from the map
+    pub fn clear(&mut self) {
+        self.sparse.clear();
+        self.keys.clear();
+        self.values.clear();
+    }
+
+    /// Get the key data of all components, in the same order as
+    /// [`ComponentSlotMap::values`]
+    pub fn keys_raw(&self) -> &[SlotMapKeyData] {
+        &self.keys
+    }
+
+    /// Get all the components as one dense slice
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// Get all the components as one dense mutable slice
+    pub fn values_mut(&mut self) -> &mut [T] {
+        &mut self.values
+    }
+
+    /// Create an iterator over the raw key data and values of all components
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.keys.iter().copied().zip(&self.values)
+    }
+
+    /// Create an iterator over the raw key data and mutable values of all
+    /// components
+    pub fn iter_mut_raw(
+        &mut self,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &mut T)> {
+        self.keys.iter().copied().zip(&mut self.values)
+    }
+
+    /// Create an iterator over the keys that have components in both this
+    /// map and the other, along with both components
+    pub fn join<'a, U>(
+        &'a self,
+        other: &'a ComponentSlotMap<K, P, U>,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &'a T, &'a U)> {
+        self.iter_raw().filter_map(|(key_data, value)| {
+            Some((key_data, value, other.get_raw(&key_data)?))
+        })
+    }
+
+    /// Same as join, but this map's components are mutable
+    pub fn join_mut<'a, U>(
+        &'a mut self,
+        other: &'a ComponentSlotMap<K, P, U>,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &'a mut T, &'a U)> {
+        self.iter_mut_raw().filter_map(|(key_data, value)| {
+            Some((key_data, value, other.get_raw(&key_data)?))
+        })
+    }
+
+    /// Find where the component for the given key data is in the dense
+    /// arrays
+    fn dense_index(&self, key_data: &SlotMapKeyData) -> Option<usize> {
+        let position = key_data.position();
+        let index =
+            self.sparse.get(position / SLOT_MAP_CHUNK_SIZE)?.as_ref()?
+                [position % SLOT_MAP_CHUNK_SIZE];
+
+        (index != EMPTY && self.keys[index as usize] == *key_data)
+            .then_some(index as usize)
+    }
+
+    fn set_dense_index(&mut self, key_data: &SlotMapKeyData, index: u32) {
+        let position = key_data.position();
+
+        self.sparse[position / SLOT_MAP_CHUNK_SIZE]
+            .as_mut()
+            .expect("components only refer to allocated sparse chunks")
+            [position % SLOT_MAP_CHUNK_SIZE] = index;
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<K, P, T> ComponentSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Send,
+{
+    /// Create a parallel iterator over the raw key data and mutable values
+    /// of all components, split across rayon's worker threads
+    pub fn par_iter_mut_raw(
+        &mut self,
+    ) -> impl rayon::iter::IndexedParallelIterator<Item = (SlotMapKeyData, &mut T)>
+    {
+        use rayon::prelude::*;
+
+        self.keys
+            .par_iter()
+            .copied()
+            .zip(self.values.par_iter_mut())
+    }
+
+    /// Same as join_mut, but split across rayon's worker threads
+    pub fn par_join_mut<'a, U: Sync>(
+        &'a mut self,
+        other: &'a ComponentSlotMap<K, P, U>,
+    ) -> impl rayon::iter::ParallelIterator<Item = (SlotMapKeyData, &'a mut T, &'a U)>
+    {
+        use rayon::prelude::*;
+
+        self.par_iter_mut_raw().filter_map(|(key_data, value)| {
+            Some((key_data, value, other.get_raw(&key_data)?))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use crate::SlotMap;
+    use std::borrow::Borrow;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_components_match_model() {
+        let mut entities = SlotMap::<TestKey, usize, ()>::new();
+        let mut components = ComponentSlotMap::<TestKey, usize, usize>::new();
+        let mut model = HashMap::<usize, TestKey>::new();
+
+        for i in 0..4000 {
+            match i % 5 {
+                0..=2 => {
+                    let key = entities.insert(i, ());
+                    assert_eq!(None, components.insert(&key, i));
+                    let _ = model.insert(i, key);
+                }
+                3 => {
+                    let (_, key) =
+                        model.iter().nth(i % model.len().max(1)).unwrap();
+                    let key = *key;
+                    assert_eq!(Some(key.pointer), components.remove(&key));
+                    assert_eq!(None, components.remove(&key));
+                    let _ = entities.remove(&key);
+                    let _ = model.remove(&key.pointer);
+                }
+                _ => {
+                    // An entity that dies without its component removed
+                    // leaves a component the next key in its slot can't see
+                    let key = entities.insert(i, ());
+                    let _ = components.insert(&key, i);
+                    let _ = entities.remove(&key);
+
+                    let reused = entities.insert(i, ());
+                    assert!(!components.contains_key(&reused));
+                    assert_eq!(None, components.insert(&reused, i));
+                    let _ = model.insert(i, reused);
+                }
+            }
+        }
+
+        assert_eq!(model.len(), components.len());
+
+        for (pointer, key) in &model {
+            assert_eq!(Some(pointer), components.get(key));
+        }
+
+        for (key_data, value) in components.iter_raw() {
+            let key = model.get(value).unwrap();
+            assert_eq!(key_data, *Borrow::<SlotMapKeyData>::borrow(key));
+        }
+
+        // Joining with a map of every other component
+        let mut halves = ComponentSlotMap::<TestKey, usize, usize>::new();
+
+        for key in model.values().filter(|k| k.pointer % 2 == 0) {
+            let _ = halves.insert(key, key.pointer / 2);
+        }
+
+        for (_, value, half) in components.join_mut(&halves) {
+            *value -= half;
+        }
+
+        for (pointer, key) in &model {
+            let expected =
+                pointer - if pointer % 2 == 0 { pointer / 2 } else { 0 };
+            assert_eq!(Some(&expected), components.get(key));
+        }
+
+        assert_eq!(halves.len(), halves.join(&components).count());
+
+        components.clear();
+        assert!(model.values().all(|k| !components.contains_key(k)));
+    }
+}
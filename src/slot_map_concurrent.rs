@@ -0,0 +1,635 @@
+use super::slot_map_concurrent_stats::{ConcurrentSlotMapStats, ShardCounters};
+use super::{SlotMap, SlotMapKey, SlotMapKeyData, SLOT_MAP_CHUNK_SIZE};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Instant;
+
+/// Slot map that can be used from many threads at once. Slots are spread
+/// across a number of independently locked shards, each with its own chain of
+/// open slots, so threads working on different shards never wait for each
+/// other. The shard holding a value is encoded in the chunk index of its key
+pub struct ConcurrentSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    shards: Box<[Shard<K, P, T>]>,
+    next_shard: AtomicUsize,
+    counters: Option<Box<[ShardCounters]>>,
+}
+
+impl<K, P, T> std::fmt::Debug for ConcurrentSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConcurrentSlotMap")
+            .field("shard_count", &self.shards.len())
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl<K, P, T> Default for ConcurrentSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        ConcurrentSlotMap::new()
+    }
+}
+
+impl<K, P, T> ConcurrentSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a map with a few shards for every thread the machine can run
+    /// in parallel
+    pub fn new() -> ConcurrentSlotMap<K, P, T> {
+        let parallelism = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+
+        ConcurrentSlotMap::with_shard_count(parallelism * 4)
+    }
+
+    /// Create a map with the given number of shards
+    ///
+    /// # Panics
+    /// If the shard count is zero
+    pub fn with_shard_count(shard_count: usize) -> ConcurrentSlotMap<K, P, T> {
+        assert!(shard_count > 0, "a concurrent slot map needs a shard");
+
+        ConcurrentSlotMap {
+            shards: (0..shard_count)
+                .map(|_| CacheAligned(RwLock::new(SlotMap::new())))
+                .collect(),
+            next_shard: AtomicUsize::new(0),
+            counters: None,
+        }
+    }
+
+    /// Make this map record lock waits, lock counts, contended inserts, and
+    /// chunk allocations for each shard, which can be read with
+    /// [`stats`](Self::stats). Recording costs a few atomic additions and a
+    /// clock read every time a shard is locked, so it is off by default
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let map = ConcurrentSlotMap::<TestKey, (), usize>::with_shard_count(2)
+    ///     .with_stats();
+    ///
+    /// let key = map.insert((), 1);
+    /// assert_eq!(Some(1), map.get_cloned(&key));
+    ///
+    /// let stats = map.stats().unwrap();
+    /// assert_eq!(2, stats.shards.len());
+    /// assert_eq!(1, stats.total().writes);
+    /// assert_eq!(1, stats.total().reads);
+    /// assert_eq!(1, stats.total().resizes);
+    /// ```
+    pub fn with_stats(mut self) -> ConcurrentSlotMap<K, P, T> {
+        self.counters = Some(
+            (0..self.shards.len())
+                .map(|_| ShardCounters::default())
+                .collect(),
+        );
+        self
+    }
+
+    /// Get the statistics recorded so far if this map was created with
+    /// [`with_stats`](Self::with_stats)
+    pub fn stats(&self) -> Option<ConcurrentSlotMapStats> {
+        self.counters
+            .as_ref()
+            .map(|counters| ConcurrentSlotMapStats {
+                shards: counters.iter().map(ShardCounters::stats).collect(),
+            })
+    }
+
+    /// Get the number of shards in this map
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Get the number of items in the map. Other threads may change the map
+    /// while the shards are being counted
+    pub fn len(&self) -> usize {
+        (0..self.shards.len()).map(|s| self.read(s).len()).sum()
+    }
+
+    /// Tells if this map is empty. Other threads may change the map while
+    /// the shards are being checked
+    pub fn is_empty(&self) -> bool {
+        (0..self.shards.len()).all(|s| self.read(s).is_empty())
+    }
+
+    /// Insert the given item into the map and return its key. Shards are
+    /// tried in turn starting from the one after the last insert, and the
+    /// item goes to the first one that isn't locked by another thread
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<usize>);
+    /// let map = ConcurrentSlotMap::<TestKey, usize, usize>::new();
+    ///
+    /// let keys = std::thread::scope(|s| {
+    ///     let handles = (0..4)
+    ///         .map(|i| {
+    ///             let map = &map;
+    ///             s.spawn(move || map.insert(i, i * 10))
+    ///         })
+    ///         .collect::<Vec<_>>();
+    ///
+    ///     handles
+    ///         .into_iter()
+    ///         .map(|h| h.join().unwrap())
+    ///         .collect::<Vec<_>>()
+    /// });
+    ///
+    /// for key in &keys {
+    ///     assert_eq!(Some(key.pointer * 10), map.get_cloned(key));
+    /// }
+    /// ```
+    pub fn insert(&self, pointer: P, value: T) -> K {
+        let shard_count = self.shards.len();
+        let start = self.next_shard.fetch_add(1, Ordering::Relaxed);
+
+        let available = (0..shard_count)
+            .map(|offset| (start + offset) % shard_count)
+            .find_map(|shard| match self.shards[shard].0.try_write() {
+                Ok(map) => {
+                    self.record(shard, |c| c.record_write(Default::default()));
+                    Some((shard, map))
+                }
+                Err(_) => {
+                    self.record(shard, ShardCounters::record_contended_insert);
+                    None
+                }
+            });
+
+        let (shard, mut map) = available.unwrap_or_else(|| {
+            let shard = start % shard_count;
+            (shard, self.write(shard))
+        });
+
+        let local = map.insert_raw(value);
+
+        // A fresh slot at the start of a chunk means a chunk was just added
+        if local.index_in_chunk == 0 && local.generation == 0 {
+            self.record(shard, ShardCounters::record_resize);
+        }
+
+        K::from((pointer, self.to_global(shard, local)))
+    }
+
+    /// Call the given function with a reference to the item for the given
+    /// key if it exists. The item's shard is read-locked during the call
+    pub fn get_with<R>(&self, key: &K, f: impl FnOnce(&T) -> R) -> Option<R> {
+        let (shard, local) = self.to_local(key.borrow());
+
+        self.read(shard).get_raw(&local).map(f)
+    }
+
+    /// Get a copy of the item for the given key if it exists
+    pub fn get_cloned(&self, key: &K) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.get_with(key, T::clone)
+    }
+
+    /// Call the given function with a mutable reference to the item for the
+    /// given key if it exists. The item's shard is write-locked during the
+    /// call
+    pub fn get_mut_with<R>(
+        &self,
+        key: &K,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> Option<R> {
+        let (shard, local) = self.to_local(key.borrow());
+
+        self.write(shard).get_mut_raw(&local).map(f)
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get_with(key, |_| ()).is_some()
+    }
+
+    /// Remove the item for the given key, calling the given function with a
+    /// mutable reference to the removed item. Like the single threaded map,
+    /// the item stays in its slot until the slot is reused
+    pub fn remove_with<R>(
+        &self,
+        key: &K,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> Option<R> {
+        let (shard, local) = self.to_local(key.borrow());
+
+        self.write(shard).remove_raw(&local).map(f)
+    }
+
+    /// Remove the item for the given key, returning whether it was present
+    pub fn remove(&self, key: &K) -> bool {
+        self.remove_with(key, |_| ()).is_some()
+    }
+
+    /// Call the given function with the key data and value of every item that
+    /// was in the map at the moment this was called, while other threads keep
+    /// inserting and removing items. The live keys of every shard are
+    /// recorded first with all shards read-locked at once, and the values are
+    /// then visited a chunk's worth at a time with only one shard locked.
+    /// Items inserted afterwards are not visited, and neither are recorded
+    /// items whose generation has changed by the time they are reached,
+    /// because they have been removed since. Values modified in place since
+    /// the recording are visited with their new value. The function is called
+    /// with the item's shard read-locked, so it must not modify the map
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<usize>);
+    /// let map = ConcurrentSlotMap::<TestKey, usize, usize>::with_shard_count(2);
+    /// let _ = (0..10).map(|i| map.insert(i, i)).collect::<Vec<_>>();
+    ///
+    /// let mut sum = 0;
+    /// map.for_each_snapshot(|_, value| sum += value);
+    ///
+    /// assert_eq!((0..10).sum::<usize>(), sum);
+    /// ```
+    pub fn for_each_snapshot(&self, mut f: impl FnMut(SlotMapKeyData, &T)) {
+        let recorded = {
+            let shards = (0..self.shards.len())
+                .map(|shard| self.read(shard))
+                .collect::<Vec<_>>();
+
+            shards
+                .iter()
+                .map(|map| {
+                    map.iter_raw()
+                        .map(|(key_data, _)| key_data)
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for (shard, keys) in recorded.into_iter().enumerate() {
+            for batch in keys.chunks(SLOT_MAP_CHUNK_SIZE) {
+                let map = self.read(shard);
+
+                for local in batch {
+                    if let Some(value) = map.get_raw(local) {
+                        f(self.to_global(shard, *local), value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Consume this map, producing the single threaded map of each shard.
+    /// Keys into a shard's map are the keys of this map with the chunk index
+    /// divided by the shard count
+    pub fn into_shards(self) -> Vec<SlotMap<K, P, T>> {
+        self.shards
+            .into_vec()
+            .into_iter()
+            .map(|s| s.0.into_inner().unwrap_or_else(|e| e.into_inner()))
+            .collect()
+    }
+
+    /// Read-lock the given shard, recording the wait if stats are recorded
+    fn read(&self, shard: usize) -> RwLockReadGuard<'_, SlotMap<K, P, T>> {
+        let Some(counters) = &self.counters else {
+            return read(&self.shards[shard].0);
+        };
+
+        let started = Instant::now();
+        let map = read(&self.shards[shard].0);
+        counters[shard].record_read(started.elapsed());
+        map
+    }
+
+    /// Write-lock the given shard, recording the wait if stats are recorded
+    fn write(&self, shard: usize) -> RwLockWriteGuard<'_, SlotMap<K, P, T>> {
+        let Some(counters) = &self.counters else {
+            return write(&self.shards[shard].0);
+        };
+
+        let started = Instant::now();
+        let map = write(&self.shards[shard].0);
+        counters[shard].record_write(started.elapsed());
+        map
+    }
+
+    /// Call the given function with the given shard's counters if stats are
+    /// recorded
+    fn record(&self, shard: usize, f: impl FnOnce(&ShardCounters)) {
+        if let Some(counters) = &self.counters {
+            f(&counters[shard]);
+        }
+    }
+
+    /// Convert key data for the given shard's map into key data for this map
+    fn to_global(&self, shard: usize, local: SlotMapKeyData) -> SlotMapKeyData {
+        SlotMapKeyData {
+            chunk_index: local.chunk_index * self.shards.len() as u32
+                + shard as u32,
+            ..local
+        }
+    }
+
+    /// Find the shard and the shard's key data for the given key data for
+    /// this map
+    fn to_local(&self, global: &SlotMapKeyData) -> (usize, SlotMapKeyData) {
+        let shard_count = self.shards.len() as u32;
+
+        (
+            (global.chunk_index % shard_count) as usize,
+            SlotMapKeyData {
+                chunk_index: global.chunk_index / shard_count,
+                ..*global
+            },
+        )
+    }
+}
+
+/// Value given its own cache line with the `cache-align` feature, so that
+/// writes to neighboring values, like the locks of other shards, don't slow
+/// down threads using this one
+#[derive(Debug)]
+#[cfg_attr(feature = "cache-align", repr(align(64)))]
+struct CacheAligned<T>(T);
+
+/// A shard of a concurrent map
+type Shard<K, P, T> = CacheAligned<RwLock<SlotMap<K, P, T>>>;
+
+/// Read-lock the given lock. A panic while a map was locked can't leave the
+/// map in an inconsistent state, so poisoning is ignored
+pub(crate) fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Write-lock the given lock, ignoring poisoning
+pub(crate) fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Lock the given mutex, ignoring poisoning
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use std::borrow::Borrow;
+    use std::collections::HashSet;
+
+    // Shards and their counters never share a cache line
+    #[cfg(feature = "cache-align")]
+    const_assert_eq!(std::mem::align_of::<Shard<TestKey, usize, u8>>(), 64);
+    #[cfg(feature = "cache-align")]
+    const_assert_eq!(
+        std::mem::align_of::<crate::slot_map_concurrent_stats::ShardCounters>(),
+        64
+    );
+
+    #[test]
+    fn test_concurrent_crud() {
+        let map =
+            ConcurrentSlotMap::<TestKey, usize, String>::with_shard_count(3);
+
+        let per_thread = 2000;
+
+        let keys = std::thread::scope(|s| {
+            let handles = (0..8)
+                .map(|t| {
+                    let map = &map;
+
+                    s.spawn(move || {
+                        let mut kept = Vec::new();
+
+                        for i in 0..per_thread {
+                            let p = t * per_thread + i;
+                            let key = map.insert(p, format!("{}", p));
+
+                            if i % 3 == 0 {
+                                assert!(map.remove(&key));
+                                assert!(!map.contains_key(&key));
+                            } else {
+                                kept.push(key);
+                            }
+                        }
+
+                        kept
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(keys.len(), map.len());
+
+        let unique = keys
+            .iter()
+            .map(|k| u64::from(*Borrow::<SlotMapKeyData>::borrow(k)))
+            .collect::<HashSet<_>>();
+        assert_eq!(keys.len(), unique.len());
+
+        for k in &keys {
+            assert_eq!(Some(format!("{}", k.pointer)), map.get_cloned(k));
+        }
+
+        let _ = map.get_mut_with(&keys[0], |v| v.push('!'));
+        assert_eq!(
+            Some(format!("{}!", keys[0].pointer)),
+            map.get_cloned(&keys[0])
+        );
+    }
+
+    #[test]
+    fn test_keys_translate_to_shards() {
+        let map =
+            ConcurrentSlotMap::<TestKey, usize, usize>::with_shard_count(4);
+
+        let keys = (0..1000).map(|i| map.insert(i, i)).collect::<Vec<_>>();
+        let shards = map.into_shards();
+
+        assert_eq!(1000, shards.iter().map(|s| s.len()).sum::<usize>());
+
+        for k in &keys {
+            let global: &SlotMapKeyData = k.borrow();
+            let local = SlotMapKeyData {
+                chunk_index: global.chunk_index / 4,
+                ..*global
+            };
+
+            assert_eq!(
+                Some(&k.pointer),
+                shards[global.chunk_index as usize % 4].get_raw(&local)
+            );
+        }
+    }
+
+    #[test]
+    fn test_stats_count_operations() {
+        let unrecorded =
+            ConcurrentSlotMap::<TestKey, usize, usize>::with_shard_count(2);
+        let _ = unrecorded.insert(0, 0);
+        assert_eq!(None, unrecorded.stats());
+
+        let map =
+            ConcurrentSlotMap::<TestKey, usize, usize>::with_shard_count(3)
+                .with_stats();
+        let per_thread = SLOT_MAP_CHUNK_SIZE * 2;
+
+        let keys = std::thread::scope(|s| {
+            let handles = (0..4)
+                .map(|t| {
+                    let map = &map;
+
+                    s.spawn(move || {
+                        (0..per_thread)
+                            .map(|i| map.insert(t * per_thread + i, i))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        for key in &keys[..10] {
+            assert!(map.contains_key(key));
+        }
+        assert!(map.remove(&keys[0]));
+
+        let stats = map.stats().unwrap();
+        let total = stats.total();
+
+        assert_eq!(3, stats.shards.len());
+        assert_eq!(keys.len() as u64 + 1, total.writes);
+        assert_eq!(10, total.reads);
+
+        let shards = map.into_shards();
+
+        // Every shard added one chunk per chunk's worth of slots
+        for (shard, map) in stats.shards.iter().zip(&shards) {
+            assert_eq!(
+                map.slots().slot_count().div_ceil(SLOT_MAP_CHUNK_SIZE) as u64,
+                shard.resizes
+            );
+        }
+    }
+
+    #[test]
+    fn test_stats_count_each_shard() {
+        let map =
+            ConcurrentSlotMap::<TestKey, usize, usize>::with_shard_count(2)
+                .with_stats();
+
+        // Inserts alternate between shards, so the first shard gets one item
+        // past a chunk and adds a second chunk for it
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 2 + 1)
+            .map(|i| map.insert(i, i))
+            .collect::<Vec<_>>();
+
+        for key in &keys {
+            assert_eq!(Some(key.pointer), map.get_cloned(key));
+        }
+
+        let stats = map.stats().unwrap();
+        let inserted =
+            [SLOT_MAP_CHUNK_SIZE as u64 + 1, SLOT_MAP_CHUNK_SIZE as u64];
+
+        for (shard, inserted) in stats.shards.iter().zip(inserted) {
+            assert_eq!(inserted, shard.writes);
+            assert_eq!(inserted, shard.reads);
+            assert_eq!(0, shard.contended_inserts);
+        }
+        assert_eq!(2, stats.shards[0].resizes);
+        assert_eq!(1, stats.shards[1].resizes);
+
+        // An insert starting at a locked shard counts a contended insert and
+        // moves on to the other shard, which it write-locks
+        let held = write(&map.shards[1].0);
+        let key = map.insert(0, 0);
+        let stats = map.stats().unwrap();
+        drop(held);
+
+        assert_eq!(1, stats.shards[1].contended_inserts);
+        assert_eq!(SLOT_MAP_CHUNK_SIZE as u64 + 2, stats.shards[0].writes);
+        assert!(map.remove(&key));
+
+        // The total adds up every shard
+        let stats = map.stats().unwrap();
+        let total = stats.total();
+
+        assert_eq!(keys.len() as u64 + 2, total.writes);
+        assert_eq!(keys.len() as u64, total.reads);
+        assert_eq!(1, total.contended_inserts);
+        assert_eq!(3, total.resizes);
+        assert_eq!(
+            stats.shards[0].read_wait + stats.shards[1].read_wait,
+            total.read_wait
+        );
+        assert_eq!(
+            stats.shards[0].write_wait + stats.shards[1].write_wait,
+            total.write_wait
+        );
+    }
+
+    #[test]
+    fn test_snapshot_iteration_with_writers() {
+        let map =
+            ConcurrentSlotMap::<TestKey, usize, usize>::with_shard_count(3);
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 8)
+            .map(|i| map.insert(i, i))
+            .collect::<Vec<_>>();
+        let (kept, removed) = keys.split_at(keys.len() / 2);
+
+        let mut visited = Vec::new();
+        let started = std::sync::Barrier::new(2);
+
+        std::thread::scope(|s| {
+            let _ = s.spawn(|| {
+                let _ = started.wait();
+
+                for (i, key) in removed.iter().enumerate() {
+                    assert!(map.remove(key));
+                    let _ = map.insert(usize::MAX, usize::MAX - i);
+                }
+            });
+
+            let mut first = true;
+
+            map.for_each_snapshot(|_, value| {
+                if first {
+                    first = false;
+                    let _ = started.wait();
+                }
+
+                visited.push(*value);
+            });
+        });
+
+        visited.sort_unstable();
+
+        // Every kept item is visited, no new item is, and removed items are
+        // only visited if they were reached before being removed
+        assert!(kept
+            .iter()
+            .all(|k| visited.binary_search(&k.pointer).is_ok()));
+        assert!(visited.iter().all(|v| *v < keys.len()));
+        assert!(visited.windows(2).all(|w| w[0] < w[1]));
+    }
+}
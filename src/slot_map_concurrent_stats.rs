@@ -0,0 +1,107 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Statistics recorded by a [`ConcurrentSlotMap`](crate::ConcurrentSlotMap)
+/// created with
+/// [`with_stats`](crate::ConcurrentSlotMap::with_stats), one entry per shard.
+/// Shards with much more waiting than the others, or many contended inserts
+/// overall, are signs that the map needs more shards
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ConcurrentSlotMapStats {
+    /// Statistics of each shard, in shard order
+    pub shards: Vec<ShardStats>,
+}
+
+impl ConcurrentSlotMapStats {
+    /// Get the statistics of all shards added together
+    pub fn total(&self) -> ShardStats {
+        self.shards
+            .iter()
+            .fold(ShardStats::default(), |total, shard| ShardStats {
+                reads: total.reads + shard.reads,
+                writes: total.writes + shard.writes,
+                read_wait: total.read_wait + shard.read_wait,
+                write_wait: total.write_wait + shard.write_wait,
+                contended_inserts: total.contended_inserts
+                    + shard.contended_inserts,
+                resizes: total.resizes + shard.resizes,
+            })
+    }
+}
+
+/// Statistics of a single shard of a concurrent slot map
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ShardStats {
+    /// Number of times the shard was read-locked
+    pub reads: u64,
+    /// Number of times the shard was write-locked
+    pub writes: u64,
+    /// Total time spent waiting to read-lock the shard
+    pub read_wait: Duration,
+    /// Total time spent waiting to write-lock the shard
+    pub write_wait: Duration,
+    /// Number of inserts that found the shard locked and moved on to the
+    /// next one
+    pub contended_inserts: u64,
+    /// Number of chunks added to the shard's map as it grew
+    pub resizes: u64,
+}
+
+/// Counters a shard's statistics are recorded into. With the `cache-align`
+/// feature, each shard's counters get their own cache line, so threads on
+/// different shards don't contend for it
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "cache-align", repr(align(64)))]
+pub(crate) struct ShardCounters {
+    reads: AtomicU64,
+    writes: AtomicU64,
+    read_wait_nanos: AtomicU64,
+    write_wait_nanos: AtomicU64,
+    contended_inserts: AtomicU64,
+    resizes: AtomicU64,
+}
+
+impl ShardCounters {
+    /// Record that the shard was read-locked after the given wait
+    pub(crate) fn record_read(&self, wait: Duration) {
+        let _ = self.reads.fetch_add(1, Ordering::Relaxed);
+        let _ = self
+            .read_wait_nanos
+            .fetch_add(wait.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Record that the shard was write-locked after the given wait
+    pub(crate) fn record_write(&self, wait: Duration) {
+        let _ = self.writes.fetch_add(1, Ordering::Relaxed);
+        let _ = self
+            .write_wait_nanos
+            .fetch_add(wait.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Record that an insert found the shard locked
+    pub(crate) fn record_contended_insert(&self) {
+        let _ = self.contended_inserts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record that a chunk was added to the shard's map
+    pub(crate) fn record_resize(&self) {
+        let _ = self.resizes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Read the counters. Counters are read one at a time, so operations
+    /// running at the same time may only be partly included
+    pub(crate) fn stats(&self) -> ShardStats {
+        ShardStats {
+            reads: self.reads.load(Ordering::Relaxed),
+            writes: self.writes.load(Ordering::Relaxed),
+            read_wait: Duration::from_nanos(
+                self.read_wait_nanos.load(Ordering::Relaxed),
+            ),
+            write_wait: Duration::from_nanos(
+                self.write_wait_nanos.load(Ordering::Relaxed),
+            ),
+            contended_inserts: self.contended_inserts.load(Ordering::Relaxed),
+            resizes: self.resizes.load(Ordering::Relaxed),
+        }
+    }
+}
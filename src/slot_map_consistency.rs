@@ -0,0 +1,147 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+use std::collections::HashSet;
+
+/// Differences between the keys a caller expects to be live in a
+/// [`SlotMap`] and the items actually in it. Found with
+/// [`SlotMap::key_discrepancies`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyDiscrepancies {
+    /// Key data of expected keys whose items aren't in the map, either
+    /// because they were removed or because the key is from somewhere else
+    pub missing: Vec<SlotMapKeyData>,
+
+    /// Key data of items in the map that weren't among the expected keys
+    pub unexpected: Vec<SlotMapKeyData>,
+}
+
+impl KeyDiscrepancies {
+    /// Tells if the expected keys and the map agree exactly
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty()
+    }
+}
+
+impl std::fmt::Display for KeyDiscrepancies {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} expected keys missing from the map: {:?}; \
+            {} unexpected items in the map: {:?}",
+            self.missing.len(),
+            self.missing,
+            self.unexpected.len(),
+            self.unexpected
+        )
+    }
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Compare the items in this map with the given keys, which are expected
+    /// to be exactly the keys of the items in the map. Both lists of
+    /// differences are in slot order, and repeated keys count once
+    pub fn key_discrepancies<'a>(
+        &self,
+        expected: impl IntoIterator<Item = &'a K>,
+    ) -> KeyDiscrepancies
+    where
+        K: 'a,
+    {
+        let expected = expected
+            .into_iter()
+            .map(|key| *key.borrow())
+            .collect::<HashSet<SlotMapKeyData>>();
+
+        let mut missing = expected
+            .iter()
+            .filter(|key_data| !self.contains_key_raw(key_data))
+            .copied()
+            .collect::<Vec<_>>();
+
+        missing
+            .sort_by_key(|key_data| (key_data.position(), key_data.generation));
+
+        KeyDiscrepancies {
+            missing,
+            unexpected: self
+                .iter_raw()
+                .map(|(key_data, _)| key_data)
+                .filter(|key_data| !expected.contains(key_data))
+                .collect(),
+        }
+    }
+
+    /// Panic with a list of the differences if the given keys aren't exactly
+    /// the keys of the items in this map. See [`SlotMap::key_discrepancies`]
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()> : Clone);
+    /// let mut map = SlotMap::<TestKey, (), usize>::new();
+    ///
+    /// let mut live = (0..10).map(|i| map.insert((), i)).collect::<Vec<_>>();
+    /// let removed = live.remove(3);
+    /// let _ = map.remove(&removed);
+    ///
+    /// map.assert_consistent_with(&live);
+    ///
+    /// live.push(removed);
+    /// let result = std::panic::catch_unwind(|| map.assert_consistent_with(&live));
+    /// assert!(result.is_err());
+    /// ```
+    #[track_caller]
+    pub fn assert_consistent_with<'a>(
+        &self,
+        expected: impl IntoIterator<Item = &'a K>,
+    ) where
+        K: 'a,
+    {
+        let discrepancies = self.key_discrepancies(expected);
+
+        if !discrepancies.is_empty() {
+            panic!("map does not match the expected keys: {}", discrepancies);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::churned_map;
+    use std::borrow::Borrow;
+
+    #[test]
+    fn test_discrepancies_are_found() {
+        let (map, mut live, removed) = churned_map(1, |i| i);
+
+        map.assert_consistent_with(&live);
+        map.assert_consistent_with(live.iter().chain(live.iter()));
+
+        // Forget some live keys and believe some removed ones are live
+        let forgotten = live.split_off(live.len() / 2);
+        let stale = &removed[..removed.len().min(5)];
+
+        let discrepancies = map.key_discrepancies(live.iter().chain(stale));
+
+        let mut expected_unexpected = forgotten
+            .iter()
+            .map(|key| *Borrow::<SlotMapKeyData>::borrow(key))
+            .collect::<Vec<_>>();
+        expected_unexpected.sort_by_key(SlotMapKeyData::position);
+
+        assert_eq!(expected_unexpected, discrepancies.unexpected);
+        assert_eq!(
+            stale
+                .iter()
+                .map(|key| *Borrow::<SlotMapKeyData>::borrow(key))
+                .collect::<HashSet<_>>(),
+            discrepancies
+                .missing
+                .iter()
+                .copied()
+                .collect::<HashSet<_>>()
+        );
+    }
+}
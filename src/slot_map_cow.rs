@@ -0,0 +1,165 @@
+use super::{SlotMap, SlotMapKey};
+use std::ops::{Deref, DerefMut};
+
+/// Slot map whose clones share their full chunks instead of copying them.
+/// Cloning costs a reference count per chunk plus a copy of the partially
+/// filled chunk, and a shared chunk is copied by whichever map first modifies
+/// it, so double-buffered state only pays for the chunks that actually
+/// change. Everything else works exactly like the [`SlotMap`] it derefs to
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()>);
+/// let mut front = CowSlotMap::<TestKey, (), String>::new();
+/// let key = front.insert((), "Hello".to_owned());
+///
+/// let mut back = front.clone();
+/// back.get_mut(&key).unwrap().push_str(", World");
+///
+/// assert_eq!(Some(&"Hello".to_owned()), front.get(&key));
+/// assert_eq!(Some(&"Hello, World".to_owned()), back.get(&key));
+/// ```
+pub struct CowSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, T>,
+}
+
+impl<K, P, T> std::fmt::Debug for CowSlotMap<K, P, T>
+where
+    T: std::fmt::Debug,
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.map.fmt(f)
+    }
+}
+
+impl<K, P, T> Default for CowSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        CowSlotMap::new()
+    }
+}
+
+impl<K, P, T> Clone for CowSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Clone + Sync,
+{
+    fn clone(&self) -> Self {
+        CowSlotMap {
+            map: self.map.share_all(),
+        }
+    }
+}
+
+impl<K, P, T> Deref for CowSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    type Target = SlotMap<K, P, T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
+
+impl<K, P, T> DerefMut for CowSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.map
+    }
+}
+
+impl<K, P, T> From<SlotMap<K, P, T>> for CowSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn from(map: SlotMap<K, P, T>) -> Self {
+        CowSlotMap { map }
+    }
+}
+
+impl<K, P, T> CowSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> CowSlotMap<K, P, T> {
+        CowSlotMap {
+            map: SlotMap::new(),
+        }
+    }
+
+    /// Get the underlying slot map, which keeps copying shared chunks before
+    /// modifying them
+    pub fn into_inner(self) -> SlotMap<K, P, T> {
+        self.map
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use crate::SLOT_MAP_CHUNK_SIZE;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static CLONES: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug, PartialEq)]
+    struct Counted(usize);
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            let _ = CLONES.fetch_add(1, Ordering::Relaxed);
+            Counted(self.0)
+        }
+    }
+
+    #[test]
+    fn test_clone_copies_only_modified_chunks() {
+        let mut front = CowSlotMap::<TestKey, usize, Counted>::new();
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 3 + 10)
+            .map(|i| front.insert(i, Counted(i)))
+            .collect::<Vec<_>>();
+
+        // Only the partially filled chunk is copied
+        let mut back = front.clone();
+        assert_eq!(10, CLONES.swap(0, Ordering::Relaxed));
+
+        back.get_mut(&keys[0]).unwrap().0 += 1000;
+        let _ = back.remove(&keys[SLOT_MAP_CHUNK_SIZE]);
+        assert_eq!(2 * SLOT_MAP_CHUNK_SIZE, CLONES.swap(0, Ordering::Relaxed));
+
+        // Modifying a chunk again doesn't copy it again
+        back.get_mut(&keys[1]).unwrap().0 += 1000;
+        front.get_mut(&keys[SLOT_MAP_CHUNK_SIZE * 2]).unwrap().0 += 1000;
+        assert_eq!(SLOT_MAP_CHUNK_SIZE, CLONES.swap(0, Ordering::Relaxed));
+
+        for (i, key) in keys.iter().enumerate() {
+            let front_value = front.get(key).map(|v| v.0);
+            let back_value = back.get(key).map(|v| v.0);
+
+            match i {
+                0 | 1 => assert_eq!(Some(i + 1000), back_value),
+                _ if i == SLOT_MAP_CHUNK_SIZE => assert_eq!(None, back_value),
+                _ if i == SLOT_MAP_CHUNK_SIZE * 2 => {
+                    assert_eq!(Some(i + 1000), front_value);
+                    assert_eq!(Some(i), back_value);
+                    continue;
+                }
+                _ => assert_eq!(Some(i), back_value),
+            }
+
+            assert_eq!(Some(i), front_value);
+        }
+    }
+}
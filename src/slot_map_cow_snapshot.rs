@@ -0,0 +1,262 @@
+use super::slot_map::FilledChunk;
+use super::{SlotMap, SlotMapKey, SlotMapKeyData, SLOT_MAP_CHUNK_SIZE};
+use std::marker::PhantomData;
+
+/// Read-only view of a slot map at the moment it was taken. Full chunks are
+/// shared with the map rather than copied, and the map copies a shared chunk
+/// the first time it modifies it afterwards, so taking a snapshot costs about
+/// as much as copying a single chunk. Created with
+/// [`SlotMap::snapshot`](crate::SlotMap::snapshot)
+pub struct SlotMapSnapshot<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    filled_chunks: Vec<FilledChunk<T>>,
+    current_chunk: Vec<(SlotMapKeyData, T)>,
+    len: usize,
+
+    _phantom: PhantomData<fn(P, K)>,
+}
+
+impl<K, P, T> std::fmt::Debug for SlotMapSnapshot<K, P, T>
+where
+    T: std::fmt::Debug,
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.values()).finish()
+    }
+}
+
+impl<K, P, T> Clone for SlotMapSnapshot<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        SlotMapSnapshot {
+            filled_chunks: self.filled_chunks.clone(),
+            current_chunk: self.current_chunk.clone(),
+            len: self.len,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<K, P, T> SlotMapSnapshot<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Get the number of items in the map when the snapshot was taken
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Tells if the map was empty when the snapshot was taken
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get a reference to the item that corresponded to the given key when
+    /// the snapshot was taken
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        let chunk_index = key_data.chunk_index as usize;
+        let index_in_chunk = key_data.index_in_chunk as usize;
+
+        let slot = if chunk_index < self.filled_chunks.len() {
+            self.filled_chunks[chunk_index].get(index_in_chunk)
+        } else if chunk_index == self.filled_chunks.len() {
+            self.current_chunk
+                .get(index_in_chunk)
+                .map(|(key_data, value)| (key_data, value))
+        } else {
+            None
+        };
+
+        slot.filter(|(k, _)| k.is_filled())
+            .filter(|(k, _)| k.generation == key_data.generation)
+            .map(|(_, value)| value)
+    }
+
+    /// Tells if the given key was in the map when the snapshot was taken
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Create an iterator over the raw key data and values of the items in
+    /// the snapshot
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.filled_chunks
+            .iter()
+            .flat_map(|chunk| chunk.iter())
+            .chain(
+                self.current_chunk
+                    .iter()
+                    .map(|(key_data, value)| (key_data, value)),
+            )
+            .enumerate()
+            .filter(|(_, (k, _))| k.is_filled())
+            .map(|(linear_index, (k, value))| {
+                let key_data = SlotMapKeyData {
+                    generation: k.generation,
+                    ..SlotMapKeyData::from(linear_index as u64)
+                };
+
+                (key_data, value)
+            })
+    }
+
+    /// Create an iterator over the values of the items in the snapshot
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.iter_raw().map(|(_, value)| value)
+    }
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Take a consistent read-only view of this map that can be handed to
+    /// another thread while this map continues to be modified. Only the
+    /// partially filled last chunk is copied up front, and each full chunk is
+    /// copied at most once, when this map first modifies it while the
+    /// snapshot is alive. Values are shared between the map and its snapshots
+    /// until they are copied, so changes made through interior mutability are
+    /// visible in both
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey,(),usize>::new();
+    /// let key = map.insert((), 1);
+    ///
+    /// let snapshot = map.snapshot();
+    ///
+    /// *map.get_mut(&key).unwrap() = 2;
+    /// let _ = map.insert((), 3);
+    ///
+    /// assert_eq!(Some(&1), snapshot.get(&key));
+    /// assert_eq!(1, snapshot.len());
+    /// assert_eq!(Some(&2), map.get(&key));
+    /// ```
+    pub fn snapshot(&self) -> SlotMapSnapshot<K, P, T>
+    where
+        T: Clone + Sync,
+    {
+        let (filled_chunks, current_chunk) = self.slots().share();
+
+        debug_assert!(current_chunk.len() < SLOT_MAP_CHUNK_SIZE);
+
+        SlotMapSnapshot {
+            filled_chunks,
+            current_chunk,
+            len: self.len(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_snapshot_is_isolated_from_writes() {
+        let mut map = SlotMap::<TestKey, usize, String>::new();
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 3 + 5)
+            .map(|i| map.insert(i, format!("{}", i)))
+            .collect::<Vec<_>>();
+        let _ = map.remove(&keys[4]);
+
+        let expected = map.clone();
+        let snapshot = map.snapshot();
+
+        *map.get_mut(&keys[0]).unwrap() = "changed".to_owned();
+        let _ = map.remove(&keys[SLOT_MAP_CHUNK_SIZE + 1]);
+        for v in map.values_mut() {
+            v.push('!');
+        }
+        let new_keys = (0..SLOT_MAP_CHUNK_SIZE)
+            .map(|i| map.insert(i, "new".to_owned()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(expected.len(), snapshot.len());
+
+        for k in keys.iter().chain(&new_keys) {
+            assert_eq!(expected.get(k), snapshot.get(k));
+        }
+
+        assert!(expected.iter_raw().eq(snapshot.iter_raw()));
+        assert_eq!(Some(&"changed!".to_owned()), map.get(&keys[0]));
+
+        // The map keeps working normally once the snapshot is gone
+        drop(snapshot);
+        *map.get_mut(&keys[1]).unwrap() = "again".to_owned();
+        assert_eq!(Some(&"again".to_owned()), map.get(&keys[1]));
+    }
+
+    #[derive(Debug)]
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            let _ = self.0.fetch_add(1, Ordering::SeqCst);
+            Counted(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn test_only_modified_chunks_are_copied() {
+        let clones = Arc::new(AtomicUsize::new(0));
+        let mut map = SlotMap::<TestKey, usize, Counted>::new();
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 4 + 10)
+            .map(|i| map.insert(i, Counted(clones.clone())))
+            .collect::<Vec<_>>();
+
+        let snapshot = map.snapshot();
+
+        // Only the partially filled chunk is copied up front
+        assert_eq!(10, clones.load(Ordering::SeqCst));
+
+        let _ = map.get_mut(&keys[SLOT_MAP_CHUNK_SIZE * 2]);
+        let _ = map.get_mut(&keys[SLOT_MAP_CHUNK_SIZE * 2 + 1]);
+        assert_eq!(10 + SLOT_MAP_CHUNK_SIZE, clones.load(Ordering::SeqCst));
+
+        let _ = map.remove(&keys[0]);
+        assert_eq!(10 + SLOT_MAP_CHUNK_SIZE * 2, clones.load(Ordering::SeqCst));
+
+        drop(snapshot);
+
+        // Chunks are no longer shared, so nothing more is copied
+        let _ = map.remove(&keys[SLOT_MAP_CHUNK_SIZE * 3]);
+        assert_eq!(10 + SLOT_MAP_CHUNK_SIZE * 2, clones.load(Ordering::SeqCst));
+
+        let export = map.export();
+        assert_eq!(10 + SLOT_MAP_CHUNK_SIZE * 2, clones.load(Ordering::SeqCst));
+        assert_eq!(SLOT_MAP_CHUNK_SIZE * 4 + 8, export.values().len());
+    }
+
+    #[test]
+    fn test_consuming_map_while_shared() {
+        let mut map = SlotMap::<TestKey, usize, String>::new();
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 2)
+            .map(|i| map.insert(i, format!("{}", i)))
+            .collect::<Vec<_>>();
+
+        let snapshot = map.snapshot();
+        let export = map.export();
+
+        assert_eq!(SLOT_MAP_CHUNK_SIZE * 2, export.values().len());
+        assert_eq!(Some(&"7".to_owned()), snapshot.get(&keys[7]));
+    }
+}
@@ -0,0 +1,149 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData, SLOT_MAP_CHUNK_SIZE};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Policy used by new maps until [`StaleKeyPolicy::set_default`] is called
+static DEFAULT_POLICY: AtomicU8 = AtomicU8::new(StaleKeyPolicy::Log as u8);
+
+/// What a [`SlotMap`] does when a key whose item has been removed is passed
+/// to `get`, `get_mut`, or `remove`. Without this, the stale key just finds
+/// nothing, which can hide use-after-remove bugs
+///
+/// ```should_panic
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()>);
+/// let mut map = SlotMap::<TestKey, (), usize>::new();
+/// map.set_stale_key_policy(StaleKeyPolicy::Panic);
+///
+/// let key = map.insert((), 5);
+/// let _ = map.remove(&key);
+///
+/// // Panics, naming the slot, its current generation, and this line
+/// let _ = map.get(&key);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum StaleKeyPolicy {
+    /// Return `None` without reporting anything, like without the feature
+    Ignore,
+
+    /// Print a description of the stale key and where it was used to stderr
+    Log,
+
+    /// Panic with a description of the stale key and where it was used
+    Panic,
+}
+
+impl StaleKeyPolicy {
+    /// Set the policy that maps created from now on will start with.
+    /// Initially this is [`StaleKeyPolicy::Log`]
+    pub fn set_default(policy: StaleKeyPolicy) {
+        DEFAULT_POLICY.store(policy as u8, Ordering::Relaxed);
+    }
+
+    /// Get the policy that new maps start with
+    pub fn current_default() -> StaleKeyPolicy {
+        match DEFAULT_POLICY.load(Ordering::Relaxed) {
+            0 => StaleKeyPolicy::Ignore,
+            1 => StaleKeyPolicy::Log,
+            _ => StaleKeyPolicy::Panic,
+        }
+    }
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Report the given key data according to this map's policy if it was
+    /// issued for a slot whose item has since been removed. Key data for
+    /// slots that were never written may come from another map, so it isn't
+    /// reported
+    #[track_caller]
+    pub(crate) fn check_stale_key(
+        &self,
+        operation: &str,
+        key_data: &SlotMapKeyData,
+    ) {
+        if self.stale_key_policy() == StaleKeyPolicy::Ignore
+            || !key_data.is_filled()
+        {
+            return;
+        }
+
+        let Some((slot_key_data, _)) = self
+            .slots()
+            .chunk(key_data.chunk_index as usize)
+            .and_then(|chunk| chunk.get(key_data.index_in_chunk as usize))
+        else {
+            return;
+        };
+
+        if slot_key_data.generation == key_data.generation {
+            return;
+        }
+
+        let state = if slot_key_data.is_filled() {
+            "has been reused"
+        } else {
+            "is vacant"
+        };
+
+        let message = format!(
+            "stale key passed to SlotMap::{} at {}: the item at slot {} \
+            (generation {}) was removed and the slot {} (generation {})",
+            operation,
+            std::panic::Location::caller(),
+            key_data.chunk_index as usize * SLOT_MAP_CHUNK_SIZE
+                + key_data.index_in_chunk as usize,
+            key_data.generation,
+            state,
+            slot_key_data.generation,
+        );
+
+        match self.stale_key_policy() {
+            StaleKeyPolicy::Ignore => {}
+            StaleKeyPolicy::Log => eprintln!("{}", message),
+            StaleKeyPolicy::Panic => panic!("{}", message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::{churned_map, TestKey};
+    use std::panic::AssertUnwindSafe;
+
+    #[test]
+    fn test_only_stale_keys_are_reported() {
+        let (mut map, live, stale) = churned_map(2, |i| i);
+        map.set_stale_key_policy(StaleKeyPolicy::Panic);
+
+        // Live keys and keys for slots that were never written pass quietly
+        for key in &live {
+            assert_eq!(Some(&key.pointer), map.get(key));
+        }
+
+        let unwritten = TestKey::from((
+            0,
+            SlotMapKeyData {
+                chunk_index: 1000,
+                ..Default::default()
+            },
+        ));
+        assert_eq!(None, map.get(&unwritten));
+
+        for key in stale.iter().take(20) {
+            let result =
+                std::panic::catch_unwind(AssertUnwindSafe(|| map.get(key)));
+            assert!(result.is_err());
+        }
+
+        map.set_stale_key_policy(StaleKeyPolicy::Ignore);
+
+        for key in &stale {
+            assert_eq!(None, map.get_mut(key));
+            assert_eq!(None, map.remove(key));
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData, SnapshotError};
+
+/// Difference between two states of a slot map, keyed by slot key data.
+/// Created with [`SlotMap::diff`](crate::SlotMap::diff) and applied with
+/// [`SlotMap::apply_delta`](crate::SlotMap::apply_delta)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SlotMapDelta<T> {
+    inserts: Vec<(SlotMapKeyData, T)>,
+    removals: Vec<SlotMapKeyData>,
+    changes: Vec<(SlotMapKeyData, T)>,
+}
+
+impl<T> SlotMapDelta<T> {
+    /// Entries that are live in the new state but not in the old one
+    pub fn inserts(&self) -> &[(SlotMapKeyData, T)] {
+        &self.inserts
+    }
+
+    /// Keys that are live in the old state but not in the new one
+    pub fn removals(&self) -> &[SlotMapKeyData] {
+        &self.removals
+    }
+
+    /// Entries that are live in both states with different values, along
+    /// with their new values
+    pub fn changes(&self) -> &[(SlotMapKeyData, T)] {
+        &self.changes
+    }
+
+    /// Tells if the two states this delta was taken between hold the same
+    /// entries
+    pub fn is_empty(&self) -> bool {
+        self.inserts.is_empty()
+            && self.removals.is_empty()
+            && self.changes.is_empty()
+    }
+}
+
+/// Get the key data and value of the given slot at the given linear index if
+/// it is filled
+fn live<'a, T>(
+    slot: Option<(&SlotMapKeyData, &'a T)>,
+    linear_index: usize,
+) -> Option<(SlotMapKeyData, &'a T)> {
+    slot.filter(|(key_data, _)| key_data.is_filled()).map(
+        |(key_data, value)| {
+            let key_data = SlotMapKeyData {
+                generation: key_data.generation,
+                ..SlotMapKeyData::from(linear_index as u64)
+            };
+            (key_data, value)
+        },
+    )
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Find the entries that were inserted, removed, or changed between the
+    /// old and new states of a map. A key that was removed and whose slot was
+    /// then reused shows up as both a removal and an insertion
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut old = SlotMap::<TestKey, (), &'static str>::new();
+    /// let kept = old.insert((), "kept");
+    /// let removed = old.insert((), "removed");
+    ///
+    /// let mut replica = old.clone();
+    ///
+    /// let mut new = old.clone();
+    /// *new.get_mut(&kept).unwrap() = "changed";
+    /// let _ = new.remove(&removed);
+    /// let added = new.insert((), "added");
+    ///
+    /// let delta = SlotMap::diff(&old, &new);
+    /// assert_eq!(1, delta.inserts().len());
+    /// assert_eq!(1, delta.removals().len());
+    /// assert_eq!(1, delta.changes().len());
+    ///
+    /// replica.apply_delta(delta).unwrap();
+    ///
+    /// assert_eq!(Some(&"changed"), replica.get(&kept));
+    /// assert_eq!(None, replica.get(&removed));
+    /// assert_eq!(Some(&"added"), replica.get(&added));
+    /// ```
+    pub fn diff(old: &Self, new: &Self) -> SlotMapDelta<T>
+    where
+        T: Clone + PartialEq,
+    {
+        let mut delta = SlotMapDelta {
+            inserts: Vec::new(),
+            removals: Vec::new(),
+            changes: Vec::new(),
+        };
+
+        let mut old_slots = old.slots().values();
+        let mut new_slots = new.slots().values();
+
+        for linear_index in 0.. {
+            let (old_slot, new_slot) =
+                match (old_slots.next(), new_slots.next()) {
+                    (None, None) => break,
+                    (old_slot, new_slot) => (
+                        live(old_slot, linear_index),
+                        live(new_slot, linear_index),
+                    ),
+                };
+
+            match (old_slot, new_slot) {
+                (Some((old_key, old_value)), Some((new_key, new_value)))
+                    if old_key == new_key =>
+                {
+                    if old_value != new_value {
+                        delta.changes.push((new_key, new_value.clone()));
+                    }
+                }
+                (old_slot, new_slot) => {
+                    if let Some((old_key, _)) = old_slot {
+                        delta.removals.push(old_key);
+                    }
+                    if let Some((new_key, new_value)) = new_slot {
+                        delta.inserts.push((new_key, new_value.clone()));
+                    }
+                }
+            }
+        }
+
+        delta
+    }
+
+    /// Bring this map in line with the new state of a delta taken from a map
+    /// in the same state as this one. Removals are applied first, then
+    /// insertions and changes are written under their exact key data. Keys
+    /// that this map does not hold are skipped when removing and written
+    /// anyway when changing, so a replica that missed a delta converges on
+    /// the entries it receives. The entries end up identical to the new
+    /// state, though vacant slots may be reused in a different order. The
+    /// map can grow by up to a chunk's worth of slots per entry written, and
+    /// the delta is rejected without changing the map if it names a slot
+    /// further past the end than that
+    pub fn apply_delta(
+        &mut self,
+        delta: SlotMapDelta<T>,
+    ) -> Result<(), SnapshotError>
+    where
+        T: Default,
+    {
+        // Removed keys are never also changed, so which changed keys this map
+        // holds is the same before and after the removals
+        let (changes, missing) =
+            delta.changes.into_iter().partition::<Vec<_>, _>(
+                |(key_data, _)| self.get_raw(key_data).is_some(),
+            );
+
+        let mut writes = delta.inserts;
+        writes.extend(missing);
+
+        if let Some(key_data) = self.find_out_of_reach(&writes) {
+            return Err(SnapshotError::EntryOutOfRange {
+                chunk_index: key_data.chunk_index as usize,
+                index_in_chunk: key_data.index_in_chunk as usize,
+            });
+        }
+
+        for key_data in &delta.removals {
+            let _ = self.remove_raw(key_data);
+        }
+
+        for (key_data, value) in changes {
+            if let Some(existing) = self.get_mut_raw(&key_data) {
+                *existing = value;
+            }
+        }
+
+        self.insert_all_at(writes);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use crate::SLOT_MAP_CHUNK_SIZE;
+    use std::borrow::Borrow;
+
+    #[test]
+    fn test_apply_reproduces_new_state() {
+        let mut old = SlotMap::<TestKey, usize, String>::new();
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE + 10)
+            .map(|i| old.insert(i, format!("{}", i)))
+            .collect::<Vec<_>>();
+        let _ = old.remove(&keys[8]);
+
+        let mut replica = old.clone();
+        let mut new = old.clone();
+
+        assert!(SlotMap::diff(&old, &new).is_empty());
+
+        let _ = new.remove(&keys[2]);
+        let reused = new.insert(2, "reused".to_owned());
+        *new.get_mut(&keys[100]).unwrap() = "changed".to_owned();
+        let _ = new.remove(&keys[200]);
+        let mut added = (0..SLOT_MAP_CHUNK_SIZE)
+            .map(|i| new.insert(i, format!("added {}", i)))
+            .collect::<Vec<_>>();
+        added.push(reused);
+
+        let delta = SlotMap::diff(&old, &new);
+        assert_eq!(
+            &[(*keys[100].borrow(), "changed".to_owned())],
+            delta.changes()
+        );
+        assert_eq!(2, delta.removals().len());
+
+        replica.apply_delta(delta).unwrap();
+
+        assert_eq!(new.len(), replica.len());
+        for k in keys.iter().chain(&added) {
+            assert_eq!(new.get(k), replica.get(k));
+        }
+        assert!(SlotMap::diff(&new, &replica).is_empty());
+    }
+
+    #[test]
+    fn test_changes_converge_on_missing_keys() {
+        let mut old = SlotMap::<TestKey, usize, usize>::new();
+        let key = old.insert(0, 1);
+
+        let mut new = old.clone();
+        *new.get_mut(&key).unwrap() = 2;
+
+        // This replica never received the insertion of the changed key
+        let mut replica = SlotMap::<TestKey, usize, usize>::new();
+        replica.apply_delta(SlotMap::diff(&old, &new)).unwrap();
+
+        assert_eq!(Some(&2), replica.get(&key));
+        assert_eq!(1, replica.len());
+    }
+
+    #[test]
+    fn test_delta_far_past_end_rejected() {
+        let mut old = SlotMap::<TestKey, usize, usize>::new();
+        let removed = old.insert(0, 0);
+
+        let mut new = old.clone();
+        let _ = new.remove(&removed);
+
+        let mut entries = SlotMap::diff(&old, &new);
+        entries.inserts.push((
+            SlotMapKeyData {
+                chunk_index: u32::MAX - 1,
+                ..SlotMapKeyData::from(0u64)
+            },
+            1,
+        ));
+
+        let mut replica = old.clone();
+
+        assert_eq!(
+            Err(SnapshotError::EntryOutOfRange {
+                chunk_index: (u32::MAX - 1) as usize,
+                index_in_chunk: 0
+            }),
+            replica.apply_delta(entries)
+        );
+
+        // Nothing was applied, not even the removal
+        assert_eq!(Some(&0), replica.get(&removed));
+        assert_eq!(1, replica.slots().slot_count());
+    }
+}
@@ -0,0 +1,248 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+
+/// Slot map whose values are stored next to each other in a single vector, so
+/// iterating over them is a scan of contiguous memory. Slots hold the index
+/// of their value in the vector, which costs an extra lookup on every access,
+/// and removal moves the last value into the removed value's place. Keys are
+/// handed out exactly as a [`SlotMap`] would
+pub struct DenseOneWaySlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    indices: SlotMap<K, P, usize>,
+    values: Vec<T>,
+
+    /// Key data of the item each value belongs to, in the same order as the
+    /// values
+    keys: Vec<SlotMapKeyData>,
+}
+
+impl<K, P, T> std::fmt::Debug for DenseOneWaySlotMap<K, P, T>
+where
+    T: std::fmt::Debug,
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.values.iter()).finish()
+    }
+}
+
+impl<K, P, T> Default for DenseOneWaySlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        DenseOneWaySlotMap::new()
+    }
+}
+
+impl<K, P, T> Clone for DenseOneWaySlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        DenseOneWaySlotMap {
+            indices: self.indices.clone(),
+            values: self.values.clone(),
+            keys: self.keys.clone(),
+        }
+    }
+}
+
+impl<K, P, T> DenseOneWaySlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> DenseOneWaySlotMap<K, P, T> {
+        DenseOneWaySlotMap {
+            indices: SlotMap::new(),
+            values: Vec::new(),
+            keys: Vec::new(),
+        }
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Insert the given item into the map and return its key
+    pub fn insert(&mut self, pointer: P, value: T) -> K {
+        let key_data = self.indices.insert_raw(self.values.len());
+
+        self.values.push(value);
+        self.keys.push(key_data);
+
+        K::from((pointer, key_data))
+    }
+
+    /// Get a reference to the item for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.indices
+            .get_raw(key_data)
+            .map(|index| &self.values[*index])
+    }
+
+    /// Get a mutable reference to the item for the given key if it exists
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Same as get_mut, but only requires slot map key data
+    pub fn get_mut_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        self.indices
+            .get_raw(key_data)
+            .map(|index| &mut self.values[*index])
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    /// Remove the item for the given key and return it. The last value in the
+    /// map takes the removed value's place
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = DenseOneWaySlotMap::<TestKey,(),&'static str>::new();
+    ///
+    /// let first = map.insert((), "first");
+    /// let second = map.insert((), "second");
+    /// let third = map.insert((), "third");
+    ///
+    /// assert_eq!(Some("first"), map.remove(&first));
+    /// assert_eq!(None, map.remove(&first));
+    ///
+    /// assert_eq!(&["third", "second"], map.values());
+    /// assert_eq!(Some(&"third"), map.get(&third));
+    /// assert_eq!(Some(&"second"), map.get(&second));
+    /// ```
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<T> {
+        let index = *self.indices.remove_raw(key_data)?;
+
+        let value = self.values.swap_remove(index);
+        let _ = self.keys.swap_remove(index);
+
+        if let Some(moved) = self.keys.get(index) {
+            *self
+                .indices
+                .get_mut_raw(moved)
+                .expect("missing slot for moved value") = index;
+        }
+
+        Some(value)
+    }
+
+    /// Remove all items from the map
+    pub fn clear(&mut self) {
+        self.indices.clear();
+        self.values.clear();
+        self.keys.clear();
+    }
+
+    /// Get the values of all items in the map. Values are in the order they
+    /// were inserted until items are removed
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// Get mutable access to the values of all items in the map
+    pub fn values_mut(&mut self) -> &mut [T] {
+        &mut self.values
+    }
+
+    /// Get the key data of all items in the map, in the same order as
+    /// [`values`](Self::values)
+    pub fn keys_raw(&self) -> &[SlotMapKeyData] {
+        &self.keys
+    }
+
+    /// Create an iterator over the raw key data and values of all items in
+    /// the map
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.keys.iter().copied().zip(&self.values)
+    }
+
+    /// Create an iterator over the raw key data and mutable values of all
+    /// items in the map
+    pub fn iter_mut_raw(
+        &mut self,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &mut T)> {
+        self.keys.iter().copied().zip(&mut self.values)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use crate::SLOT_MAP_CHUNK_SIZE;
+    use std::borrow::Borrow;
+
+    #[test]
+    fn test_dense_crud_matches_slot_map() {
+        let mut dense = DenseOneWaySlotMap::<TestKey, usize, usize>::new();
+        let mut expected = SlotMap::<TestKey, usize, usize>::new();
+
+        let mut keys = Vec::new();
+
+        for i in 0..SLOT_MAP_CHUNK_SIZE * 3 {
+            let key = dense.insert(i, i);
+            assert_eq!(expected.insert(i, i), key);
+            keys.push(key);
+
+            if i % 3 == 0 {
+                let key = keys.swap_remove(i * 7 % keys.len());
+                assert_eq!(Some(key.pointer), dense.remove(&key));
+                assert!(expected.remove(&key).is_some());
+                assert!(!dense.contains_key(&key));
+            }
+        }
+
+        assert_eq!(keys.len(), dense.len());
+
+        for key in &keys {
+            *dense.get_mut(key).unwrap() += 1;
+            assert_eq!(Some(&(key.pointer + 1)), dense.get(key));
+        }
+
+        // Values and their keys stay paired as values move
+        for (key_data, value) in dense.iter_raw() {
+            assert_eq!(Some(value), dense.get_raw(&key_data));
+        }
+
+        let mut pointers =
+            dense.values().iter().map(|v| v - 1).collect::<Vec<_>>();
+        pointers.sort_unstable();
+
+        let mut expected_pointers =
+            keys.iter().map(|k| k.pointer).collect::<Vec<_>>();
+        expected_pointers.sort_unstable();
+
+        assert_eq!(expected_pointers, pointers);
+
+        dense.clear();
+        assert!(dense.is_empty());
+        assert!(keys.iter().all(|k| !dense.contains_key(k)));
+        assert!(dense.get_raw(keys[0].borrow()).is_none());
+    }
+}
@@ -0,0 +1,180 @@
+use super::{SlotMap, SlotMapKey};
+use std::hash::{Hash, Hasher};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64 bit FNV-1a hasher. Unlike the hashers in std, its output is specified,
+/// so digests computed with it can be compared between processes, machines,
+/// and compiler versions
+#[derive(Debug, Clone, Copy)]
+struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        StableHasher(FNV_OFFSET_BASIS)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    // Integers are always written little-endian and usizes as u64s so the
+    // digest does not depend on the platform
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64)
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16)
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32)
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64)
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128)
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as u64)
+    }
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Hash,
+{
+    /// Feed the complete state of this map into the given hasher. This covers
+    /// the values of filled slots, the generation of every slot, and the chain
+    /// of open slots, so two maps that hash equally will hand out the same
+    /// keys for the same future operations. Values left behind in vacant
+    /// slots are not included. Any hasher can be used here, for example one
+    /// from the xxhash or blake3 crates
+    pub fn hash_state<H: Hasher>(&self, state: &mut H) {
+        let mut slot_count = 0u64;
+
+        state.write_u64(self.len() as u64);
+        state.write_u64(u64::from(self.next_open_slot()));
+
+        for (key_data, value) in self.slots().values() {
+            state.write_u64(u64::from(*key_data));
+
+            if key_data.is_filled() {
+                value.hash(state);
+            }
+
+            slot_count += 1;
+        }
+
+        state.write_u64(slot_count);
+    }
+
+    /// Compute a digest of the complete state of this map (see
+    /// [`SlotMap::hash_state`]) with a hash function whose output does not
+    /// depend on the platform or the process, so replicas can verify that
+    /// they hold identical maps by comparing digests
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut left = SlotMap::<TestKey,(),&'static str>::new();
+    /// let mut right = SlotMap::<TestKey,(),&'static str>::new();
+    ///
+    /// let _ = left.insert((), "Hello");
+    /// let key = right.insert((), "Hello");
+    ///
+    /// assert_eq!(left.state_digest(), right.state_digest());
+    ///
+    /// *right.get_mut(&key).unwrap() = "World";
+    ///
+    /// assert_ne!(left.state_digest(), right.state_digest());
+    /// ```
+    pub fn state_digest(&self) -> u64 {
+        let mut hasher = StableHasher::default();
+        self.hash_state(&mut hasher);
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::{churned_map, TestKey};
+
+    #[test]
+    fn test_digest_is_stable() {
+        // Changing this value breaks comparisons with digests computed by
+        // earlier versions of this crate
+        let mut map = SlotMap::<TestKey, usize, String>::new();
+        let key = map.insert(0, "Hello".to_owned());
+        let _ = map.insert(1, "World".to_owned());
+        let _ = map.remove(&key);
+
+        assert_eq!(0xd38a_4f7d_86ce_1e93, map.state_digest());
+
+        let (left, _, _) = churned_map(14, |i| format!("{}", i));
+        let (right, _, _) = churned_map(14, |i| format!("{}", i));
+
+        assert_eq!(left.state_digest(), right.state_digest());
+    }
+
+    #[test]
+    fn test_digest_covers_free_list_order() {
+        let (mut left, keys, _) = churned_map(14, |i| format!("{}", i));
+        let (mut right, _, _) = churned_map(14, |i| format!("{}", i));
+
+        let _ = left.remove(&keys[0]);
+        let _ = left.remove(&keys[1]);
+
+        let _ = right.remove(&keys[1]);
+        let _ = right.remove(&keys[0]);
+
+        assert_eq!(left.len(), right.len());
+        assert_ne!(left.state_digest(), right.state_digest());
+    }
+
+    #[test]
+    fn test_digest_ignores_vacant_values() {
+        let (mut left, keys, _) = churned_map(14, |i| format!("{}", i));
+        let (mut right, _, _) = churned_map(14, |i| format!("{}", i));
+
+        *right.get_mut(&keys[5]).unwrap() = "changed".to_owned();
+        assert_ne!(left.state_digest(), right.state_digest());
+
+        let _ = left.remove(&keys[5]);
+        let _ = right.remove(&keys[5]);
+        assert_eq!(left.state_digest(), right.state_digest());
+    }
+}
@@ -0,0 +1,165 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData, SLOT_MAP_CHUNK_SIZE};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Render the layout of this map as a Graphviz DOT graph for visual
+    /// debugging. Each chunk is drawn as a cluster of its written slots,
+    /// with filled slots shaded and retired slots crossed out, and every
+    /// vacant slot has an edge to the slot it links to, so the chain of open
+    /// slots can be followed from the `next open slot` node. Every written
+    /// slot is drawn, so this is only practical for small maps
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey, (), &str>::new();
+    ///
+    /// let key = map.insert((), "a");
+    /// let _ = map.insert((), "b");
+    /// let _ = map.remove(&key);
+    ///
+    /// let dot = map.to_dot();
+    ///
+    /// assert!(dot.starts_with("digraph SlotMap {"));
+    /// assert!(dot.contains("next_open -> s0_0;"));
+    /// assert!(dot.contains("s0_0 -> s0_2;"));
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let slots = self.slots();
+        let mut links = vec![("next_open".to_owned(), self.next_open_slot())];
+
+        // Writing to a string can't fail, so results are ignored throughout
+        let mut dot = String::from("digraph SlotMap {\n");
+        dot.push_str(
+            "  rankdir=LR;\n  node [shape=box, fontname=monospace];\n",
+        );
+
+        let _ = writeln!(
+            dot,
+            "  next_open [shape=plaintext, label=\"next open slot\\n{} items\"];",
+            self.len()
+        );
+
+        for chunk_index in 0..slots.chunk_count() {
+            let chunk = slots.chunk(chunk_index).unwrap_or_default();
+            let filled = chunk.iter().filter(|(k, _)| k.is_filled()).count();
+
+            let _ = writeln!(
+                dot,
+                "  subgraph cluster_{} {{\n    label=\"chunk {} ({} of {} filled)\";",
+                chunk_index,
+                chunk_index,
+                filled,
+                chunk.len()
+            );
+
+            for (index_in_chunk, (key_data, _)) in chunk.iter().enumerate() {
+                let position = SlotMapKeyData::from(
+                    (chunk_index * SLOT_MAP_CHUNK_SIZE + index_in_chunk) as u64,
+                );
+
+                let style = if key_data.is_filled() {
+                    "style=filled, fillcolor=lightgray"
+                } else if key_data.is_retired_at(&position) {
+                    "style=dashed, color=red"
+                } else {
+                    links.push((node_name(&position), *key_data));
+                    "style=solid"
+                };
+
+                let _ = writeln!(
+                    dot,
+                    "    {} [label=\"{}\\ngen {}\", {}];",
+                    node_name(&position),
+                    coordinates(&position),
+                    key_data.generation,
+                    style
+                );
+            }
+
+            dot.push_str("  }\n");
+        }
+
+        // Links past the written slots point at the frontier of the map
+        let unwritten = links
+            .iter()
+            .map(|(_, to)| to.position())
+            .filter(|position| *position >= slots.slot_count())
+            .collect::<BTreeSet<_>>();
+
+        for position in unwritten {
+            let _ = writeln!(
+                dot,
+                "  {} [label=\"{}\\nunwritten\", style=dotted];",
+                node_name(&SlotMapKeyData::from(position as u64)),
+                coordinates(&SlotMapKeyData::from(position as u64))
+            );
+        }
+
+        for (from, to) in &links {
+            let _ = writeln!(dot, "  {} -> {};", from, node_name(to));
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Get the name of the node for the slot at the given key data's coordinates
+fn node_name(key_data: &SlotMapKeyData) -> String {
+    format!("s{}_{}", key_data.chunk_index, key_data.index_in_chunk)
+}
+
+/// Format the coordinates of the given key data as `chunk:index`
+fn coordinates(key_data: &SlotMapKeyData) -> String {
+    format!("{}:{}", key_data.chunk_index, key_data.index_in_chunk)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::churned_map;
+
+    #[test]
+    fn test_every_vacant_slot_has_one_link() {
+        let (map, _, _) = churned_map(3, |i| i);
+
+        let dot = map.to_dot();
+        let slot_count = map.slots().slot_count();
+        let vacant = slot_count - map.len();
+
+        assert_eq!(
+            map.len(),
+            dot.lines()
+                .filter(|l| l.contains("fillcolor=lightgray"))
+                .count()
+        );
+        assert_eq!(
+            vacant + 1,
+            dot.lines().filter(|l| l.contains(" -> ")).count()
+        );
+        assert_eq!(1, dot.lines().filter(|l| l.contains("unwritten")).count());
+        assert_eq!(
+            map.slots().chunk_count(),
+            dot.matches("subgraph cluster_").count()
+        );
+
+        // Every vacant slot is the target of exactly one link, and filled slots
+        // aren't linked to at all
+        for (position, (key_data, _)) in map.slots().values().enumerate() {
+            let name = node_name(&SlotMapKeyData::from(position as u64));
+            let targeted = dot
+                .lines()
+                .filter(|l| l.ends_with(&format!("-> {};", name)))
+                .count();
+
+            assert_eq!(usize::from(!key_data.is_filled()), targeted);
+        }
+
+        assert!(dot.ends_with("}\n"));
+    }
+}
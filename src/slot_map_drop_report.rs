@@ -0,0 +1,156 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+
+/// What a [`SlotMap`] reports when it's dropped while it still holds items.
+/// Items that are never removed are usually fine, but in maps whose items
+/// stand for things with a lifecycle, like entities, they often mean a
+/// removal was forgotten somewhere. Set with [`SlotMap::set_drop_report`]
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()>);
+/// let mut map = SlotMap::<TestKey, (), &str>::new();
+/// map.set_drop_report(DropReport::Notify(|leaked| {
+///     assert_eq!(1, leaked.live.len());
+/// }));
+///
+/// let _ = map.insert((), "despawned");
+/// let _ = map.insert((), "forgotten");
+/// let _ = map.remove_raw(&SlotMapKeyData::default());
+///
+/// // Notifies about the forgotten item
+/// drop(map);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub enum DropReport {
+    /// Report nothing, like without the feature
+    Silent,
+
+    /// Print the number of discarded items to stderr along with the number
+    /// of inserts and removals. This is the default
+    #[default]
+    Log,
+
+    /// Print the same as `Log` along with the key data of every discarded
+    /// item
+    LogKeys,
+
+    /// Call the given function with the discarded items
+    Notify(fn(&LeakedItems)),
+}
+
+/// Items a [`SlotMap`] still held when it was dropped, along with the
+/// history of the map
+#[derive(Debug, Clone)]
+pub struct LeakedItems {
+    /// Name of the type of the items in the map
+    pub value_type: &'static str,
+
+    /// Number of items that were ever inserted into the map
+    pub inserts: u64,
+
+    /// Number of items that were removed from the map, including by clearing
+    /// it
+    pub removals: u64,
+
+    /// Key data of every item that was still in the map
+    pub live: Vec<SlotMapKeyData>,
+}
+
+impl std::fmt::Display for LeakedItems {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "SlotMap<{}> dropped with {} live items ({} inserted, {} removed)",
+            self.value_type,
+            self.live.len(),
+            self.inserts,
+            self.removals
+        )
+    }
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Get the number of items that were ever removed from this map,
+    /// including by clearing it
+    pub fn removal_count(&self) -> u64 {
+        self.insert_count() - self.len() as u64
+    }
+}
+
+/// Report the given items left in a map being dropped according to the given
+/// policy. Nothing is reported if there are no items left
+pub(crate) fn report_dropped<T>(
+    report: DropReport,
+    inserts: u64,
+    live: impl Iterator<Item = SlotMapKeyData>,
+) {
+    if let DropReport::Silent = report {
+        return;
+    }
+
+    let live = live.collect::<Vec<_>>();
+
+    if live.is_empty() {
+        return;
+    }
+
+    let leaked = LeakedItems {
+        value_type: std::any::type_name::<T>(),
+        inserts,
+        removals: inserts - live.len() as u64,
+        live,
+    };
+
+    match report {
+        DropReport::Silent => {}
+        DropReport::Log => eprintln!("{}", leaked),
+        DropReport::LogKeys => eprintln!("{}: {:?}", leaked, leaked.live),
+        DropReport::Notify(notify) => notify(&leaked),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::churned_map;
+    use std::sync::Mutex;
+
+    /// Reports for maps of the test-only value type below
+    static REPORTS: Mutex<Vec<(u64, u64, usize)>> = Mutex::new(Vec::new());
+
+    #[derive(Clone)]
+    struct Entity(#[allow(dead_code)] usize);
+
+    fn record(leaked: &LeakedItems) {
+        REPORTS.lock().unwrap().push((
+            leaked.inserts,
+            leaked.removals,
+            leaked.live.len(),
+        ));
+    }
+
+    #[test]
+    fn test_dropping_reports_live_items() {
+        let (mut map, keys, removed) = churned_map(5, Entity);
+        map.set_drop_report(DropReport::Notify(record));
+
+        let removals = removed.len() as u64;
+
+        assert_eq!(removals, map.removal_count());
+        assert_eq!(keys.len() as u64 + removals, map.insert_count());
+
+        // Clones are reported on their own, and emptied maps aren't reported
+        let mut cleared = map.clone();
+        cleared.clear();
+        assert_eq!(map.insert_count(), cleared.removal_count());
+        drop(cleared);
+
+        let expected = (map.insert_count(), removals, keys.len());
+        drop(map);
+
+        assert_eq!(vec![expected], *REPORTS.lock().unwrap());
+    }
+}
@@ -0,0 +1,174 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData, SLOT_MAP_CHUNK_SIZE};
+use std::io::{Result, Write};
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Write a description of the internal state of this map for debugging:
+    /// the occupancy of each chunk, the generation of every written slot and
+    /// where each vacant slot links to, and the chain of open slots from its
+    /// head. Slots are named by `chunk:index`
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey, (), &str>::new();
+    ///
+    /// let key = map.insert((), "a");
+    /// let _ = map.insert((), "b");
+    /// let _ = map.remove(&key);
+    ///
+    /// let mut out = Vec::new();
+    /// map.dump(&mut out).unwrap();
+    ///
+    /// assert_eq!(
+    ///     "SlotMap: 1 items in 2 slots, next open slot 0:0\n\
+    ///      chunk 0: 1 of 2 written slots filled\n\
+    ///      \x20 0:0 generation 1 vacant, links to 0:2\n\
+    ///      \x20 0:1 generation 0 filled\n\
+    ///      open slots: 0:0 -> 0:2 (unwritten)\n",
+    ///     String::from_utf8(out).unwrap()
+    /// );
+    /// ```
+    pub fn dump(&self, out: impl Write) -> Result<()> {
+        self.dump_with(out, |_, _| Ok(()))
+    }
+
+    /// Same as dump, but also writes the value of every written slot,
+    /// including the values left behind in vacant slots
+    pub fn dump_with_values(&self, out: impl Write) -> Result<()>
+    where
+        T: std::fmt::Debug,
+    {
+        self.dump_with(out, |out, value| write!(out, ": {:?}", value))
+    }
+
+    fn dump_with<W: Write>(
+        &self,
+        mut out: W,
+        mut write_value: impl FnMut(&mut W, &T) -> Result<()>,
+    ) -> Result<()> {
+        let slots = self.slots();
+        let slot_count = slots.slot_count();
+        let next_open_slot = self.next_open_slot();
+
+        writeln!(
+            out,
+            "SlotMap: {} items in {} slots, next open slot {}",
+            self.len(),
+            slot_count,
+            coordinates(&next_open_slot)
+        )?;
+
+        for chunk_index in 0..slots.chunk_count() {
+            let chunk = slots.chunk(chunk_index).unwrap_or_default();
+            let filled = chunk.iter().filter(|(k, _)| k.is_filled()).count();
+
+            writeln!(
+                out,
+                "chunk {}: {} of {} written slots filled",
+                chunk_index,
+                filled,
+                chunk.len()
+            )?;
+
+            for (index_in_chunk, (key_data, value)) in chunk.iter().enumerate()
+            {
+                let position = SlotMapKeyData::from(
+                    (chunk_index * SLOT_MAP_CHUNK_SIZE + index_in_chunk) as u64,
+                );
+
+                write!(
+                    out,
+                    "  {} generation {}",
+                    coordinates(&position),
+                    key_data.generation
+                )?;
+
+                if key_data.is_filled() {
+                    write!(out, " filled")?;
+                } else if key_data.is_retired_at(&position) {
+                    write!(out, " retired")?;
+                } else {
+                    write!(out, " vacant, links to {}", coordinates(key_data))?;
+                }
+
+                write_value(&mut out, value)?;
+                writeln!(out)?;
+            }
+        }
+
+        // The chain can't be longer than the number of slots unless the map
+        // is corrupted, so the walk is cut off there
+        write!(out, "open slots: {}", coordinates(&next_open_slot))?;
+
+        let mut cursor = next_open_slot;
+
+        for _ in 0..=slot_count {
+            match slots
+                .chunk(cursor.chunk_index as usize)
+                .and_then(|chunk| chunk.get(cursor.index_in_chunk as usize))
+            {
+                None => return writeln!(out, " (unwritten)"),
+                Some((key_data, _)) if key_data.is_filled() => {
+                    return writeln!(out, " (filled!)");
+                }
+                Some((key_data, _)) => {
+                    cursor = *key_data;
+                    write!(out, " -> {}", coordinates(&cursor))?;
+                }
+            }
+        }
+
+        writeln!(out, " (cycle!)")
+    }
+}
+
+/// Format the coordinates of the given key data as `chunk:index`
+fn coordinates(key_data: &SlotMapKeyData) -> String {
+    format!("{}:{}", key_data.chunk_index, key_data.index_in_chunk)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::churned_map;
+    use std::borrow::Borrow;
+
+    #[test]
+    fn test_dump_describes_every_slot() {
+        let (map, keys, _) = churned_map(4, |i| i);
+
+        let mut out = Vec::new();
+        map.dump_with_values(&mut out).unwrap();
+        let dump = String::from_utf8(out).unwrap();
+
+        let slot_count = map.slots().slot_count();
+        let vacant = slot_count - map.len();
+
+        assert_eq!(
+            map.len(),
+            dump.lines().filter(|l| l.contains(" filled: ")).count()
+        );
+        assert_eq!(
+            vacant,
+            dump.lines().filter(|l| l.contains(" vacant, ")).count()
+        );
+
+        // The chain visits every vacant slot before reaching the frontier
+        let chain = dump.lines().last().unwrap();
+        assert_eq!(vacant, chain.matches(" -> ").count());
+        assert!(chain.ends_with(" (unwritten)"));
+
+        for key in &keys {
+            let line = format!(
+                "  {} generation {} filled: {}",
+                coordinates(key.borrow()),
+                Borrow::<SlotMapKeyData>::borrow(key).generation,
+                key.pointer
+            );
+            assert!(dump.lines().any(|l| l == line));
+        }
+    }
+}
@@ -0,0 +1,598 @@
+use super::slot_map_append_only::{bucket_len, locate, BUCKET_COUNT};
+use super::{SlotMapKey, SlotMapKeyData, SLOT_MAP_CHUNK_SIZE};
+use crossbeam_epoch::{Atomic, Collector, Guard, Owned, Shared};
+use std::marker::PhantomData;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicPtr, AtomicU64, AtomicUsize, Ordering};
+
+/// Packed key data marking the end of the stack of open slots. These are the
+/// coordinates of the last slot a key can address, which is never handed out
+const NO_OPEN_SLOT: u64 = u64::MAX;
+
+/// Number of slots that can be handed out, leaving out the last one so its
+/// packed key data can mark the end of the stack of open slots
+const MAX_SLOTS: usize = u32::MAX as usize * SLOT_MAP_CHUNK_SIZE;
+
+/// Value stored in a slot along with the generation of the key it was
+/// inserted under
+struct Entry<T> {
+    generation: u32,
+    value: T,
+}
+
+/// A single slot of the map
+struct Slot<T> {
+    entry: Atomic<Entry<T>>,
+
+    /// Packed key data of the open slot below this one in the stack of open
+    /// slots, only meaningful while this slot is open
+    next_open_slot: AtomicU64,
+}
+
+/// Slot map that can be read from many threads without locking while other
+/// threads insert and remove items. Removed items are retired rather than
+/// dropped, and are only dropped once every thread that might still be
+/// reading them has unpinned, so references obtained before a removal stay
+/// valid for as long as the reader's [`EpochGuard`] is held. Inserts and
+/// removals don't lock either. Open slots are kept in a lock-free stack of
+/// packed key data, where the generation of each open slot keeps a stale
+/// view of the stack from being mistaken for the current one, so used from a
+/// single thread the map hands out keys exactly as a [`SlotMap`] would
+///
+/// [`SlotMap`]: crate::SlotMap
+pub struct EpochSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    buckets: [AtomicPtr<Slot<T>>; BUCKET_COUNT],
+
+    /// Packed key data of the open slot on top of the stack, with the
+    /// generation it had when it was removed
+    open_slots: AtomicU64,
+
+    /// Number of slots that have been handed out at least once
+    cursor: AtomicUsize,
+    len: AtomicUsize,
+
+    /// Collector that removed items are retired to
+    collector: Collector,
+
+    _phantom: PhantomData<fn(P, K)>,
+}
+
+// Safety - Values are moved in by inserting threads, read by any thread, and
+// may be dropped by whichever thread collects them after removal
+unsafe impl<K, P, T> Send for EpochSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Send,
+{
+}
+
+unsafe impl<K, P, T> Sync for EpochSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Send + Sync,
+{
+}
+
+impl<K, P, T> std::fmt::Debug for EpochSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EpochSlotMap")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl<K, P, T> Default for EpochSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        EpochSlotMap::new()
+    }
+}
+
+/// Proof that the current thread is pinned, which keeps every item it reads
+/// from an [`EpochSlotMap`] from being dropped. Guards should be short-lived,
+/// because nothing removed while any guard is held can be dropped
+pub struct EpochGuard(Guard);
+
+impl std::fmt::Debug for EpochGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EpochGuard").finish_non_exhaustive()
+    }
+}
+
+impl<K, P, T> EpochSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map that retires removed items to crossbeam's
+    /// default collector
+    pub fn new() -> EpochSlotMap<K, P, T> {
+        EpochSlotMap::with_collector(
+            crossbeam_epoch::default_collector().clone(),
+        )
+    }
+
+    /// Create a new empty map that retires removed items to the given
+    /// collector, so they can't be held up by threads pinned for unrelated
+    /// work, and are all dropped once the collector, the map, and every guard
+    /// pinned by them are gone. Pinning registers a new handle with the
+    /// collector each time, which is slower than pinning the default one
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let collector = crossbeam_epoch::Collector::new();
+    /// let map = EpochSlotMap::<TestKey, (), usize>::with_collector(collector);
+    ///
+    /// let key = map.insert((), 1);
+    /// assert_eq!(Some(&1), map.get(&key, &map.pin()));
+    /// ```
+    pub fn with_collector(collector: Collector) -> EpochSlotMap<K, P, T> {
+        EpochSlotMap {
+            buckets: std::array::from_fn(|_| AtomicPtr::new(null_mut())),
+            open_slots: AtomicU64::new(NO_OPEN_SLOT),
+            cursor: AtomicUsize::new(0),
+            len: AtomicUsize::new(0),
+            collector,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Pin the current thread so items can be read from the map
+    pub fn pin(&self) -> EpochGuard {
+        EpochGuard(self.guard())
+    }
+
+    /// Pin the current thread with this map's collector
+    fn guard(&self) -> Guard {
+        if self.collector == *crossbeam_epoch::default_collector() {
+            crossbeam_epoch::pin()
+        } else {
+            self.collector.register().pin()
+        }
+    }
+
+    /// Get the number of items in the map. Other threads may change the map
+    /// while this is being read
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Insert the given item into the map and return its key
+    pub fn insert(&self, pointer: P, value: T) -> K {
+        let (slot, key_data) = match self.pop_open_slot() {
+            Some((slot, mut key_data)) => {
+                key_data.increment_generation();
+                (slot, key_data)
+            }
+            None => {
+                let linear_index = self.cursor.fetch_add(1, Ordering::Relaxed);
+                assert!(linear_index < MAX_SLOTS, "epoch slot map is full");
+
+                let key_data = SlotMapKeyData::from(linear_index as u64);
+                (self.slot_or_insert(&key_data), key_data)
+            }
+        };
+
+        let entry = Owned::new(Entry {
+            generation: key_data.generation,
+            value,
+        });
+
+        // The slot is empty because its previous entry was taken out when it
+        // was removed, and this thread is the only one that took it from the
+        // stack of open slots or the cursor
+        slot.entry.store(entry, Ordering::Release);
+        let _ = self.len.fetch_add(1, Ordering::Release);
+
+        K::from((pointer, key_data))
+    }
+
+    /// Get a reference to the item for the given key if it exists. The item
+    /// stays alive for as long as the guard is held, even if it is removed
+    /// in the meantime
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let map = EpochSlotMap::<TestKey,(),String>::new();
+    /// let key = map.insert((), "Hello".to_owned());
+    ///
+    /// let guard = map.pin();
+    /// let value = map.get(&key, &guard).unwrap();
+    ///
+    /// assert!(map.remove(&key));
+    /// assert_eq!(None, map.get(&key, &guard));
+    ///
+    /// // Still readable until the guard is dropped
+    /// assert_eq!("Hello", value);
+    /// ```
+    pub fn get<'a>(&'a self, key: &K, guard: &'a EpochGuard) -> Option<&'a T> {
+        self.get_raw(key.borrow(), guard)
+    }
+
+    /// Same as get, but only requires slot map key data
+    ///
+    /// # Panics
+    /// Panics if the guard wasn't pinned by a map sharing this map's
+    /// collector, because it wouldn't keep this map's items alive
+    pub fn get_raw<'a>(
+        &'a self,
+        key_data: &SlotMapKeyData,
+        guard: &'a EpochGuard,
+    ) -> Option<&'a T> {
+        assert!(
+            guard.0.collector() == Some(&self.collector),
+            "guard was pinned with another collector"
+        );
+
+        let entry =
+            self.slot(key_data)?.entry.load(Ordering::Acquire, &guard.0);
+
+        // Safety - Entries are only dropped once no guard that could have
+        // loaded them remains
+        unsafe { entry.as_ref() }
+            .filter(|e| e.generation == key_data.generation)
+            .map(|e| &e.value)
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key, &self.pin()).is_some()
+    }
+
+    /// Remove the item for the given key, returning whether it was present.
+    /// The item is dropped once no thread can be reading it
+    pub fn remove(&self, key: &K) -> bool
+    where
+        T: Send + 'static,
+    {
+        let key_data: &SlotMapKeyData = key.borrow();
+
+        let Some(slot) = self.slot(key_data) else {
+            return false;
+        };
+
+        let guard = self.guard();
+        let mut entry = slot.entry.load(Ordering::Acquire, &guard);
+
+        // Only the thread that swaps out the entry for this generation removes
+        // it, so each removal opens the slot once
+        loop {
+            // Safety - The guard keeps the entry alive
+            match unsafe { entry.as_ref() } {
+                Some(e) if e.generation == key_data.generation => {}
+                _ => return false,
+            }
+
+            match slot.entry.compare_exchange(
+                entry,
+                Shared::null(),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+                &guard,
+            ) {
+                Ok(_) => break,
+                Err(e) => entry = e.current,
+            }
+        }
+
+        // Safety - The entry was just unlinked, so only readers pinned before
+        // now can still see it
+        unsafe { guard.defer_destroy(entry) };
+
+        let _ = self.len.fetch_sub(1, Ordering::Release);
+
+        let mut open_key_data = *key_data;
+        open_key_data.increment_generation();
+        self.push_open_slot(slot, open_key_data);
+
+        true
+    }
+
+    /// Take the open slot on top of the stack of open slots if there is one,
+    /// along with its key data as of its removal
+    fn pop_open_slot(&self) -> Option<(&Slot<T>, SlotMapKeyData)> {
+        let mut top = self.open_slots.load(Ordering::Acquire);
+
+        loop {
+            if top == NO_OPEN_SLOT {
+                return None;
+            }
+
+            let key_data = SlotMapKeyData::from(top);
+            let slot = self.slot(&key_data).expect("missing open slot");
+
+            // If the slot is taken and reopened by other threads after the
+            // top was read, this link may be stale, but then the slot's
+            // generation has changed and the exchange below fails
+            let next = slot.next_open_slot.load(Ordering::Acquire);
+
+            match self.open_slots.compare_exchange_weak(
+                top,
+                next,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return Some((slot, key_data)),
+                Err(current) => top = current,
+            }
+        }
+    }
+
+    /// Put the given slot on top of the stack of open slots
+    fn push_open_slot(&self, slot: &Slot<T>, key_data: SlotMapKeyData) {
+        let packed = u64::from(key_data);
+        let mut top = self.open_slots.load(Ordering::Relaxed);
+
+        loop {
+            slot.next_open_slot.store(top, Ordering::Relaxed);
+
+            match self.open_slots.compare_exchange_weak(
+                top,
+                packed,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return,
+                Err(current) => top = current,
+            }
+        }
+    }
+
+    /// Get the slot for the given key data if its bucket exists
+    fn slot(&self, key_data: &SlotMapKeyData) -> Option<&Slot<T>> {
+        let (bucket, index) = locate(linear_index(key_data));
+        let ptr = self.buckets.get(bucket)?.load(Ordering::Acquire);
+
+        // Safety - Installed buckets are never freed or moved while the map
+        // is alive, and the index is within the bucket's length
+        (!ptr.is_null()).then(|| unsafe { &*ptr.add(index) })
+    }
+
+    /// Get the slot for the given key data, installing its bucket if needed.
+    /// When threads race to install a bucket, the losers free theirs
+    fn slot_or_insert(&self, key_data: &SlotMapKeyData) -> &Slot<T> {
+        let (bucket, index) = locate(linear_index(key_data));
+        let mut ptr = self.buckets[bucket].load(Ordering::Acquire);
+
+        if ptr.is_null() {
+            let new_bucket = Box::into_raw(
+                (0..bucket_len(bucket))
+                    .map(|_| Slot {
+                        entry: Atomic::null(),
+                        next_open_slot: AtomicU64::new(NO_OPEN_SLOT),
+                    })
+                    .collect::<Box<[Slot<T>]>>(),
+            ) as *mut Slot<T>;
+
+            ptr = match self.buckets[bucket].compare_exchange(
+                null_mut(),
+                new_bucket,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => new_bucket,
+                Err(installed) => {
+                    // Safety - The new bucket was never shared
+                    drop(unsafe { boxed_bucket(new_bucket, bucket) });
+                    installed
+                }
+            };
+        }
+
+        // Safety - The bucket was installed above if it didn't exist
+        unsafe { &*ptr.add(index) }
+    }
+}
+
+/// Get the position of the slot for the given key data in order of slots
+fn linear_index(key_data: &SlotMapKeyData) -> usize {
+    key_data.chunk_index as usize * SLOT_MAP_CHUNK_SIZE
+        + key_data.index_in_chunk as usize
+}
+
+/// Take back ownership of the given bucket
+///
+/// # Safety
+/// The pointer must have come from a boxed slice of the given bucket's
+/// length that nothing else uses
+unsafe fn boxed_bucket<T>(ptr: *mut Slot<T>, bucket: usize) -> Box<[Slot<T>]> {
+    Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, bucket_len(bucket)))
+}
+
+impl<K, P, T> Drop for EpochSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn drop(&mut self) {
+        for (bucket, ptr) in self.buckets.iter_mut().enumerate() {
+            let ptr = *ptr.get_mut();
+
+            if ptr.is_null() {
+                continue;
+            }
+
+            // Safety - This map owns its buckets and nothing else can be
+            // using them during the drop
+            let slots = unsafe { boxed_bucket(ptr, bucket) };
+
+            for slot in slots.iter() {
+                // Safety - No reader can be using the map while it is dropped,
+                // and removed entries were already unlinked
+                let entry = slot.entry.load(Ordering::Relaxed, unsafe {
+                    crossbeam_epoch::unprotected()
+                });
+
+                if !entry.is_null() {
+                    drop(unsafe { entry.into_owned() });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use crate::SlotMap;
+    use std::borrow::Borrow;
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    /// Value that counts how many times it has been dropped
+    struct DropCounter(Arc<AtomicUsize>, usize);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            let _ = self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_readers_survive_removal() {
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        // The map's own collector drops every retired value with the map, so
+        // threads pinned by other tests can't hold them up
+        let map = EpochSlotMap::<TestKey, usize, DropCounter>::with_collector(
+            Collector::new(),
+        );
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE + 10)
+            .map(|i| map.insert(i, DropCounter(drops.clone(), i)))
+            .collect::<Vec<_>>();
+
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let (map, keys) = (&map, &keys);
+
+                let _ = s.spawn(move || {
+                    for (i, key) in keys.iter().enumerate().skip(t) {
+                        let guard = map.pin();
+
+                        if let Some(value) = map.get(key, &guard) {
+                            // The value can't be dropped while it is pinned
+                            std::thread::yield_now();
+                            assert_eq!(i, value.1);
+                        }
+                    }
+                });
+            }
+
+            for key in keys.iter().step_by(2) {
+                assert!(map.remove(key));
+            }
+        });
+
+        let removed = keys.len().div_ceil(2);
+        assert_eq!(keys.len() - removed, map.len());
+
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(i % 2 == 1, map.contains_key(key));
+        }
+
+        // Slots are reused with new generations
+        let reused = map.insert(0, DropCounter(drops.clone(), 1000));
+        assert_eq!(1000, map.get(&reused, &map.pin()).unwrap().1);
+        assert!(!map.remove(&keys[0]));
+
+        drop(map);
+
+        assert_eq!(keys.len() + 1, drops.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_keys_match_slot_map() {
+        let map = EpochSlotMap::<TestKey, usize, usize>::new();
+        let mut expected = SlotMap::<TestKey, usize, usize>::new();
+
+        let mut keys = Vec::new();
+
+        for i in 0..SLOT_MAP_CHUNK_SIZE * 3 {
+            let key = map.insert(i, i);
+            assert_eq!(expected.insert(i, i), key);
+            keys.push(key);
+
+            // Remove an item on every third insert
+            if i % 3 == 0 {
+                let key = keys.swap_remove(i / 7 % keys.len());
+                assert!(map.remove(&key));
+                assert!(expected.remove(&key).is_some());
+            }
+        }
+
+        assert_eq!(expected.len(), map.len());
+
+        for key in &keys {
+            assert_eq!(Some(&key.pointer), map.get(key, &map.pin()));
+        }
+    }
+
+    #[test]
+    fn test_concurrent_inserts_and_removes() {
+        let map = EpochSlotMap::<TestKey, usize, usize>::new();
+        let per_thread = SLOT_MAP_CHUNK_SIZE * 8;
+
+        let kept = std::thread::scope(|s| {
+            let handles = (0..8)
+                .map(|t| {
+                    let map = &map;
+
+                    s.spawn(move || {
+                        let mut kept = Vec::new();
+
+                        for i in 0..per_thread {
+                            let p = t * per_thread + i;
+                            let key = map.insert(p, p);
+
+                            if i % 2 == 0 {
+                                assert!(map.remove(&key));
+                                assert!(!map.remove(&key));
+                            } else {
+                                kept.push(key);
+                            }
+                        }
+
+                        kept
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(kept.len(), map.len());
+
+        let guard = map.pin();
+        for key in &kept {
+            assert_eq!(Some(&key.pointer), map.get(key, &guard));
+        }
+
+        // No slot was handed out to two live items
+        let slots = kept
+            .iter()
+            .map(|k| linear_index(k.borrow()))
+            .collect::<HashSet<_>>();
+        assert_eq!(kept.len(), slots.len());
+
+        // Removed slots were reused rather than new ones taken
+        assert!(map.cursor.load(Ordering::Relaxed) < kept.len() * 2);
+    }
+}
@@ -0,0 +1,331 @@
+use super::slot_map_key_data::MAX_GENERATION;
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+
+/// What a [`SlotMap`] does with a slot whose generations have run out. Each
+/// removal from a slot advances its generation, and once a slot has been
+/// removed from about 8 million times its generation wraps back to the
+/// start, so keys to items removed from it long ago can match new items
+/// again. Set with [`SlotMap::set_generation_exhaustion`]
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()>);
+/// let mut map = SlotMap::<TestKey, (), usize>::new();
+/// map.set_generation_exhaustion(GenerationExhaustion::Notify(|key_data| {
+///     eprintln!("Slot of {:?} has wrapped", key_data)
+/// }));
+///
+/// let key = map.insert((), 0);
+/// let _ = map.remove(&key);
+///
+/// assert_eq!(0, map.slots_near_exhaustion(1000));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub enum GenerationExhaustion {
+    /// Reuse the slot starting over from the first generation. This is the
+    /// default
+    #[default]
+    Wrap,
+
+    /// Never use the slot again. Each retired slot keeps its memory, so a map
+    /// that churns a few slots heavily grows slowly over time
+    Retire,
+
+    /// Call the given function with the key data of the item whose removal
+    /// used up the slot's generations, then reuse the slot like `Wrap`
+    Notify(fn(SlotMapKeyData)),
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Get the number of slots within the given number of generations of
+    /// running out, including slots that have been retired
+    pub fn slots_near_exhaustion(&self, margin: u32) -> usize {
+        let threshold = MAX_GENERATION.saturating_sub(margin);
+
+        self.slots()
+            .values()
+            .filter(|(key_data, _)| key_data.generation >= threshold)
+            .count()
+    }
+
+    /// Get the number of times an item has been inserted into a slot whose
+    /// generation wrapped back to the start, which is when keys to items
+    /// removed from that slot long ago could start matching again. This
+    /// counts wraps whatever the [`GenerationExhaustion`] policy is, but
+    /// doesn't count slots reused by [`SlotMap::compact`]. Requires the
+    /// `wrap-stats` feature
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey, (), usize>::new();
+    /// let _ = map.insert((), 0);
+    ///
+    /// assert_eq!(0, map.generation_wraps());
+    /// assert_eq!(None, map.generation_wraps_by_chunk().next());
+    /// ```
+    #[cfg(feature = "wrap-stats")]
+    pub fn generation_wraps(&self) -> u64 {
+        self.wraps().values().sum()
+    }
+
+    /// Get the number of generation wraps in each chunk that has had any, as
+    /// pairs of chunk index and count in order of chunk index
+    #[cfg(feature = "wrap-stats")]
+    pub fn generation_wraps_by_chunk(
+        &self,
+    ) -> impl Iterator<Item = (usize, u64)> + '_ {
+        self.wraps()
+            .iter()
+            .map(|(chunk_index, wraps)| (*chunk_index as usize, *wraps))
+    }
+
+    /// Get the number of slots that have been retired by
+    /// [`GenerationExhaustion::Retire`]
+    pub fn retired_slots(&self) -> usize {
+        self.slots()
+            .values()
+            .enumerate()
+            .filter(|(position, (key_data, _))| {
+                key_data.is_retired_at(&SlotMapKeyData::from(*position as u64))
+            })
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use crate::SLOT_MAP_CHUNK_SIZE;
+    use rand::{thread_rng, Rng};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Create a map of items whose slots are all a few removals away from
+    /// running out of generations
+    fn create_worn_map(
+        policy: GenerationExhaustion,
+    ) -> SlotMap<TestKey, usize, usize> {
+        let mut map = SlotMap::new();
+        map.set_generation_exhaustion(policy);
+
+        for i in 0..SLOT_MAP_CHUNK_SIZE * 2 {
+            let _ = map.insert(i, i);
+        }
+
+        for chunk in map.chunks_mut() {
+            for (key_data, _) in chunk {
+                key_data.generation = MAX_GENERATION - 7;
+            }
+        }
+
+        map
+    }
+
+    #[test]
+    fn test_clear_retires_worn_slots() {
+        let mut map = create_worn_map(GenerationExhaustion::Retire);
+
+        // Wear out every other slot so that clearing uses up its last
+        // generation
+        for chunk in map.chunks_mut() {
+            for (key_data, _) in chunk.into_iter().step_by(2) {
+                key_data.generation = MAX_GENERATION - 1;
+            }
+        }
+
+        map.clear();
+
+        assert_eq!(Ok(()), map.check_invariants());
+        assert_eq!(SLOT_MAP_CHUNK_SIZE, map.retired_slots());
+
+        // Only the slots that weren't retired are reused
+        for i in 0..SLOT_MAP_CHUNK_SIZE {
+            let key_data = map.insert_raw(i);
+            assert_eq!(i * 2 + 1, key_data.position());
+        }
+        assert_eq!(SLOT_MAP_CHUNK_SIZE * 2, map.insert_raw(0).position());
+    }
+
+    /// Create a worn map whose first slot has been retired and which has a
+    /// couple of open slots after it, returning it with the key data of the
+    /// items that are left
+    fn create_map_with_retired_slot(
+    ) -> (SlotMap<TestKey, usize, usize>, Vec<SlotMapKeyData>) {
+        let mut map = create_worn_map(GenerationExhaustion::Retire);
+
+        for chunk in map.chunks_mut() {
+            if let Some((key_data, _)) = chunk.into_iter().next() {
+                key_data.generation = MAX_GENERATION - 1;
+                break;
+            }
+        }
+
+        let mut live = map.iter_raw().map(|(k, _)| k).collect::<Vec<_>>();
+
+        for position in [10, 3, 0] {
+            let key_data = live.remove(position);
+            assert!(map.remove_raw(&key_data).is_some());
+        }
+        assert_eq!(1, map.retired_slots());
+
+        (map, live)
+    }
+
+    #[test]
+    fn test_compaction_skips_retired_slots() {
+        let (mut map, live) = create_map_with_retired_slot();
+
+        let mut moves = Vec::new();
+        map.compact(|old, new| moves.push((old, new)));
+
+        // The last two items fill the open slots, leaving the retired one
+        let last = SLOT_MAP_CHUNK_SIZE as u64 * 2;
+        assert_eq!(
+            vec![(last - 2, 3), (last - 1, 10)],
+            moves
+                .iter()
+                .map(|(old, new)| (
+                    old.position() as u64,
+                    new.position() as u64
+                ))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(1, map.retired_slots());
+        assert_eq!(Ok(()), map.check_invariants());
+
+        // Items keep their values, which are their original positions
+        for key_data in live {
+            let moved = moves
+                .iter()
+                .find(|(old, _)| *old == key_data)
+                .map_or(key_data, |(_, new)| *new);
+            assert_eq!(Some(&key_data.position()), map.get_raw(&moved));
+        }
+
+        // The retired slot is still never reused
+        let inserted = map.insert_raw(0);
+        assert_eq!(last - 2, inserted.position() as u64);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_compaction_skips_retired_slots() {
+        let (mut sequential, _) = create_map_with_retired_slot();
+        let (mut parallel, _) = create_map_with_retired_slot();
+
+        let mut expected = Vec::new();
+        sequential.compact(|old, new| expected.push((old, new)));
+
+        let mut moves = Vec::new();
+        parallel.par_compact(|old, new| moves.push((old, new)));
+
+        assert_eq!(expected, moves);
+        assert_eq!(1, parallel.retired_slots());
+        assert_eq!(Ok(()), parallel.check_invariants());
+        assert_eq!(sequential.state_digest(), parallel.state_digest());
+    }
+
+    #[test]
+    fn test_retired_slots_are_never_reused() {
+        let mut rng = thread_rng();
+        let mut map = create_worn_map(GenerationExhaustion::Retire);
+        let mut live = map.iter_raw().map(|(k, _)| k).collect::<Vec<_>>();
+        let mut removed = Vec::new();
+
+        assert_eq!(live.len(), map.slots_near_exhaustion(7));
+        assert_eq!(0, map.slots_near_exhaustion(6));
+
+        for i in 0..SLOT_MAP_CHUNK_SIZE * 20 {
+            if live.is_empty() || rng.gen_bool(0.5) {
+                live.push(map.insert_raw(i));
+            } else {
+                let key_data = live.swap_remove(rng.gen_range(0..live.len()));
+                assert!(map.remove_raw(&key_data).is_some());
+                removed.push(key_data);
+            }
+
+            assert_eq!(Ok(()), map.check_invariants());
+        }
+
+        // No worn slot wrapped around, and no key was handed out twice
+        let retired = map.retired_slots();
+        assert!(retired > 0);
+        #[cfg(feature = "wrap-stats")]
+        assert_eq!(0, map.generation_wraps());
+        assert_eq!(SLOT_MAP_CHUNK_SIZE * 2, map.slots_near_exhaustion(7));
+
+        for key_data in &removed {
+            assert!(!map.contains_key_raw(key_data));
+            assert!(!live.contains(key_data));
+        }
+
+        // Retired slots aren't counted as free
+        assert_eq!(
+            map.slots().slot_count() - map.len() - retired,
+            map.stats().free_slots
+        );
+
+        map.canonicalize();
+        assert_eq!(Ok(()), map.check_invariants());
+        assert_eq!(retired, map.retired_slots());
+    }
+
+    #[test]
+    fn test_notify_and_wrap() {
+        static NOTIFIED: AtomicUsize = AtomicUsize::new(0);
+
+        let mut map = create_worn_map(GenerationExhaustion::Notify(|_| {
+            let _ = NOTIFIED.fetch_add(1, Ordering::Relaxed);
+        }));
+
+        let mut key_data = map.iter_raw().next().unwrap().0;
+
+        for _ in 0..4 {
+            let _ = map.remove_raw(&key_data);
+            key_data = map.insert_raw(0);
+        }
+
+        assert_eq!(1, NOTIFIED.load(Ordering::Relaxed));
+        assert_eq!(0, key_data.generation);
+        assert_eq!(0, map.retired_slots());
+        #[cfg(feature = "wrap-stats")]
+        assert_eq!(1, map.generation_wraps());
+
+        map.set_generation_exhaustion(GenerationExhaustion::Wrap);
+        map.clear();
+        assert_eq!(1, NOTIFIED.load(Ordering::Relaxed));
+        assert_eq!(Ok(()), map.check_invariants());
+    }
+
+    #[test]
+    #[cfg(feature = "wrap-stats")]
+    fn test_wraps_are_counted_by_chunk() {
+        let mut map = create_worn_map(GenerationExhaustion::Wrap);
+        let keys = map.iter_raw().map(|(k, _)| k).collect::<Vec<_>>();
+
+        // Worn slots wrap on their fourth reuse
+        for (key_data, reuses) in [
+            (keys[SLOT_MAP_CHUNK_SIZE - 1], 4),
+            (keys[SLOT_MAP_CHUNK_SIZE], 4),
+            (keys[SLOT_MAP_CHUNK_SIZE + 1], 3),
+        ] {
+            let mut key_data = key_data;
+
+            for _ in 0..reuses {
+                let _ = map.remove_raw(&key_data);
+                key_data = map.insert_raw(0);
+            }
+        }
+
+        assert_eq!(2, map.generation_wraps());
+        assert_eq!(
+            vec![(0, 1), (1, 1)],
+            map.generation_wraps_by_chunk().collect::<Vec<_>>()
+        );
+        assert_eq!(2, map.clone().generation_wraps());
+    }
+}
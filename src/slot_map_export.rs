@@ -0,0 +1,136 @@
+use super::{SlotMapKeyData, SLOT_MAP_CHUNK_SIZE};
+
+/// Densely packed values taken out of a slot map, along with a record of how
+/// the keys of the original map translate to the keys of a map rebuilt with
+/// [`SlotMap::import`](crate::SlotMap::import)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SlotMapExport<T> {
+    values: Vec<T>,
+    remap: Vec<(SlotMapKeyData, SlotMapKeyData)>,
+}
+
+impl<T> SlotMapExport<T> {
+    pub(crate) fn new(
+        values: Vec<T>,
+        remap: Vec<(SlotMapKeyData, SlotMapKeyData)>,
+    ) -> SlotMapExport<T> {
+        SlotMapExport { values, remap }
+    }
+
+    /// The exported values in the order of their original slots
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// Pairs of original key data and the key data each value will have once
+    /// imported. Pairs are in the same order as the values, which is also the
+    /// order of the original slots
+    pub fn remap(&self) -> &[(SlotMapKeyData, SlotMapKeyData)] {
+        &self.remap
+    }
+
+    /// Find the key data that the value stored under the given original key
+    /// data will have once imported. Keys that were not live in the original
+    /// map produce `None`
+    pub fn remap_key(&self, old: &SlotMapKeyData) -> Option<SlotMapKeyData> {
+        let position = |key: &SlotMapKeyData| {
+            key.chunk_index as usize * SLOT_MAP_CHUNK_SIZE
+                + key.index_in_chunk as usize
+        };
+
+        self.remap
+            .binary_search_by_key(&position(old), |(k, _)| position(k))
+            .ok()
+            .map(|i| self.remap[i])
+            .filter(|(k, _)| k.generation == old.generation)
+            .map(|(_, new)| new)
+    }
+
+    /// Take the exported values, discarding the remap table
+    pub fn into_values(self) -> Vec<T> {
+        self.values
+    }
+
+    /// Split this export into its values and its remap table
+    pub fn into_parts(self) -> (Vec<T>, Vec<(SlotMapKeyData, SlotMapKeyData)>) {
+        (self.values, self.remap)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use crate::SlotMap;
+    use std::borrow::Borrow;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_export_import_with_churn() {
+        let mut map = SlotMap::<TestKey, usize, String>::new();
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 3 + 17)
+            .map(|i| map.insert(i, format!("{}", i)))
+            .collect::<Vec<_>>();
+
+        for k in keys.iter().step_by(2) {
+            let _ = map.remove(k);
+        }
+
+        // Reuse a few slots so some live keys have later generations
+        let reused = (0..5)
+            .map(|i| map.insert(i, format!("reused {}", i)))
+            .collect::<Vec<_>>();
+
+        let expected = keys
+            .iter()
+            .chain(reused.iter())
+            .filter_map(|k| map.get(k).map(|v| (*k, v.clone())))
+            .collect::<Vec<_>>();
+
+        let export = map.export();
+
+        assert_eq!(expected.len(), export.values().len());
+
+        // Stale key data, including for slots that were reused, must not
+        // resolve
+        for key in keys.iter().step_by(2) {
+            assert_eq!(None, export.remap_key(key.borrow()));
+        }
+
+        let remapped = expected
+            .iter()
+            .map(|(k, v)| (export.remap_key(k.borrow()).unwrap(), v.clone()))
+            .collect::<Vec<_>>();
+
+        let new_map = SlotMap::<TestKey, usize, String>::import(export);
+
+        assert_eq!(expected.len(), new_map.len());
+
+        for (key_data, value) in remapped {
+            assert_eq!(Some(&value), new_map.get_raw(&key_data));
+        }
+    }
+
+    #[test]
+    fn test_export_drops_values_once() {
+        let counter = Arc::new(());
+        let mut map = SlotMap::<TestKey, usize, Arc<()>>::new();
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE + 3)
+            .map(|i| map.insert(i, counter.clone()))
+            .collect::<Vec<_>>();
+
+        let _ = map.remove(&keys[0]);
+
+        let export = map.export();
+        assert_eq!(SLOT_MAP_CHUNK_SIZE + 2, Arc::strong_count(&counter) - 1);
+
+        let mut new_map = SlotMap::<TestKey, usize, Arc<()>>::import(export);
+        let _ = new_map.insert(0, counter.clone());
+        drop(new_map);
+
+        assert_eq!(1, Arc::strong_count(&counter));
+    }
+}
@@ -0,0 +1,225 @@
+//! C API over a slot map of untyped pointers. Keys cross the boundary in
+//! their packed `u64` form, and the map never dereferences or frees the
+//! stored pointers, so ownership of whatever they point to stays with the
+//! caller
+
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+use std::ffi::c_void;
+
+/// Key of the map behind the C API, which only ever uses raw key data
+#[derive(Debug)]
+struct FfiKey(SlotMapKeyData);
+
+impl std::borrow::Borrow<SlotMapKeyData> for FfiKey {
+    fn borrow(&self) -> &SlotMapKeyData {
+        &self.0
+    }
+}
+
+impl From<((), SlotMapKeyData)> for FfiKey {
+    fn from((_, key_data): ((), SlotMapKeyData)) -> Self {
+        FfiKey(key_data)
+    }
+}
+
+impl SlotMapKey<()> for FfiKey {}
+
+/// Opaque handle to a slot map of untyped pointers, created with
+/// [`owsm_map_new`] and destroyed with [`owsm_map_free`]
+#[derive(Debug)]
+pub struct OwsmMap {
+    map: SlotMap<FfiKey, (), *mut c_void>,
+}
+
+/// Callback given every item of a map by [`owsm_map_for_each`], along with
+/// the user data passed to it
+pub type OwsmVisitor =
+    extern "C" fn(user_data: *mut c_void, key: u64, value: *mut c_void);
+
+/// Create a new empty map. The map must be destroyed with [`owsm_map_free`]
+#[no_mangle]
+pub extern "C" fn owsm_map_new() -> *mut OwsmMap {
+    Box::into_raw(Box::new(OwsmMap {
+        map: SlotMap::new(),
+    }))
+}
+
+/// Destroy a map created with [`owsm_map_new`]. The pointers stored in the
+/// map are not freed. Passing null does nothing
+///
+/// # Safety
+/// The map must have been created with [`owsm_map_new`] and not destroyed
+/// already
+#[no_mangle]
+pub unsafe extern "C" fn owsm_map_free(map: *mut OwsmMap) {
+    if !map.is_null() {
+        drop(Box::from_raw(map));
+    }
+}
+
+/// Get the number of items in the map
+///
+/// # Safety
+/// The map must be a live map created with [`owsm_map_new`]
+#[no_mangle]
+pub unsafe extern "C" fn owsm_map_len(map: *const OwsmMap) -> usize {
+    (*map).map.len()
+}
+
+/// Insert the given pointer into the map and return its key
+///
+/// # Safety
+/// The map must be a live map created with [`owsm_map_new`]
+#[no_mangle]
+pub unsafe extern "C" fn owsm_map_insert(
+    map: *mut OwsmMap,
+    value: *mut c_void,
+) -> u64 {
+    u64::from((*map).map.insert_raw(value))
+}
+
+/// Look up the pointer for the given key, writing it to `out` and returning
+/// true if the key is in the map. `out` is left alone otherwise, and may be
+/// null if only the presence of the key is wanted
+///
+/// # Safety
+/// The map must be a live map created with [`owsm_map_new`], and `out` must
+/// be null or valid for writes
+#[no_mangle]
+pub unsafe extern "C" fn owsm_map_get(
+    map: *const OwsmMap,
+    key: u64,
+    out: *mut *mut c_void,
+) -> bool {
+    write_found(out, (*map).map.get_raw(&SlotMapKeyData::from(key)))
+}
+
+/// Remove the item for the given key, writing its pointer to `out` and
+/// returning true if the key was in the map. `out` is left alone otherwise,
+/// and may be null
+///
+/// # Safety
+/// The map must be a live map created with [`owsm_map_new`], and `out` must
+/// be null or valid for writes
+#[no_mangle]
+pub unsafe extern "C" fn owsm_map_remove(
+    map: *mut OwsmMap,
+    key: u64,
+    out: *mut *mut c_void,
+) -> bool {
+    write_found(
+        out,
+        (*map)
+            .map
+            .remove_raw(&SlotMapKeyData::from(key))
+            .map(|value| &*value),
+    )
+}
+
+/// Remove every item from the map without freeing the stored pointers
+///
+/// # Safety
+/// The map must be a live map created with [`owsm_map_new`]
+#[no_mangle]
+pub unsafe extern "C" fn owsm_map_clear(map: *mut OwsmMap) {
+    (*map).map.clear();
+}
+
+/// Call the given function with the key and pointer of every item in the
+/// map, in slot order, passing the given user data along. The map must not
+/// be modified from the callback
+///
+/// # Safety
+/// The map must be a live map created with [`owsm_map_new`]
+#[no_mangle]
+pub unsafe extern "C" fn owsm_map_for_each(
+    map: *const OwsmMap,
+    visit: OwsmVisitor,
+    user_data: *mut c_void,
+) {
+    for (key_data, value) in (*map).map.iter_raw() {
+        visit(user_data, u64::from(key_data), *value);
+    }
+}
+
+/// Write the found value, if any, to the given out pointer if it isn't null
+unsafe fn write_found(
+    out: *mut *mut c_void,
+    found: Option<&*mut c_void>,
+) -> bool {
+    match found {
+        Some(value) => {
+            if !out.is_null() {
+                *out = *value;
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::seeded_rng;
+    use rand::Rng;
+    use std::collections::HashMap;
+
+    extern "C" fn collect(
+        user_data: *mut c_void,
+        key: u64,
+        value: *mut c_void,
+    ) {
+        let seen = unsafe { &mut *(user_data as *mut Vec<(u64, usize)>) };
+        seen.push((key, value as usize));
+    }
+
+    #[test]
+    fn test_c_api_matches_model() {
+        let mut rng = seeded_rng(11);
+        let map = owsm_map_new();
+        let mut model = HashMap::<u64, usize>::new();
+        let mut keys = Vec::<u64>::new();
+
+        unsafe {
+            for i in 1..2000usize {
+                if keys.is_empty() || rng.gen_bool(0.6) {
+                    let key = owsm_map_insert(map, i as *mut c_void);
+                    assert!(model.insert(key, i).is_none());
+                    keys.push(key);
+                } else {
+                    let key = keys[rng.gen_range(0..keys.len())];
+                    let mut out = std::ptr::null_mut();
+
+                    assert_eq!(
+                        model.remove(&key).is_some(),
+                        owsm_map_remove(map, key, &mut out)
+                    );
+                    assert!(!owsm_map_get(map, key, std::ptr::null_mut()));
+                }
+            }
+
+            assert_eq!(model.len(), owsm_map_len(map));
+
+            for (key, value) in &model {
+                let mut out = std::ptr::null_mut();
+                assert!(owsm_map_get(map, *key, &mut out));
+                assert_eq!(*value, out as usize);
+            }
+
+            let mut seen = Vec::<(u64, usize)>::new();
+            owsm_map_for_each(
+                map,
+                collect,
+                &mut seen as *mut Vec<(u64, usize)> as *mut c_void,
+            );
+            assert_eq!(model, seen.into_iter().collect());
+
+            owsm_map_clear(map);
+            assert_eq!(0, owsm_map_len(map));
+
+            owsm_map_free(map);
+            owsm_map_free(std::ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,375 @@
+use super::{SlotMapKey, SlotMapKeyData};
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+
+/// Slot map with room for a fixed number of items stored inline, so it never
+/// allocates after it's created. Keys are handed out exactly as a
+/// [`SlotMap`](crate::SlotMap) would hand them out, and inserting into a full
+/// map fails instead of growing, which makes it usable on targets without an
+/// allocator and in threads that can't block on one
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()> : Debug + PartialEq);
+/// let mut map = FixedSlotMap::<TestKey, (), &'static str, 2>::new();
+///
+/// let first = map.try_insert((), "first").unwrap();
+/// let _ = map.try_insert((), "second").unwrap();
+///
+/// assert_eq!(Err("third"), map.try_insert((), "third"));
+///
+/// let _ = map.remove(&first);
+/// assert!(map.try_insert((), "third").is_ok());
+/// ```
+pub struct FixedSlotMap<K, P, T, const N: usize>
+where
+    K: SlotMapKey<P>,
+{
+    /// Slots below the slot count always hold a value, either a live item or
+    /// one left behind by a removal. Slots above it have never been written
+    slots: [(SlotMapKeyData, MaybeUninit<T>); N],
+    slot_count: usize,
+
+    next_open_slot: SlotMapKeyData,
+    len: usize,
+
+    _phantom: PhantomData<fn(P, K)>,
+}
+
+impl<K, P, T, const N: usize> std::fmt::Debug for FixedSlotMap<K, P, T, N>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.values()).finish()
+    }
+}
+
+impl<K, P, T, const N: usize> Default for FixedSlotMap<K, P, T, N>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        FixedSlotMap::new()
+    }
+}
+
+impl<K, P, T, const N: usize> Clone for FixedSlotMap<K, P, T, N>
+where
+    K: SlotMapKey<P>,
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        let mut result = FixedSlotMap::new();
+
+        for (target, (key_data, value)) in
+            result.slots.iter_mut().zip(self.written_slots())
+        {
+            // Safety - written slots always hold a value
+            *target = (
+                *key_data,
+                MaybeUninit::new(unsafe { value.assume_init_ref().clone() }),
+            );
+
+            // Count as we go so a panicking clone drops what was written
+            result.slot_count += 1;
+        }
+
+        result.next_open_slot = self.next_open_slot;
+        result.len = self.len;
+
+        result
+    }
+}
+
+impl<K, P, T, const N: usize> Drop for FixedSlotMap<K, P, T, N>
+where
+    K: SlotMapKey<P>,
+{
+    fn drop(&mut self) {
+        for (_, value) in &mut self.slots[..self.slot_count] {
+            // Safety - written slots always hold a value
+            unsafe { value.assume_init_drop() };
+        }
+    }
+}
+
+impl<K, P, T, const N: usize> FixedSlotMap<K, P, T, N>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> FixedSlotMap<K, P, T, N> {
+        FixedSlotMap {
+            slots: std::array::from_fn(|_| {
+                (SlotMapKeyData::default(), MaybeUninit::uninit())
+            }),
+            slot_count: 0,
+            next_open_slot: SlotMapKeyData::default(),
+            len: 0,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the maximum number of items in the map
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Tells if the map is full, so the next insert will fail
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Insert the given item into the map and return its key, or give the
+    /// item back if the map is full
+    pub fn try_insert(&mut self, pointer: P, value: T) -> Result<K, T> {
+        self.try_insert_raw(value)
+            .map(|key_data| K::from((pointer, key_data)))
+    }
+
+    /// Insert the given item into the map and return its key data, or give
+    /// the item back if the map is full
+    fn try_insert_raw(&mut self, value: T) -> Result<SlotMapKeyData, T> {
+        let position = self.next_open_slot.position();
+
+        if position == self.slot_count {
+            let Some(slot) = self.slots.get_mut(position) else {
+                return Err(value);
+            };
+
+            *slot = (self.next_open_slot, MaybeUninit::new(value));
+            self.slot_count += 1;
+            self.len += 1;
+
+            let key_data = self.next_open_slot;
+            let _ = self.next_open_slot.increment_coordinates();
+
+            return Ok(key_data);
+        }
+
+        let (key, slot_value) = &mut self.slots[position];
+
+        // Safety - the slot is below the slot count, so it holds a value left
+        // behind when its last item was removed
+        unsafe { slot_value.assume_init_drop() };
+        *slot_value = MaybeUninit::new(value);
+
+        key.increment_generation();
+        key.swap_coordinates(&mut self.next_open_slot);
+        self.len += 1;
+
+        Ok(*key)
+    }
+
+    /// Get the slot for the given key data if it's filled and in the key
+    /// data's generation
+    fn filled_slot(
+        &self,
+        key_data: &SlotMapKeyData,
+    ) -> Option<&(SlotMapKeyData, MaybeUninit<T>)> {
+        self.slots[..self.slot_count]
+            .get(key_data.position())
+            .filter(|(key, _)| key.is_filled())
+            .filter(|(key, _)| key.generation == key_data.generation)
+    }
+
+    /// Mutable version of filled_slot
+    fn filled_slot_mut(
+        &mut self,
+        key_data: &SlotMapKeyData,
+    ) -> Option<&mut (SlotMapKeyData, MaybeUninit<T>)> {
+        self.slots[..self.slot_count]
+            .get_mut(key_data.position())
+            .filter(|(key, _)| key.is_filled())
+            .filter(|(key, _)| key.generation == key_data.generation)
+    }
+
+    /// Get the slots that have been written
+    fn written_slots(&self) -> &[(SlotMapKeyData, MaybeUninit<T>)] {
+        &self.slots[..self.slot_count]
+    }
+
+    /// Get a reference to the item for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.filled_slot(key_data)
+            // Safety - written slots always hold a value
+            .map(|(_, value)| unsafe { value.assume_init_ref() })
+    }
+
+    /// Get a mutable reference to the item for the given key if it exists
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Same as get_mut, but only requires slot map key data
+    pub fn get_mut_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        self.filled_slot_mut(key_data)
+            // Safety - written slots always hold a value
+            .map(|(_, value)| unsafe { value.assume_init_mut() })
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.filled_slot(key.borrow()).is_some()
+    }
+
+    /// Remove the item for the given key and return a mutable ref to it if
+    /// there was one. Like a slot map, the item stays in its slot until the
+    /// slot is reused
+    pub fn remove(&mut self, key: &K) -> Option<&mut T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        let position = key_data.position();
+        let _ = self.filled_slot(key_data)?;
+
+        let (key, value) = &mut self.slots[position];
+
+        key.increment_generation();
+        key.swap_coordinates(&mut self.next_open_slot);
+        self.len -= 1;
+
+        // Safety - written slots always hold a value
+        Some(unsafe { value.assume_init_mut() })
+    }
+
+    /// Remove all items from the map. Values stay in their slots until the
+    /// slots are reused. Like [`SlotMap::clear`](crate::SlotMap::clear), the
+    /// chain of open slots is rebuilt in order of position
+    pub fn clear(&mut self) {
+        let mut next_open_slot = SlotMapKeyData::from(self.slot_count as u64);
+
+        // Link each slot to the one after it, walking back from the end
+        for (position, (key, _)) in
+            self.slots[..self.slot_count].iter_mut().enumerate().rev()
+        {
+            if key.is_filled() {
+                key.increment_generation();
+            }
+
+            *key = SlotMapKeyData {
+                generation: key.generation,
+                ..next_open_slot
+            };
+            next_open_slot = SlotMapKeyData::from(position as u64);
+        }
+
+        self.next_open_slot = next_open_slot;
+        self.len = 0;
+    }
+
+    /// Create an iterator over the raw key data and values of all items in
+    /// the map
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.written_slots()
+            .iter()
+            .filter(|(key, _)| key.is_filled())
+            // Safety - written slots always hold a value
+            .map(|(key, value)| (*key, unsafe { value.assume_init_ref() }))
+    }
+
+    /// Create an iterator over the raw key data and mutable values of all
+    /// items in the map
+    pub fn iter_mut_raw(
+        &mut self,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &mut T)> {
+        self.slots[..self.slot_count]
+            .iter_mut()
+            .filter(|(key, _)| key.is_filled())
+            // Safety - written slots always hold a value
+            .map(|(key, value)| (*key, unsafe { value.assume_init_mut() }))
+    }
+
+    /// Create an iterator over the values of all items in the map
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.iter_raw().map(|(_, value)| value)
+    }
+
+    /// Create an iterator over the mutable values of all items in the map
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.iter_mut_raw().map(|(_, value)| value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use crate::{SlotMap, SLOT_MAP_CHUNK_SIZE};
+    use std::rc::Rc;
+
+    #[test]
+    fn test_fixed_matches_slot_map() {
+        const CAPACITY: usize = SLOT_MAP_CHUNK_SIZE + 10;
+
+        let tracker = Rc::new(());
+        let mut fixed = FixedSlotMap::<TestKey, usize, Rc<()>, CAPACITY>::new();
+        let mut expected = SlotMap::<TestKey, usize, ()>::new();
+
+        let mut keys = Vec::new();
+
+        for i in 0..CAPACITY * 4 {
+            if fixed.is_full() {
+                assert!(fixed.try_insert(i, tracker.clone()).is_err());
+            } else {
+                let key = fixed.try_insert(i, tracker.clone()).unwrap();
+                assert_eq!(expected.insert(i, ()), key);
+                keys.push(key);
+            }
+
+            if i % 3 == 0 {
+                let key = keys.swap_remove(i * 7 % keys.len());
+                assert!(fixed.remove(&key).is_some());
+                assert!(expected.remove(&key).is_some());
+                assert!(!fixed.contains_key(&key));
+                assert!(fixed.remove(&key).is_none());
+            }
+        }
+
+        assert_eq!(keys.len(), fixed.len());
+        assert!(keys.iter().all(|k| fixed.get(k).is_some()));
+
+        let mut iterated = fixed.iter_raw().map(|(k, _)| k).collect::<Vec<_>>();
+        let mut expected_keys =
+            expected.iter_raw().map(|(k, _)| k).collect::<Vec<_>>();
+        iterated.sort_by_key(|k| u64::from(*k));
+        expected_keys.sort_by_key(|k| u64::from(*k));
+        assert_eq!(expected_keys, iterated);
+
+        // Values left behind by removals are held until dropped with the map
+        let cloned = fixed.clone();
+        assert_eq!(CAPACITY * 2 + 1, Rc::strong_count(&tracker));
+        drop(cloned);
+
+        fixed.clear();
+        expected.clear();
+        assert!(fixed.is_empty());
+        assert!(keys.iter().all(|k| !fixed.contains_key(k)));
+        assert_eq!(
+            expected.insert(0, ()),
+            fixed.try_insert(0, tracker.clone()).unwrap()
+        );
+
+        drop(fixed);
+        assert_eq!(1, Rc::strong_count(&tracker));
+    }
+}
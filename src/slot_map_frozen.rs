@@ -0,0 +1,198 @@
+use super::slot_map::Slots;
+use super::{SlotMap, SlotMapKey, SlotMapKeyData, SLOT_MAP_CHUNK_SIZE};
+use std::marker::PhantomData;
+
+/// Read-only slot map whose slots are stored in one contiguous allocation.
+/// It can't be modified, so it is `Sync` whenever its values are, and can be
+/// wrapped in an `Arc` and read from any number of threads without locking.
+/// Created with [`SlotMap::freeze`](crate::SlotMap::freeze)
+pub struct FrozenSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    slots: Box<[(SlotMapKeyData, T)]>,
+    next_open_slot: SlotMapKeyData,
+    len: usize,
+
+    _phantom: PhantomData<fn(P, K)>,
+}
+
+impl<K, P, T> std::fmt::Debug for FrozenSlotMap<K, P, T>
+where
+    T: std::fmt::Debug,
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.values()).finish()
+    }
+}
+
+impl<K, P, T> Clone for FrozenSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        FrozenSlotMap {
+            slots: self.slots.clone(),
+            next_open_slot: self.next_open_slot,
+            len: self.len,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<K, P, T> FrozenSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get a reference to the item for the given key
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        let linear_index = key_data.chunk_index as usize * SLOT_MAP_CHUNK_SIZE
+            + key_data.index_in_chunk as usize;
+
+        self.slots
+            .get(linear_index)
+            .filter(|(k, _)| k.is_filled())
+            .filter(|(k, _)| k.generation == key_data.generation)
+            .map(|(_, value)| value)
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Same as contains_key, but only requires slot map key data
+    pub fn contains_key_raw(&self, key_data: &SlotMapKeyData) -> bool {
+        self.get_raw(key_data).is_some()
+    }
+
+    /// Create an iterator over the raw key data and values of the items in
+    /// the map
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(_, (k, _))| k.is_filled())
+            .map(|(linear_index, (k, value))| {
+                let key_data = SlotMapKeyData {
+                    generation: k.generation,
+                    ..SlotMapKeyData::from(linear_index as u64)
+                };
+
+                (key_data, value)
+            })
+    }
+
+    /// Create an iterator over the values of the items in the map
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.iter_raw().map(|(_, value)| value)
+    }
+
+    /// Turn this back into a slot map that can be modified. Existing keys
+    /// stay valid, and the map hands out the same keys it would have if it
+    /// had never been frozen
+    pub fn thaw(self) -> SlotMap<K, P, T> {
+        let mut slots = Slots::new();
+
+        for slot in self.slots.into_vec() {
+            slots.push_slot(slot);
+        }
+
+        SlotMap::from_raw_state(slots, self.next_open_slot, self.len)
+    }
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Consume this map to produce a read-only version of it that stores all
+    /// of its slots in a single allocation. Keys from this map remain valid
+    /// for the frozen map. This suits maps that are built once and then only
+    /// read, especially from many threads at once
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # use std::sync::Arc;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey,(),&'static str>::new();
+    /// let key = map.insert((), "Hello");
+    ///
+    /// let frozen = Arc::new(map.freeze());
+    ///
+    /// let reader = frozen.clone();
+    /// std::thread::spawn(move || {
+    ///     assert_eq!(Some(&"Hello"), reader.get(&key));
+    /// }).join().unwrap();
+    /// ```
+    pub fn freeze(self) -> FrozenSlotMap<K, P, T> {
+        let next_open_slot = self.next_open_slot();
+        let len = self.len();
+
+        FrozenSlotMap {
+            slots: self.into_slots().collect(),
+            next_open_slot,
+            len,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+
+    #[test]
+    fn test_freeze_and_thaw() {
+        let mut map = SlotMap::<TestKey, usize, String>::new();
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 2 + 7)
+            .map(|i| map.insert(i, format!("{}", i)))
+            .collect::<Vec<_>>();
+        let _ = map.remove(&keys[3]);
+        let _ = map.remove(&keys[SLOT_MAP_CHUNK_SIZE + 4]);
+
+        let expected = map.clone();
+        let frozen = map.freeze();
+
+        assert_eq!(expected.len(), frozen.len());
+        assert!(expected.iter_raw().eq(frozen.iter_raw()));
+
+        for key in &keys {
+            assert_eq!(expected.get(key), frozen.get(key));
+        }
+
+        let unknown = TestKey::from((
+            0,
+            SlotMapKeyData::from((SLOT_MAP_CHUNK_SIZE * 5) as u64),
+        ));
+        assert_eq!(None, frozen.get(&unknown));
+
+        let mut thawed = frozen.thaw();
+        let mut expected = expected;
+
+        assert_eq!(expected.state_digest(), thawed.state_digest());
+        assert_eq!(
+            expected.insert(0, "new".to_owned()),
+            thawed.insert(0, "new".to_owned())
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use super::{SlotMap, SlotMapKey};
+
+/// A single operation on a [`SlotMap`] that can be generated by a fuzzer or
+/// property test. Operations that take a key refer to it by its position in
+/// the list of keys issued so far, wrapping around, so every generated
+/// sequence is meaningful and keys to removed items are reused as stale keys.
+/// Operations that refer to a key before any have been issued do nothing
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Op<T> {
+    /// Insert the given value
+    Insert(T),
+
+    /// Look up the value for a key
+    Get(usize),
+
+    /// Overwrite the value for a key if it's in the map
+    Replace(usize, T),
+
+    /// Check whether a key is in the map
+    ContainsKey(usize),
+
+    /// Remove the value for a key
+    Remove(usize),
+
+    /// Remove all values
+    Clear,
+}
+
+impl<T> Op<T> {
+    /// Apply this operation to the given map, adding the key of an inserted
+    /// item to the given list of issued keys
+    pub fn apply<K, P>(&self, map: &mut SlotMap<K, P, T>, keys: &mut Vec<K>)
+    where
+        K: SlotMapKey<P>,
+        P: Default,
+        T: Clone,
+    {
+        let key = |index: &usize| match keys.len() {
+            0 => None,
+            len => Some(&keys[index % len]),
+        };
+
+        match self {
+            Op::Insert(value) => {
+                keys.push(map.insert(P::default(), value.clone()))
+            }
+            Op::Get(index) => {
+                if let Some(key) = key(index) {
+                    let _ = map.get(key);
+                }
+            }
+            Op::Replace(index, value) => {
+                if let Some(existing) =
+                    key(index).and_then(|key| map.get_mut(key))
+                {
+                    *existing = value.clone();
+                }
+            }
+            Op::ContainsKey(index) => {
+                if let Some(key) = key(index) {
+                    let _ = map.contains_key(key);
+                }
+            }
+            Op::Remove(index) => {
+                if let Some(key) = key(index) {
+                    let _ = map.remove(key);
+                }
+            }
+            Op::Clear => map.clear(),
+        }
+    }
+}
+
+/// Apply the given operations to the given map in order and return the keys
+/// that were issued, including those of items that were later removed. This
+/// only drives the map; pair it with [`SlotMap::len`] checks, the
+/// `invariants` feature, or [`OracleMap`](crate::testing::OracleMap) to find
+/// out whether the map still behaves
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # use one_way_slot_map::fuzz::{apply_ops, Op};
+/// # define_key_type!(TestKey<()>);
+/// let mut map = SlotMap::<TestKey, (), u8>::new();
+///
+/// let keys = apply_ops(
+///     &mut map,
+///     &[Op::Insert(1), Op::Insert(2), Op::Remove(0), Op::Replace(1, 3)],
+/// );
+///
+/// assert_eq!(2, keys.len());
+/// assert_eq!(None, map.get(&keys[0]));
+/// assert_eq!(Some(&3), map.get(&keys[1]));
+/// ```
+pub fn apply_ops<K, P, T>(map: &mut SlotMap<K, P, T>, ops: &[Op<T>]) -> Vec<K>
+where
+    K: SlotMapKey<P>,
+    P: Default,
+    T: Clone,
+{
+    let mut keys = Vec::new();
+
+    for op in ops {
+        op.apply(map, &mut keys);
+    }
+
+    keys
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use rand::{thread_rng, Rng};
+    use std::collections::HashSet;
+
+    fn random_ops(count: usize) -> Vec<Op<usize>> {
+        let mut rng = thread_rng();
+
+        (0..count)
+            .map(|i| match rng.gen_range(0..20) {
+                0..=7 => Op::Insert(i),
+                8..=9 => Op::Get(rng.gen()),
+                10..=11 => Op::Replace(rng.gen(), i),
+                12..=13 => Op::ContainsKey(rng.gen()),
+                14..=18 => Op::Remove(rng.gen()),
+                _ => Op::Clear,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_apply_ops_matches_model() {
+        let ops = random_ops(5000);
+        let mut map = SlotMap::<TestKey, usize, usize>::new();
+        let keys = apply_ops(&mut map, &ops);
+
+        // Replay the operations against a set of live key positions
+        let mut live = HashSet::<usize>::new();
+        let mut issued = 0;
+
+        for op in &ops {
+            match op {
+                Op::Insert(_) => {
+                    let _ = live.insert(issued);
+                    issued += 1;
+                }
+                Op::Remove(index) if issued > 0 => {
+                    let _ = live.remove(&(index % issued));
+                }
+                Op::Clear => live.clear(),
+                _ => {}
+            }
+        }
+
+        assert_eq!(issued, keys.len());
+        assert_eq!(live.len(), map.len());
+
+        for (position, key) in keys.iter().enumerate() {
+            assert_eq!(live.contains(&position), map.contains_key(key));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_ops_round_trip() {
+        let ops = random_ops(100);
+        let json = serde_json::to_string(&ops).unwrap();
+
+        assert_eq!(ops, serde_json::from_str::<Vec<Op<usize>>>(&json).unwrap());
+    }
+}
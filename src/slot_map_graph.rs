@@ -0,0 +1,397 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+
+/// Item of a [`GraphSlotMap`] along with the edges that touch it
+struct Node<T, E> {
+    value: T,
+
+    /// Edges from this item to others with their data. There is at most one
+    /// edge to each item
+    outgoing: Vec<(SlotMapKeyData, E)>,
+
+    /// Items with an edge to this one
+    incoming: Vec<SlotMapKeyData>,
+}
+
+/// Slot map whose items can have edges to other items, each carrying data of
+/// type `E`, for building arenas like scene graphs or dependency trees.
+/// Removing an item also removes every edge to and from it, so edges never
+/// dangle. [`GraphSlotMap::remove_cascade`] removes an item along with every
+/// item reachable from it through its edges, like a node and its subtree
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(NodeKey<()>);
+/// let mut scene = GraphSlotMap::<NodeKey, (), &str, &str>::new();
+///
+/// let root = scene.insert((), "root");
+/// let arm = scene.insert((), "arm");
+/// let hand = scene.insert((), "hand");
+///
+/// scene.add_edge(&root, &arm, "child");
+/// scene.add_edge(&arm, &hand, "child");
+///
+/// assert_eq!(1, scene.neighbors(&root).count());
+///
+/// assert_eq!(2, scene.remove_cascade(&arm));
+/// assert_eq!(0, scene.neighbors(&root).count());
+/// assert!(!scene.contains_key(&hand));
+/// ```
+pub struct GraphSlotMap<K, P, T, E = ()>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, Node<T, E>>,
+}
+
+impl<K, P, T, E> std::fmt::Debug for GraphSlotMap<K, P, T, E>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.map.values().map(|node| &node.value))
+            .finish()
+    }
+}
+
+impl<K, P, T, E> Default for GraphSlotMap<K, P, T, E>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        GraphSlotMap::new()
+    }
+}
+
+impl<K, P, T, E> GraphSlotMap<K, P, T, E>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> GraphSlotMap<K, P, T, E> {
+        GraphSlotMap {
+            map: SlotMap::new(),
+        }
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Insert the given item into the map, without any edges, and return its
+    /// key
+    pub fn insert(&mut self, pointer: P, value: T) -> K {
+        self.map.insert(
+            pointer,
+            Node {
+                value,
+                outgoing: Vec::new(),
+                incoming: Vec::new(),
+            },
+        )
+    }
+
+    /// Get a reference to the item for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.map.get_raw(key_data).map(|node| &node.value)
+    }
+
+    /// Get a mutable reference to the item for the given key if it exists
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Same as get_mut, but only requires slot map key data
+    pub fn get_mut_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        self.map.get_mut_raw(key_data).map(|node| &mut node.value)
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Add an edge with the given data from one item to another, replacing
+    /// the data of any edge already between them. Returns whether both items
+    /// exist
+    pub fn add_edge(&mut self, from: &K, to: &K, edge: E) -> bool {
+        self.add_edge_raw(from.borrow(), to.borrow(), edge)
+    }
+
+    /// Same as add_edge, but only requires slot map key data
+    pub fn add_edge_raw(
+        &mut self,
+        from: &SlotMapKeyData,
+        to: &SlotMapKeyData,
+        edge: E,
+    ) -> bool {
+        if !self.map.contains_key_raw(to) {
+            return false;
+        }
+
+        let Some(node) = self.map.get_mut_raw(from) else {
+            return false;
+        };
+
+        match node.outgoing.iter_mut().find(|(target, _)| target == to) {
+            Some((_, existing)) => *existing = edge,
+            None => {
+                node.outgoing.push((*to, edge));
+                self.node_mut(to).incoming.push(*from);
+            }
+        }
+
+        true
+    }
+
+    /// Remove the edge from one item to another and return its data if there
+    /// was one
+    pub fn remove_edge(&mut self, from: &K, to: &K) -> Option<E> {
+        self.remove_edge_raw(from.borrow(), to.borrow())
+    }
+
+    /// Same as remove_edge, but only requires slot map key data
+    pub fn remove_edge_raw(
+        &mut self,
+        from: &SlotMapKeyData,
+        to: &SlotMapKeyData,
+    ) -> Option<E> {
+        let node = self.map.get_mut_raw(from)?;
+        let position = node.outgoing.iter().position(|(t, _)| t == to)?;
+        let (_, edge) = node.outgoing.swap_remove(position);
+
+        let incoming = &mut self.node_mut(to).incoming;
+        let position = incoming.iter().position(|source| source == from);
+        let _ = incoming.swap_remove(position.expect("edge is mirrored"));
+
+        Some(edge)
+    }
+
+    /// Get the data of the edge from one item to another if there is one
+    pub fn edge(&self, from: &K, to: &K) -> Option<&E> {
+        self.edge_raw(from.borrow(), to.borrow())
+    }
+
+    /// Same as edge, but only requires slot map key data
+    pub fn edge_raw(
+        &self,
+        from: &SlotMapKeyData,
+        to: &SlotMapKeyData,
+    ) -> Option<&E> {
+        self.map
+            .get_raw(from)?
+            .outgoing
+            .iter()
+            .find(|(target, _)| target == to)
+            .map(|(_, edge)| edge)
+    }
+
+    /// Create an iterator over the key data of the items the item for the
+    /// given key has edges to, along with the edges' data
+    pub fn neighbors(
+        &self,
+        key: &K,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &E)> {
+        self.neighbors_raw(key.borrow())
+    }
+
+    /// Same as neighbors, but only requires slot map key data
+    pub fn neighbors_raw(
+        &self,
+        key_data: &SlotMapKeyData,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &E)> {
+        self.map
+            .get_raw(key_data)
+            .into_iter()
+            .flat_map(|node| &node.outgoing)
+            .map(|(target, edge)| (*target, edge))
+    }
+
+    /// Create an iterator over the key data of the items that have edges to
+    /// the item for the given key
+    pub fn incoming(
+        &self,
+        key: &K,
+    ) -> impl Iterator<Item = SlotMapKeyData> + '_ {
+        self.incoming_raw(key.borrow())
+    }
+
+    /// Same as incoming, but only requires slot map key data
+    pub fn incoming_raw(
+        &self,
+        key_data: &SlotMapKeyData,
+    ) -> impl Iterator<Item = SlotMapKeyData> + '_ {
+        self.map
+            .get_raw(key_data)
+            .into_iter()
+            .flat_map(|node| &node.incoming)
+            .copied()
+    }
+
+    /// Remove the item for the given key along with every edge to and from
+    /// it, and return a mutable ref to it if there was one
+    pub fn remove(&mut self, key: &K) -> Option<&mut T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        let node = self.map.get_mut_raw(key_data)?;
+        let outgoing = std::mem::take(&mut node.outgoing);
+        let incoming = std::mem::take(&mut node.incoming);
+
+        for (target, _) in outgoing.iter().filter(|(t, _)| t != key_data) {
+            self.node_mut(target).incoming.retain(|s| s != key_data);
+        }
+
+        for source in incoming.iter().filter(|s| *s != key_data) {
+            self.node_mut(source)
+                .outgoing
+                .retain(|(t, _)| t != key_data);
+        }
+
+        self.map.remove_raw(key_data).map(|node| &mut node.value)
+    }
+
+    /// Remove the item for the given key and every item reachable from it
+    /// through edges, and return how many items were removed
+    pub fn remove_cascade(&mut self, key: &K) -> usize {
+        self.remove_cascade_with(key, |_, _| {})
+    }
+
+    /// Same as remove_cascade, but the given function is called with the key
+    /// data and value of each removed item
+    pub fn remove_cascade_with(
+        &mut self,
+        key: &K,
+        mut f: impl FnMut(SlotMapKeyData, &mut T),
+    ) -> usize {
+        let mut pending = vec![*key.borrow()];
+        let mut removed = 0;
+
+        while let Some(key_data) = pending.pop() {
+            let Some(node) = self.map.get_raw(&key_data) else {
+                continue;
+            };
+
+            pending.extend(node.outgoing.iter().map(|(target, _)| *target));
+
+            let value = self.remove_raw(&key_data).expect("item exists");
+            f(key_data, value);
+            removed += 1;
+        }
+
+        removed
+    }
+
+    /// Remove all items and edges from the map
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Create an iterator over the raw key data and values of all items in
+    /// the map
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.map
+            .iter_raw()
+            .map(|(key_data, node)| (key_data, &node.value))
+    }
+
+    /// Create an iterator over the values of all items in the map
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.map.values().map(|node| &node.value)
+    }
+
+    /// Create an iterator over the mutable values of all items in the map
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.map.values_mut().map(|node| &mut node.value)
+    }
+
+    /// Get the node for key data that an edge refers to, which always exists
+    fn node_mut(&mut self, key_data: &SlotMapKeyData) -> &mut Node<T, E> {
+        self.map
+            .get_mut_raw(key_data)
+            .expect("edges only refer to items in the map")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+
+    /// Check that every edge refers to live items and is mirrored
+    fn assert_edges_consistent(map: &GraphSlotMap<TestKey, usize, usize>) {
+        for (key_data, node) in map.map.iter_raw() {
+            for (target, _) in &node.outgoing {
+                let target_node = map.map.get_raw(target).unwrap();
+                assert!(target_node.incoming.contains(&key_data));
+            }
+
+            for source in &node.incoming {
+                assert!(map.edge_raw(source, &key_data).is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn test_remove_cascade_leaves_no_dangling_edges() {
+        let mut map = GraphSlotMap::<TestKey, usize, usize>::new();
+
+        // A binary tree of 127 items with extra edges across it
+        let keys = (0..127).map(|i| map.insert(i, i)).collect::<Vec<_>>();
+
+        for i in 1..keys.len() {
+            assert!(map.add_edge(&keys[(i - 1) / 2], &keys[i], ()));
+        }
+
+        for i in (0..keys.len()).step_by(5) {
+            let _ = map.add_edge(&keys[i], &keys[(i * 7) % keys.len()], ());
+        }
+
+        assert!(map.add_edge(&keys[3], &keys[3], ()));
+        assert_eq!(Some(()), map.remove_edge(&keys[3], &keys[3]));
+        assert_eq!(None, map.remove_edge(&keys[3], &keys[3]));
+        assert_edges_consistent(&map);
+
+        // Removing a leaf only removes that leaf
+        assert_eq!(Some(&mut 126), map.remove(&keys[126]));
+        assert!(!map.add_edge(&keys[0], &keys[126], ()));
+        assert_eq!(None, map.edge(&keys[62], &keys[126]));
+        assert_edges_consistent(&map);
+
+        // Removing the subtree under item 2 doesn't touch item 1's subtree
+        // unless a cross edge reaches into it
+        let mut removed = Vec::new();
+        let count = map.remove_cascade_with(&keys[2], |_, v| removed.push(*v));
+        assert_eq!(count, removed.len());
+        assert_edges_consistent(&map);
+
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(
+                !removed.contains(&i) && i != 126,
+                map.contains_key(key)
+            );
+        }
+
+        for i in [2, 5, 6, 11, 14, 30, 61, 62, 125] {
+            assert!(removed.contains(&i));
+        }
+
+        assert_eq!(map.len(), 126 - count);
+        assert_eq!(map.len(), map.remove_cascade(&keys[0]));
+        assert!(map.is_empty());
+    }
+}
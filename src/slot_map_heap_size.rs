@@ -0,0 +1,185 @@
+use super::{SlotMap, SlotMapKey};
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Get the number of bytes this map has allocated on the heap for its
+    /// slots, not counting anything the values themselves own. Storage is
+    /// allocated a whole chunk at a time, so this grows in steps and doesn't
+    /// shrink when items are removed. Chunks shared with snapshots or clones
+    /// are counted in full by every map that shares them
+    pub fn heap_size(&self) -> usize {
+        self.slots().allocated_bytes()
+    }
+
+    /// Get the full memory footprint of this map, including the map itself,
+    /// its slots, and the heap memory owned by each value as measured by the
+    /// given function. Values left behind in vacant slots are still owned by
+    /// the map, so they are measured too
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey, (), String>::new();
+    ///
+    /// let _ = map.insert((), "a".repeat(1000));
+    /// let _ = map.insert((), "b".repeat(500));
+    ///
+    /// let size = map.deep_size_of_with(|value| value.capacity());
+    ///
+    /// assert!(size >= map.heap_size() + 1500);
+    /// ```
+    pub fn deep_size_of_with(
+        &self,
+        mut measure: impl FnMut(&T) -> usize,
+    ) -> usize {
+        std::mem::size_of_val(self)
+            + self.heap_size()
+            + self
+                .slots()
+                .values()
+                .map(|(_, value)| measure(value))
+                .sum::<usize>()
+    }
+}
+
+/// Values that can report how many bytes they own on the heap, so a
+/// [`SlotMap`] of them can report its full memory footprint. This is this
+/// crate's own trait, shaped like the one in the `heapsize` crate. The traits
+/// of `heapsize` and `malloc_size_of` aren't implemented, but either can be
+/// hooked up through [`SlotMap::deep_size_of_with`]
+#[cfg(feature = "heap-size-trait")]
+pub trait HeapSize {
+    /// Get the number of bytes owned by this value on the heap, not counting
+    /// the value itself
+    fn heap_size_of_children(&self) -> usize;
+}
+
+#[cfg(feature = "heap-size-trait")]
+macro_rules! impl_heap_size_for_inline_types (
+    ($($inline_type:ty),*) => {
+        $(
+            impl HeapSize for $inline_type {
+                fn heap_size_of_children(&self) -> usize {
+                    0
+                }
+            }
+        )*
+    };
+);
+
+#[cfg(feature = "heap-size-trait")]
+impl_heap_size_for_inline_types!(
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    &'static str
+);
+
+#[cfg(feature = "heap-size-trait")]
+impl HeapSize for String {
+    fn heap_size_of_children(&self) -> usize {
+        self.capacity()
+    }
+}
+
+#[cfg(feature = "heap-size-trait")]
+impl<T: HeapSize> HeapSize for Box<T> {
+    fn heap_size_of_children(&self) -> usize {
+        std::mem::size_of::<T>() + (**self).heap_size_of_children()
+    }
+}
+
+#[cfg(feature = "heap-size-trait")]
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn heap_size_of_children(&self) -> usize {
+        self.as_ref().map_or(0, T::heap_size_of_children)
+    }
+}
+
+#[cfg(feature = "heap-size-trait")]
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size_of_children(&self) -> usize {
+        self.capacity() * std::mem::size_of::<T>()
+            + self.iter().map(T::heap_size_of_children).sum::<usize>()
+    }
+}
+
+#[cfg(feature = "heap-size-trait")]
+impl<K, P, T> HeapSize for SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: HeapSize,
+{
+    fn heap_size_of_children(&self) -> usize {
+        self.heap_size()
+            + self
+                .slots()
+                .values()
+                .map(|(_, value)| value.heap_size_of_children())
+                .sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use crate::SLOT_MAP_CHUNK_SIZE;
+
+    #[test]
+    fn test_size_grows_by_chunk() {
+        let mut map = SlotMap::<TestKey, usize, Vec<u8>>::new();
+        let empty_size = map.heap_size();
+
+        assert!(empty_size > 0);
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 2)
+            .map(|i| map.insert(i, vec![0; i]))
+            .collect::<Vec<_>>();
+
+        let full_size = map.heap_size();
+        assert!(full_size > empty_size * 2);
+
+        let deep_size = map.deep_size_of_with(Vec::capacity);
+        let value_bytes = (0..SLOT_MAP_CHUNK_SIZE * 2).sum::<usize>();
+        assert!(deep_size >= full_size + value_bytes);
+
+        // Removed values are still owned by the map
+        for key in &keys {
+            let _ = map.remove(key);
+        }
+
+        assert_eq!(full_size, map.heap_size());
+        assert_eq!(deep_size, map.deep_size_of_with(Vec::capacity));
+    }
+
+    #[cfg(feature = "heap-size-trait")]
+    #[test]
+    fn test_heap_size_trait() {
+        let mut map = SlotMap::<TestKey, usize, Option<String>>::new();
+
+        let _ = map.insert(0, Some("a".repeat(100)));
+        let _ = map.insert(1, None);
+
+        assert_eq!(map.heap_size() + 100, map.heap_size_of_children());
+
+        let boxed = Box::new(vec![map]);
+        assert!(boxed.heap_size_of_children() > 100);
+    }
+}
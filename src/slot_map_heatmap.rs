@@ -0,0 +1,183 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Number of reads and writes made to a single slot. Reads happen through
+/// shared references, so the counts are atomic
+#[derive(Debug, Default)]
+pub(crate) struct AccessCounter {
+    reads: AtomicU64,
+    writes: AtomicU64,
+}
+
+impl Clone for AccessCounter {
+    fn clone(&self) -> Self {
+        AccessCounter {
+            reads: AtomicU64::new(self.reads.load(Ordering::Relaxed)),
+            writes: AtomicU64::new(self.writes.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Count a read of the slot at the given coordinates. Slots that were
+/// written without being counted aren't tracked until their first counted
+/// write
+pub(crate) fn record_read(
+    counters: &[AccessCounter],
+    key_data: &SlotMapKeyData,
+) {
+    if let Some(counter) = counters.get(key_data.position()) {
+        let _ = counter.reads.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Count a write to the slot at the given coordinates
+pub(crate) fn record_write(
+    counters: &mut Vec<AccessCounter>,
+    key_data: &SlotMapKeyData,
+) {
+    let position = key_data.position();
+
+    if counters.len() <= position {
+        counters.resize_with(position + 1, Default::default);
+    }
+
+    *counters[position].writes.get_mut() += 1;
+}
+
+/// Access counts of a slot reported by [`SlotMap::hottest_slots`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotAccess {
+    /// Coordinates of the slot along with its current generation. This is the
+    /// key data of the slot's item if it is filled
+    pub key_data: SlotMapKeyData,
+
+    /// Number of times an item in the slot was read
+    pub reads: u64,
+
+    /// Number of times an item was inserted into, modified in, or removed
+    /// from the slot
+    pub writes: u64,
+}
+
+impl SlotAccess {
+    /// Get the total number of accesses to the slot
+    pub fn total(&self) -> u64 {
+        self.reads + self.writes
+    }
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Get the access counts of the given number of most accessed slots,
+    /// most accessed first. Successful `get` calls count as reads, and
+    /// inserts, successful `get_mut` calls, and removals count as writes.
+    /// Counts accumulate across the items that use a slot
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey, (), usize>::new();
+    ///
+    /// let keys = (0..10).map(|i| map.insert((), i)).collect::<Vec<_>>();
+    ///
+    /// for _ in 0..100 {
+    ///     let _ = map.get(&keys[7]);
+    /// }
+    ///
+    /// let hottest = map.hottest_slots(1);
+    ///
+    /// assert_eq!(map.get(&keys[7]).copied(), map.get_raw(&hottest[0].key_data).copied());
+    /// assert_eq!(100, hottest[0].reads);
+    /// assert_eq!(1, hottest[0].writes);
+    /// ```
+    pub fn hottest_slots(&self, n: usize) -> Vec<SlotAccess> {
+        let mut accesses = self
+            .slots()
+            .values()
+            .zip(self.access_counters())
+            .enumerate()
+            .map(|(position, ((slot_key_data, _), counter))| SlotAccess {
+                key_data: SlotMapKeyData {
+                    generation: slot_key_data.generation,
+                    ..SlotMapKeyData::from(position as u64)
+                },
+                reads: counter.reads.load(Ordering::Relaxed),
+                writes: counter.writes.load(Ordering::Relaxed),
+            })
+            .filter(|access| access.total() > 0)
+            .collect::<Vec<_>>();
+
+        accesses.sort_by_key(|access| std::cmp::Reverse(access.total()));
+        accesses.truncate(n);
+        accesses
+    }
+
+    /// Set the access counts of every slot back to zero
+    pub fn reset_access_counts(&mut self) {
+        self.access_counters_mut().clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn test_counts_match_model() {
+        let mut rng = thread_rng();
+        let mut map = SlotMap::<TestKey, usize, usize>::new();
+        let keys = (0..600).map(|i| map.insert(i, i)).collect::<Vec<_>>();
+
+        // Reads and writes of each key, with the insert counted
+        let mut model = vec![(0u64, 1u64); keys.len()];
+
+        for _ in 0..20_000 {
+            // Skew accesses towards the first keys
+            let bound = rng.gen_range(1..=keys.len());
+            let i = rng.gen_range(0..bound);
+
+            if rng.gen_bool(0.7) {
+                assert_eq!(Some(&i), map.get(&keys[i]));
+                model[i].0 += 1;
+            } else {
+                assert!(map.get_mut(&keys[i]).is_some());
+                model[i].1 += 1;
+            }
+        }
+
+        let mut expected = model
+            .iter()
+            .map(|(reads, writes)| reads + writes)
+            .collect::<Vec<_>>();
+        expected.sort_unstable_by(|a, b| b.cmp(a));
+        expected.truncate(20);
+
+        let hottest = map.hottest_slots(20);
+
+        assert_eq!(
+            expected,
+            hottest.iter().map(SlotAccess::total).collect::<Vec<_>>()
+        );
+
+        for access in hottest {
+            let i = *map.get_raw(&access.key_data).unwrap();
+            assert_eq!(model[i], (access.reads, access.writes));
+        }
+
+        // Copies keep the counts
+        assert_eq!(
+            map.hottest_slots(20),
+            map.map(|value| *value as u8).hottest_slots(20)
+        );
+
+        map.reset_access_counts();
+        assert!(map.hottest_slots(10).is_empty());
+
+        let _ = map.remove(&keys[3]);
+        assert_eq!(1, map.hottest_slots(10)[0].writes);
+    }
+}
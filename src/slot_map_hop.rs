@@ -0,0 +1,586 @@
+use super::{SlotMapKey, SlotMapKeyData};
+use std::marker::PhantomData;
+
+/// Marks the end of the list of runs of open slots
+const NO_RUN: usize = usize::MAX;
+
+/// Contents of a slot of a [`HopOneWaySlotMap`]
+#[derive(Clone)]
+enum Content<T> {
+    Filled(T),
+
+    /// Open slot in a run of adjacent open slots. The fields are only
+    /// meaningful at the ends of the run, where `other_end` is the position
+    /// of the run's other end, and only at the start of the run for the links
+    /// to the starts of the neighboring runs in the list of runs
+    Open {
+        other_end: usize,
+        previous: usize,
+        next: usize,
+    },
+}
+
+#[derive(Clone)]
+struct Slot<T> {
+    generation: u32,
+    content: Content<T>,
+}
+
+/// Slot map that keeps track of runs of adjacent open slots, so iterating
+/// skips each run in a single step and takes time in proportion to the
+/// number of items rather than the number of slots. This suits maps that
+/// grow large and then shrink to a small fraction of their size. Inserts
+/// reuse the first slot of a run of open slots, and removals join the
+/// removed item's slot with the runs on either side of it
+pub struct HopOneWaySlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    slots: Vec<Slot<T>>,
+
+    /// Start of the first run in the list of runs of open slots
+    first_run: usize,
+    len: usize,
+
+    _phantom: PhantomData<fn(P, K)>,
+}
+
+impl<K, P, T> std::fmt::Debug for HopOneWaySlotMap<K, P, T>
+where
+    T: std::fmt::Debug,
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.values()).finish()
+    }
+}
+
+impl<K, P, T> Default for HopOneWaySlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        HopOneWaySlotMap::new()
+    }
+}
+
+impl<K, P, T> Clone for HopOneWaySlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        HopOneWaySlotMap {
+            slots: self.slots.clone(),
+            first_run: self.first_run,
+            len: self.len,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<K, P, T> HopOneWaySlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> HopOneWaySlotMap<K, P, T> {
+        HopOneWaySlotMap {
+            slots: Vec::new(),
+            first_run: NO_RUN,
+            len: 0,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Insert the given item into the map and return its key
+    pub fn insert(&mut self, pointer: P, value: T) -> K {
+        let position = if self.first_run == NO_RUN {
+            self.slots.push(Slot {
+                generation: 0,
+                content: Content::Filled(value),
+            });
+
+            self.slots.len() - 1
+        } else {
+            let start = self.first_run;
+            let (end, next) = match self.slots[start].content {
+                Content::Open {
+                    other_end, next, ..
+                } => (other_end, next),
+                Content::Filled(_) => panic!("run starts with a filled slot"),
+            };
+
+            if start == end {
+                self.unlink_run(start);
+            } else {
+                self.move_run_start(start, start + 1, end, NO_RUN, next);
+            }
+
+            let slot = &mut self.slots[start];
+            slot.generation = next_generation(start, slot.generation);
+            slot.content = Content::Filled(value);
+
+            start
+        };
+
+        self.len += 1;
+
+        K::from((pointer, self.key_data(position)))
+    }
+
+    /// Get a reference to the item for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        match self.slots.get(key_data.position()) {
+            Some(Slot {
+                generation,
+                content: Content::Filled(value),
+            }) if *generation == key_data.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Get a mutable reference to the item for the given key if it exists
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Same as get_mut, but only requires slot map key data
+    pub fn get_mut_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        match self.slots.get_mut(key_data.position()) {
+            Some(Slot {
+                generation,
+                content: Content::Filled(value),
+            }) if *generation == key_data.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Remove the item for the given key and return it
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<usize>);
+    /// let mut map = HopOneWaySlotMap::<TestKey,usize,usize>::new();
+    ///
+    /// let keys = (0..1000).map(|i| map.insert(i, i)).collect::<Vec<_>>();
+    ///
+    /// for key in keys.iter().filter(|k| k.pointer % 100 != 0) {
+    ///     assert_eq!(Some(key.pointer), map.remove(key));
+    /// }
+    ///
+    /// // Iteration hops over the runs of open slots between the items
+    /// assert_eq!(
+    ///     vec![0, 100, 200, 300, 400, 500, 600, 700, 800, 900],
+    ///     map.values().copied().collect::<Vec<_>>()
+    /// );
+    /// ```
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<T> {
+        let position = key_data.position();
+
+        self.get_raw(key_data)?;
+
+        // Runs of open slots on either side of the removed item's slot, as
+        // the start of the run on the left and the end of the run on the
+        // right
+        let left = position.checked_sub(1).and_then(|p| self.other_end(p));
+        let right = self.other_end(position + 1);
+
+        let slot = &mut self.slots[position];
+        slot.generation = next_generation(position, slot.generation);
+
+        let removed = std::mem::replace(
+            &mut slot.content,
+            Content::Open {
+                other_end: position,
+                previous: NO_RUN,
+                next: NO_RUN,
+            },
+        );
+
+        match (left, right) {
+            (None, None) => self.push_run(position),
+            (Some(start), None) => self.set_run_ends(start, position),
+            (None, Some(end)) => {
+                let (previous, next) = self.run_links(position + 1);
+                self.move_run_start(position + 1, position, end, previous, next)
+            }
+            (Some(start), Some(end)) => {
+                self.unlink_run(position + 1);
+                self.set_run_ends(start, end);
+            }
+        }
+
+        self.len -= 1;
+
+        match removed {
+            Content::Filled(value) => Some(value),
+            Content::Open { .. } => unreachable!("removed an open slot"),
+        }
+    }
+
+    /// Remove all items from the map, keeping the generations of their slots
+    /// so that their keys stay invalid
+    pub fn clear(&mut self) {
+        let keys = self.iter_raw().map(|(k, _)| k).collect::<Vec<_>>();
+
+        for key_data in keys {
+            let _ = self.remove_raw(&key_data);
+        }
+    }
+
+    /// Create an iterator over the raw key data and values of all items in
+    /// the map
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        let mut slots = self.slots.iter().enumerate();
+
+        std::iter::from_fn(move || {
+            let mut current = slots.next();
+
+            loop {
+                let (position, slot) = current?;
+
+                match &slot.content {
+                    Content::Filled(value) => {
+                        let key_data = SlotMapKeyData {
+                            generation: slot.generation,
+                            ..SlotMapKeyData::from(position as u64)
+                        };
+
+                        return Some((key_data, value));
+                    }
+                    Content::Open { other_end, .. } => {
+                        current = slots.nth(other_end - position);
+                    }
+                }
+            }
+        })
+    }
+
+    /// Create an iterator over the raw key data and mutable values of all
+    /// items in the map
+    pub fn iter_mut_raw(
+        &mut self,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &mut T)> {
+        let mut slots = self.slots.iter_mut().enumerate();
+
+        std::iter::from_fn(move || {
+            let mut current = slots.next();
+
+            loop {
+                let (position, slot) = current?;
+
+                match &mut slot.content {
+                    Content::Filled(value) => {
+                        let key_data = SlotMapKeyData {
+                            generation: slot.generation,
+                            ..SlotMapKeyData::from(position as u64)
+                        };
+
+                        return Some((key_data, value));
+                    }
+                    Content::Open { other_end, .. } => {
+                        current = slots.nth(*other_end - position);
+                    }
+                }
+            }
+        })
+    }
+
+    /// Create an iterator over the values of all items in the map
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.iter_raw().map(|(_, value)| value)
+    }
+
+    /// Create an iterator over the mutable values of all items in the map
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.iter_mut_raw().map(|(_, value)| value)
+    }
+
+    /// Get the key data for the item at the given position
+    fn key_data(&self, position: usize) -> SlotMapKeyData {
+        SlotMapKeyData {
+            generation: self.slots[position].generation,
+            ..SlotMapKeyData::from(position as u64)
+        }
+    }
+
+    /// Get the other end of the run with an end at the given position if the
+    /// slot there is open
+    fn other_end(&self, position: usize) -> Option<usize> {
+        match self.slots.get(position)?.content {
+            Content::Open { other_end, .. } => Some(other_end),
+            Content::Filled(_) => None,
+        }
+    }
+
+    /// Get the links of the run starting at the given position
+    fn run_links(&self, start: usize) -> (usize, usize) {
+        match self.slots[start].content {
+            Content::Open { previous, next, .. } => (previous, next),
+            Content::Filled(_) => panic!("run starts with a filled slot"),
+        }
+    }
+
+    /// Record the ends of a run at both of its ends, keeping the links of
+    /// the run's start
+    fn set_run_ends(&mut self, start: usize, end: usize) {
+        let (previous, next) = self.run_links(start);
+
+        self.slots[start].content = Content::Open {
+            other_end: end,
+            previous,
+            next,
+        };
+        self.slots[end].content = Content::Open {
+            other_end: start,
+            previous: NO_RUN,
+            next: NO_RUN,
+        };
+    }
+
+    /// Make the run with the given links start at a new position, pointing
+    /// its neighbors in the list of runs at the new start
+    fn move_run_start(
+        &mut self,
+        old_start: usize,
+        new_start: usize,
+        end: usize,
+        previous: usize,
+        next: usize,
+    ) {
+        self.slots[new_start].content = Content::Open {
+            other_end: end,
+            previous,
+            next,
+        };
+
+        if end != new_start {
+            self.slots[end].content = Content::Open {
+                other_end: new_start,
+                previous: NO_RUN,
+                next: NO_RUN,
+            };
+        }
+
+        self.relink(previous, next, old_start, new_start);
+    }
+
+    /// Add a run of a single open slot at the front of the list of runs
+    fn push_run(&mut self, start: usize) {
+        let next = self.first_run;
+
+        self.slots[start].content = Content::Open {
+            other_end: start,
+            previous: NO_RUN,
+            next,
+        };
+
+        if next != NO_RUN {
+            self.set_link(next, |previous, _| *previous = start);
+        }
+
+        self.first_run = start;
+    }
+
+    /// Take the run starting at the given position out of the list of runs
+    fn unlink_run(&mut self, start: usize) {
+        let (previous, next) = self.run_links(start);
+
+        if previous == NO_RUN {
+            self.first_run = next;
+        } else {
+            self.set_link(previous, |_, n| *n = next);
+        }
+
+        if next != NO_RUN {
+            self.set_link(next, |p, _| *p = previous);
+        }
+    }
+
+    /// Point the neighbors of a run that moved from the old start to the new
+    /// one at the new start
+    fn relink(
+        &mut self,
+        previous: usize,
+        next: usize,
+        old_start: usize,
+        new_start: usize,
+    ) {
+        if previous == NO_RUN {
+            debug_assert_eq!(old_start, self.first_run);
+            self.first_run = new_start;
+        } else {
+            self.set_link(previous, |_, n| *n = new_start);
+        }
+
+        if next != NO_RUN {
+            self.set_link(next, |p, _| *p = new_start);
+        }
+    }
+
+    /// Modify the links of the run starting at the given position
+    fn set_link(
+        &mut self,
+        start: usize,
+        f: impl FnOnce(&mut usize, &mut usize),
+    ) {
+        match &mut self.slots[start].content {
+            Content::Open { previous, next, .. } => f(previous, next),
+            Content::Filled(_) => panic!("run starts with a filled slot"),
+        }
+    }
+}
+
+/// Get the generation after the given one for the slot at the given position
+fn next_generation(position: usize, generation: u32) -> u32 {
+    let mut key_data = SlotMapKeyData {
+        generation,
+        ..SlotMapKeyData::from(position as u64)
+    };
+
+    key_data.increment_generation();
+    key_data.generation
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use crate::SlotMap;
+    use rand::{thread_rng, Rng};
+    use std::borrow::Borrow;
+
+    /// Check that the runs of open slots are exactly the maximal runs of
+    /// adjacent open slots, each listed once
+    fn assert_runs_consistent(map: &HopOneWaySlotMap<TestKey, usize, usize>) {
+        let mut expected = Vec::new();
+        let mut position = 0;
+
+        while position < map.slots.len() {
+            if let Content::Open { .. } = map.slots[position].content {
+                let start = position;
+
+                while position + 1 < map.slots.len()
+                    && matches!(
+                        map.slots[position + 1].content,
+                        Content::Open { .. }
+                    )
+                {
+                    position += 1;
+                }
+
+                assert_eq!(Some(position), map.other_end(start));
+                assert_eq!(Some(start), map.other_end(position));
+                expected.push(start);
+            }
+
+            position += 1;
+        }
+
+        let mut listed = Vec::new();
+        let mut previous = NO_RUN;
+        let mut run = map.first_run;
+
+        while run != NO_RUN {
+            let (p, next) = map.run_links(run);
+            assert_eq!(previous, p);
+            listed.push(run);
+            previous = run;
+            run = next;
+        }
+
+        listed.sort_unstable();
+        assert_eq!(expected, listed);
+    }
+
+    #[test]
+    fn test_random_operations_match_slot_map() {
+        let mut rng = thread_rng();
+        let mut map = HopOneWaySlotMap::<TestKey, usize, usize>::new();
+        let mut expected = SlotMap::<TestKey, usize, usize>::new();
+        let mut keys = Vec::new();
+        let mut removed = Vec::new();
+
+        for i in 0..20_000 {
+            // Grow, then shrink to a small fraction, then grow again
+            let remove_chance = if i % 10_000 < 6_000 { 0.2 } else { 0.95 };
+
+            if !keys.is_empty() && rng.gen_bool(remove_chance) {
+                let (key, expected_key): (TestKey, TestKey) =
+                    keys.swap_remove(rng.gen_range(0..keys.len()));
+
+                assert_eq!(Some(key.pointer), map.remove(&key));
+                assert!(expected.remove(&expected_key).is_some());
+                removed.push(key);
+            } else {
+                keys.push((map.insert(i, i), expected.insert(i, i)));
+            }
+
+            if i % 1000 == 0 {
+                assert_runs_consistent(&map);
+            }
+        }
+
+        assert_runs_consistent(&map);
+        assert_eq!(expected.len(), map.len());
+
+        let mut values = map.values().copied().collect::<Vec<_>>();
+        let mut expected_values =
+            expected.values().copied().collect::<Vec<_>>();
+        values.sort_unstable();
+        expected_values.sort_unstable();
+        assert_eq!(expected_values, values);
+
+        for (key, _) in &keys {
+            assert_eq!(Some(&key.pointer), map.get(key));
+        }
+
+        for key in &removed {
+            assert_eq!(None, map.get(key));
+            assert_eq!(None, map.remove(key));
+        }
+
+        for (key_data, value) in map.iter_mut_raw() {
+            *value += key_data.chunk_index as usize;
+        }
+
+        for (key, _) in &keys {
+            let key_data: &SlotMapKeyData = key.borrow();
+            assert_eq!(
+                Some(&(key.pointer + key_data.chunk_index as usize)),
+                map.get(key)
+            );
+        }
+
+        map.clear();
+        assert!(map.is_empty());
+        assert_runs_consistent(&map);
+        assert!(keys.iter().all(|(k, _)| !map.contains_key(k)));
+    }
+}
@@ -0,0 +1,388 @@
+//! Alternate serde representation of a slot map meant to be read and edited
+//! by people. The map is serialized as a single object with an entry for every
+//! slot, keyed by a `"generation:chunk:index"` string. Filled slots map to
+//! their values and vacant slots map to `null`
+
+use super::{
+    SlotMap, SlotMapKey, SlotMapKeyData, SnapshotBuilder, SnapshotHeader,
+    SLOT_MAP_CHUNK_SIZE,
+};
+use serde::de::{Error, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
+
+/// Human readable serialization of a slot map. Created with
+/// [`SlotMap::human_readable`]
+pub struct HumanReadableSlotMap<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    map: &'a SlotMap<K, P, T>,
+}
+
+impl<'a, K, P, T> std::fmt::Debug for HumanReadableSlotMap<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HumanReadableSlotMap")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
+
+/// Key string for the slot at the given linear index with the given
+/// generation
+struct SlotName {
+    generation: u32,
+    linear_index: usize,
+}
+
+impl Display for SlotName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.generation,
+            self.linear_index / SLOT_MAP_CHUNK_SIZE,
+            self.linear_index % SLOT_MAP_CHUNK_SIZE
+        )
+    }
+}
+
+impl Serialize for SlotName {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// Parse a `"generation:chunk:index"` string into the generation and the
+/// linear index of the slot it names
+fn parse_slot_name(name: &str) -> Option<(u32, usize)> {
+    let mut parts = name.split(':');
+
+    let generation = parts.next()?.trim().parse::<u32>().ok()?;
+    let chunk_index = parts.next()?.trim().parse::<u32>().ok()?;
+    let index_in_chunk = parts.next()?.trim().parse::<u16>().ok()?;
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let key_data = SlotMapKeyData {
+        index_in_chunk,
+        chunk_index,
+        generation,
+    };
+
+    // Anything that doesn't survive packing is out of range for a key
+    if SlotMapKeyData::from(u64::from(key_data)) != key_data {
+        return None;
+    }
+
+    Some((
+        generation,
+        chunk_index as usize * SLOT_MAP_CHUNK_SIZE + index_in_chunk as usize,
+    ))
+}
+
+impl<'a, K, P, T> Serialize for HumanReadableSlotMap<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let slots = self.map.slots();
+        let mut state = serializer.serialize_map(Some(slots.slot_count()))?;
+
+        for (linear_index, (key_data, value)) in slots.values().enumerate() {
+            let name = SlotName {
+                generation: key_data.generation,
+                linear_index,
+            };
+
+            if key_data.is_filled() {
+                state.serialize_entry(&name, &Some(value))?;
+            } else {
+                state.serialize_entry(&name, &None::<&T>)?;
+            }
+        }
+
+        state.end()
+    }
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Get a view of this map that serializes as an object keyed by
+    /// `"generation:chunk:index"` strings, for config files and debug dumps
+    /// that people need to read and edit. Load it again with
+    /// [`SlotMap::deserialize_human_readable`]
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey, (), String>::new();
+    /// let hello = map.insert((), "Hello".to_owned());
+    /// let _ = map.insert((), "World".to_owned());
+    /// let _ = map.remove(&hello);
+    ///
+    /// assert_eq!(
+    ///     r#"{"1:0:0":null,"0:0:1":"World"}"#,
+    ///     serde_json::to_string(&map.human_readable()).unwrap()
+    /// );
+    /// ```
+    pub fn human_readable(&self) -> HumanReadableSlotMap<'_, K, P, T> {
+        HumanReadableSlotMap { map: self }
+    }
+
+    /// Load a map written with [`SlotMap::human_readable`]. Every slot up to
+    /// the last one must be present exactly once, filled slots must have even
+    /// generations and values, and vacant slots must have odd generations and
+    /// `null` values. Vacant slots are filled with default values and are
+    /// reused in order of position, so keys handed out after loading may
+    /// differ from those the original map would have produced, but keys into
+    /// vacant slots stay invalid
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let json = r#"{ "0:0:1": "World", "1:0:0": null }"#;
+    ///
+    /// let map = SlotMap::<TestKey, (), String>::deserialize_human_readable(
+    ///     &mut serde_json::Deserializer::from_str(json),
+    /// )
+    /// .unwrap();
+    ///
+    /// assert_eq!(1, map.len());
+    /// assert_eq!(vec!["World"], map.values().collect::<Vec<_>>());
+    /// ```
+    pub fn deserialize_human_readable<'de, D>(
+        deserializer: D,
+    ) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de> + Default,
+    {
+        deserializer.deserialize_map(HumanReadableVisitor(PhantomData))
+    }
+}
+
+struct HumanReadableVisitor<K, P, T>(PhantomData<SlotMap<K, P, T>>)
+where
+    K: SlotMapKey<P>;
+
+impl<'de, K, P, T> Visitor<'de> for HumanReadableVisitor<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Deserialize<'de> + Default,
+{
+    type Value = SlotMap<K, P, T>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("an object keyed by \"generation:chunk:index\" strings")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut entries = BTreeMap::new();
+
+        while let Some(name) = map.next_key::<String>()? {
+            let (generation, linear_index) = parse_slot_name(&name)
+                .ok_or_else(|| {
+                    Error::custom(format!("invalid slot name {:?}", name))
+                })?;
+
+            let value = map.next_value::<Option<T>>()?;
+
+            match (generation % 2 == 0, value.is_some()) {
+                (true, false) => {
+                    return Err(Error::custom(format!(
+                        "filled slot {:?} has no value",
+                        name
+                    )))
+                }
+                (false, true) => {
+                    return Err(Error::custom(format!(
+                        "vacant slot {:?} must be null",
+                        name
+                    )))
+                }
+                _ => {}
+            }
+
+            if entries.insert(linear_index, (generation, value)).is_some() {
+                return Err(Error::custom(format!(
+                    "slot {:?} appears more than once",
+                    name
+                )));
+            }
+        }
+
+        build_map(entries).map_err(Error::custom)
+    }
+}
+
+/// Rebuild a map from the given slots, ordered by linear index. Vacant slots
+/// are chained together in order of position
+fn build_map<K, P, T>(
+    entries: BTreeMap<usize, (u32, Option<T>)>,
+) -> Result<SlotMap<K, P, T>, String>
+where
+    K: SlotMapKey<P>,
+    T: Default,
+{
+    let slot_count = entries.len();
+
+    if let Some((&last, _)) = entries.iter().next_back() {
+        if last != slot_count - 1 {
+            let missing = (0..)
+                .zip(entries.keys())
+                .find(|(expected, found)| expected != *found)
+                .map(|(expected, _)| expected)
+                .unwrap_or(slot_count);
+
+            return Err(format!(
+                "slot {}:{} is missing",
+                missing / SLOT_MAP_CHUNK_SIZE,
+                missing % SLOT_MAP_CHUNK_SIZE
+            ));
+        }
+    }
+
+    let vacant = entries
+        .iter()
+        .filter(|(_, (_, value))| value.is_none())
+        .map(|(linear_index, _)| *linear_index)
+        .chain(std::iter::once(slot_count))
+        .collect::<Vec<_>>();
+
+    let header = SnapshotHeader {
+        len: slot_count + 1 - vacant.len(),
+        next_open_slot: SlotMapKeyData::from(vacant[0] as u64),
+        chunk_count: slot_count.div_ceil(SLOT_MAP_CHUNK_SIZE),
+        value_version: 0,
+    };
+
+    let mut next_vacant = vacant.iter().skip(1);
+
+    let mut slots =
+        entries
+            .into_iter()
+            .map(|(linear_index, (generation, value))| match value {
+                Some(value) => {
+                    let mut key_data =
+                        SlotMapKeyData::from(linear_index as u64);
+                    key_data.generation = generation;
+                    (key_data, value)
+                }
+                None => {
+                    let next = next_vacant.next().copied().unwrap_or_default();
+                    let mut key_data = SlotMapKeyData::from(next as u64);
+                    key_data.generation = generation;
+                    (key_data, T::default())
+                }
+            });
+
+    let mut builder = SnapshotBuilder::new(header);
+
+    for _ in 0..header.chunk_count {
+        builder
+            .push_chunk(slots.by_ref().take(SLOT_MAP_CHUNK_SIZE))
+            .map_err(|e| e.to_string())?;
+    }
+
+    builder.build().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+
+    fn load(json: &str) -> Result<SlotMap<TestKey, usize, String>, String> {
+        SlotMap::deserialize_human_readable(
+            &mut serde_json::Deserializer::from_str(json),
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut map = SlotMap::<TestKey, usize, String>::new();
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE + 20)
+            .map(|i| map.insert(i, format!("{}", i)))
+            .collect::<Vec<_>>();
+
+        let _ = map.remove(&keys[260]);
+        let _ = map.remove(&keys[4]);
+        let reused = map.insert(4, "reused".to_owned());
+        let _ = map.remove(&keys[7]);
+
+        let json = serde_json::to_string(&map.human_readable()).unwrap();
+        let mut copy = load(&json).unwrap();
+
+        assert_eq!(map.len(), copy.len());
+
+        for k in keys.iter().chain(Some(&reused)) {
+            assert_eq!(map.get(k), copy.get(k));
+        }
+
+        // Vacant slots are reused in order of position, and stale keys stay
+        // invalid after reuse
+        let first = copy.insert(7, "new".to_owned());
+        let second = copy.insert(260, "new".to_owned());
+
+        assert_eq!(None, copy.get(&keys[7]));
+        assert_eq!(None, copy.get(&keys[260]));
+        assert_eq!(Some(&"new".to_owned()), copy.get(&first));
+        assert_eq!(Some(&"new".to_owned()), copy.get(&second));
+        assert_eq!(map.len() + 2, copy.len());
+    }
+
+    #[test]
+    fn test_edited_values_are_loaded() {
+        let map =
+            load(r#"{ "0:0:0": "a", "3:0:1": null, "2:0:2": "c" }"#).unwrap();
+
+        assert_eq!(
+            r#"{"0:0:0":"a","3:0:1":null,"2:0:2":"c"}"#,
+            serde_json::to_string(&map.human_readable()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_invalid_documents_rejected() {
+        let err = load(r#"{ "0:0": "a" }"#).unwrap_err();
+        assert!(err.contains("invalid slot name"), "{}", err);
+
+        let err = load(r#"{ "0:0:256": "a" }"#).unwrap_err();
+        assert!(err.contains("invalid slot name"), "{}", err);
+
+        let err = load(r#"{ "0:0:0": null }"#).unwrap_err();
+        assert!(err.contains("has no value"), "{}", err);
+
+        let err = load(r#"{ "1:0:0": "a" }"#).unwrap_err();
+        assert!(err.contains("must be null"), "{}", err);
+
+        let err = load(r#"{ "0:0:0": "a", "2:0:0": "b" }"#).unwrap_err();
+        assert!(err.contains("more than once"), "{}", err);
+
+        let err = load(r#"{ "0:0:0": "a", "0:0:2": "b" }"#).unwrap_err();
+        assert!(err.contains("slot 0:1 is missing"), "{}", err);
+    }
+}
@@ -0,0 +1,433 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet};
+use std::marker::PhantomData;
+use std::ops::RangeBounds;
+
+/// Handle to an index added to an [`IndexedSlotMap`], used to query it. It is
+/// only meaningful to the map that created it
+pub struct IndexId<I> {
+    position: usize,
+    _phantom: PhantomData<fn() -> I>,
+}
+
+impl<I> std::fmt::Debug for IndexId<I> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("IndexId").field(&self.position).finish()
+    }
+}
+
+impl<I> Clone for IndexId<I> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<I> Copy for IndexId<I> {}
+
+impl<I> PartialEq for IndexId<I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.position == other.position
+    }
+}
+
+impl<I> Eq for IndexId<I> {}
+
+/// Operations every index supports regardless of its key type
+trait AnyIndex<T>: Send {
+    fn insert(&mut self, value: &T, key_data: SlotMapKeyData);
+    fn remove(&mut self, value: &T, key_data: SlotMapKeyData);
+    fn clear(&mut self);
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// Index of items by the keys an extractor function produces for them
+struct Index<T, I> {
+    extract: Box<dyn Fn(&T) -> I + Send>,
+
+    /// Packed key data of items by their index key
+    entries: BTreeMap<I, BTreeSet<u64>>,
+}
+
+impl<T, I> AnyIndex<T> for Index<T, I>
+where
+    T: 'static,
+    I: Ord + Send + 'static,
+{
+    fn insert(&mut self, value: &T, key_data: SlotMapKeyData) {
+        let _ = self
+            .entries
+            .entry((self.extract)(value))
+            .or_default()
+            .insert(key_data.into());
+    }
+
+    fn remove(&mut self, value: &T, key_data: SlotMapKeyData) {
+        let index_key = (self.extract)(value);
+
+        if let Some(entry) = self.entries.get_mut(&index_key) {
+            let _ = entry.remove(&key_data.into());
+
+            if entry.is_empty() {
+                let _ = self.entries.remove(&index_key);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Slot map that keeps indexes of its items by keys derived from them, like
+/// a small in-memory table. Each index is added with a function that
+/// extracts an index key from an item, and the map keeps every index up to
+/// date as items are inserted, changed, and removed. Items can only be
+/// changed through [`IndexedSlotMap::update`] and
+/// [`IndexedSlotMap::replace`] so the indexes can't go stale
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # use std::borrow::Borrow;
+/// # define_key_type!(TestKey<()>);
+/// struct User {
+///     name: &'static str,
+///     age: u32,
+/// }
+///
+/// let mut users = IndexedSlotMap::<TestKey, (), User>::new();
+/// let by_age = users.add_index(|user| user.age);
+///
+/// let ada = users.insert((), User { name: "Ada", age: 36 });
+/// let _ = users.insert((), User { name: "Alan", age: 41 });
+///
+/// let found = users.find_by_index(by_age, &36).collect::<Vec<_>>();
+/// assert_eq!(vec![SlotMapKeyData::clone(ada.borrow())], found);
+/// assert_eq!("Ada", users.get_raw(&found[0]).unwrap().name);
+///
+/// users.update(&ada, |user| user.age += 1);
+/// assert_eq!(0, users.find_by_index(by_age, &36).count());
+/// assert_eq!(2, users.range_by_index(by_age, 37..).count());
+/// ```
+pub struct IndexedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, T>,
+    indexes: Vec<Box<dyn AnyIndex<T>>>,
+}
+
+impl<K, P, T> std::fmt::Debug for IndexedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IndexedSlotMap")
+            .field("items", &self.map)
+            .field("index_count", &self.indexes.len())
+            .finish()
+    }
+}
+
+impl<K, P, T> Default for IndexedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: 'static,
+{
+    fn default() -> Self {
+        IndexedSlotMap::new()
+    }
+}
+
+impl<K, P, T> IndexedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: 'static,
+{
+    /// Create a new empty map without any indexes
+    pub fn new() -> IndexedSlotMap<K, P, T> {
+        IndexedSlotMap {
+            map: SlotMap::new(),
+            indexes: Vec::new(),
+        }
+    }
+
+    /// Add an index of items by the keys the given function extracts from
+    /// them, including items already in the map, and return its ID
+    pub fn add_index<I>(
+        &mut self,
+        extract: impl Fn(&T) -> I + Send + 'static,
+    ) -> IndexId<I>
+    where
+        I: Ord + Send + 'static,
+    {
+        let mut index = Index {
+            extract: Box::new(extract),
+            entries: BTreeMap::new(),
+        };
+
+        for (key_data, value) in self.map.iter_raw() {
+            index.insert(value, key_data);
+        }
+
+        self.indexes.push(Box::new(index));
+
+        IndexId {
+            position: self.indexes.len() - 1,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Insert the given item into the map, adding it to every index, and
+    /// return its key
+    pub fn insert(&mut self, pointer: P, value: T) -> K {
+        let key_data = self.map.insert_raw(value);
+        let value = self.map.get_raw(&key_data).expect("item just inserted");
+
+        for index in &mut self.indexes {
+            index.insert(value, key_data);
+        }
+
+        K::from((pointer, key_data))
+    }
+
+    /// Get a reference to the item for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.map.get_raw(key_data)
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Change the item for the given key with the given function, moving it
+    /// to its new place in every index. Returns whether the item exists
+    pub fn update(&mut self, key: &K, f: impl FnOnce(&mut T)) -> bool {
+        self.update_raw(key.borrow(), f)
+    }
+
+    /// Same as update, but only requires slot map key data
+    pub fn update_raw(
+        &mut self,
+        key_data: &SlotMapKeyData,
+        f: impl FnOnce(&mut T),
+    ) -> bool {
+        let Some(value) = self.map.get_mut_raw(key_data) else {
+            return false;
+        };
+
+        for index in &mut self.indexes {
+            index.remove(value, *key_data);
+        }
+
+        f(value);
+
+        for index in &mut self.indexes {
+            index.insert(value, *key_data);
+        }
+
+        true
+    }
+
+    /// Replace the item for the given key with the given value and return
+    /// the old value, or give the new value back if the key isn't in the map
+    pub fn replace(&mut self, key: &K, value: T) -> Result<T, T> {
+        self.replace_raw(key.borrow(), value)
+    }
+
+    /// Same as replace, but only requires slot map key data
+    pub fn replace_raw(
+        &mut self,
+        key_data: &SlotMapKeyData,
+        value: T,
+    ) -> Result<T, T> {
+        if !self.map.contains_key_raw(key_data) {
+            return Err(value);
+        }
+
+        let mut value = Some(value);
+        let _ = self.update_raw(key_data, |current| {
+            value = value.take().map(|v| std::mem::replace(current, v));
+        });
+
+        Ok(value.expect("the item exists"))
+    }
+
+    /// Remove the item for the given key from the map and every index, and
+    /// return a mutable ref to it if there was one
+    pub fn remove(&mut self, key: &K) -> Option<&mut T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        let value = self.map.remove_raw(key_data)?;
+
+        for index in &mut self.indexes {
+            index.remove(value, *key_data);
+        }
+
+        Some(value)
+    }
+
+    /// Remove all items from the map and every index. The indexes themselves
+    /// are kept
+    pub fn clear(&mut self) {
+        self.map.clear();
+
+        for index in &mut self.indexes {
+            index.clear();
+        }
+    }
+
+    /// Create an iterator over the key data of all items whose index key in
+    /// the given index equals the given key
+    ///
+    /// # Panics
+    /// If the index ID came from another map
+    pub fn find_by_index<I>(
+        &self,
+        index: IndexId<I>,
+        index_key: &I,
+    ) -> impl Iterator<Item = SlotMapKeyData> + '_
+    where
+        I: Ord + Send + 'static,
+    {
+        self.index(index)
+            .entries
+            .get(index_key)
+            .into_iter()
+            .flatten()
+            .map(|packed| SlotMapKeyData::from(*packed))
+    }
+
+    /// Create an iterator over the key data of all items whose index key in
+    /// the given index is in the given range, in order of index key
+    ///
+    /// # Panics
+    /// If the index ID came from another map
+    pub fn range_by_index<I>(
+        &self,
+        index: IndexId<I>,
+        range: impl RangeBounds<I>,
+    ) -> impl Iterator<Item = SlotMapKeyData> + '_
+    where
+        I: Ord + Send + 'static,
+    {
+        self.index(index)
+            .entries
+            .range(range)
+            .flat_map(|(_, entry)| entry)
+            .map(|packed| SlotMapKeyData::from(*packed))
+    }
+
+    /// Create an iterator over the raw key data and values of all items in
+    /// the map
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.map.iter_raw()
+    }
+
+    /// Create an iterator over the values of all items in the map
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.map.values()
+    }
+
+    /// Get the index with the given ID
+    fn index<I>(&self, index: IndexId<I>) -> &Index<T, I>
+    where
+        I: Ord + Send + 'static,
+    {
+        self.indexes
+            .get(index.position)
+            .and_then(|index| index.as_any().downcast_ref())
+            .expect("index ID from another map")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use std::borrow::Borrow;
+
+    #[test]
+    fn test_indexes_match_scans() {
+        let mut map = IndexedSlotMap::<TestKey, usize, (usize, String)>::new();
+
+        let by_bucket = map.add_index(|(number, _)| number % 10);
+
+        let mut keys = (0..500)
+            .map(|i| map.insert(i, (i, format!("{}", i))))
+            .collect::<Vec<_>>();
+
+        // Indexes added later include the items already in the map
+        let by_name = map.add_index(|(_, name)| name.clone());
+
+        for (i, key) in keys.clone().iter().enumerate() {
+            match i % 4 {
+                0 => assert!(map.update(key, |(number, _)| *number += 3)),
+                1 => assert!(map
+                    .replace(key, (i * 2, format!("{}", i * 2)))
+                    .is_ok()),
+                2 => assert!(map.remove(key).is_some()),
+                _ => {}
+            }
+        }
+
+        keys.retain(|k| map.contains_key(k));
+        assert!(!map.update_raw(&SlotMapKeyData::from(u64::MAX), |_| ()));
+
+        for bucket in 0..10 {
+            let mut found =
+                map.find_by_index(by_bucket, &bucket).collect::<Vec<_>>();
+            let mut expected = map
+                .iter_raw()
+                .filter(|(_, (number, _))| number % 10 == bucket)
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>();
+
+            found.sort_by_key(|k| u64::from(*k));
+            expected.sort_by_key(|k| u64::from(*k));
+            assert_eq!(expected, found);
+        }
+
+        for key in &keys {
+            let (_, name) = map.get(key).unwrap();
+            let found = map.find_by_index(by_name, name).collect::<Vec<_>>();
+            assert!(found.contains(key.borrow()));
+        }
+
+        let in_range = map.range_by_index(by_bucket, 3..=5).count();
+        assert_eq!(
+            map.values()
+                .filter(|(n, _)| (3..=5).contains(&(n % 10)))
+                .count(),
+            in_range
+        );
+
+        map.clear();
+        assert_eq!(0, map.range_by_index(by_bucket, ..).count());
+        assert_eq!(0, map.range_by_index(by_name, ..).count());
+    }
+}
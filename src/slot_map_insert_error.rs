@@ -0,0 +1,101 @@
+use std::fmt::{Display, Formatter};
+
+/// Reasons [`SlotMap::try_insert`](crate::SlotMap::try_insert) couldn't make
+/// room for an item. The item is given back in every case
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InsertError<T> {
+    /// Every slot coordinate that key data can name is already in use, so
+    /// the map can't grow any further
+    ChunkIndexOverflow(T),
+
+    /// Memory for a new chunk of slots couldn't be allocated
+    AllocationFailed(T),
+}
+
+impl<T> InsertError<T> {
+    /// Get back the item that couldn't be inserted
+    pub fn into_value(self) -> T {
+        match self {
+            InsertError::ChunkIndexOverflow(value)
+            | InsertError::AllocationFailed(value) => value,
+        }
+    }
+
+    /// Attach the given item to this error
+    pub(crate) fn with_value<U>(self, value: U) -> InsertError<U> {
+        match self {
+            InsertError::ChunkIndexOverflow(_) => {
+                InsertError::ChunkIndexOverflow(value)
+            }
+            InsertError::AllocationFailed(_) => {
+                InsertError::AllocationFailed(value)
+            }
+        }
+    }
+}
+
+impl<T> Display for InsertError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InsertError::ChunkIndexOverflow(_) => {
+                write!(f, "slot map has run out of slot coordinates")
+            }
+            InsertError::AllocationFailed(_) => {
+                write!(f, "failed to allocate a new chunk of slots")
+            }
+        }
+    }
+}
+
+impl<T: std::fmt::Debug> std::error::Error for InsertError<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::slot_map::Slots;
+    use crate::test_support::TestKey;
+    use crate::{SlotMap, SlotMapKeyData, SLOT_MAP_CHUNK_SIZE};
+
+    #[test]
+    fn test_into_value_gives_back_item() {
+        assert_eq!(5, InsertError::ChunkIndexOverflow(5).into_value());
+        assert_eq!("a", InsertError::AllocationFailed("a").into_value());
+
+        assert_eq!(
+            InsertError::AllocationFailed(5),
+            InsertError::AllocationFailed(()).with_value(5)
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            "slot map has run out of slot coordinates",
+            InsertError::ChunkIndexOverflow(()).to_string()
+        );
+        assert_eq!(
+            "failed to allocate a new chunk of slots",
+            InsertError::AllocationFailed(()).to_string()
+        );
+    }
+
+    #[test]
+    fn test_exhausted_coordinates() {
+        // A map whose next slot would be the very last coordinates
+        let mut map = SlotMap::<TestKey, usize, String>::from_raw_state(
+            Slots::new(),
+            SlotMapKeyData {
+                chunk_index: u32::MAX,
+                index_in_chunk: (SLOT_MAP_CHUNK_SIZE - 1) as u16,
+                generation: 0,
+            },
+            0,
+        );
+
+        let error = map.try_insert(0, "item".to_owned()).unwrap_err();
+
+        assert_eq!(InsertError::ChunkIndexOverflow("item".to_owned()), error);
+        assert_eq!("item", error.into_value());
+        assert!(map.is_empty());
+    }
+}
@@ -0,0 +1,329 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+
+/// Key data of items indexed by the hash of their values, shared by the maps
+/// that look items up by value. Values aren't stored in the index, so every
+/// lookup confirms a match against the map holding the values
+pub(crate) struct ValueIndex<S> {
+    /// Nearly every bucket holds a single item
+    buckets: HashMap<u64, Vec<SlotMapKeyData>>,
+    hasher: S,
+}
+
+impl<S> ValueIndex<S> {
+    pub(crate) fn new(hasher: S) -> ValueIndex<S> {
+        ValueIndex {
+            buckets: HashMap::new(),
+            hasher,
+        }
+    }
+
+    /// Add the given key data under the given hash
+    pub(crate) fn insert(&mut self, hash: u64, key_data: SlotMapKeyData) {
+        self.buckets.entry(hash).or_default().push(key_data);
+    }
+
+    /// Remove the given key data from under the given hash
+    pub(crate) fn remove(&mut self, hash: u64, key_data: &SlotMapKeyData) {
+        if let Some(bucket) = self.buckets.get_mut(&hash) {
+            bucket.retain(|k| k != key_data);
+
+            if bucket.is_empty() {
+                let _ = self.buckets.remove(&hash);
+            }
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.buckets.clear();
+    }
+
+    /// Get the number of key data entries in the index
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.buckets.values().map(Vec::len).sum()
+    }
+
+    /// Find the item in the given map with the given hash whose value is
+    /// equal to the given value
+    pub(crate) fn find<K, P, T, Q>(
+        &self,
+        hash: u64,
+        map: &SlotMap<K, P, T>,
+        value: &Q,
+    ) -> Option<SlotMapKeyData>
+    where
+        K: SlotMapKey<P>,
+        T: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.bucket(hash).find(|key_data| {
+            map.get_raw(key_data)
+                .is_some_and(|existing| existing.borrow() == value)
+        })
+    }
+
+    /// Get all the key data under the given hash
+    pub(crate) fn bucket(
+        &self,
+        hash: u64,
+    ) -> impl Iterator<Item = SlotMapKeyData> + '_ {
+        self.buckets.get(&hash).into_iter().flatten().copied()
+    }
+}
+
+impl<S: BuildHasher> ValueIndex<S> {
+    pub(crate) fn hash<Q: Hash + ?Sized>(&self, value: &Q) -> u64 {
+        self.hasher.hash_one(value)
+    }
+}
+
+/// Slot map that stores each distinct value once. Interning a value that is
+/// equal to one already in the map gives back the existing item's key
+/// instead of inserting a copy. Values are indexed by their hash rather than
+/// copied into the index, so they don't need to be `Clone`, and since
+/// changing a value would leave the index stale, values can't be modified
+/// while they're in the map
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # use std::borrow::Borrow;
+/// # define_key_type!(TestKey<()> : Debug + PartialEq);
+/// let mut strings = InterningSlotMap::<TestKey, (), String>::new();
+///
+/// let hello = strings.intern((), "Hello".to_owned());
+/// let world = strings.intern((), "World".to_owned());
+///
+/// assert_eq!(hello, strings.intern((), "Hello".to_owned()));
+/// assert_ne!(hello, world);
+/// assert_eq!(2, strings.len());
+///
+/// assert_eq!(Some(*world.borrow()), strings.find_raw("World"));
+/// ```
+pub struct InterningSlotMap<K, P, T, S = RandomState>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, T>,
+    index: ValueIndex<S>,
+}
+
+impl<K, P, T, S> std::fmt::Debug for InterningSlotMap<K, P, T, S>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.map.fmt(f)
+    }
+}
+
+impl<K, P, T, S> Default for InterningSlotMap<K, P, T, S>
+where
+    K: SlotMapKey<P>,
+    S: Default,
+{
+    fn default() -> Self {
+        InterningSlotMap::with_hasher(S::default())
+    }
+}
+
+impl<K, P, T> InterningSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> InterningSlotMap<K, P, T> {
+        InterningSlotMap::with_hasher(RandomState::new())
+    }
+}
+
+impl<K, P, T, S> InterningSlotMap<K, P, T, S>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map that hashes values with the given hasher
+    pub fn with_hasher(hasher: S) -> InterningSlotMap<K, P, T, S> {
+        InterningSlotMap {
+            map: SlotMap::new(),
+            index: ValueIndex::new(hasher),
+        }
+    }
+
+    /// Get the number of distinct values in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Get a reference to the item for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.map.get_raw(key_data)
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Create an iterator over the raw key data and values of all items in
+    /// the map
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.map.iter_raw()
+    }
+
+    /// Create an iterator over the values of all items in the map
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.map.values()
+    }
+
+    /// Remove all items from the map
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.index.clear();
+    }
+}
+
+impl<K, P, T, S> InterningSlotMap<K, P, T, S>
+where
+    K: SlotMapKey<P>,
+    T: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Get the key of the item equal to the given value, inserting the value
+    /// as a new item if there isn't one
+    pub fn intern(&mut self, pointer: P, value: T) -> K {
+        let hash = self.index.hash(&value);
+
+        let key_data = match self.index.find(hash, &self.map, &value) {
+            Some(key_data) => key_data,
+            None => {
+                let key_data = self.map.insert_raw(value);
+                self.index.insert(hash, key_data);
+                key_data
+            }
+        };
+
+        K::from((pointer, key_data))
+    }
+
+    /// Get the key data of the item equal to the given value if there is one.
+    /// The value can be anything the items can be borrowed as, like a `&str`
+    /// for a map of `String`s
+    pub fn find_raw<Q>(&self, value: &Q) -> Option<SlotMapKeyData>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.index.find(self.index.hash(value), &self.map, value)
+    }
+
+    /// Tells if there is an item equal to the given value
+    pub fn contains_value<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find_raw(value).is_some()
+    }
+
+    /// Remove the item for the given key and return a mutable ref to it if
+    /// there was one. Interning an equal value afterwards inserts a new item
+    /// with a new key
+    pub fn remove(&mut self, key: &K) -> Option<&mut T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        let hash = self.index.hash(self.map.get_raw(key_data)?);
+        self.index.remove(hash, key_data);
+
+        self.map.remove_raw(key_data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use std::hash::{BuildHasherDefault, Hasher};
+
+    /// Hasher that sends everything to a few buckets so collisions are common
+    #[derive(Default)]
+    struct CollidingHasher(u64);
+
+    impl Hasher for CollidingHasher {
+        fn finish(&self) -> u64 {
+            self.0 % 7
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for byte in bytes {
+                self.0 = self.0.wrapping_mul(31).wrapping_add(*byte as u64);
+            }
+        }
+    }
+
+    fn key_data(key: &TestKey) -> SlotMapKeyData {
+        *key.borrow()
+    }
+
+    #[test]
+    fn test_intern_with_collisions() {
+        let mut map = InterningSlotMap::<
+            TestKey,
+            usize,
+            String,
+            BuildHasherDefault<CollidingHasher>,
+        >::default();
+
+        let keys = (0..500)
+            .map(|i| map.intern(i, format!("{}", i % 100)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(100, map.len());
+
+        // Keys of equal values share key data but keep their own pointers
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(key_data(&keys[i % 100]), key_data(key));
+            assert_eq!(Some(&format!("{}", i % 100)), map.get(key));
+        }
+
+        // Removing a value lets an equal value be interned again
+        for key in keys.iter().take(50).step_by(2) {
+            assert!(map.remove(key).is_some());
+            assert!(map.remove(key).is_none());
+        }
+
+        assert_eq!(75, map.len());
+
+        for (i, old_key) in keys.iter().enumerate().take(100) {
+            let value = format!("{}", i);
+            let kept = i >= 50 || i % 2 == 1;
+            assert_eq!(kept, map.contains_value(value.as_str()));
+
+            let key = map.intern(i, value);
+            assert_eq!(kept, key_data(&key) == key_data(old_key));
+        }
+
+        assert_eq!(100, map.len());
+        assert_eq!(100, map.index.len());
+
+        map.clear();
+        assert!(map.is_empty());
+        assert!(!map.contains_value("1"));
+    }
+}
@@ -2,19 +2,21 @@ use std::{convert::From, mem::swap};
 
 use super::SLOT_MAP_CHUNK_SIZE;
 
-const INDEX_IN_CHUNK_BITS: u8 = SLOT_MAP_CHUNK_SIZE.trailing_zeros() as u8;
-const CHUNK_INDEX_BITS: u8 = 32;
-const GENERATION_BITS: u8 = 64 - CHUNK_INDEX_BITS - INDEX_IN_CHUNK_BITS;
+pub(crate) const INDEX_IN_CHUNK_BITS: u8 =
+    SLOT_MAP_CHUNK_SIZE.trailing_zeros() as u8;
+pub(crate) const CHUNK_INDEX_BITS: u8 = 32;
+pub(crate) const GENERATION_BITS: u8 =
+    64 - CHUNK_INDEX_BITS - INDEX_IN_CHUNK_BITS;
 
 const INDEX_IN_CHUNK_MASK: u64 = (0x1 << INDEX_IN_CHUNK_BITS) - 1;
-const CHUNK_INDEX_SHIFT: u8 = INDEX_IN_CHUNK_BITS;
+pub(crate) const CHUNK_INDEX_SHIFT: u8 = INDEX_IN_CHUNK_BITS;
 const CHUNK_INDEX_MASK: u64 =
     ((0x1 << CHUNK_INDEX_BITS) - 1) << CHUNK_INDEX_SHIFT;
-const GENERATION_SHIFT: u8 = CHUNK_INDEX_SHIFT + CHUNK_INDEX_BITS;
+pub(crate) const GENERATION_SHIFT: u8 = CHUNK_INDEX_SHIFT + CHUNK_INDEX_BITS;
 const GENERATION_MASK: u64 = ((0x1 << GENERATION_BITS) - 1) << GENERATION_SHIFT;
 
 const MAX_INDEX_IN_CHUNK: u16 = INDEX_IN_CHUNK_MASK as u16;
-const MAX_GENERATION: u32 = (0x1 << GENERATION_BITS) - 1;
+pub(crate) const MAX_GENERATION: u32 = (0x1 << GENERATION_BITS) - 1;
 
 /// Encapsulation of all the information that defines a slot in the slot map.
 #[derive(Debug, Hash, Clone, Copy, PartialEq, Default, Eq)]
@@ -48,6 +50,22 @@ impl SlotMapKeyData {
         }
     }
 
+    /// Tells if this is the key data of a vacant slot at the given
+    /// coordinates that has been retired because its generations ran out.
+    /// Retired slots link to themselves, which keeps them out of the chain
+    /// of open slots
+    pub(crate) fn is_retired_at(&self, coordinates: &SlotMapKeyData) -> bool {
+        self.generation == MAX_GENERATION
+            && self.chunk_index == coordinates.chunk_index
+            && self.index_in_chunk == coordinates.index_in_chunk
+    }
+
+    /// Get the position of the slot at these coordinates in order of slots
+    pub(crate) fn position(&self) -> usize {
+        self.chunk_index as usize * SLOT_MAP_CHUNK_SIZE
+            + self.index_in_chunk as usize
+    }
+
     /// Swap the chunk index and index in chunk fields between self and other
     pub(crate) fn swap_coordinates(&mut self, other: &mut Self) {
         swap(&mut self.index_in_chunk, &mut other.index_in_chunk);
@@ -60,7 +78,10 @@ impl SlotMapKeyData {
     pub(crate) fn increment_coordinates(&mut self) -> bool {
         if self.index_in_chunk == MAX_INDEX_IN_CHUNK {
             self.index_in_chunk = 0;
-            self.chunk_index += 1;
+            self.chunk_index = self
+                .chunk_index
+                .checked_add(1)
+                .expect("Slot coordinates exhausted the chunk index space");
             true
         } else {
             self.index_in_chunk += 1;
@@ -68,8 +89,17 @@ impl SlotMapKeyData {
         }
     }
 
+    /// Tells if these are the coordinates of the last slot that can be
+    /// addressed, which can't be incremented
+    pub(crate) fn is_last_coordinates(&self) -> bool {
+        self.chunk_index == u32::MAX
+            && self.index_in_chunk == MAX_INDEX_IN_CHUNK
+    }
+
     /// Checks the generation to see if the slot associated with this key data
     /// is filled (even)
+    // `is_multiple_of` would need a newer compiler than the crate supports
+    #[allow(clippy::manual_is_multiple_of)]
     pub(crate) fn is_filled(&self) -> bool {
         self.generation % 2 == 0
     }
@@ -96,6 +126,42 @@ impl From<SlotMapKeyData> for u64 {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for SlotMapKeyData {
+    /// Key data is serialized in its packed u64 form
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_u64(u64::from(*self))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SlotMapKeyData {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        <u64 as serde::Deserialize>::deserialize(deserializer)
+            .map(SlotMapKeyData::from)
+    }
+}
+
+#[test]
+#[should_panic(expected = "chunk index space")]
+fn test_coordinate_overflow_panics() {
+    let mut last = SlotMapKeyData {
+        chunk_index: u32::MAX,
+        index_in_chunk: MAX_INDEX_IN_CHUNK,
+        generation: 0,
+    };
+
+    assert!(last.is_last_coordinates());
+
+    let _ = last.increment_coordinates();
+}
+
 #[test]
 fn test_coordinate_serialization() {
     let inc: u64 = 91;
@@ -0,0 +1,411 @@
+//! Eventually consistent slot map with a single writer and any number of
+//! readers that never block. The map is kept in two copies: readers look at
+//! one while the writer modifies the other and records its operations. When
+//! the writer refreshes, readers are switched over to the modified copy and
+//! the recorded operations are replayed onto the copy they left. Because a
+//! slot map hands out keys deterministically, replaying the operations puts
+//! every value at the same key in both copies
+
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+use std::cell::UnsafeCell;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Create a left-right slot map, returning the handle for its writer and a
+/// handle for reading it. More readers can be created by cloning the read
+/// handle
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()>);
+/// let (mut writer, mut reader) = left_right::<TestKey, (), usize>();
+///
+/// let key = writer.insert((), 42);
+/// assert_eq!(None, reader.enter().get(&key));
+///
+/// writer.refresh();
+/// assert_eq!(Some(&42), reader.enter().get(&key));
+/// ```
+pub fn left_right<K, P, T>() -> (WriteHandle<K, P, T>, ReadHandle<K, P, T>)
+where
+    K: SlotMapKey<P>,
+    T: Clone,
+{
+    let shared = Arc::new(Shared {
+        maps: [
+            UnsafeCell::new(SlotMap::new()),
+            UnsafeCell::new(SlotMap::new()),
+        ],
+        read_index: AtomicUsize::new(0),
+        epochs: Mutex::new(Vec::new()),
+    });
+
+    let reader = ReadHandle::register(shared.clone());
+
+    (
+        WriteHandle {
+            shared,
+            log: Vec::new(),
+        },
+        reader,
+    )
+}
+
+/// State shared between the writer and the readers
+struct Shared<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    maps: [UnsafeCell<SlotMap<K, P, T>>; 2],
+
+    /// Index of the copy readers should use
+    read_index: AtomicUsize,
+
+    /// Counter for every reader that is odd while the reader is looking at a
+    /// copy
+    epochs: Mutex<Vec<Arc<AtomicUsize>>>,
+}
+
+// Safety - Readers only get shared access to the copy they are switched to,
+// and the writer only modifies the other copy once every reader has left it
+unsafe impl<K, P, T> Sync for Shared<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Send + Sync,
+{
+}
+
+unsafe impl<K, P, T> Send for Shared<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Send,
+{
+}
+
+/// Operation recorded by the writer to be replayed on the other copy
+enum Op<T> {
+    Insert(T),
+    Remove(SlotMapKeyData),
+    Set(SlotMapKeyData, T),
+}
+
+/// Handle for the single writer of a left-right slot map. Changes are made
+/// to the writer's copy immediately, and become visible to readers on the
+/// next [`refresh`](WriteHandle::refresh)
+pub struct WriteHandle<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    shared: Arc<Shared<K, P, T>>,
+    log: Vec<Op<T>>,
+}
+
+impl<K, P, T> std::fmt::Debug for WriteHandle<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WriteHandle")
+            .field("pending", &self.log.len())
+            .finish()
+    }
+}
+
+impl<K, P, T> WriteHandle<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Clone,
+{
+    /// Get the writer's copy of the map, which includes changes that haven't
+    /// been published yet
+    pub fn map(&self) -> &SlotMap<K, P, T> {
+        let index = 1 - self.shared.read_index.load(Ordering::SeqCst);
+
+        // Safety - Readers never look at the copy the writer is using
+        unsafe { &*self.shared.maps[index].get() }
+    }
+
+    /// Get mutable access to the writer's copy
+    fn map_mut(&mut self) -> &mut SlotMap<K, P, T> {
+        let index = 1 - self.shared.read_index.load(Ordering::SeqCst);
+
+        // Safety - Readers never look at the copy the writer is using, and
+        // there is only one writer
+        unsafe { &mut *self.shared.maps[index].get() }
+    }
+
+    /// Get the number of changes that haven't been published to readers
+    pub fn pending(&self) -> usize {
+        self.log.len()
+    }
+
+    /// Insert the given item and return its key
+    pub fn insert(&mut self, pointer: P, value: T) -> K {
+        let key_data = self.map_mut().insert_raw(value.clone());
+        self.log.push(Op::Insert(value));
+
+        K::from((pointer, key_data))
+    }
+
+    /// Remove the item for the given key, returning whether it was present
+    pub fn remove(&mut self, key: &K) -> bool {
+        let key_data = *key.borrow();
+        let removed = self.map_mut().remove_raw(&key_data).is_some();
+
+        if removed {
+            self.log.push(Op::Remove(key_data));
+        }
+
+        removed
+    }
+
+    /// Replace the item for the given key, returning whether it was present
+    pub fn set(&mut self, key: &K, value: T) -> bool {
+        let key_data = *key.borrow();
+
+        match self.map_mut().get_mut_raw(&key_data) {
+            Some(existing) => {
+                *existing = value.clone();
+                self.log.push(Op::Set(key_data, value));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Publish all changes made since the last refresh to readers. This waits
+    /// for readers that are looking at the old copy to finish, but readers
+    /// never wait for the writer
+    pub fn refresh(&mut self) {
+        if self.log.is_empty() {
+            return;
+        }
+
+        let old_read_index = self.shared.read_index.load(Ordering::SeqCst);
+        self.shared
+            .read_index
+            .store(1 - old_read_index, Ordering::SeqCst);
+
+        self.wait_for_readers();
+
+        // Safety - Every reader has now left the old copy, so the writer has
+        // it to itself
+        let map = unsafe { &mut *self.shared.maps[old_read_index].get() };
+
+        for op in self.log.drain(..) {
+            match op {
+                Op::Insert(value) => {
+                    let _ = map.insert_raw(value);
+                }
+                Op::Remove(key_data) => {
+                    let _ = map.remove_raw(&key_data);
+                }
+                Op::Set(key_data, value) => {
+                    if let Some(existing) = map.get_mut_raw(&key_data) {
+                        *existing = value;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Create a new handle for reading the published copy of the map
+    pub fn reader(&self) -> ReadHandle<K, P, T> {
+        ReadHandle::register(self.shared.clone())
+    }
+
+    /// Wait until no reader is still looking at the copy it saw before the
+    /// last switch
+    fn wait_for_readers(&self) {
+        let mut epochs =
+            self.shared.epochs.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Readers that have been dropped don't need to be waited for
+        epochs.retain(|epoch| Arc::strong_count(epoch) > 1);
+
+        for epoch in epochs.iter() {
+            let seen = epoch.load(Ordering::SeqCst);
+
+            if seen % 2 == 1 {
+                while epoch.load(Ordering::SeqCst) == seen {
+                    std::thread::yield_now();
+                }
+            }
+        }
+    }
+}
+
+/// Handle for reading the published copy of a left-right slot map. Reading
+/// never blocks
+pub struct ReadHandle<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    shared: Arc<Shared<K, P, T>>,
+    epoch: Arc<AtomicUsize>,
+}
+
+impl<K, P, T> std::fmt::Debug for ReadHandle<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReadHandle").finish_non_exhaustive()
+    }
+}
+
+impl<K, P, T> Clone for ReadHandle<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn clone(&self) -> Self {
+        ReadHandle::register(self.shared.clone())
+    }
+}
+
+impl<K, P, T> ReadHandle<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a read handle with its own epoch counter
+    fn register(shared: Arc<Shared<K, P, T>>) -> ReadHandle<K, P, T> {
+        let epoch = Arc::new(AtomicUsize::new(0));
+
+        shared
+            .epochs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(epoch.clone());
+
+        ReadHandle { shared, epoch }
+    }
+
+    /// Get access to the published copy of the map. The writer's next
+    /// refresh waits until the returned guard is dropped, so guards should
+    /// be short-lived
+    pub fn enter(&mut self) -> ReadGuard<'_, K, P, T> {
+        let _ = self.epoch.fetch_add(1, Ordering::SeqCst);
+        let index = self.shared.read_index.load(Ordering::SeqCst);
+
+        // Safety - The writer won't modify this copy until the epoch shows
+        // that this reader has left it
+        let map = unsafe { &*self.shared.maps[index].get() };
+
+        ReadGuard {
+            map,
+            epoch: &self.epoch,
+        }
+    }
+}
+
+/// Access to the published copy of a left-right slot map
+pub struct ReadGuard<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    map: &'a SlotMap<K, P, T>,
+    epoch: &'a AtomicUsize,
+}
+
+impl<'a, K, P, T> std::fmt::Debug for ReadGuard<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.map.fmt(f)
+    }
+}
+
+impl<'a, K, P, T> Deref for ReadGuard<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    type Target = SlotMap<K, P, T>;
+
+    fn deref(&self) -> &Self::Target {
+        self.map
+    }
+}
+
+impl<'a, K, P, T> Drop for ReadGuard<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn drop(&mut self) {
+        let _ = self.epoch.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+
+    #[test]
+    fn test_copies_converge() {
+        let (mut writer, mut reader) = left_right::<TestKey, usize, String>();
+
+        let keys = (0..600)
+            .map(|i| writer.insert(i, format!("{}", i)))
+            .collect::<Vec<_>>();
+        assert_eq!(0, reader.enter().len());
+
+        writer.refresh();
+        assert_eq!(600, reader.enter().len());
+
+        assert!(writer.remove(&keys[3]));
+        assert!(writer.set(&keys[4], "changed".to_owned()));
+        let reused = writer.insert(3, "reused".to_owned());
+
+        assert_eq!(Some(&"3".to_owned()), reader.enter().get(&keys[3]));
+        writer.refresh();
+
+        // Both copies now hold the same values under the same keys
+        let guard = reader.enter();
+        assert_eq!(None, guard.get(&keys[3]));
+        assert_eq!(Some(&"changed".to_owned()), guard.get(&keys[4]));
+        assert_eq!(Some(&"reused".to_owned()), guard.get(&reused));
+        assert_eq!(writer.map().state_digest(), guard.state_digest());
+    }
+
+    #[test]
+    fn test_readers_on_other_threads() {
+        let (mut writer, reader) = left_right::<TestKey, usize, usize>();
+
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                let mut reader = reader.clone();
+
+                let _ = s.spawn(move || {
+                    let mut last_len = 0;
+
+                    while last_len < 1000 {
+                        let guard = reader.enter();
+                        assert!(guard.len() >= last_len);
+
+                        // Everything published is visible at once
+                        for (key_data, value) in guard.iter_raw() {
+                            assert_eq!(u64::from(key_data) as usize, *value);
+                        }
+
+                        last_len = guard.len();
+                    }
+                });
+            }
+
+            for i in 0..1000 {
+                let _ = writer.insert(i, i);
+
+                if i % 10 == 9 {
+                    writer.refresh();
+                }
+            }
+        });
+
+        drop(reader);
+        writer.refresh();
+        assert_eq!(1000, writer.map().len());
+    }
+}
@@ -0,0 +1,386 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+
+/// Item of a [`LinkedSlotMap`] along with its neighbors in the list
+struct Entry<T> {
+    value: T,
+    previous: Option<SlotMapKeyData>,
+    next: Option<SlotMapKeyData>,
+}
+
+/// Slot map that keeps its items in a list, in insertion order unless they
+/// are moved. Each item stores links to its neighbors, so items can be
+/// inserted next to others, moved to either end, and removed in O(1), and
+/// iterating follows the list rather than the slots
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TrackKey<()>);
+/// let mut playlist = LinkedSlotMap::<TrackKey, (), &str>::new();
+///
+/// let intro = playlist.insert((), "intro");
+/// let _ = playlist.insert((), "verse");
+/// let _ = playlist.insert_after(&intro, (), "bridge").unwrap();
+///
+/// playlist.move_to_back(&intro);
+///
+/// assert_eq!(
+///     vec!["bridge", "verse", "intro"],
+///     playlist.values().copied().collect::<Vec<_>>()
+/// );
+/// ```
+pub struct LinkedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, Entry<T>>,
+    first: Option<SlotMapKeyData>,
+    last: Option<SlotMapKeyData>,
+}
+
+impl<K, P, T> std::fmt::Debug for LinkedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.values()).finish()
+    }
+}
+
+impl<K, P, T> Default for LinkedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        LinkedSlotMap::new()
+    }
+}
+
+impl<K, P, T> LinkedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> LinkedSlotMap<K, P, T> {
+        LinkedSlotMap {
+            map: SlotMap::new(),
+            first: None,
+            last: None,
+        }
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Insert the given item at the back of the list and return its key
+    pub fn insert(&mut self, pointer: P, value: T) -> K {
+        let key_data = self.insert_unlinked(value);
+        self.link(key_data, self.last, None);
+
+        K::from((pointer, key_data))
+    }
+
+    /// Insert the given item at the front of the list and return its key
+    pub fn insert_front(&mut self, pointer: P, value: T) -> K {
+        let key_data = self.insert_unlinked(value);
+        self.link(key_data, None, self.first);
+
+        K::from((pointer, key_data))
+    }
+
+    /// Insert the given item right after the item for the given key and
+    /// return its key, or give the item back if the key isn't in the map
+    pub fn insert_after(
+        &mut self,
+        key: &K,
+        pointer: P,
+        value: T,
+    ) -> Result<K, T> {
+        let Some(next) = self.map.get(key).map(|entry| entry.next) else {
+            return Err(value);
+        };
+
+        let key_data = self.insert_unlinked(value);
+        self.link(key_data, Some(*key.borrow()), next);
+
+        Ok(K::from((pointer, key_data)))
+    }
+
+    /// Insert the given item right before the item for the given key and
+    /// return its key, or give the item back if the key isn't in the map
+    pub fn insert_before(
+        &mut self,
+        key: &K,
+        pointer: P,
+        value: T,
+    ) -> Result<K, T> {
+        let Some(previous) = self.map.get(key).map(|entry| entry.previous)
+        else {
+            return Err(value);
+        };
+
+        let key_data = self.insert_unlinked(value);
+        self.link(key_data, previous, Some(*key.borrow()));
+
+        Ok(K::from((pointer, key_data)))
+    }
+
+    /// Get a reference to the item for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.map.get_raw(key_data).map(|entry| &entry.value)
+    }
+
+    /// Get a mutable reference to the item for the given key if it exists
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Same as get_mut, but only requires slot map key data
+    pub fn get_mut_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        self.map.get_mut_raw(key_data).map(|entry| &mut entry.value)
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Get the key data of the item at the front of the list
+    pub fn first_raw(&self) -> Option<SlotMapKeyData> {
+        self.first
+    }
+
+    /// Get the key data of the item at the back of the list
+    pub fn last_raw(&self) -> Option<SlotMapKeyData> {
+        self.last
+    }
+
+    /// Get the key data of the item after the item for the given key data
+    pub fn next_raw(
+        &self,
+        key_data: &SlotMapKeyData,
+    ) -> Option<SlotMapKeyData> {
+        self.map.get_raw(key_data)?.next
+    }
+
+    /// Get the key data of the item before the item for the given key data
+    pub fn previous_raw(
+        &self,
+        key_data: &SlotMapKeyData,
+    ) -> Option<SlotMapKeyData> {
+        self.map.get_raw(key_data)?.previous
+    }
+
+    /// Move the item for the given key to the back of the list. Returns
+    /// whether the item exists
+    pub fn move_to_back(&mut self, key: &K) -> bool {
+        let key_data = *key.borrow();
+
+        if !self.map.contains_key_raw(&key_data) {
+            return false;
+        }
+
+        self.unlink(key_data);
+        self.link(key_data, self.last, None);
+
+        true
+    }
+
+    /// Move the item for the given key to the front of the list. Returns
+    /// whether the item exists
+    pub fn move_to_front(&mut self, key: &K) -> bool {
+        let key_data = *key.borrow();
+
+        if !self.map.contains_key_raw(&key_data) {
+            return false;
+        }
+
+        self.unlink(key_data);
+        self.link(key_data, None, self.first);
+
+        true
+    }
+
+    /// Remove the item for the given key and return a mutable ref to it if
+    /// there was one
+    pub fn remove(&mut self, key: &K) -> Option<&mut T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        if !self.map.contains_key_raw(key_data) {
+            return None;
+        }
+
+        self.unlink(*key_data);
+
+        self.map.remove_raw(key_data).map(|entry| &mut entry.value)
+    }
+
+    /// Remove all items from the map
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.first = None;
+        self.last = None;
+    }
+
+    /// Create an iterator over the raw key data and values of all items in
+    /// list order
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        std::iter::successors(self.first, |key_data| self.entry(key_data).next)
+            .map(|key_data| (key_data, &self.entry(&key_data).value))
+    }
+
+    /// Create an iterator over the values of all items in list order
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.iter_raw().map(|(_, value)| value)
+    }
+
+    /// Create an iterator over the mutable values of all items, in slot
+    /// order rather than list order
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.map.values_mut().map(|entry| &mut entry.value)
+    }
+
+    fn insert_unlinked(&mut self, value: T) -> SlotMapKeyData {
+        self.map.insert_raw(Entry {
+            value,
+            previous: None,
+            next: None,
+        })
+    }
+
+    /// Link the given unlinked item in between the given neighbors, which
+    /// must be adjacent
+    fn link(
+        &mut self,
+        key_data: SlotMapKeyData,
+        previous: Option<SlotMapKeyData>,
+        next: Option<SlotMapKeyData>,
+    ) {
+        let entry = self.entry_mut(&key_data);
+        entry.previous = previous;
+        entry.next = next;
+
+        match previous {
+            Some(previous) => self.entry_mut(&previous).next = Some(key_data),
+            None => self.first = Some(key_data),
+        }
+
+        match next {
+            Some(next) => self.entry_mut(&next).previous = Some(key_data),
+            None => self.last = Some(key_data),
+        }
+    }
+
+    /// Take the given item out of the list, joining its neighbors
+    fn unlink(&mut self, key_data: SlotMapKeyData) {
+        let entry = self.entry_mut(&key_data);
+        let previous = entry.previous.take();
+        let next = entry.next.take();
+
+        match previous {
+            Some(previous) => self.entry_mut(&previous).next = next,
+            None => self.first = next,
+        }
+
+        match next {
+            Some(next) => self.entry_mut(&next).previous = previous,
+            None => self.last = previous,
+        }
+    }
+
+    /// Get the entry for key data that a link refers to, which always exists
+    fn entry(&self, key_data: &SlotMapKeyData) -> &Entry<T> {
+        self.map
+            .get_raw(key_data)
+            .expect("links only refer to items in the map")
+    }
+
+    fn entry_mut(&mut self, key_data: &SlotMapKeyData) -> &mut Entry<T> {
+        self.map
+            .get_mut_raw(key_data)
+            .expect("links only refer to items in the map")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn test_random_operations_match_model() {
+        let mut rng = thread_rng();
+        let mut map = LinkedSlotMap::<TestKey, usize, usize>::new();
+        let mut model = Vec::<TestKey>::new();
+
+        for i in 0..5000 {
+            let target =
+                (!model.is_empty()).then(|| rng.gen_range(0..model.len()));
+
+            match (rng.gen_range(0..7), target) {
+                (0, _) | (_, None) => model.push(map.insert(i, i)),
+                (1, _) => model.insert(0, map.insert_front(i, i)),
+                (2, Some(t)) => {
+                    let key = map.insert_after(&model[t], i, i).unwrap();
+                    model.insert(t + 1, key);
+                }
+                (3, Some(t)) => {
+                    let key = map.insert_before(&model[t], i, i).unwrap();
+                    model.insert(t, key);
+                }
+                (4, Some(t)) => {
+                    let key = model.remove(t);
+                    assert!(map.move_to_back(&key));
+                    model.push(key);
+                }
+                (5, Some(t)) => {
+                    let key = model.remove(t);
+                    assert!(map.move_to_front(&key));
+                    model.insert(0, key);
+                }
+                (_, Some(t)) => {
+                    let key = model.remove(t);
+                    assert_eq!(Some(key.pointer), map.remove(&key).copied());
+                    assert!(!map.move_to_back(&key));
+                    assert_eq!(Err(0), map.insert_after(&key, 0, 0));
+                }
+            }
+        }
+
+        assert_eq!(model.len(), map.len());
+        assert_eq!(
+            model.iter().map(|k| k.pointer).collect::<Vec<_>>(),
+            map.values().copied().collect::<Vec<_>>()
+        );
+
+        let backwards =
+            std::iter::successors(map.last_raw(), |k| map.previous_raw(k))
+                .map(|k| *map.get_raw(&k).unwrap())
+                .collect::<Vec<_>>();
+
+        assert_eq!(
+            model.iter().rev().map(|k| k.pointer).collect::<Vec<_>>(),
+            backwards
+        );
+
+        map.clear();
+        assert_eq!(0, map.iter_raw().count());
+        assert_eq!(None, map.first_raw());
+    }
+}
@@ -0,0 +1,260 @@
+use super::slot_map_concurrent::{read, write};
+use super::{SlotMap, SlotMapKey};
+use std::ops::{Deref, DerefMut};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Slot map where every item has its own lock, so many threads can read and
+/// modify different items at the same time. The map's own bookkeeping has a
+/// separate lock that is only held exclusively while inserting or removing
+/// items. Because of that, inserting or removing waits for every outstanding
+/// item guard to be dropped, and doing either while the same thread holds a
+/// guard will deadlock
+pub struct LockedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    map: RwLock<SlotMap<K, P, RwLock<T>>>,
+}
+
+impl<K, P, T> std::fmt::Debug for LockedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LockedSlotMap")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl<K, P, T> Default for LockedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        LockedSlotMap::new()
+    }
+}
+
+impl<K, P, T> LockedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create an empty map
+    pub fn new() -> LockedSlotMap<K, P, T> {
+        LockedSlotMap {
+            map: RwLock::new(SlotMap::new()),
+        }
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        read(&self.map).len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        read(&self.map).is_empty()
+    }
+
+    /// Insert the given item into the map and return its key
+    pub fn insert(&self, pointer: P, value: T) -> K {
+        write(&self.map).insert(pointer, RwLock::new(value))
+    }
+
+    /// Read-lock the item for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<SlotReadGuard<'_, K, P, T>> {
+        let map = read(&self.map);
+        let lock: *const RwLock<T> = map.get(key)?;
+
+        // Safety - The map guard is kept in the returned guard and dropped
+        // after the item guard, and the item can't move while the map is
+        // read-locked
+        let slot = read(unsafe { &*lock });
+
+        Some(SlotReadGuard { slot, _map: map })
+    }
+
+    /// Write-lock the item for the given key if it exists
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let map = LockedSlotMap::<TestKey,(),usize>::new();
+    /// let first = map.insert((), 1);
+    /// let second = map.insert((), 2);
+    ///
+    /// // Different items can be locked at the same time
+    /// let mut first_guard = map.get_mut(&first).unwrap();
+    /// let mut second_guard = map.get_mut(&second).unwrap();
+    ///
+    /// *first_guard += 10;
+    /// *second_guard += 20;
+    ///
+    /// drop((first_guard, second_guard));
+    /// assert_eq!(22, *map.get(&second).unwrap());
+    /// ```
+    pub fn get_mut(&self, key: &K) -> Option<SlotWriteGuard<'_, K, P, T>> {
+        let map = read(&self.map);
+        let lock: *const RwLock<T> = map.get(key)?;
+
+        // Safety - Same as get
+        let slot = write(unsafe { &*lock });
+
+        Some(SlotWriteGuard { slot, _map: map })
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        read(&self.map).contains_key(key)
+    }
+
+    /// Remove the item for the given key, calling the given function with a
+    /// mutable reference to the removed item
+    pub fn remove_with<R>(
+        &self,
+        key: &K,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> Option<R> {
+        let mut map = write(&self.map);
+
+        map.remove(key)
+            .map(|lock| f(lock.get_mut().unwrap_or_else(|e| e.into_inner())))
+    }
+
+    /// Remove the item for the given key, returning whether it was present
+    pub fn remove(&self, key: &K) -> bool {
+        self.remove_with(key, |_| ()).is_some()
+    }
+}
+
+/// Shared access to an item of a [`LockedSlotMap`]
+pub struct SlotReadGuard<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    // Declared first so it is dropped before the map guard
+    slot: RwLockReadGuard<'a, T>,
+    _map: RwLockReadGuard<'a, SlotMap<K, P, RwLock<T>>>,
+}
+
+impl<'a, K, P, T> std::fmt::Debug for SlotReadGuard<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.slot.fmt(f)
+    }
+}
+
+impl<'a, K, P, T> Deref for SlotReadGuard<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.slot
+    }
+}
+
+/// Exclusive access to an item of a [`LockedSlotMap`]
+pub struct SlotWriteGuard<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    // Declared first so it is dropped before the map guard
+    slot: RwLockWriteGuard<'a, T>,
+    _map: RwLockReadGuard<'a, SlotMap<K, P, RwLock<T>>>,
+}
+
+impl<'a, K, P, T> std::fmt::Debug for SlotWriteGuard<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.slot.fmt(f)
+    }
+}
+
+impl<'a, K, P, T> Deref for SlotWriteGuard<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.slot
+    }
+}
+
+impl<'a, K, P, T> DerefMut for SlotWriteGuard<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.slot
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use std::sync::Barrier;
+
+    #[test]
+    fn test_items_are_locked_independently() {
+        let map = LockedSlotMap::<TestKey, usize, usize>::new();
+        let keys = (0..8).map(|i| map.insert(i, 0)).collect::<Vec<_>>();
+
+        // Every thread holds the lock on its own item until all of them have
+        // taken theirs, which only works if the items are locked separately
+        let barrier = Barrier::new(keys.len());
+
+        std::thread::scope(|s| {
+            for key in &keys {
+                let (map, barrier) = (&map, &barrier);
+
+                let _ = s.spawn(move || {
+                    let mut guard = map.get_mut(key).unwrap();
+                    let _ = barrier.wait();
+
+                    for _ in 0..1000 {
+                        *guard += 1;
+                    }
+                });
+            }
+        });
+
+        for key in &keys {
+            assert_eq!(1000, *map.get(key).unwrap());
+        }
+
+        assert!(map.remove(&keys[0]));
+        assert!(map.get(&keys[0]).is_none());
+        assert!(map.get_mut(&keys[0]).is_none());
+        assert!(!map.remove(&keys[0]));
+        assert_eq!(7, map.len());
+    }
+
+    #[test]
+    fn test_inserts_wait_for_guards() {
+        let map = LockedSlotMap::<TestKey, usize, String>::new();
+        let key = map.insert(0, "first".to_owned());
+
+        std::thread::scope(|s| {
+            let guard = map.get(&key).unwrap();
+
+            let inserter = s.spawn(|| map.insert(1, "second".to_owned()));
+
+            assert_eq!("first", &*guard);
+            drop(guard);
+
+            let second = inserter.join().unwrap();
+            assert_eq!("second", &*map.get(&second).unwrap());
+        });
+    }
+}
@@ -0,0 +1,347 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+
+/// Value of an [`LruSlotMap`] along with its neighbors in order of recency
+struct Entry<T> {
+    /// The value, which is taken out when its item is removed or evicted
+    value: Option<T>,
+
+    newer: Option<SlotMapKeyData>,
+    older: Option<SlotMapKeyData>,
+}
+
+/// Slot map that holds at most a fixed number of items, evicting the least
+/// recently used item to make room for a new one. Inserting an item or
+/// getting it through [`LruSlotMap::get`] or [`LruSlotMap::get_mut`] makes it
+/// the most recently used, while [`LruSlotMap::peek`] leaves the order alone.
+/// Keys of evicted items become invalid just like keys of removed items, and
+/// evicted values are given to the callback set with
+/// [`LruSlotMap::on_evict`]
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # use std::sync::mpsc::channel;
+/// # define_key_type!(TestKey<()>);
+/// let mut cache = LruSlotMap::<TestKey, (), &'static str>::new(2);
+///
+/// let (sender, evicted) = channel();
+/// cache.on_evict(move |_, value| sender.send(value).unwrap());
+///
+/// let first = cache.insert((), "first");
+/// let second = cache.insert((), "second");
+///
+/// // Using the first item makes the second the least recently used
+/// assert_eq!(Some(&"first"), cache.get(&first));
+///
+/// let third = cache.insert((), "third");
+///
+/// assert_eq!(vec!["second"], evicted.try_iter().collect::<Vec<_>>());
+/// assert!(!cache.contains_key(&second));
+/// assert!(cache.contains_key(&first) && cache.contains_key(&third));
+/// ```
+pub struct LruSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, Entry<T>>,
+    capacity: usize,
+
+    newest: Option<SlotMapKeyData>,
+    oldest: Option<SlotMapKeyData>,
+
+    on_evict: Option<Box<dyn FnMut(SlotMapKeyData, T) + Send>>,
+}
+
+impl<K, P, T> std::fmt::Debug for LruSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LruSlotMap")
+            .field("capacity", &self.capacity)
+            .field("items", &self.iter_raw().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl<K, P, T> LruSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create an empty map that holds at most the given number of items
+    ///
+    /// # Panics
+    /// If the capacity is zero
+    pub fn new(capacity: usize) -> LruSlotMap<K, P, T> {
+        assert!(capacity > 0, "an LRU slot map needs room for an item");
+
+        LruSlotMap {
+            map: SlotMap::new(),
+            capacity,
+            newest: None,
+            oldest: None,
+            on_evict: None,
+        }
+    }
+
+    /// Call the given function with the key data and value of every item
+    /// evicted from now on, replacing any function set before. Items that
+    /// are removed explicitly are not passed to it
+    pub fn on_evict(
+        &mut self,
+        on_evict: impl FnMut(SlotMapKeyData, T) + Send + 'static,
+    ) {
+        self.on_evict = Some(Box::new(on_evict));
+    }
+
+    /// Get the maximum number of items in the map
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Change the maximum number of items in the map, evicting the least
+    /// recently used items until the map fits
+    ///
+    /// # Panics
+    /// If the capacity is zero
+    pub fn set_capacity(&mut self, capacity: usize) {
+        assert!(capacity > 0, "an LRU slot map needs room for an item");
+
+        self.capacity = capacity;
+
+        while self.len() > capacity {
+            self.evict_oldest();
+        }
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Insert the given item as the most recently used one and return its
+    /// key, evicting the least recently used item first if the map is full
+    pub fn insert(&mut self, pointer: P, value: T) -> K {
+        if self.len() == self.capacity {
+            self.evict_oldest();
+        }
+
+        let key = self.map.insert(
+            pointer,
+            Entry {
+                value: Some(value),
+                newer: None,
+                older: None,
+            },
+        );
+
+        self.push_newest(*key.borrow());
+
+        key
+    }
+
+    /// Get a reference to the item for the given key if it exists, making it
+    /// the most recently used item
+    pub fn get(&mut self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.get_mut_raw(key_data).map(|value| &*value)
+    }
+
+    /// Get a mutable reference to the item for the given key if it exists,
+    /// making it the most recently used item
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Same as get_mut, but only requires slot map key data
+    pub fn get_mut_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        if !self.map.contains_key_raw(key_data) {
+            return None;
+        }
+
+        if self.newest != Some(*key_data) {
+            self.unlink(key_data);
+            self.push_newest(*key_data);
+        }
+
+        self.map.get_mut_raw(key_data)?.value.as_mut()
+    }
+
+    /// Get a reference to the item for the given key if it exists without
+    /// changing how recently it was used
+    pub fn peek(&self, key: &K) -> Option<&T> {
+        self.peek_raw(key.borrow())
+    }
+
+    /// Same as peek, but only requires slot map key data
+    pub fn peek_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.map.get_raw(key_data)?.value.as_ref()
+    }
+
+    /// Tells if the given key is in the map without changing how recently
+    /// its item was used
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Remove the item for the given key and return it
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<T> {
+        if !self.map.contains_key_raw(key_data) {
+            return None;
+        }
+
+        self.unlink(key_data);
+        self.map.remove_raw(key_data)?.value.take()
+    }
+
+    /// Remove the least recently used item and return it along with its key
+    /// data. The eviction callback is not called
+    pub fn pop_oldest(&mut self) -> Option<(SlotMapKeyData, T)> {
+        let key_data = self.oldest?;
+
+        self.remove_raw(&key_data).map(|value| (key_data, value))
+    }
+
+    /// Create an iterator over the raw key data and values of all items, from
+    /// the most recently used to the least
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        let mut next = self.newest;
+
+        std::iter::from_fn(move || {
+            let key_data = next?;
+            let entry = self.map.get_raw(&key_data)?;
+
+            next = entry.older;
+
+            Some((key_data, entry.value.as_ref()?))
+        })
+    }
+
+    /// Remove the least recently used item, giving it to the eviction
+    /// callback
+    fn evict_oldest(&mut self) {
+        if let Some((key_data, value)) = self.pop_oldest() {
+            if let Some(on_evict) = &mut self.on_evict {
+                on_evict(key_data, value);
+            }
+        }
+    }
+
+    /// Put the item for the given key data at the front of the recency order
+    fn push_newest(&mut self, key_data: SlotMapKeyData) {
+        let older = self.newest.replace(key_data);
+
+        if let Some(entry) = self.map.get_mut_raw(&key_data) {
+            entry.newer = None;
+            entry.older = older;
+        }
+
+        match older.and_then(|o| self.map.get_mut_raw(&o)) {
+            Some(entry) => entry.newer = Some(key_data),
+            None => self.oldest = Some(key_data),
+        }
+    }
+
+    /// Take the item for the given key data out of the recency order
+    fn unlink(&mut self, key_data: &SlotMapKeyData) {
+        let Some(entry) = self.map.get_mut_raw(key_data) else {
+            return;
+        };
+
+        let (newer, older) = (entry.newer.take(), entry.older.take());
+
+        match newer.and_then(|n| self.map.get_mut_raw(&n)) {
+            Some(entry) => entry.older = older,
+            None => self.newest = older,
+        }
+
+        match older.and_then(|o| self.map.get_mut_raw(&o)) {
+            Some(entry) => entry.newer = newer,
+            None => self.oldest = newer,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use std::borrow::Borrow;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_eviction_order_matches_model() {
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let mut map = LruSlotMap::<TestKey, usize, usize>::new(50);
+
+        let sink = evicted.clone();
+        map.on_evict(move |key_data, value| {
+            sink.lock().unwrap().push((key_data, value))
+        });
+
+        // Keys from most to least recently used
+        let mut model = VecDeque::<TestKey>::new();
+        let mut expected_evictions = Vec::new();
+
+        for i in 0..1000 {
+            match i % 7 {
+                0 | 3 if !model.is_empty() => {
+                    let key = model.remove(i * 13 % model.len()).unwrap();
+                    assert_eq!(Some(&key.pointer), map.get(&key));
+                    model.push_front(key);
+                }
+                5 if !model.is_empty() => {
+                    let key = model.remove(i * 11 % model.len()).unwrap();
+                    assert_eq!(Some(key.pointer), map.remove(&key));
+                    assert_eq!(None, map.remove(&key));
+                }
+                _ => {
+                    if model.len() == 50 {
+                        let key = model.pop_back().unwrap();
+                        expected_evictions.push((*key.borrow(), key.pointer));
+                    }
+
+                    model.push_front(map.insert(i, i));
+                }
+            }
+
+            if let Some(key) = model.back() {
+                assert_eq!(Some(&key.pointer), map.peek(key));
+            }
+        }
+
+        assert_eq!(expected_evictions, *evicted.lock().unwrap());
+        assert_eq!(model.len(), map.len());
+
+        let order = map.iter_raw().map(|(k, _)| k).collect::<Vec<_>>();
+        let expected_order = model
+            .iter()
+            .map(|k| *Borrow::<SlotMapKeyData>::borrow(k))
+            .collect::<Vec<_>>();
+        assert_eq!(expected_order, order);
+
+        // Shrinking evicts the least recently used items
+        map.set_capacity(10);
+        assert_eq!(10, map.len());
+        assert!(model.iter().take(10).all(|k| map.contains_key(k)));
+        assert!(model.iter().skip(10).all(|k| !map.contains_key(k)));
+
+        let oldest = model[9];
+        assert_eq!(Some((*oldest.borrow(), oldest.pointer)), map.pop_oldest());
+    }
+}
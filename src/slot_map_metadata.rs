@@ -0,0 +1,339 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+
+/// Slot map that keeps a small piece of metadata next to every item, such as
+/// flags or timestamps, without making it part of the item. Metadata is kept
+/// in its own vector indexed by slot position, so it doesn't make the items
+/// any bigger, and it lives and dies with its item so there is no second map
+/// to keep in sync. Created with [`SlotMap::with_metadata`]
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()>);
+/// let mut map = SlotMap::<TestKey, (), &'static str>::with_metadata::<u64>();
+///
+/// let key = map.insert((), "Hello");
+/// assert_eq!(Some(&0), map.get_meta(&key));
+///
+/// *map.get_meta_mut(&key).unwrap() = 1234;
+/// assert_eq!(Some(&1234), map.get_meta(&key));
+/// assert_eq!(Some(&"Hello"), map.get(&key));
+///
+/// let _ = map.remove(&key);
+/// assert_eq!(None, map.get_meta(&key));
+/// ```
+pub struct MetadataSlotMap<K, P, T, M>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, T>,
+
+    /// Metadata for every slot that has been written, by slot position
+    metadata: Vec<M>,
+}
+
+impl<K, P, T, M> std::fmt::Debug for MetadataSlotMap<K, P, T, M>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+    M: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.iter_with_meta_raw().map(|(_, v, m)| (v, m)))
+            .finish()
+    }
+}
+
+impl<K, P, T, M> Default for MetadataSlotMap<K, P, T, M>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        MetadataSlotMap::new()
+    }
+}
+
+impl<K, P, T, M> Clone for MetadataSlotMap<K, P, T, M>
+where
+    K: SlotMapKey<P>,
+    T: Clone,
+    M: Clone,
+{
+    fn clone(&self) -> Self {
+        MetadataSlotMap {
+            map: self.map.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map that keeps metadata of the given type next to
+    /// every item
+    pub fn with_metadata<M>() -> MetadataSlotMap<K, P, T, M> {
+        MetadataSlotMap::new()
+    }
+}
+
+impl<K, P, T, M> MetadataSlotMap<K, P, T, M>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> MetadataSlotMap<K, P, T, M> {
+        MetadataSlotMap {
+            map: SlotMap::new(),
+            metadata: Vec::new(),
+        }
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Insert the given item into the map with default metadata and return
+    /// its key
+    pub fn insert(&mut self, pointer: P, value: T) -> K
+    where
+        M: Default,
+    {
+        self.insert_with_meta(pointer, value, M::default())
+    }
+
+    /// Insert the given item and its metadata into the map and return its key
+    pub fn insert_with_meta(&mut self, pointer: P, value: T, meta: M) -> K {
+        let key_data = self.map.insert_raw(value);
+        let position = key_data.position();
+
+        if position == self.metadata.len() {
+            self.metadata.push(meta);
+        } else {
+            self.metadata[position] = meta;
+        }
+
+        K::from((pointer, key_data))
+    }
+
+    /// Get a reference to the item for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.map.get_raw(key_data)
+    }
+
+    /// Get a mutable reference to the item for the given key if it exists
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Same as get_mut, but only requires slot map key data
+    pub fn get_mut_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        self.map.get_mut_raw(key_data)
+    }
+
+    /// Get a reference to the metadata of the item for the given key if it
+    /// exists
+    pub fn get_meta(&self, key: &K) -> Option<&M> {
+        self.get_meta_raw(key.borrow())
+    }
+
+    /// Same as get_meta, but only requires slot map key data
+    pub fn get_meta_raw(&self, key_data: &SlotMapKeyData) -> Option<&M> {
+        if !self.map.contains_key_raw(key_data) {
+            return None;
+        }
+
+        self.metadata.get(key_data.position())
+    }
+
+    /// Get a mutable reference to the metadata of the item for the given key
+    /// if it exists
+    pub fn get_meta_mut(&mut self, key: &K) -> Option<&mut M> {
+        self.get_meta_mut_raw(key.borrow())
+    }
+
+    /// Same as get_meta_mut, but only requires slot map key data
+    pub fn get_meta_mut_raw(
+        &mut self,
+        key_data: &SlotMapKeyData,
+    ) -> Option<&mut M> {
+        if !self.map.contains_key_raw(key_data) {
+            return None;
+        }
+
+        self.metadata.get_mut(key_data.position())
+    }
+
+    /// Get mutable references to both the item for the given key and its
+    /// metadata if it exists
+    pub fn get_mut_with_meta(&mut self, key: &K) -> Option<(&mut T, &mut M)> {
+        self.get_mut_with_meta_raw(key.borrow())
+    }
+
+    /// Same as get_mut_with_meta, but only requires slot map key data
+    pub fn get_mut_with_meta_raw(
+        &mut self,
+        key_data: &SlotMapKeyData,
+    ) -> Option<(&mut T, &mut M)> {
+        let value = self.map.get_mut_raw(key_data)?;
+        let meta = self.metadata.get_mut(key_data.position())?;
+
+        Some((value, meta))
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Remove the item for the given key and return a mutable ref to it if
+    /// there was one. Like the item, its metadata stays in its slot until
+    /// the slot is reused
+    pub fn remove(&mut self, key: &K) -> Option<&mut T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        self.map.remove_raw(key_data)
+    }
+
+    /// Remove all items from the map
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Create an iterator over the raw key data and values of all items in
+    /// the map
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.map.iter_raw()
+    }
+
+    /// Create an iterator over the raw key data and mutable values of all
+    /// items in the map
+    pub fn iter_mut_raw(
+        &mut self,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &mut T)> {
+        self.map.iter_mut_raw()
+    }
+
+    /// Create an iterator over the raw key data, values, and metadata of all
+    /// items in the map
+    pub fn iter_with_meta_raw(
+        &self,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &T, &M)> {
+        self.map.iter_raw().map(|(key_data, value)| {
+            (key_data, value, &self.metadata[key_data.position()])
+        })
+    }
+
+    /// Create an iterator over the raw key data and mutable metadata of all
+    /// items in the map
+    pub fn iter_meta_mut_raw(
+        &mut self,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &mut M)> {
+        // Items come in order of position, so the metadata can be walked
+        // alongside them
+        let mut metadata = self.metadata.iter_mut().enumerate();
+
+        self.map.iter_raw().filter_map(move |(key_data, _)| {
+            let position = key_data.position();
+
+            metadata
+                .find(|(p, _)| *p == position)
+                .map(|(_, meta)| (key_data, meta))
+        })
+    }
+
+    /// Create an iterator over the values of all items in the map
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.map.values()
+    }
+
+    /// Create an iterator over the mutable values of all items in the map
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.map.values_mut()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use crate::SLOT_MAP_CHUNK_SIZE;
+
+    #[test]
+    fn test_metadata_follows_items() {
+        let mut map = SlotMap::<TestKey, usize, usize>::with_metadata::<u32>();
+        let mut expected = SlotMap::<TestKey, usize, usize>::new();
+
+        let mut keys = Vec::new();
+
+        for i in 0..SLOT_MAP_CHUNK_SIZE * 3 {
+            let key = if i % 2 == 0 {
+                map.insert_with_meta(i, i, i as u32)
+            } else {
+                map.insert(i, i)
+            };
+            assert_eq!(expected.insert(i, i), key);
+            keys.push(key);
+
+            if i % 3 == 0 {
+                let key = keys.swap_remove(i * 7 % keys.len());
+                assert_eq!(Some(&mut key.pointer.clone()), map.remove(&key));
+                assert!(expected.remove(&key).is_some());
+                assert_eq!(None, map.get_meta(&key));
+                assert_eq!(None, map.get_meta_mut(&key));
+            }
+        }
+
+        // Reused slots get the new item's metadata
+        for key in &keys {
+            let expected_meta = if key.pointer % 2 == 0 {
+                key.pointer as u32
+            } else {
+                0
+            };
+            assert_eq!(Some(&expected_meta), map.get_meta(key));
+        }
+
+        for (_, meta) in map.iter_meta_mut_raw() {
+            *meta += 1;
+        }
+
+        for key in &keys {
+            let (value, meta) = map.get_mut_with_meta(key).unwrap();
+            assert_eq!(key.pointer, *value);
+            assert_eq!(
+                if key.pointer % 2 == 0 {
+                    key.pointer as u32 + 1
+                } else {
+                    1
+                },
+                *meta
+            );
+        }
+
+        assert_eq!(keys.len(), map.iter_meta_mut_raw().count());
+        assert!(map
+            .iter_with_meta_raw()
+            .all(|(k, v, m)| map.get_raw(&k) == Some(v)
+                && map.get_meta_raw(&k) == Some(m)));
+
+        map.clear();
+        assert!(keys.iter().all(|k| map.get_meta(k).is_none()));
+        assert_eq!(0, map.iter_meta_mut_raw().count());
+    }
+}
@@ -0,0 +1,483 @@
+use super::{SlotMapKey, SlotMapKeyData, SLOT_MAP_CHUNK_SIZE};
+use bytemuck::Pod;
+use memmap2::MmapMut;
+use std::fs::{File, OpenOptions};
+use std::io::{Error, ErrorKind};
+use std::marker::PhantomData;
+use std::mem::{align_of, size_of};
+use std::path::Path;
+
+const MAGIC: u64 = u64::from_le_bytes(*b"OWSLTMAP");
+const FORMAT_VERSION: u64 = 1;
+
+// Byte offsets of the fields in the file header. Every field is a u64
+const MAGIC_OFFSET: usize = 0;
+const VERSION_OFFSET: usize = 8;
+const VALUE_SIZE_OFFSET: usize = 16;
+const VALUE_ALIGN_OFFSET: usize = 24;
+const LEN_OFFSET: usize = 32;
+const NEXT_OPEN_SLOT_OFFSET: usize = 40;
+const SLOT_COUNT_OFFSET: usize = 48;
+const CHUNK_CAPACITY_OFFSET: usize = 56;
+const HEADER_SIZE: usize = 64;
+
+/// Round the given size up to the next multiple of the given power of 2
+const fn round_up(size: usize, align: usize) -> usize {
+    (size + align - 1) & !(align - 1)
+}
+
+/// Byte layout of chunks in the file for a given value type. Each chunk holds
+/// the packed key data for all of its slots followed by all of its values, so
+/// no padding is needed between a slot's key and its value
+#[derive(Debug, Clone, Copy)]
+struct ChunkLayout {
+    data_offset: usize,
+    values_offset: usize,
+    stride: usize,
+}
+
+impl ChunkLayout {
+    fn of<T>() -> ChunkLayout {
+        let align = align_of::<T>().max(align_of::<u64>());
+        let values_offset =
+            round_up(SLOT_MAP_CHUNK_SIZE * size_of::<u64>(), align_of::<T>());
+
+        ChunkLayout {
+            data_offset: round_up(HEADER_SIZE, align),
+            values_offset,
+            stride: round_up(
+                values_offset + SLOT_MAP_CHUNK_SIZE * size_of::<T>(),
+                align,
+            ),
+        }
+    }
+
+    fn file_len(&self, chunk_capacity: usize) -> u64 {
+        (self.data_offset + self.stride * chunk_capacity) as u64
+    }
+}
+
+/// Position of the given coordinates if all slots were laid out end to end
+fn linear_index(key: &SlotMapKeyData) -> usize {
+    key.chunk_index as usize * SLOT_MAP_CHUNK_SIZE + key.index_in_chunk as usize
+}
+
+/// Slot map whose chunks live in a memory-mapped file. Opening an existing
+/// file only maps it, so even very large maps are available immediately
+/// without a load phase. Values must be plain-old-data because they are read
+/// directly from the file's bytes.
+///
+/// Changes are written to the mapped memory and reach the file at the
+/// discretion of the operating system. Call [`MmapSlotMap::flush`] to make
+/// sure they are durable. The file format uses the native byte order and
+/// layout of `T`, so files are not portable between architectures
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()>);
+/// # let path = std::env::temp_dir().join(format!("doc-{}.slots", std::process::id()));
+/// let mut map = MmapSlotMap::<TestKey, (), u64>::create(&path).unwrap();
+///
+/// let key = map.insert((), 42).unwrap();
+/// map.flush().unwrap();
+/// drop(map);
+///
+/// let map = MmapSlotMap::<TestKey, (), u64>::open(&path).unwrap();
+/// assert_eq!(Some(&42), map.get(&key));
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+pub struct MmapSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Pod,
+{
+    file: File,
+    map: MmapMut,
+    layout: ChunkLayout,
+
+    _phantom: PhantomData<fn(P, K) -> T>,
+}
+
+impl<K, P, T> std::fmt::Debug for MmapSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Pod,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MmapSlotMap")
+            .field("len", &self.len())
+            .field("chunk_capacity", &self.chunk_capacity())
+            .finish()
+    }
+}
+
+impl<K, P, T> MmapSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Pod,
+{
+    /// Create a new, empty map backed by the file at the given path. Any
+    /// existing file at that path is truncated
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+
+        let layout = ChunkLayout::of::<T>();
+        file.set_len(layout.file_len(1))?;
+
+        // Safety - The file was just created by this process. As with any
+        // memory map, modification by other processes is not guarded against
+        let map = unsafe { MmapMut::map_mut(&file)? };
+
+        let mut result = MmapSlotMap {
+            file,
+            map,
+            layout,
+            _phantom: PhantomData,
+        };
+
+        result.set_header_field(MAGIC_OFFSET, MAGIC);
+        result.set_header_field(VERSION_OFFSET, FORMAT_VERSION);
+        result.set_header_field(VALUE_SIZE_OFFSET, size_of::<T>() as u64);
+        result.set_header_field(VALUE_ALIGN_OFFSET, align_of::<T>() as u64);
+        result.set_header_field(LEN_OFFSET, 0);
+        result.set_header_field(NEXT_OPEN_SLOT_OFFSET, 0);
+        result.set_header_field(SLOT_COUNT_OFFSET, 0);
+        result.set_header_field(CHUNK_CAPACITY_OFFSET, 1);
+
+        Ok(result)
+    }
+
+    /// Open a map previously created with [`MmapSlotMap::create`]. The file's
+    /// header is checked against the value type of this map, but the contents
+    /// of the slots are trusted
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let layout = ChunkLayout::of::<T>();
+
+        if file.metadata()?.len() < layout.file_len(0) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "file is too short to contain a slot map header",
+            ));
+        }
+
+        // Safety - As with any memory map, modification by other processes is
+        // not guarded against
+        let map = unsafe { MmapMut::map_mut(&file)? };
+
+        let result = MmapSlotMap {
+            file,
+            map,
+            layout,
+            _phantom: PhantomData,
+        };
+
+        let invalid =
+            |message| Err(Error::new(ErrorKind::InvalidData, message));
+
+        if result.header_field(MAGIC_OFFSET) != MAGIC {
+            return invalid("file is not a memory-mapped slot map");
+        }
+
+        if result.header_field(VERSION_OFFSET) != FORMAT_VERSION {
+            return invalid("unsupported memory-mapped slot map version");
+        }
+
+        if result.header_field(VALUE_SIZE_OFFSET) != size_of::<T>() as u64
+            || result.header_field(VALUE_ALIGN_OFFSET) != align_of::<T>() as u64
+        {
+            return invalid("file was written with a different value type");
+        }
+
+        if (result.map.len() as u64) < layout.file_len(result.chunk_capacity())
+            || result.slot_count()
+                > result.chunk_capacity() * SLOT_MAP_CHUNK_SIZE
+        {
+            return invalid("file is shorter than its header declares");
+        }
+
+        Ok(result)
+    }
+
+    /// Write all outstanding changes to the backing file and wait for the
+    /// write to complete
+    pub fn flush(&self) -> Result<(), Error> {
+        self.map.flush()
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.header_field(LEN_OFFSET) as usize
+    }
+
+    /// Tells if this map is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Insert the given value into the map and return its key. This can fail
+    /// if the backing file needs to grow and cannot
+    pub fn insert(&mut self, pointer: P, value: T) -> Result<K, Error> {
+        let mut next_slot =
+            SlotMapKeyData::from(self.header_field(NEXT_OPEN_SLOT_OFFSET));
+        let slot_count = self.slot_count();
+        let index = linear_index(&next_slot);
+
+        let key_data = if index < slot_count {
+            let mut slot_key = SlotMapKeyData::from(*self.key_mut(index));
+
+            slot_key.increment_generation();
+            slot_key.swap_coordinates(&mut next_slot);
+
+            *self.key_mut(index) = u64::from(slot_key);
+            *self.value_mut(index) = value;
+            slot_key
+        } else {
+            if index == self.chunk_capacity() * SLOT_MAP_CHUNK_SIZE {
+                self.grow()?;
+            }
+
+            let key_data = next_slot;
+
+            *self.key_mut(index) = u64::from(key_data);
+            *self.value_mut(index) = value;
+
+            next_slot.increment_coordinates();
+            self.set_header_field(SLOT_COUNT_OFFSET, (slot_count + 1) as u64);
+            key_data
+        };
+
+        self.set_header_field(NEXT_OPEN_SLOT_OFFSET, u64::from(next_slot));
+        self.set_header_field(LEN_OFFSET, (self.len() + 1) as u64);
+
+        Ok(K::from((pointer, key_data)))
+    }
+
+    /// Get a reference to the value for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Similar to get, but only requires the slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.live_index(key_data).map(|index| self.value(index))
+    }
+
+    /// Get a mutable reference to the value for the given key if it exists
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Similar to get_mut, but only requires the slot map key data
+    pub fn get_mut_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        self.live_index(key_data)
+            .map(move |index| self.value_mut(index))
+    }
+
+    /// Check to see if the given key is still valid in this map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.live_index(key.borrow()).is_some()
+    }
+
+    /// Remove the item for the given key and return a mutable reference to
+    /// the removed value if there was one
+    pub fn remove(&mut self, key: &K) -> Option<&mut T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Similar to remove, but only requires the slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        let index = self.live_index(key_data)?;
+
+        let mut next_slot =
+            SlotMapKeyData::from(self.header_field(NEXT_OPEN_SLOT_OFFSET));
+        let mut slot_key = SlotMapKeyData::from(*self.key_mut(index));
+
+        slot_key.increment_generation();
+        slot_key.swap_coordinates(&mut next_slot);
+
+        *self.key_mut(index) = u64::from(slot_key);
+        self.set_header_field(NEXT_OPEN_SLOT_OFFSET, u64::from(next_slot));
+        self.set_header_field(LEN_OFFSET, (self.len() - 1) as u64);
+
+        Some(self.value_mut(index))
+    }
+
+    /// Create an iterator over all raw key data and values for items present
+    /// in the map
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        (0..self.slot_count()).filter_map(move |index| {
+            let key_data = SlotMapKeyData::from(self.key(index));
+
+            key_data.is_filled().then(|| (key_data, self.value(index)))
+        })
+    }
+
+    /// Create an iterator over all values present in the map
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.iter_raw().map(|(_, value)| value)
+    }
+
+    /// Number of chunks the backing file currently has room for
+    pub fn chunk_capacity(&self) -> usize {
+        self.header_field(CHUNK_CAPACITY_OFFSET) as usize
+    }
+
+    /// Find the index of the slot for the given key data if the key is live
+    fn live_index(&self, key_data: &SlotMapKeyData) -> Option<usize> {
+        let index = linear_index(key_data);
+
+        Some(index)
+            .filter(|index| *index < self.slot_count())
+            .filter(|_| key_data.is_filled())
+            .filter(|index| {
+                SlotMapKeyData::from(self.key(*index)).generation
+                    == key_data.generation
+            })
+    }
+
+    /// Double the number of chunks in the backing file and remap it
+    fn grow(&mut self) -> Result<(), Error> {
+        let new_capacity = self.chunk_capacity() * 2;
+
+        self.map.flush()?;
+        self.file.set_len(self.layout.file_len(new_capacity))?;
+
+        // Safety - See create
+        self.map = unsafe { MmapMut::map_mut(&self.file)? };
+        self.set_header_field(CHUNK_CAPACITY_OFFSET, new_capacity as u64);
+
+        Ok(())
+    }
+
+    fn slot_count(&self) -> usize {
+        self.header_field(SLOT_COUNT_OFFSET) as usize
+    }
+
+    fn header_field(&self, offset: usize) -> u64 {
+        bytemuck::pod_read_unaligned(&self.map[offset..offset + 8])
+    }
+
+    fn set_header_field(&mut self, offset: usize, value: u64) {
+        self.map[offset..offset + 8]
+            .copy_from_slice(bytemuck::bytes_of(&value));
+    }
+
+    /// Byte offset of the start of the chunk containing the given slot
+    fn chunk_offset(&self, index: usize) -> usize {
+        self.layout.data_offset
+            + (index / SLOT_MAP_CHUNK_SIZE) * self.layout.stride
+    }
+
+    fn key_offset(&self, index: usize) -> usize {
+        self.chunk_offset(index)
+            + (index % SLOT_MAP_CHUNK_SIZE) * size_of::<u64>()
+    }
+
+    fn value_offset(&self, index: usize) -> usize {
+        self.chunk_offset(index)
+            + self.layout.values_offset
+            + (index % SLOT_MAP_CHUNK_SIZE) * size_of::<T>()
+    }
+
+    fn key(&self, index: usize) -> u64 {
+        let offset = self.key_offset(index);
+        *bytemuck::from_bytes(&self.map[offset..offset + size_of::<u64>()])
+    }
+
+    fn key_mut(&mut self, index: usize) -> &mut u64 {
+        let offset = self.key_offset(index);
+        bytemuck::from_bytes_mut(
+            &mut self.map[offset..offset + size_of::<u64>()],
+        )
+    }
+
+    fn value(&self, index: usize) -> &T {
+        let offset = self.value_offset(index);
+        bytemuck::from_bytes(&self.map[offset..offset + size_of::<T>()])
+    }
+
+    fn value_mut(&mut self, index: usize) -> &mut T {
+        let offset = self.value_offset(index);
+        bytemuck::from_bytes_mut(&mut self.map[offset..offset + size_of::<T>()])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use std::borrow::Borrow;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "one-way-{}-{}.slots",
+            name,
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn test_crud_across_reopen() {
+        let path = temp_path("crud");
+        let insertions = SLOT_MAP_CHUNK_SIZE * 5 + 3;
+
+        let keys = {
+            let mut map =
+                MmapSlotMap::<TestKey, usize, [u32; 3]>::create(&path).unwrap();
+
+            let keys = (0..insertions)
+                .map(|i| map.insert(i, [i as u32; 3]).unwrap())
+                .collect::<Vec<_>>();
+
+            for k in keys.iter().step_by(2) {
+                assert_eq!(Some(&mut [k.pointer as u32; 3]), map.remove(k));
+            }
+
+            map.flush().unwrap();
+            keys
+        };
+
+        let mut map =
+            MmapSlotMap::<TestKey, usize, [u32; 3]>::open(&path).unwrap();
+
+        assert_eq!(insertions / 2, map.len());
+        assert_eq!(8, map.chunk_capacity());
+
+        for (i, k) in keys.iter().enumerate() {
+            let expected = (i % 2 == 1).then_some([i as u32; 3]);
+            assert_eq!(expected.as_ref(), map.get(k));
+        }
+
+        // Removed slots are reused before any new slots are written
+        let reused: SlotMapKeyData = *map.insert(0, [7; 3]).unwrap().borrow();
+        let last_removed: &SlotMapKeyData = keys[insertions - 1].borrow();
+
+        assert_eq!(linear_index(last_removed), linear_index(&reused));
+        assert_eq!(insertions / 2 + 1, map.values().count());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_open_rejects_other_value_types() {
+        let path = temp_path("types");
+
+        let _ = MmapSlotMap::<TestKey, usize, u64>::create(&path).unwrap();
+
+        assert_eq!(
+            ErrorKind::InvalidData,
+            MmapSlotMap::<TestKey, usize, u32>::open(&path)
+                .unwrap_err()
+                .kind()
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
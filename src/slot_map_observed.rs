@@ -0,0 +1,309 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// Change made to an [`ObservedSlotMap`]. Events carry the raw key data of
+/// the affected item, which can be compared with the key data borrowed from
+/// a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SlotMapEvent {
+    /// An item was inserted under the given key data
+    Inserted(SlotMapKeyData),
+
+    /// The item for the given key data was removed
+    Removed(SlotMapKeyData),
+
+    /// The value for the given key data was replaced or modified
+    Replaced(SlotMapKeyData),
+}
+
+/// Handle for a listener registered with an [`ObservedSlotMap`], used to
+/// unsubscribe it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+/// Destination for the events of an observed map
+enum Listener {
+    Callback(Box<dyn FnMut(SlotMapEvent) + Send>),
+    Channel(Sender<SlotMapEvent>),
+}
+
+/// Slot map wrapper that notifies registered listeners of every change, so
+/// caches and indexes built on top of the map can be kept up to date without
+/// relying on every call site. Values can only be changed through
+/// [`ObservedSlotMap::replace`] and [`ObservedSlotMap::update`], because
+/// changes made through a mutable reference could not be announced
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # use std::borrow::Borrow;
+/// # define_key_type!(TestKey<()>);
+/// let mut map = ObservedSlotMap::<TestKey, (), &'static str>::new();
+/// let events = map.subscribe_channel();
+///
+/// let key = map.insert((), "Hello");
+/// let _ = map.replace(&key, "World");
+/// let _ = map.remove(&key);
+///
+/// let key_data = *key.borrow();
+///
+/// assert_eq!(
+///     vec![
+///         SlotMapEvent::Inserted(key_data),
+///         SlotMapEvent::Replaced(key_data),
+///         SlotMapEvent::Removed(key_data),
+///     ],
+///     events.try_iter().collect::<Vec<_>>()
+/// );
+/// ```
+pub struct ObservedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, T>,
+    listeners: Vec<(SubscriptionId, Listener)>,
+    next_subscription: u64,
+}
+
+impl<K, P, T> std::fmt::Debug for ObservedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ObservedSlotMap")
+            .field("map", &self.map)
+            .field("listeners", &self.listeners.len())
+            .finish()
+    }
+}
+
+impl<K, P, T> Default for ObservedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        ObservedSlotMap::new()
+    }
+}
+
+impl<K, P, T> From<SlotMap<K, P, T>> for ObservedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn from(map: SlotMap<K, P, T>) -> Self {
+        ObservedSlotMap {
+            map,
+            listeners: Vec::new(),
+            next_subscription: 0,
+        }
+    }
+}
+
+impl<K, P, T> ObservedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create an empty map with no listeners
+    pub fn new() -> Self {
+        SlotMap::new().into()
+    }
+
+    /// Call the given function with every future event
+    pub fn subscribe(
+        &mut self,
+        listener: impl FnMut(SlotMapEvent) + Send + 'static,
+    ) -> SubscriptionId {
+        self.add_listener(Listener::Callback(Box::new(listener)))
+    }
+
+    /// Send every future event to the given channel. The sender is dropped
+    /// once its receiver is gone
+    pub fn subscribe_sender(
+        &mut self,
+        sender: Sender<SlotMapEvent>,
+    ) -> SubscriptionId {
+        self.add_listener(Listener::Channel(sender))
+    }
+
+    /// Create a channel that receives every future event
+    pub fn subscribe_channel(&mut self) -> Receiver<SlotMapEvent> {
+        let (sender, receiver) = channel();
+        let _ = self.subscribe_sender(sender);
+        receiver
+    }
+
+    /// Stop notifying the given listener, returning whether it was still
+    /// subscribed
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let before = self.listeners.len();
+        self.listeners.retain(|(listener_id, _)| *listener_id != id);
+        self.listeners.len() != before
+    }
+
+    /// Get read access to the underlying map
+    pub fn map(&self) -> &SlotMap<K, P, T> {
+        &self.map
+    }
+
+    /// Stop observing the map and get it back
+    pub fn into_map(self) -> SlotMap<K, P, T> {
+        self.map
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Get a reference to the item for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.map.get(key)
+    }
+
+    /// Check to see if the given key is still valid in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Insert the given value and announce it
+    pub fn insert(&mut self, pointer: P, value: T) -> K {
+        let key_data = self.map.insert_raw(value);
+        self.notify(SlotMapEvent::Inserted(key_data));
+
+        K::from((pointer, key_data))
+    }
+
+    /// Remove the item for the given key and announce the removal if it was
+    /// present
+    pub fn remove(&mut self, key: &K) -> Option<&mut T> {
+        let key_data = *key.borrow();
+
+        if !self.map.contains_key_raw(&key_data) {
+            return None;
+        }
+
+        self.notify(SlotMapEvent::Removed(key_data));
+        self.map.remove_raw(&key_data)
+    }
+
+    /// Replace the value for the given key and announce it, returning the
+    /// previous value. Nothing is announced and the given value is dropped if
+    /// the key is not present
+    pub fn replace(&mut self, key: &K, value: T) -> Option<T> {
+        self.update(key, |slot| std::mem::replace(slot, value))
+    }
+
+    /// Call the given function with a mutable reference to the value for the
+    /// given key and announce the change, if the key is present
+    pub fn update<R>(
+        &mut self,
+        key: &K,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> Option<R> {
+        let key_data = *key.borrow();
+        let result = self.map.get_mut_raw(&key_data).map(f)?;
+
+        self.notify(SlotMapEvent::Replaced(key_data));
+        Some(result)
+    }
+
+    /// Remove all items from the map, announcing each removal
+    pub fn clear(&mut self) {
+        let removed = self
+            .map
+            .iter_raw()
+            .map(|(key_data, _)| key_data)
+            .collect::<Vec<_>>();
+
+        self.map.clear();
+
+        for key_data in removed {
+            self.notify(SlotMapEvent::Removed(key_data));
+        }
+    }
+
+    /// Register the given listener
+    fn add_listener(&mut self, listener: Listener) -> SubscriptionId {
+        let id = SubscriptionId(self.next_subscription);
+        self.next_subscription += 1;
+        self.listeners.push((id, listener));
+        id
+    }
+
+    /// Deliver the given event to every listener, dropping channels whose
+    /// receivers are gone
+    fn notify(&mut self, event: SlotMapEvent) {
+        self.listeners.retain_mut(|(_, listener)| match listener {
+            Listener::Callback(callback) => {
+                callback(event);
+                true
+            }
+            Listener::Channel(sender) => sender.send(event).is_ok(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use std::borrow::Borrow;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_listeners() {
+        let mut map = ObservedSlotMap::<TestKey, usize, usize>::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        let callback = {
+            let seen = seen.clone();
+            map.subscribe(move |event| seen.lock().unwrap().push(event))
+        };
+        let dropped = map.subscribe_channel();
+        drop(dropped);
+
+        let keys = (0..3).map(|i| map.insert(i, i)).collect::<Vec<_>>();
+        let key_data = keys.iter().map(|k| *k.borrow()).collect::<Vec<_>>();
+
+        // The channel whose receiver is gone is dropped on the first event
+        assert_eq!(1, map.listeners.len());
+
+        assert_eq!(Some(1), map.update(&keys[1], |v| std::mem::replace(v, 10)));
+        assert!(map.remove(&keys[0]).is_some());
+        assert!(map.remove(&keys[0]).is_none());
+        assert_eq!(None, map.replace(&keys[0], 5));
+
+        let channel = map.subscribe_channel();
+        map.clear();
+
+        assert_eq!(
+            vec![
+                SlotMapEvent::Inserted(key_data[0]),
+                SlotMapEvent::Inserted(key_data[1]),
+                SlotMapEvent::Inserted(key_data[2]),
+                SlotMapEvent::Replaced(key_data[1]),
+                SlotMapEvent::Removed(key_data[0]),
+                SlotMapEvent::Removed(key_data[1]),
+                SlotMapEvent::Removed(key_data[2]),
+            ],
+            *seen.lock().unwrap()
+        );
+        assert_eq!(
+            vec![
+                SlotMapEvent::Removed(key_data[1]),
+                SlotMapEvent::Removed(key_data[2]),
+            ],
+            channel.try_iter().collect::<Vec<_>>()
+        );
+
+        assert!(map.unsubscribe(callback));
+        assert!(!map.unsubscribe(callback));
+        let _ = map.insert(0, 0);
+        assert_eq!(7, seen.lock().unwrap().len());
+    }
+}
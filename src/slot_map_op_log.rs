@@ -0,0 +1,412 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+use std::io::{Error, ErrorKind, Read, Write};
+
+const INSERT_TAG: u8 = 1;
+const REMOVE_TAG: u8 = 2;
+const REPLACE_TAG: u8 = 3;
+const CLEAR_TAG: u8 = 4;
+
+/// Values that can be written to and read back from an operation log. Values
+/// are written without any framing, so implementations must be able to tell
+/// where their own encoding ends
+pub trait OpLogValue: Sized {
+    /// Append the encoding of this value to the given sink
+    fn write_to(&self, sink: &mut dyn Write) -> Result<(), Error>;
+
+    /// Read a value previously written with `write_to`
+    fn read_from(source: &mut dyn Read) -> Result<Self, Error>;
+}
+
+macro_rules! impl_op_log_value_for_numbers (
+    ($($number_type:ty),*) => {
+        $(
+            impl OpLogValue for $number_type {
+                fn write_to(&self, sink: &mut dyn Write) -> Result<(), Error> {
+                    sink.write_all(&self.to_le_bytes())
+                }
+
+                fn read_from(source: &mut dyn Read) -> Result<Self, Error> {
+                    let mut bytes = [0u8; std::mem::size_of::<$number_type>()];
+                    source.read_exact(&mut bytes)?;
+                    Ok(<$number_type>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+);
+
+impl_op_log_value_for_numbers!(
+    u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64
+);
+
+impl OpLogValue for bool {
+    fn write_to(&self, sink: &mut dyn Write) -> Result<(), Error> {
+        (*self as u8).write_to(sink)
+    }
+
+    fn read_from(source: &mut dyn Read) -> Result<Self, Error> {
+        match u8::read_from(source)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::new(ErrorKind::InvalidData, "invalid bool")),
+        }
+    }
+}
+
+impl OpLogValue for Vec<u8> {
+    fn write_to(&self, sink: &mut dyn Write) -> Result<(), Error> {
+        (self.len() as u64).write_to(sink)?;
+        sink.write_all(self)
+    }
+
+    fn read_from(source: &mut dyn Read) -> Result<Self, Error> {
+        let len = u64::read_from(source)?;
+        let mut bytes = Vec::new();
+
+        source.take(len).read_to_end(&mut bytes)?;
+
+        if bytes.len() as u64 != len {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+
+        Ok(bytes)
+    }
+}
+
+impl OpLogValue for String {
+    fn write_to(&self, sink: &mut dyn Write) -> Result<(), Error> {
+        (self.len() as u64).write_to(sink)?;
+        sink.write_all(self.as_bytes())
+    }
+
+    fn read_from(source: &mut dyn Read) -> Result<Self, Error> {
+        String::from_utf8(Vec::<u8>::read_from(source)?)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+}
+
+/// Slot map wrapper that appends every mutating operation to a sink before
+/// applying it, so the map can be reconstructed with [`SlotMap::replay`] after
+/// a crash. Values can only be changed through [`LoggedSlotMap::replace`],
+/// because changes made through a mutable reference could not be logged
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()>);
+/// let mut map = LoggedSlotMap::<TestKey, (), String, _>::new(Vec::new());
+///
+/// let hello = map.insert((), "Hello".to_owned()).unwrap();
+/// let world = map.insert((), "World".to_owned()).unwrap();
+///
+/// map.replace(&hello, "Goodbye".to_owned()).unwrap();
+/// map.remove(&world).unwrap();
+///
+/// let (_, log) = map.into_parts();
+/// let recovered = SlotMap::<TestKey, (), String>::replay(&log[..]).unwrap();
+///
+/// assert_eq!(Some(&"Goodbye".to_owned()), recovered.get(&hello));
+/// assert_eq!(None, recovered.get(&world));
+/// ```
+#[derive(Debug)]
+pub struct LoggedSlotMap<K, P, T, W>
+where
+    K: SlotMapKey<P>,
+    T: OpLogValue,
+    W: Write,
+{
+    map: SlotMap<K, P, T>,
+    sink: W,
+}
+
+impl<K, P, T, W> LoggedSlotMap<K, P, T, W>
+where
+    K: SlotMapKey<P>,
+    T: OpLogValue,
+    W: Write,
+{
+    /// Create an empty map that logs to the given sink
+    pub fn new(sink: W) -> Self {
+        LoggedSlotMap::from_map(SlotMap::new(), sink)
+    }
+
+    /// Continue logging operations on an existing map. The sink should
+    /// already contain the operations that produced the given map (as it
+    /// does when the map was recovered with [`SlotMap::replay`]), or
+    /// replaying the sink will not reproduce the map
+    pub fn from_map(map: SlotMap<K, P, T>, sink: W) -> Self {
+        LoggedSlotMap { map, sink }
+    }
+
+    /// Get read access to the underlying map
+    pub fn map(&self) -> &SlotMap<K, P, T> {
+        &self.map
+    }
+
+    /// Get access to the sink operations are logged to
+    pub fn sink_mut(&mut self) -> &mut W {
+        &mut self.sink
+    }
+
+    /// Split this wrapper into the map and the sink
+    pub fn into_parts(self) -> (SlotMap<K, P, T>, W) {
+        (self.map, self.sink)
+    }
+
+    /// Flush the sink
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.sink.flush()
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Get a reference to the item for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.map.get(key)
+    }
+
+    /// Check to see if the given key is still valid in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Log an insertion and then insert the given value
+    pub fn insert(&mut self, pointer: P, value: T) -> Result<K, Error> {
+        let key_data = self.map.next_key_data();
+
+        self.sink.write_all(&[INSERT_TAG])?;
+        u64::from(key_data).write_to(&mut self.sink)?;
+        value.write_to(&mut self.sink)?;
+
+        Ok(self.map.insert(pointer, value))
+    }
+
+    /// Log a removal and then remove the item for the given key. Nothing is
+    /// logged if the key is not present
+    pub fn remove(&mut self, key: &K) -> Result<Option<&mut T>, Error> {
+        let key_data: &SlotMapKeyData = key.borrow();
+
+        if self.map.get_raw(key_data).is_some() {
+            self.sink.write_all(&[REMOVE_TAG])?;
+            u64::from(*key_data).write_to(&mut self.sink)?;
+        }
+
+        Ok(self.map.remove_raw(key_data))
+    }
+
+    /// Log a replacement and then replace the value for the given key,
+    /// returning the previous value. Nothing is logged and the given value is
+    /// dropped if the key is not present
+    pub fn replace(&mut self, key: &K, value: T) -> Result<Option<T>, Error> {
+        let key_data: &SlotMapKeyData = key.borrow();
+
+        if self.map.get_raw(key_data).is_none() {
+            return Ok(None);
+        }
+
+        self.sink.write_all(&[REPLACE_TAG])?;
+        u64::from(*key_data).write_to(&mut self.sink)?;
+        value.write_to(&mut self.sink)?;
+
+        Ok(self
+            .map
+            .get_mut_raw(key_data)
+            .map(|slot| std::mem::replace(slot, value)))
+    }
+
+    /// Log a clear and then remove all items from the map
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.sink.write_all(&[CLEAR_TAG])?;
+        self.map.clear();
+        Ok(())
+    }
+}
+
+/// Read the key data of a logged operation
+fn read_key_data(source: &mut dyn Read) -> Result<SlotMapKeyData, Error> {
+    u64::read_from(source).map(SlotMapKeyData::from)
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: OpLogValue,
+{
+    /// Reconstruct a map by applying all the operations written by a
+    /// [`LoggedSlotMap`]. A final operation that was only partially written
+    /// (for example because of a crash mid-write) is ignored. Any operation
+    /// that does not apply cleanly to the map is reported as invalid data
+    pub fn replay(mut source: impl Read) -> Result<SlotMap<K, P, T>, Error> {
+        let mut map = SlotMap::new();
+
+        loop {
+            let mut tag = [0u8];
+
+            if source.read(&mut tag)? == 0 {
+                return Ok(map);
+            }
+
+            match map.replay_operation(tag[0], &mut source) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                    return Ok(map)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Apply a single logged operation with the given tag
+    fn replay_operation(
+        &mut self,
+        tag: u8,
+        source: &mut dyn Read,
+    ) -> Result<(), Error> {
+        let diverged = |operation| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "logged {} does not apply to the replayed map",
+                    operation
+                ),
+            )
+        };
+
+        match tag {
+            INSERT_TAG => {
+                let key_data = read_key_data(source)?;
+                let value = T::read_from(source)?;
+
+                if self.insert_raw(value) != key_data {
+                    return Err(diverged("insert"));
+                }
+            }
+            REMOVE_TAG => {
+                let key_data = read_key_data(source)?;
+
+                if self.remove_raw(&key_data).is_none() {
+                    return Err(diverged("remove"));
+                }
+            }
+            REPLACE_TAG => {
+                let key_data = read_key_data(source)?;
+                let value = T::read_from(source)?;
+
+                *self
+                    .get_mut_raw(&key_data)
+                    .ok_or_else(|| diverged("replace"))? = value;
+            }
+            CLEAR_TAG => self.clear(),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("unknown operation tag {}", tag),
+                ))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use crate::SLOT_MAP_CHUNK_SIZE;
+
+    #[test]
+    fn test_replay_reproduces_map() {
+        let mut map =
+            LoggedSlotMap::<TestKey, usize, String, _>::new(Vec::new());
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 2 + 5)
+            .map(|i| map.insert(i, format!("{}", i)).unwrap())
+            .collect::<Vec<_>>();
+
+        for k in keys.iter().step_by(3) {
+            assert!(map.remove(k).unwrap().is_some());
+        }
+
+        for k in keys.iter().skip(1).step_by(3) {
+            let old =
+                map.replace(k, format!("{} replaced", k.pointer)).unwrap();
+            assert_eq!(Some(format!("{}", k.pointer)), old);
+        }
+
+        let later = (0..10)
+            .map(|i| map.insert(i, format!("later {}", i)).unwrap())
+            .collect::<Vec<_>>();
+
+        // Operations on missing keys are not logged
+        assert!(map.remove(&keys[0]).unwrap().is_none());
+        assert_eq!(None, map.replace(&keys[0], String::new()).unwrap());
+
+        let (original, log) = map.into_parts();
+        let mut recovered =
+            SlotMap::<TestKey, usize, String>::replay(&log[..]).unwrap();
+
+        assert_eq!(original.len(), recovered.len());
+
+        for k in keys.iter().chain(later.iter()) {
+            assert_eq!(original.get(k), recovered.get(k));
+        }
+
+        // Recovered maps keep logging from where the original left off
+        let mut resumed = LoggedSlotMap::from_map(recovered, log);
+        let resumed_key = resumed.insert(0, "resumed".to_owned()).unwrap();
+        resumed.clear().unwrap();
+
+        let (_, log) = resumed.into_parts();
+        recovered = SlotMap::replay(&log[..]).unwrap();
+
+        assert!(recovered.is_empty());
+        assert!(!recovered.contains_key(&resumed_key));
+    }
+
+    #[test]
+    fn test_torn_tail_is_ignored() {
+        let mut map = LoggedSlotMap::<TestKey, usize, u64, _>::new(Vec::new());
+
+        let first = map.insert(0, 1).unwrap();
+        let _ = map.insert(1, 2).unwrap();
+
+        let (_, mut log) = map.into_parts();
+        log.truncate(log.len() - 3);
+
+        let recovered =
+            SlotMap::<TestKey, usize, u64>::replay(&log[..]).unwrap();
+
+        assert_eq!(1, recovered.len());
+        assert_eq!(Some(&1), recovered.get(&first));
+    }
+
+    #[test]
+    fn test_divergent_log_is_rejected() {
+        let mut log = Vec::new();
+
+        log.push(REMOVE_TAG);
+        0u64.write_to(&mut log).unwrap();
+
+        assert_eq!(
+            ErrorKind::InvalidData,
+            SlotMap::<TestKey, usize, u64>::replay(&log[..])
+                .unwrap_err()
+                .kind()
+        );
+
+        assert_eq!(
+            ErrorKind::InvalidData,
+            SlotMap::<TestKey, usize, u64>::replay(&[42u8][..])
+                .unwrap_err()
+                .kind()
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+use std::collections::HashMap;
+
+/// Slot map that mirrors every operation into a plain `HashMap` model keyed
+/// by slot map key data and panics as soon as the two disagree, either in
+/// what an operation returns or in the contents of the maps afterward. Meant
+/// for differential testing and fuzzing of code built on top of
+/// [`SlotMap`], not for production use, since every operation is checked
+/// against the whole model
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # use one_way_slot_map::testing::OracleMap;
+/// # define_key_type!(TestKey<()>);
+/// let mut map = OracleMap::<TestKey, (), usize>::new();
+///
+/// let key = map.insert((), 5);
+/// map.update(&key, |value| *value += 1);
+///
+/// assert_eq!(Some(&6), map.get(&key));
+/// assert_eq!(Some(6), map.remove(&key));
+/// assert_eq!(None, map.get(&key));
+/// ```
+pub struct OracleMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, T>,
+    model: HashMap<SlotMapKeyData, T>,
+}
+
+impl<K, P, T> std::fmt::Debug for OracleMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OracleMap")
+            .field("map", &self.map)
+            .field("model", &self.model)
+            .finish()
+    }
+}
+
+impl<K, P, T> Default for OracleMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        OracleMap {
+            map: SlotMap::new(),
+            model: HashMap::new(),
+        }
+    }
+}
+
+impl<K, P, T> Clone for OracleMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        OracleMap {
+            map: self.map.clone(),
+            model: self.model.clone(),
+        }
+    }
+}
+
+impl<K, P, T> OracleMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Clone + PartialEq + std::fmt::Debug,
+{
+    /// Create a new empty map and model
+    pub fn new() -> OracleMap<K, P, T> {
+        OracleMap::default()
+    }
+
+    /// Get the slot map being checked
+    pub fn map(&self) -> &SlotMap<K, P, T> {
+        &self.map
+    }
+
+    /// Get the model the slot map is checked against
+    pub fn model(&self) -> &HashMap<SlotMapKeyData, T> {
+        &self.model
+    }
+
+    /// Get the slot map being checked, dropping the model
+    pub fn into_inner(self) -> SlotMap<K, P, T> {
+        self.map
+    }
+
+    /// Get the number of items in the map
+    #[track_caller]
+    pub fn len(&self) -> usize {
+        assert_eq!(self.model.len(), self.map.len(), "len");
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    #[track_caller]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Insert the given item into both maps, checking that the key the slot
+    /// map returns isn't already in use
+    #[track_caller]
+    pub fn insert(&mut self, pointer: P, value: T) -> K {
+        let key = self.map.insert(pointer, value.clone());
+        let key_data = *key.borrow();
+
+        assert!(
+            self.model.insert(key_data, value).is_none(),
+            "insert returned key {:?}, which is already in use",
+            key_data
+        );
+
+        self.assert_consistent();
+        key
+    }
+
+    /// Get a reference to the item for the given key, checking that both
+    /// maps find the same item
+    #[track_caller]
+    pub fn get(&self, key: &K) -> Option<&T> {
+        let key_data = key.borrow();
+        let found = self.map.get(key);
+
+        assert_eq!(self.model.get(key_data), found, "get {:?}", key_data);
+        found
+    }
+
+    /// Tells if the given key is in the map, checking that both maps agree
+    #[track_caller]
+    pub fn contains_key(&self, key: &K) -> bool {
+        let key_data = key.borrow();
+        let found = self.map.contains_key(key);
+
+        assert_eq!(
+            self.model.contains_key(key_data),
+            found,
+            "contains_key {:?}",
+            key_data
+        );
+        found
+    }
+
+    /// Apply the given change to the item for the given key in both maps, and
+    /// tell if there was an item to change
+    #[track_caller]
+    pub fn update(&mut self, key: &K, mut change: impl FnMut(&mut T)) -> bool {
+        let key_data = *key.borrow();
+
+        let updated =
+            match (self.map.get_mut(key), self.model.get_mut(&key_data)) {
+                (Some(value), Some(expected)) => {
+                    change(value);
+                    change(expected);
+                    true
+                }
+                (None, None) => false,
+                (found, expected) => panic!(
+                    "get_mut {:?}: expected {:?} but found {:?}",
+                    key_data, expected, found
+                ),
+            };
+
+        self.assert_consistent();
+        updated
+    }
+
+    /// Remove the item for the given key from both maps, checking that both
+    /// removed the same item, and return a copy of it
+    #[track_caller]
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        let key_data = *key.borrow();
+        let removed = self.map.remove(key).cloned();
+
+        assert_eq!(
+            self.model.remove(&key_data),
+            removed,
+            "remove {:?}",
+            key_data
+        );
+
+        self.assert_consistent();
+        removed
+    }
+
+    /// Remove all items from both maps
+    #[track_caller]
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.model.clear();
+        self.assert_consistent();
+    }
+
+    /// Check that the slot map holds exactly the items in the model, under the
+    /// same key data
+    #[track_caller]
+    pub fn assert_consistent(&self) {
+        assert_eq!(self.model.len(), self.map.len(), "len");
+
+        let mut seen = 0;
+
+        for (key_data, value) in self.map.iter_raw() {
+            assert_eq!(
+                self.model.get(&key_data),
+                Some(value),
+                "item at {:?}",
+                key_data
+            );
+            seen += 1;
+        }
+
+        assert_eq!(self.model.len(), seen, "number of items iterated");
+
+        for (key_data, expected) in &self.model {
+            assert_eq!(
+                Some(expected),
+                self.map.get_raw(key_data),
+                "get_raw {:?}",
+                key_data
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn test_random_operations_agree_with_model() {
+        let mut rng = thread_rng();
+        let mut map = OracleMap::<TestKey, usize, usize>::new();
+        let mut keys = Vec::<TestKey>::new();
+
+        for i in 0..3000 {
+            let target =
+                (!keys.is_empty()).then(|| rng.gen_range(0..keys.len()));
+
+            match (rng.gen_range(0..6), target) {
+                (0, _) | (_, None) => keys.push(map.insert(i, i)),
+                (1, Some(t)) => {
+                    let _ = map.get(&keys[t]);
+                }
+                (2, Some(t)) => {
+                    let _ = map.update(&keys[t], |value| *value += 1);
+                }
+                (3, Some(t)) => {
+                    // Stale keys are kept around half the time
+                    let _ = map.remove(&keys[t]);
+                    if rng.gen_bool(0.5) {
+                        let _ = keys.swap_remove(t);
+                    }
+                }
+                (4, Some(t)) => {
+                    let _ = map.contains_key(&keys[t]);
+                }
+                (_, Some(_)) if i % 1000 == 999 => map.clear(),
+                (_, Some(_)) => {
+                    let _ = map.len();
+                }
+            }
+        }
+
+        map.assert_consistent();
+    }
+
+    #[test]
+    #[should_panic(expected = "get")]
+    fn test_disagreement_panics() {
+        let mut map = OracleMap::<TestKey, usize, usize>::new();
+        let key = map.insert(0, 0);
+
+        // Change the slot map behind the model's back
+        *map.map.get_mut(&key).unwrap() = 1;
+
+        let _ = map.get(&key);
+    }
+}
@@ -0,0 +1,140 @@
+use super::slot_map_key_data::{
+    CHUNK_INDEX_BITS, CHUNK_INDEX_SHIFT, GENERATION_BITS, GENERATION_SHIFT,
+    INDEX_IN_CHUNK_BITS,
+};
+use super::SlotMapKeyData;
+
+/// Key data in its packed `u64` form, laid out exactly like a `u64`. With
+/// the `bytemuck` feature this is `Pod`, so slices of keys can be cast to
+/// bytes for GPU buffers or binary files and back without any unsafe code
+///
+/// The layout is guaranteed, so C and C++ code can take keys apart without
+/// calling back into Rust. From the least significant bit up, the packed
+/// value holds:
+///
+/// | Bits    | Field            |
+/// |---------|------------------|
+/// | 0 - 7   | index in chunk   |
+/// | 8 - 39  | chunk index      |
+/// | 40 - 63 | generation       |
+///
+/// The slot's position in the map is `chunk_index * 256 + index_in_chunk`,
+/// or simply the low 40 bits, and keys for items always have even
+/// generations
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()>);
+/// # use std::borrow::Borrow;
+/// let mut map = SlotMap::<TestKey, (), &str>::new();
+/// let key = map.insert((), "mesh");
+///
+/// let packed = PackedKeyData::from(*Borrow::<SlotMapKeyData>::borrow(&key));
+///
+/// assert_eq!(Some(&"mesh"), map.get_raw(&packed.into()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(transparent)]
+pub struct PackedKeyData(pub u64);
+
+// The layout promised above, checked at compile time
+const _: () = {
+    assert!(std::mem::size_of::<PackedKeyData>() == 8);
+    assert!(std::mem::align_of::<PackedKeyData>() == 8);
+    assert!(PackedKeyData::INDEX_IN_CHUNK_BITS == 8);
+    assert!(PackedKeyData::CHUNK_INDEX_SHIFT == 8);
+    assert!(PackedKeyData::CHUNK_INDEX_BITS == 32);
+    assert!(PackedKeyData::GENERATION_SHIFT == 40);
+    assert!(PackedKeyData::GENERATION_BITS == 24);
+};
+
+impl PackedKeyData {
+    /// Number of bits holding the index in chunk, starting at bit 0
+    pub const INDEX_IN_CHUNK_BITS: u32 = INDEX_IN_CHUNK_BITS as u32;
+
+    /// Position of the lowest bit of the chunk index
+    pub const CHUNK_INDEX_SHIFT: u32 = CHUNK_INDEX_SHIFT as u32;
+
+    /// Number of bits holding the chunk index
+    pub const CHUNK_INDEX_BITS: u32 = CHUNK_INDEX_BITS as u32;
+
+    /// Position of the lowest bit of the generation
+    pub const GENERATION_SHIFT: u32 = GENERATION_SHIFT as u32;
+
+    /// Number of bits holding the generation, which run to the top bit
+    pub const GENERATION_BITS: u32 = GENERATION_BITS as u32;
+
+    /// Get the index within its chunk of the slot this names
+    pub const fn index_in_chunk(self) -> u16 {
+        (self.0 & ((1 << Self::INDEX_IN_CHUNK_BITS) - 1)) as u16
+    }
+
+    /// Get the index of the chunk containing the slot this names
+    pub const fn chunk_index(self) -> u32 {
+        (self.0 >> Self::CHUNK_INDEX_SHIFT) as u32
+    }
+
+    /// Get the generation of the slot this names
+    pub const fn generation(self) -> u32 {
+        (self.0 >> Self::GENERATION_SHIFT) as u32
+    }
+}
+
+impl From<SlotMapKeyData> for PackedKeyData {
+    fn from(key_data: SlotMapKeyData) -> Self {
+        PackedKeyData(u64::from(key_data))
+    }
+}
+
+impl From<PackedKeyData> for SlotMapKeyData {
+    fn from(packed: PackedKeyData) -> Self {
+        SlotMapKeyData::from(packed.0)
+    }
+}
+
+// Safety - PackedKeyData is a transparent wrapper around a u64, so it has no
+// padding, and every bit pattern, including all zeros, is a valid value
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Zeroable for PackedKeyData {}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Pod for PackedKeyData {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::churned_map;
+    use std::borrow::Borrow;
+
+    #[test]
+    fn test_packed_keys_find_items() {
+        let (map, keys, _) = churned_map(6, |i| i);
+
+        let packed = keys
+            .iter()
+            .map(|key| {
+                PackedKeyData::from(*Borrow::<SlotMapKeyData>::borrow(key))
+            })
+            .collect::<Vec<_>>();
+
+        #[cfg(feature = "bytemuck")]
+        let packed = {
+            let bytes: &[u8] = bytemuck::cast_slice(&packed);
+            assert_eq!(packed.len() * 8, bytes.len());
+
+            bytes
+                .chunks(8)
+                .map(bytemuck::pod_read_unaligned::<PackedKeyData>)
+                .collect::<Vec<_>>()
+        };
+
+        for (key, packed) in keys.iter().zip(packed) {
+            let key_data = Borrow::<SlotMapKeyData>::borrow(key);
+
+            assert_eq!(key_data.chunk_index, packed.chunk_index());
+            assert_eq!(key_data.index_in_chunk, packed.index_in_chunk());
+            assert_eq!(key_data.generation, packed.generation());
+            assert_eq!(Some(&key.pointer), map.get_raw(&packed.into()));
+        }
+    }
+}
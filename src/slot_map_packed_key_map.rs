@@ -0,0 +1,129 @@
+use super::slot_map::FilledSlotsError;
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+use serde::de::{Error, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::Formatter;
+use std::marker::PhantomData;
+
+/// Serialize the given map as an ordinary map from the packed `u64` form of
+/// each item's key data to the item. Formats that need string map keys, like
+/// JSON, write the keys as decimal strings
+pub fn serialize<K, P, T, S>(
+    map: &SlotMap<K, P, T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    K: SlotMapKey<P>,
+    T: Serialize,
+    S: Serializer,
+{
+    let mut out = serializer.serialize_map(Some(map.len()))?;
+
+    for (key_data, value) in map.iter_raw() {
+        out.serialize_entry(&u64::from(key_data), value)?;
+    }
+
+    out.end()
+}
+
+/// Deserialize a map written by [`serialize`], or any map from packed key
+/// data to items. Every item is stored in the slot its key names, so keys
+/// from before serialization keep working, and slots between them are left
+/// vacant holding default values
+pub fn deserialize<'de, K, P, T, D>(
+    deserializer: D,
+) -> Result<SlotMap<K, P, T>, D::Error>
+where
+    K: SlotMapKey<P>,
+    T: Deserialize<'de> + Default,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_map(PackedKeyMapVisitor(PhantomData))
+}
+
+struct PackedKeyMapVisitor<K, P, T>(PhantomData<SlotMap<K, P, T>>)
+where
+    K: SlotMapKey<P>;
+
+impl<'de, K, P, T> Visitor<'de> for PackedKeyMapVisitor<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Deserialize<'de> + Default,
+{
+    type Value = SlotMap<K, P, T>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("a map from packed slot map keys to values")
+    }
+
+    fn visit_map<A>(self, mut access: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut items = Vec::with_capacity(access.size_hint().unwrap_or(0));
+
+        while let Some((key, value)) = access.next_entry::<u64, T>()? {
+            items.push((SlotMapKeyData::from(key), value));
+        }
+
+        SlotMap::from_filled_slots(items).map_err(|error| match error {
+            FilledSlotsError::Collision(key_data)
+            | FilledSlotsError::VacantGeneration(key_data) => {
+                A::Error::custom(format!(
+                    "key {} names a vacant or repeated slot",
+                    u64::from(key_data)
+                ))
+            }
+            FilledSlotsError::OutOfRange(key_data) => {
+                A::Error::custom(format!(
+                    "key {} names a slot too far along for the number of items",
+                    u64::from(key_data)
+                ))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::{churned_map, TestKey};
+
+    #[derive(Serialize, Deserialize)]
+    struct World {
+        #[serde(with = "crate::packed_key_map")]
+        entities: SlotMap<TestKey, usize, String>,
+    }
+
+    #[test]
+    fn test_keys_survive_round_trip() {
+        let (entities, keys, _) = churned_map(7, |i| format!("entity {}", i));
+        let world = World { entities };
+
+        let json = serde_json::to_string(&world).unwrap();
+        let loaded = serde_json::from_str::<World>(&json).unwrap();
+
+        assert_eq!(keys.len(), loaded.entities.len());
+
+        for key in &keys {
+            assert_eq!(
+                Some(&format!("entity {}", key.pointer)),
+                loaded.entities.get(key)
+            );
+        }
+
+        let value = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(keys.len(), value["entities"].as_object().unwrap().len());
+
+        // Generations start at bit 40, so this is generation 1 of slot 0
+        assert!(serde_json::from_str::<World>(
+            r#"{ "entities": { "1099511627776": "vacant generation" } }"#
+        )
+        .is_err());
+        assert!(serde_json::from_str::<World>(
+            r#"{ "entities": { "0": "first", "0": "repeated" } }"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,193 @@
+use super::slot_map::FilledSlotsError;
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+use std::fmt::{Display, Formatter};
+
+/// Reasons [`SlotMap::from_pairs`] couldn't place an item at its key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FromPairsError {
+    /// Two items were given keys that name the same slot
+    Collision(u64),
+
+    /// A key had an odd generation, which only vacant slots have, so it
+    /// can't have been the key of an item
+    VacantGeneration(u64),
+
+    /// A key named a slot further along than a map of the given number of
+    /// items may reach, which is a chunk's worth of slots per item
+    OutOfRange(u64),
+}
+
+impl Display for FromPairsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FromPairsError::Collision(key) => {
+                write!(f, "key {} names the same slot as another key", key)
+            }
+            FromPairsError::VacantGeneration(key) => {
+                write!(f, "key {} has the generation of a vacant slot", key)
+            }
+            FromPairsError::OutOfRange(key) => {
+                write!(
+                    f,
+                    "key {} names a slot too far along for the number of items",
+                    key
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for FromPairsError {}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Copy every item in this map into a list of pairs of its packed key
+    /// data and its value, in slot order
+    pub fn to_pairs(&self) -> Vec<(u64, T)>
+    where
+        T: Clone,
+    {
+        self.iter_raw()
+            .map(|(key_data, value)| (u64::from(key_data), value.clone()))
+            .collect()
+    }
+
+    /// Build a map holding each of the given values at exactly the given
+    /// packed key data, so the same keys find them again. Slots between the
+    /// given keys are left vacant holding default values. Keys may name slots
+    /// up to [`SLOT_MAP_CHUNK_SIZE`](crate::SLOT_MAP_CHUNK_SIZE) times the
+    /// number of pairs, so one corrupt key can't make the map allocate
+    /// enormous numbers of slots
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey, (), &str>::new();
+    /// let key = map.insert((), "first");
+    /// let _ = map.insert((), "second");
+    /// let _ = map.remove(&key);
+    ///
+    /// let pairs = map.to_pairs();
+    /// let rebuilt = SlotMap::<TestKey, (), &str>::from_pairs(pairs).unwrap();
+    ///
+    /// assert_eq!(map.to_pairs(), rebuilt.to_pairs());
+    ///
+    /// assert_eq!(
+    ///     Err(FromPairsError::Collision(0)),
+    ///     SlotMap::<TestKey, (), &str>::from_pairs([(0, "a"), (0, "b")])
+    ///         .map(|map| map.len())
+    /// );
+    /// ```
+    pub fn from_pairs(
+        pairs: impl IntoIterator<Item = (u64, T)>,
+    ) -> Result<SlotMap<K, P, T>, FromPairsError>
+    where
+        T: Default,
+    {
+        SlotMap::from_filled_slots(
+            pairs
+                .into_iter()
+                .map(|(key, value)| (SlotMapKeyData::from(key), value))
+                .collect(),
+        )
+        .map_err(|error| match error {
+            FilledSlotsError::Collision(key_data) => {
+                FromPairsError::Collision(u64::from(key_data))
+            }
+            FilledSlotsError::VacantGeneration(key_data) => {
+                FromPairsError::VacantGeneration(u64::from(key_data))
+            }
+            FilledSlotsError::OutOfRange(key_data) => {
+                FromPairsError::OutOfRange(u64::from(key_data))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::{churned_map, TestKey};
+    use crate::SLOT_MAP_CHUNK_SIZE;
+
+    #[test]
+    fn test_pairs_round_trip() {
+        let (map, keys, _) = churned_map(8, |i| i);
+
+        let mut pairs = map.to_pairs();
+        assert_eq!(map.len(), pairs.len());
+
+        // Order of the pairs doesn't matter
+        pairs.reverse();
+
+        let mut rebuilt =
+            SlotMap::<TestKey, usize, usize>::from_pairs(pairs).unwrap();
+
+        assert_eq!(map.to_pairs(), rebuilt.to_pairs());
+
+        for key in &keys {
+            assert_eq!(Some(&key.pointer), rebuilt.get(key));
+        }
+
+        // Every open slot is reused before the map grows
+        let slot_count = rebuilt.slots().slot_count();
+
+        for i in 0..slot_count - rebuilt.len() {
+            let _ = rebuilt.insert(i, i);
+        }
+
+        assert_eq!(slot_count, rebuilt.slots().slot_count());
+
+        // Generations start at bit 40
+        let same_slot = (2u64 << 40) | 7;
+        assert_eq!(
+            Err(FromPairsError::Collision(same_slot)),
+            SlotMap::<TestKey, usize, usize>::from_pairs([
+                (7, 0),
+                (same_slot, 1)
+            ])
+            .map(|map| map.len())
+        );
+        assert_eq!(
+            Err(FromPairsError::VacantGeneration(1 << 40)),
+            SlotMap::<TestKey, usize, usize>::from_pairs([(1 << 40, 0)])
+                .map(|map| map.len())
+        );
+    }
+
+    #[test]
+    fn test_keys_far_past_the_items_are_rejected() {
+        let last = (SLOT_MAP_CHUNK_SIZE * 2 - 1) as u64;
+
+        let map =
+            SlotMap::<TestKey, usize, usize>::from_pairs([(0, 0), (last, 1)])
+                .unwrap();
+        assert_eq!(Some(&1), map.get_raw(&SlotMapKeyData::from(last)));
+
+        assert_eq!(
+            Err(FromPairsError::OutOfRange(last + 1)),
+            SlotMap::<TestKey, usize, usize>::from_pairs([
+                (0, 0),
+                (last + 1, 1)
+            ])
+            .map(|map| map.len())
+        );
+
+        // A chunk index near the end of the coordinates would otherwise need
+        // terabytes of vacant slots
+        let stray = (u32::MAX as u64) << 8;
+        let error = SlotMap::<TestKey, usize, usize>::from_pairs([(stray, 0)])
+            .unwrap_err();
+
+        assert_eq!(FromPairsError::OutOfRange(stray), error);
+        assert_eq!(
+            format!(
+                "key {} names a slot too far along for the number of items",
+                stray
+            ),
+            error.to_string()
+        );
+    }
+}
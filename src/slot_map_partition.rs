@@ -0,0 +1,239 @@
+use super::slot_map::ChunkMut;
+use super::{SlotMap, SlotMapKey, SlotMapKeyData, SLOT_MAP_CHUNK_SIZE};
+use std::marker::PhantomData;
+use std::ops::Range;
+
+/// Mutable access to a contiguous range of chunks of a slot map. The
+/// partitions of a map never share a chunk, so each one can be handed to a
+/// different thread. Created with [`SlotMap::partitions_mut`] or
+/// [`SlotMap::scope_mut`]
+pub struct SlotMapPartition<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    first_chunk: usize,
+    chunks: Vec<ChunkMut<'a, T>>,
+
+    _phantom: PhantomData<fn(P, K)>,
+}
+
+impl<'a, K, P, T> std::fmt::Debug for SlotMapPartition<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SlotMapPartition")
+            .field("chunks", &self.chunk_range())
+            .finish()
+    }
+}
+
+impl<'a, K, P, T> SlotMapPartition<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Get the indices of the chunks in this partition
+    pub fn chunk_range(&self) -> Range<usize> {
+        self.first_chunk..self.first_chunk + self.chunks.len()
+    }
+
+    /// Tells if the given key refers to an item in this partition
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Get a reference to the item for the given key if it exists and is in
+    /// this partition
+    pub fn get(&self, key: &K) -> Option<&T> {
+        let key_data: &SlotMapKeyData = key.borrow();
+
+        self.slot(key_data)
+            .filter(|(k, _)| k.is_filled())
+            .filter(|(k, _)| k.generation == key_data.generation)
+            .map(|(_, value)| value)
+    }
+
+    /// Get a mutable reference to the item for the given key if it exists
+    /// and is in this partition
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        let key_data: &SlotMapKeyData = key.borrow();
+        let chunk = (key_data.chunk_index as usize)
+            .checked_sub(self.first_chunk)
+            .and_then(|i| self.chunks.get_mut(i))?;
+
+        chunk
+            .get_mut(key_data.index_in_chunk as usize)
+            .filter(|(k, _)| k.is_filled())
+            .filter(|(k, _)| k.generation == key_data.generation)
+            .map(|(_, value)| value)
+    }
+
+    /// Create an iterator over the raw key data and mutable references to the
+    /// values of the items in this partition
+    pub fn iter_mut_raw(
+        &mut self,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &mut T)> {
+        let first_chunk = self.first_chunk;
+
+        self.chunks
+            .iter_mut()
+            .map(ChunkMut::reborrow)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .filter(|(_, (k, _))| k.is_filled())
+            .map(move |(i, (k, value))| {
+                let key_data = SlotMapKeyData {
+                    generation: k.generation,
+                    ..SlotMapKeyData::from(
+                        (first_chunk * SLOT_MAP_CHUNK_SIZE + i) as u64,
+                    )
+                };
+
+                (key_data, value)
+            })
+    }
+
+    /// Create an iterator over mutable references to the values of the items
+    /// in this partition
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.iter_mut_raw().map(|(_, value)| value)
+    }
+
+    /// Get the slot at the coordinates in the given key data if it is in this
+    /// partition
+    fn slot(&self, key_data: &SlotMapKeyData) -> Option<(&SlotMapKeyData, &T)> {
+        (key_data.chunk_index as usize)
+            .checked_sub(self.first_chunk)
+            .and_then(|i| self.chunks.get(i))
+            .and_then(|chunk| chunk.get(key_data.index_in_chunk as usize))
+    }
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Split this map into at most the given number of partitions, each
+    /// covering a contiguous range of chunks, that can be modified
+    /// independently. Items can be modified through the partitions but not
+    /// inserted or removed
+    ///
+    /// # Panics
+    /// If the partition count is zero
+    pub fn partitions_mut(
+        &mut self,
+        partition_count: usize,
+    ) -> Vec<SlotMapPartition<'_, K, P, T>> {
+        assert!(partition_count > 0, "at least one partition is needed");
+
+        let mut chunks = self.chunks_mut();
+        let per_partition = chunks.len().div_ceil(partition_count).max(1);
+        let mut partitions = Vec::with_capacity(partition_count);
+        let mut first_chunk = 0;
+
+        while !chunks.is_empty() {
+            let rest = chunks.split_off(per_partition.min(chunks.len()));
+            let partition_chunks = std::mem::replace(&mut chunks, rest);
+            let len = partition_chunks.len();
+
+            partitions.push(SlotMapPartition {
+                first_chunk,
+                chunks: partition_chunks,
+                _phantom: PhantomData,
+            });
+
+            first_chunk += len;
+        }
+
+        partitions
+    }
+
+    /// Call the given function with partitions of this map (see
+    /// [`SlotMap::partitions_mut`]), one for each thread the machine can run
+    /// in parallel. This is meant for modifying items on several threads with
+    /// [`std::thread::scope`]
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey,(),usize>::new();
+    /// let keys = (0..10_000).map(|i| map.insert((), i)).collect::<Vec<_>>();
+    ///
+    /// map.scope_mut(|partitions| {
+    ///     std::thread::scope(|s| {
+    ///         for mut partition in partitions {
+    ///             let _ = s.spawn(move || {
+    ///                 partition.values_mut().for_each(|v| *v *= 2);
+    ///             });
+    ///         }
+    ///     });
+    /// });
+    ///
+    /// assert_eq!(Some(&20), map.get(&keys[10]));
+    /// ```
+    pub fn scope_mut<R>(
+        &mut self,
+        f: impl FnOnce(Vec<SlotMapPartition<'_, K, P, T>>) -> R,
+    ) -> R {
+        let parallelism = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+
+        f(self.partitions_mut(parallelism))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+
+    #[test]
+    fn test_partitions_cover_map() {
+        let mut map = SlotMap::<TestKey, usize, usize>::new();
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 5 + 3)
+            .map(|i| map.insert(i, i))
+            .collect::<Vec<_>>();
+        let _ = map.remove(&keys[1]);
+
+        let expected = map.iter_raw().map(|(k, v)| (k, *v)).collect::<Vec<_>>();
+
+        let mut partitions = map.partitions_mut(4);
+        assert_eq!(3, partitions.len());
+        assert_eq!(0..2, partitions[0].chunk_range());
+        assert_eq!(4..6, partitions[2].chunk_range());
+
+        let visited = partitions
+            .iter_mut()
+            .flat_map(|p| {
+                p.iter_mut_raw().map(|(k, v)| (k, *v)).collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(expected, visited);
+
+        // Keys only resolve in the partition holding their chunk
+        let last = keys.last().unwrap();
+        assert_eq!(None, partitions[0].get(last));
+        assert!(!partitions[0].contains_key(last));
+        assert_eq!(None, partitions[0].get(&keys[1]));
+        *partitions[2].get_mut(last).unwrap() += 1;
+
+        std::thread::scope(|s| {
+            for mut partition in partitions {
+                let _ = s.spawn(move || {
+                    partition.values_mut().for_each(|v| *v += 1);
+                });
+            }
+        });
+
+        assert_eq!(Some(&(keys.len() + 1)), map.get(last));
+        assert_eq!(Some(&1), map.get(&keys[0]));
+        assert!(map.partitions_mut(3).len() <= 3);
+        assert!(SlotMap::<TestKey, usize, usize>::new()
+            .partitions_mut(2)
+            .is_empty());
+    }
+}
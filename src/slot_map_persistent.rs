@@ -0,0 +1,254 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+
+/// Immutable slot map where every change produces a new version of the map
+/// and leaves the old one as it was. Versions share their full chunks, and a
+/// change only copies the chunk it touches, so keeping many versions around
+/// for undo stacks or speculative branches costs about a chunk per change
+/// rather than a whole map. Keys from one version are valid in every version
+/// derived from it that still holds their item
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()>);
+/// let empty = PersistentSlotMap::<TestKey, (), &'static str>::new();
+///
+/// let (one, first) = empty.insert((), "first");
+/// let (two, second) = one.insert((), "second");
+/// let (three, removed) = two.remove(&first);
+///
+/// assert_eq!(Some(&"first"), removed);
+///
+/// // Every version is still there
+/// assert_eq!(0, empty.len());
+/// assert_eq!(Some(&"first"), one.get(&first));
+/// assert_eq!(Some(&"second"), two.get(&second));
+/// assert_eq!(None, three.get(&first));
+/// assert_eq!(Some(&"second"), three.get(&second));
+/// ```
+pub struct PersistentSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, T>,
+}
+
+impl<K, P, T> std::fmt::Debug for PersistentSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.values()).finish()
+    }
+}
+
+impl<K, P, T> Default for PersistentSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        PersistentSlotMap::new()
+    }
+}
+
+impl<K, P, T> Clone for PersistentSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Clone + Sync,
+{
+    /// Cloning shares the full chunks rather than copying them
+    fn clone(&self) -> Self {
+        PersistentSlotMap {
+            map: self.map.share_all(),
+        }
+    }
+}
+
+impl<K, P, T> From<SlotMap<K, P, T>> for PersistentSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn from(map: SlotMap<K, P, T>) -> Self {
+        PersistentSlotMap { map }
+    }
+}
+
+impl<K, P, T> PersistentSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> PersistentSlotMap<K, P, T> {
+        PersistentSlotMap {
+            map: SlotMap::new(),
+        }
+    }
+
+    /// Get the number of items in this version of the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if this version of the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Get a reference to the item for the given key if it exists in this
+    /// version of the map
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.map.get_raw(key_data)
+    }
+
+    /// Tells if the given key is in this version of the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Create an iterator over the raw key data and values of all items in
+    /// this version of the map
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.map.iter_raw()
+    }
+
+    /// Create an iterator over the values of all items in this version of
+    /// the map
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.map.values()
+    }
+
+    /// Get a slot map with the same contents as this version of the map
+    pub fn to_slot_map(&self) -> SlotMap<K, P, T>
+    where
+        T: Clone + Sync,
+    {
+        self.map.share_all()
+    }
+}
+
+impl<K, P, T> PersistentSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Clone + Sync,
+{
+    /// Create a new version of the map with the given item inserted, and
+    /// return it along with the item's key
+    pub fn insert(&self, pointer: P, value: T) -> (Self, K) {
+        let mut map = self.map.share_all();
+        let key = map.insert(pointer, value);
+
+        (PersistentSlotMap { map }, key)
+    }
+
+    /// Create a new version of the map without the item for the given key,
+    /// and return it along with the removed item, which stays in this
+    /// version
+    pub fn remove(&self, key: &K) -> (Self, Option<&T>) {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&self, key_data: &SlotMapKeyData) -> (Self, Option<&T>) {
+        let mut map = self.map.share_all();
+        let _ = map.remove_raw(key_data);
+
+        (PersistentSlotMap { map }, self.get_raw(key_data))
+    }
+
+    /// Create a new version of the map with the item for the given key
+    /// changed by the given function, or None if the key isn't in this
+    /// version
+    pub fn update(&self, key: &K, f: impl FnOnce(&mut T)) -> Option<Self> {
+        self.update_raw(key.borrow(), f)
+    }
+
+    /// Same as update, but only requires slot map key data
+    pub fn update_raw(
+        &self,
+        key_data: &SlotMapKeyData,
+        f: impl FnOnce(&mut T),
+    ) -> Option<Self> {
+        if !self.map.contains_key_raw(key_data) {
+            return None;
+        }
+
+        let mut map = self.map.share_all();
+        f(map.get_mut_raw(key_data)?);
+
+        Some(PersistentSlotMap { map })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use crate::SLOT_MAP_CHUNK_SIZE;
+
+    #[test]
+    fn test_versions_are_independent() {
+        let mut versions =
+            vec![PersistentSlotMap::<TestKey, usize, usize>::new()];
+        let mut expected = vec![SlotMap::<TestKey, usize, usize>::new()];
+        let mut keys = Vec::new();
+
+        for i in 0..SLOT_MAP_CHUNK_SIZE * 3 {
+            let latest = versions.last().unwrap();
+            let mut next_expected = expected.last().unwrap().clone();
+
+            let next = match i % 5 {
+                3 => {
+                    let key: TestKey = keys[i * 7 % keys.len()];
+                    let (next, removed) = latest.remove(&key);
+                    assert_eq!(latest.get(&key), removed);
+                    assert_eq!(
+                        next_expected.remove(&key).map(|v| &*v),
+                        removed
+                    );
+                    next
+                }
+                4 => {
+                    let key = keys[i * 11 % keys.len()];
+                    let updated = latest.update(&key, |v| *v += 1);
+                    match next_expected.get_mut(&key) {
+                        Some(value) => *value += 1,
+                        None => assert!(updated.is_none()),
+                    }
+                    updated.unwrap_or_else(|| latest.clone())
+                }
+                _ => {
+                    let (next, key) = latest.insert(i, i);
+                    assert_eq!(next_expected.insert(i, i), key);
+                    keys.push(key);
+                    next
+                }
+            };
+
+            versions.push(next);
+            expected.push(next_expected);
+        }
+
+        // Changes to later versions never show up in earlier ones
+        for (version, expected) in versions.iter().zip(&expected) {
+            assert_eq!(expected.len(), version.len());
+
+            for key in &keys {
+                assert_eq!(expected.get(key), version.get(key));
+            }
+
+            assert!(expected
+                .iter_raw()
+                .zip(version.iter_raw())
+                .all(|(left, right)| left == right));
+        }
+
+        let mut map = versions.last().unwrap().to_slot_map();
+        let _ = map.insert(0, 0);
+        assert_eq!(versions.last().unwrap().len() + 1, map.len());
+    }
+}
@@ -0,0 +1,386 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Error returned when trying to remove an item that is pinned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PinnedError;
+
+impl Display for PinnedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the item is pinned and can't be removed")
+    }
+}
+
+impl std::error::Error for PinnedError {}
+
+/// Guard that keeps an item in a [`PinnableSlotMap`] from being removed until
+/// it and every clone of it are dropped. Guards don't borrow the map, so they
+/// can be handed to other subsystems along with the item's key
+pub struct PinGuard {
+    key_data: SlotMapKeyData,
+    pins: Arc<AtomicUsize>,
+}
+
+impl PinGuard {
+    /// Get the key data of the pinned item
+    pub fn key_data(&self) -> SlotMapKeyData {
+        self.key_data
+    }
+}
+
+impl std::fmt::Debug for PinGuard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PinGuard")
+            .field("key_data", &self.key_data)
+            .finish()
+    }
+}
+
+impl Clone for PinGuard {
+    fn clone(&self) -> Self {
+        let _ = self.pins.fetch_add(1, Ordering::Relaxed);
+
+        PinGuard {
+            key_data: self.key_data,
+            pins: self.pins.clone(),
+        }
+    }
+}
+
+impl Drop for PinGuard {
+    fn drop(&mut self) {
+        let _ = self.pins.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// Value of a [`PinnableSlotMap`] along with its pin count
+struct Entry<T> {
+    value: T,
+
+    /// Number of guards pinning the item, created the first time it's pinned
+    pins: Option<Arc<AtomicUsize>>,
+
+    /// Set when the item was removed while pinned
+    removal_deferred: bool,
+}
+
+impl<T> Entry<T> {
+    fn is_pinned(&self) -> bool {
+        self.pins
+            .as_ref()
+            .is_some_and(|pins| pins.load(Ordering::Acquire) > 0)
+    }
+}
+
+/// Slot map whose items can be pinned with a [`PinGuard`] so their keys stay
+/// valid while the guard is held. Removing a pinned item with
+/// [`PinnableSlotMap::remove`] defers the removal until the item is no longer
+/// pinned, while [`PinnableSlotMap::try_remove`] refuses instead. Deferred
+/// removals are finished on the next insert or call to
+/// [`PinnableSlotMap::reclaim_unpinned`] after the last guard drops
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()>);
+/// let mut map = PinnableSlotMap::<TestKey, (), &'static str>::new();
+///
+/// let key = map.insert((), "Hello");
+/// let guard = map.pin(&key).unwrap();
+///
+/// assert_eq!(Err(PinnedError), map.try_remove(&key));
+///
+/// // The removal waits for the guard
+/// assert_eq!(None, map.remove(&key));
+/// assert_eq!(Some(&"Hello"), map.get(&key));
+///
+/// drop(guard);
+/// assert_eq!(1, map.reclaim_unpinned());
+/// assert_eq!(None, map.get(&key));
+/// ```
+pub struct PinnableSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, Entry<T>>,
+
+    /// Key data of items whose removal is waiting for them to be unpinned
+    deferred: Vec<SlotMapKeyData>,
+}
+
+impl<K, P, T> std::fmt::Debug for PinnableSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.values()).finish()
+    }
+}
+
+impl<K, P, T> Default for PinnableSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        PinnableSlotMap::new()
+    }
+}
+
+impl<K, P, T> PinnableSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> PinnableSlotMap<K, P, T> {
+        PinnableSlotMap {
+            map: SlotMap::new(),
+            deferred: Vec::new(),
+        }
+    }
+
+    /// Get the number of items in the map, including items whose removal is
+    /// deferred
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Insert the given item into the map and return its key. Deferred
+    /// removals of items that are no longer pinned are finished first
+    pub fn insert(&mut self, pointer: P, value: T) -> K {
+        let _ = self.reclaim_unpinned();
+
+        self.map.insert(
+            pointer,
+            Entry {
+                value,
+                pins: None,
+                removal_deferred: false,
+            },
+        )
+    }
+
+    /// Pin the item for the given key, keeping it in the map until the
+    /// returned guard and all its clones are dropped. Items that are missing
+    /// or waiting to be removed can't be pinned
+    pub fn pin(&mut self, key: &K) -> Option<PinGuard> {
+        self.pin_raw(key.borrow())
+    }
+
+    /// Same as pin, but only requires slot map key data
+    pub fn pin_raw(&mut self, key_data: &SlotMapKeyData) -> Option<PinGuard> {
+        let entry = self
+            .map
+            .get_mut_raw(key_data)
+            .filter(|entry| !entry.removal_deferred)?;
+
+        let pins = entry.pins.get_or_insert_with(Default::default).clone();
+        let _ = pins.fetch_add(1, Ordering::Relaxed);
+
+        Some(PinGuard {
+            key_data: *key_data,
+            pins,
+        })
+    }
+
+    /// Tells if the item for the given key is pinned
+    pub fn is_pinned(&self, key: &K) -> bool {
+        self.map.get(key).is_some_and(Entry::is_pinned)
+    }
+
+    /// Tells if the item for the given key was removed while pinned and is
+    /// waiting to be unpinned
+    pub fn is_removal_deferred(&self, key: &K) -> bool {
+        self.map
+            .get(key)
+            .is_some_and(|entry| entry.removal_deferred)
+    }
+
+    /// Get a reference to the item for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.map.get_raw(key_data).map(|entry| &entry.value)
+    }
+
+    /// Get a mutable reference to the item for the given key if it exists
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Same as get_mut, but only requires slot map key data
+    pub fn get_mut_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        self.map.get_mut_raw(key_data).map(|entry| &mut entry.value)
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Remove the item for the given key and return a mutable ref to it. If
+    /// the item is pinned, it stays in the map until it's unpinned and None
+    /// is returned
+    pub fn remove(&mut self, key: &K) -> Option<&mut T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        let entry = self.map.get_mut_raw(key_data)?;
+
+        if entry.is_pinned() {
+            if !entry.removal_deferred {
+                entry.removal_deferred = true;
+                self.deferred.push(*key_data);
+            }
+
+            return None;
+        }
+
+        self.map.remove_raw(key_data).map(|entry| &mut entry.value)
+    }
+
+    /// Remove the item for the given key and return a mutable ref to it if
+    /// it's not pinned, or fail without changing anything if it is
+    pub fn try_remove(
+        &mut self,
+        key: &K,
+    ) -> Result<Option<&mut T>, PinnedError> {
+        self.try_remove_raw(key.borrow())
+    }
+
+    /// Same as try_remove, but only requires slot map key data
+    pub fn try_remove_raw(
+        &mut self,
+        key_data: &SlotMapKeyData,
+    ) -> Result<Option<&mut T>, PinnedError> {
+        if self.map.get_raw(key_data).is_some_and(Entry::is_pinned) {
+            return Err(PinnedError);
+        }
+
+        Ok(self.remove_raw(key_data))
+    }
+
+    /// Finish the deferred removals of items that are no longer pinned,
+    /// returning how many were removed
+    pub fn reclaim_unpinned(&mut self) -> usize {
+        let map = &mut self.map;
+        let before = self.deferred.len();
+
+        self.deferred
+            .retain(|key_data| match map.get_raw(key_data) {
+                Some(entry) if entry.is_pinned() => true,
+                Some(_) => {
+                    let _ = map.remove_raw(key_data);
+                    false
+                }
+                None => false,
+            });
+
+        before - self.deferred.len()
+    }
+
+    /// Remove all items from the map, deferring the removal of pinned items
+    pub fn clear(&mut self) {
+        let keys = self.map.iter_raw().map(|(k, _)| k).collect::<Vec<_>>();
+
+        for key_data in keys {
+            let _ = self.remove_raw(&key_data);
+        }
+    }
+
+    /// Create an iterator over the raw key data and values of all items in
+    /// the map
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.map
+            .iter_raw()
+            .map(|(key_data, entry)| (key_data, &entry.value))
+    }
+
+    /// Create an iterator over the raw key data and mutable values of all
+    /// items in the map
+    pub fn iter_mut_raw(
+        &mut self,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &mut T)> {
+        self.map
+            .iter_mut_raw()
+            .map(|(key_data, entry)| (key_data, &mut entry.value))
+    }
+
+    /// Create an iterator over the values of all items in the map
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.map.values().map(|entry| &entry.value)
+    }
+
+    /// Create an iterator over the mutable values of all items in the map
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.map.values_mut().map(|entry| &mut entry.value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use std::thread;
+
+    #[test]
+    fn test_pinned_items_outlive_removal() {
+        let mut map = PinnableSlotMap::<TestKey, usize, usize>::new();
+
+        let keys = (0..100).map(|i| map.insert(i, i)).collect::<Vec<_>>();
+
+        let guards = keys
+            .iter()
+            .filter(|k| k.pointer % 4 == 0)
+            .map(|k| map.pin(k).unwrap())
+            .collect::<Vec<_>>();
+
+        // Guards can be cloned and moved to other threads
+        let moved = guards.iter().map(PinGuard::clone).collect::<Vec<_>>();
+        let handle = thread::spawn(move || {
+            moved.iter().map(PinGuard::key_data).collect::<Vec<_>>()
+        });
+
+        for key in &keys {
+            let pinned = key.pointer % 4 == 0;
+            assert_eq!(pinned, map.is_pinned(key));
+            assert_eq!(pinned, map.try_remove(key).is_err());
+        }
+
+        map.clear();
+
+        assert_eq!(25, map.len());
+        assert!(keys.iter().all(|k| map.pin(k).is_none()));
+        assert!(keys
+            .iter()
+            .filter(|k| k.pointer % 4 == 0)
+            .all(|k| map.is_removal_deferred(k) && map.get(k).is_some()));
+
+        // Releasing guards on another thread leaves only the local ones
+        let pinned_key_data = handle.join().unwrap();
+        assert_eq!(0, map.reclaim_unpinned());
+        assert_eq!(
+            pinned_key_data,
+            guards.iter().map(PinGuard::key_data).collect::<Vec<_>>()
+        );
+
+        drop(guards);
+
+        let key = map.insert(100, 100);
+        assert_eq!(1, map.len());
+        assert_eq!(Some(&100), map.get(&key));
+        assert!(keys.iter().all(|k| !map.contains_key(k)));
+        assert_eq!(0, map.reclaim_unpinned());
+    }
+}
@@ -0,0 +1,263 @@
+use super::slot_map_interning::ValueIndex;
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+
+/// Item of a [`PointerIndexedSlotMap`] along with the pointer it was inserted
+/// with
+struct Entry<P, T> {
+    pointer: P,
+    value: T,
+}
+
+/// Slot map that remembers the pointer data each item was inserted with and
+/// indexes items by it, so the keys for a pointer can be found without
+/// keeping a second map in sync. This suits pointer data that is a stable
+/// external ID. Several items can be inserted with the same pointer
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(UserKey<u64> : Clone + Debug + PartialEq);
+/// let mut sessions = PointerIndexedSlotMap::<UserKey, u64, &str>::new();
+///
+/// let first = sessions.insert(42, "laptop");
+/// let second = sessions.insert(42, "phone");
+/// let _ = sessions.insert(7, "tablet");
+///
+/// let keys = sessions.keys_with_pointer(&42).collect::<Vec<_>>();
+/// assert_eq!(vec![first.clone(), second], keys);
+///
+/// let _ = sessions.remove(&first);
+/// assert_eq!(1, sessions.keys_with_pointer(&42).count());
+/// ```
+pub struct PointerIndexedSlotMap<K, P, T, S = RandomState>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, Entry<P, T>>,
+    index: ValueIndex<S>,
+}
+
+impl<K, P, T, S> std::fmt::Debug for PointerIndexedSlotMap<K, P, T, S>
+where
+    K: SlotMapKey<P>,
+    P: std::fmt::Debug,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.map.values().map(|e| (&e.pointer, &e.value)))
+            .finish()
+    }
+}
+
+impl<K, P, T, S> Default for PointerIndexedSlotMap<K, P, T, S>
+where
+    K: SlotMapKey<P>,
+    S: Default,
+{
+    fn default() -> Self {
+        PointerIndexedSlotMap::with_hasher(S::default())
+    }
+}
+
+impl<K, P, T> PointerIndexedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> PointerIndexedSlotMap<K, P, T> {
+        PointerIndexedSlotMap::with_hasher(RandomState::new())
+    }
+}
+
+impl<K, P, T, S> PointerIndexedSlotMap<K, P, T, S>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map that hashes pointers with the given hasher
+    pub fn with_hasher(hasher: S) -> PointerIndexedSlotMap<K, P, T, S> {
+        PointerIndexedSlotMap {
+            map: SlotMap::new(),
+            index: ValueIndex::new(hasher),
+        }
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Get a reference to the item for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.map.get_raw(key_data).map(|entry| &entry.value)
+    }
+
+    /// Get a mutable reference to the item for the given key if it exists
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Same as get_mut, but only requires slot map key data
+    pub fn get_mut_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        self.map.get_mut_raw(key_data).map(|entry| &mut entry.value)
+    }
+
+    /// Get the pointer data the item for the given key data was inserted with
+    pub fn pointer_of_raw(&self, key_data: &SlotMapKeyData) -> Option<&P> {
+        self.map.get_raw(key_data).map(|entry| &entry.pointer)
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Remove all items from the map
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.index.clear();
+    }
+
+    /// Create an iterator over the raw key data and values of all items in
+    /// the map
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.map
+            .iter_raw()
+            .map(|(key_data, entry)| (key_data, &entry.value))
+    }
+
+    /// Create an iterator over the raw key data and mutable values of all
+    /// items in the map
+    pub fn iter_mut_raw(
+        &mut self,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &mut T)> {
+        self.map
+            .iter_mut_raw()
+            .map(|(key_data, entry)| (key_data, &mut entry.value))
+    }
+
+    /// Create an iterator over the values of all items in the map
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.map.values().map(|entry| &entry.value)
+    }
+
+    /// Create an iterator over the mutable values of all items in the map
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.map.values_mut().map(|entry| &mut entry.value)
+    }
+}
+
+impl<K, P, T, S> PointerIndexedSlotMap<K, P, T, S>
+where
+    K: SlotMapKey<P>,
+    P: Hash + Eq + Clone,
+    S: BuildHasher,
+{
+    /// Insert the given item into the map and return its key
+    pub fn insert(&mut self, pointer: P, value: T) -> K {
+        let hash = self.index.hash(&pointer);
+        let key_data = self.map.insert_raw(Entry {
+            pointer: pointer.clone(),
+            value,
+        });
+
+        self.index.insert(hash, key_data);
+
+        K::from((pointer, key_data))
+    }
+
+    /// Create an iterator over the keys of all items inserted with the given
+    /// pointer data, in the order they were inserted unless slots were reused
+    pub fn keys_with_pointer<'a>(
+        &'a self,
+        pointer: &'a P,
+    ) -> impl Iterator<Item = K> + 'a {
+        self.index
+            .bucket(self.index.hash(pointer))
+            .filter(|key_data| self.pointer_of_raw(key_data) == Some(pointer))
+            .map(|key_data| K::from((pointer.clone(), key_data)))
+    }
+
+    /// Create an iterator over the keys and values of all items in the map
+    pub fn iter(&self) -> impl Iterator<Item = (K, &T)> {
+        self.map.iter_raw().map(|(key_data, entry)| {
+            (K::from((entry.pointer.clone(), key_data)), &entry.value)
+        })
+    }
+
+    /// Remove the item for the given key and return a mutable ref to it if
+    /// there was one
+    pub fn remove(&mut self, key: &K) -> Option<&mut T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        let hash = self.index.hash(self.pointer_of_raw(key_data)?);
+        self.index.remove(hash, key_data);
+
+        self.map.remove_raw(key_data).map(|entry| &mut entry.value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use std::borrow::Borrow;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_keys_with_pointer_matches_model() {
+        let mut map = PointerIndexedSlotMap::<TestKey, usize, usize>::new();
+        let mut model = HashMap::<usize, Vec<TestKey>>::new();
+
+        for i in 0..2000 {
+            let pointer = i * 7 % 50;
+
+            let bucket = model.entry(pointer).or_default();
+
+            if i % 4 == 3 && !bucket.is_empty() {
+                let key = bucket.remove(i % bucket.len());
+                assert!(map.remove(&key).is_some());
+                assert!(map.remove(&key).is_none());
+            } else {
+                bucket.push(map.insert(pointer, i));
+            }
+        }
+
+        let mut total = 0;
+
+        for (pointer, keys) in &model {
+            let mut found = map.keys_with_pointer(pointer).collect::<Vec<_>>();
+            let mut expected = keys.clone();
+            found.sort_by_key(|k| {
+                u64::from(*Borrow::<SlotMapKeyData>::borrow(k))
+            });
+            expected.sort_by_key(|k| {
+                u64::from(*Borrow::<SlotMapKeyData>::borrow(k))
+            });
+
+            assert_eq!(expected, found);
+            total += keys.len();
+        }
+
+        assert_eq!(total, map.len());
+        assert!(map.iter().all(|(key, value)| map.get(&key) == Some(value)
+            && value * 7 % 50 == key.pointer));
+
+        map.clear();
+        assert_eq!(0, map.keys_with_pointer(&0).count());
+    }
+}
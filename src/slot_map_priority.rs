@@ -0,0 +1,365 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+
+/// Item of a [`PrioritySlotMap`] along with its priority and where it sits in
+/// the heap
+struct Entry<R, T> {
+    priority: R,
+    heap_position: usize,
+    value: T,
+}
+
+/// Slot map that is also a min-heap of its items by priority. The heap holds
+/// key data and each item remembers its place in the heap, so the item with
+/// the lowest priority can be taken with [`PrioritySlotMap::pop_min`], and
+/// any item can be reprioritized or removed by key, all in O(log n) while
+/// keys stay valid. Items with equal priorities come out in no particular
+/// order
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()>);
+/// let mut tasks = PrioritySlotMap::<TestKey, (), &str, u32>::new();
+///
+/// let _ = tasks.insert(5, (), "write");
+/// let review = tasks.insert(9, (), "review");
+/// let _ = tasks.insert(1, (), "plan");
+///
+/// tasks.reprioritize(&review, 3);
+///
+/// assert_eq!(Some(&"plan"), tasks.pop_min().map(|(_, task)| &*task));
+/// assert_eq!(Some(&"review"), tasks.pop_min().map(|(_, task)| &*task));
+/// assert_eq!(Some(&"write"), tasks.pop_min().map(|(_, task)| &*task));
+/// assert!(tasks.is_empty());
+/// ```
+pub struct PrioritySlotMap<K, P, T, R>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, Entry<R, T>>,
+
+    /// Key data of all items, arranged as a binary min-heap by priority
+    heap: Vec<SlotMapKeyData>,
+}
+
+impl<K, P, T, R> std::fmt::Debug for PrioritySlotMap<K, P, T, R>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+    R: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.map.values().map(|e| (&e.priority, &e.value)))
+            .finish()
+    }
+}
+
+impl<K, P, T, R> Default for PrioritySlotMap<K, P, T, R>
+where
+    K: SlotMapKey<P>,
+    R: Ord,
+{
+    fn default() -> Self {
+        PrioritySlotMap::new()
+    }
+}
+
+impl<K, P, T, R> PrioritySlotMap<K, P, T, R>
+where
+    K: SlotMapKey<P>,
+    R: Ord,
+{
+    /// Create a new empty map
+    pub fn new() -> PrioritySlotMap<K, P, T, R> {
+        PrioritySlotMap {
+            map: SlotMap::new(),
+            heap: Vec::new(),
+        }
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Insert the given item with the given priority and return its key
+    pub fn insert(&mut self, priority: R, pointer: P, value: T) -> K {
+        let key_data = self.map.insert_raw(Entry {
+            priority,
+            heap_position: self.heap.len(),
+            value,
+        });
+
+        self.heap.push(key_data);
+        self.sift_up(self.heap.len() - 1);
+
+        K::from((pointer, key_data))
+    }
+
+    /// Get a reference to the item for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.map.get_raw(key_data).map(|entry| &entry.value)
+    }
+
+    /// Get a mutable reference to the item for the given key if it exists
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Same as get_mut, but only requires slot map key data
+    pub fn get_mut_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        self.map.get_mut_raw(key_data).map(|entry| &mut entry.value)
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Get the priority of the item for the given key if it exists
+    pub fn priority(&self, key: &K) -> Option<&R> {
+        self.map.get(key).map(|entry| &entry.priority)
+    }
+
+    /// Change the priority of the item for the given key. Returns whether the
+    /// item exists
+    pub fn reprioritize(&mut self, key: &K, priority: R) -> bool {
+        self.reprioritize_raw(key.borrow(), priority)
+    }
+
+    /// Same as reprioritize, but only requires slot map key data
+    pub fn reprioritize_raw(
+        &mut self,
+        key_data: &SlotMapKeyData,
+        priority: R,
+    ) -> bool {
+        let Some(entry) = self.map.get_mut_raw(key_data) else {
+            return false;
+        };
+
+        entry.priority = priority;
+        let position = entry.heap_position;
+
+        self.sift_up(position);
+        self.sift_down(position);
+
+        true
+    }
+
+    /// Get the key data and value of the item with the lowest priority
+    /// without removing it
+    pub fn peek_min(&self) -> Option<(SlotMapKeyData, &T)> {
+        let key_data = *self.heap.first()?;
+
+        self.get_raw(&key_data).map(|value| (key_data, value))
+    }
+
+    /// Remove the item with the lowest priority and return its key data and
+    /// a mutable ref to it
+    pub fn pop_min(&mut self) -> Option<(SlotMapKeyData, &mut T)> {
+        if self.heap.is_empty() {
+            return None;
+        }
+
+        let key_data = self.remove_from_heap(0);
+
+        self.map
+            .remove_raw(&key_data)
+            .map(|entry| (key_data, &mut entry.value))
+    }
+
+    /// Remove the item for the given key and return a mutable ref to it if
+    /// there was one
+    pub fn remove(&mut self, key: &K) -> Option<&mut T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        let position = self.map.get_raw(key_data)?.heap_position;
+        let _ = self.remove_from_heap(position);
+
+        self.map.remove_raw(key_data).map(|entry| &mut entry.value)
+    }
+
+    /// Remove all items from the map
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.heap.clear();
+    }
+
+    /// Create an iterator over the raw key data and values of all items in
+    /// the map, in slot order rather than priority order
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.map
+            .iter_raw()
+            .map(|(key_data, entry)| (key_data, &entry.value))
+    }
+
+    /// Create an iterator over the values of all items in the map, in slot
+    /// order rather than priority order
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.map.values().map(|entry| &entry.value)
+    }
+
+    /// Create an iterator over the mutable values of all items in the map,
+    /// in slot order rather than priority order
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.map.values_mut().map(|entry| &mut entry.value)
+    }
+
+    /// Take the key data at the given heap position out of the heap and
+    /// restore the heap order
+    fn remove_from_heap(&mut self, position: usize) -> SlotMapKeyData {
+        let last = self.heap.len() - 1;
+        self.swap(position, last);
+
+        let key_data = self.heap.pop().expect("heap not empty");
+
+        if position < self.heap.len() {
+            self.sift_up(position);
+            self.sift_down(position);
+        }
+
+        key_data
+    }
+
+    fn priority_at(&self, position: usize) -> &R {
+        &self
+            .map
+            .get_raw(&self.heap[position])
+            .expect("heap only holds items in the map")
+            .priority
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+
+        for position in [a, b] {
+            self.map
+                .get_mut_raw(&self.heap[position])
+                .expect("heap only holds items in the map")
+                .heap_position = position;
+        }
+    }
+
+    fn sift_up(&mut self, mut position: usize) {
+        while position > 0 {
+            let parent = (position - 1) / 2;
+
+            if self.priority_at(position) >= self.priority_at(parent) {
+                break;
+            }
+
+            self.swap(position, parent);
+            position = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut position: usize) {
+        loop {
+            let mut smallest = position;
+
+            for child in [2 * position + 1, 2 * position + 2] {
+                if child < self.heap.len()
+                    && self.priority_at(child) < self.priority_at(smallest)
+                {
+                    smallest = child;
+                }
+            }
+
+            if smallest == position {
+                break;
+            }
+
+            self.swap(position, smallest);
+            position = smallest;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use rand::{thread_rng, Rng};
+    use std::borrow::Borrow;
+
+    #[test]
+    fn test_random_operations_match_model() {
+        let mut rng = thread_rng();
+        let mut map = PrioritySlotMap::<TestKey, usize, usize, u32>::new();
+        let mut model = Vec::<(TestKey, u32)>::new();
+
+        for i in 0..5000 {
+            match rng.gen_range(0..6) {
+                0..=2 => {
+                    let priority = rng.gen_range(0..100);
+                    model.push((map.insert(priority, i, i), priority));
+                }
+                3 if !model.is_empty() => {
+                    let position = rng.gen_range(0..model.len());
+                    let (key, priority) = &mut model[position];
+                    *priority = rng.gen_range(0..100);
+                    assert!(map.reprioritize(key, *priority));
+                }
+                4 if !model.is_empty() => {
+                    let (key, _) =
+                        model.swap_remove(rng.gen_range(0..model.len()));
+                    assert_eq!(Some(key.pointer), map.remove(&key).copied());
+                    assert!(!map.reprioritize(&key, 0));
+                }
+                _ => {
+                    let min = model.iter().map(|(_, p)| *p).min();
+                    let popped = map.pop_min().map(|(k, v)| (k, *v));
+
+                    assert_eq!(min.is_some(), popped.is_some());
+
+                    if let Some((key_data, value)) = popped {
+                        let position = model
+                            .iter()
+                            .position(|(k, _)| k.pointer == value)
+                            .unwrap();
+                        let (key, priority) = model.swap_remove(position);
+
+                        assert_eq!(
+                            *Borrow::<SlotMapKeyData>::borrow(&key),
+                            key_data
+                        );
+                        assert_eq!(min, Some(priority));
+                    }
+                }
+            }
+
+            assert_eq!(model.len(), map.len());
+        }
+
+        for (key, priority) in &model {
+            assert_eq!(Some(priority), map.priority(key));
+        }
+
+        let mut last = 0;
+
+        while let Some((_, value)) = map.pop_min() {
+            let value = *value;
+            let (_, priority) =
+                model.iter().find(|(k, _)| k.pointer == value).unwrap();
+
+            assert!(last <= *priority);
+            last = *priority;
+        }
+
+        assert!(map.is_empty());
+        assert!(map.peek_min().is_none());
+    }
+}
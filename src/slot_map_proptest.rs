@@ -0,0 +1,206 @@
+//! Proptest strategies for generating slot maps with realistic histories and
+//! keys into them
+
+use super::{SlotMap, SlotMapKey};
+use proptest::collection::{vec, SizeRange};
+use proptest::prelude::*;
+use proptest::sample::{select, Index};
+
+/// A generated map along with every key it has handed out. Created with
+/// [`churned_slot_map`]
+pub struct ChurnedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// The generated map
+    pub map: SlotMap<K, P, T>,
+
+    /// Keys to values that are still in the map, in order of insertion
+    pub live_keys: Vec<K>,
+
+    /// Keys to values that have been removed from the map, in order of
+    /// removal. These keys may name slots that have since been reused
+    pub stale_keys: Vec<K>,
+}
+
+impl<K, P, T> std::fmt::Debug for ChurnedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P> + std::fmt::Debug,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChurnedSlotMap")
+            .field("map", &self.map)
+            .field("live_keys", &self.live_keys)
+            .field("stale_keys", &self.stale_keys)
+            .finish()
+    }
+}
+
+impl<K, P, T> Clone for ChurnedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P> + Clone,
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        ChurnedSlotMap {
+            map: self.map.clone(),
+            live_keys: self.live_keys.clone(),
+            stale_keys: self.stale_keys.clone(),
+        }
+    }
+}
+
+impl<K, P, T> ChurnedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P> + Clone + std::fmt::Debug,
+{
+    /// Strategy that picks one of the keys this map has handed out, live or
+    /// stale. Produces no values if the map never handed out a key
+    pub fn any_key(&self) -> impl Strategy<Value = K> {
+        select(
+            self.live_keys
+                .iter()
+                .chain(&self.stale_keys)
+                .cloned()
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Strategy that picks one of the keys to a value still in this map.
+    /// Produces no values if the map is empty
+    pub fn live_key(&self) -> impl Strategy<Value = K> {
+        select(self.live_keys.clone())
+    }
+
+    /// Strategy that picks one of the keys to a value removed from this map.
+    /// Produces no values if nothing was removed
+    pub fn stale_key(&self) -> impl Strategy<Value = K> {
+        select(self.stale_keys.clone())
+    }
+}
+
+/// Strategy that generates maps by applying a sequence of operations with
+/// the given number of steps. Each step inserts a value (with a pointer and
+/// value drawn from the given strategies) or, about a third of the time,
+/// removes a randomly chosen live value, so generated maps have vacant slots,
+/// reused slots, and stale keys
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # use proptest::prelude::*;
+/// # define_key_type!(TestKey<u8> : Clone + Debug);
+/// proptest!(|(
+///     (churned, key) in churned_slot_map::<TestKey, _, _>(
+///         any::<u8>(), any::<u32>(), 1..100
+///     )
+///     .prop_filter("needs a key", |c| !c.live_keys.is_empty())
+///     .prop_flat_map(|c| { let key = c.any_key(); (Just(c), key) })
+/// )| {
+///     let live = churned.live_keys.iter().any(|k| {
+///         std::borrow::Borrow::<SlotMapKeyData>::borrow(k)
+///             == std::borrow::Borrow::<SlotMapKeyData>::borrow(&key)
+///     });
+///     prop_assert_eq!(live, churned.map.get(&key).is_some());
+/// });
+/// ```
+pub fn churned_slot_map<K, P, T>(
+    pointer: impl Strategy<Value = P>,
+    value: impl Strategy<Value = T>,
+    steps: impl Into<SizeRange>,
+) -> impl Strategy<Value = ChurnedSlotMap<K, P, T>>
+where
+    K: SlotMapKey<P> + Clone + std::fmt::Debug,
+    P: std::fmt::Debug,
+    T: std::fmt::Debug,
+{
+    vec(
+        (
+            prop::bool::weighted(1.0 / 3.0),
+            pointer,
+            value,
+            any::<Index>(),
+        ),
+        steps,
+    )
+    .prop_map(|steps| {
+        let mut churned = ChurnedSlotMap {
+            map: SlotMap::new(),
+            live_keys: Vec::new(),
+            stale_keys: Vec::new(),
+        };
+
+        for (remove, pointer, value, index) in steps {
+            if remove && !churned.live_keys.is_empty() {
+                let key = churned
+                    .live_keys
+                    .remove(index.index(churned.live_keys.len()));
+                let _ = churned.map.remove(&key);
+                churned.stale_keys.push(key);
+            } else {
+                churned.live_keys.push(churned.map.insert(pointer, value));
+            }
+        }
+
+        churned
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use std::borrow::Borrow;
+
+    fn test_maps() -> impl Strategy<Value = ChurnedSlotMap<TestKey, usize, u16>>
+    {
+        churned_slot_map(any::<usize>(), any::<u16>(), 0..600)
+    }
+
+    proptest! {
+        #[test]
+        fn test_keys_match_map_contents(churned in test_maps()) {
+            prop_assert_eq!(churned.live_keys.len(), churned.map.len());
+
+            for key in &churned.live_keys {
+                prop_assert!(churned.map.get(key).is_some());
+            }
+
+            for key in &churned.stale_keys {
+                prop_assert_eq!(None, churned.map.get(key));
+            }
+        }
+
+        #[test]
+        fn test_stale_keys_are_unique(churned in test_maps()) {
+            let mut key_data = churned
+                .live_keys
+                .iter()
+                .chain(&churned.stale_keys)
+                .map(|k| u64::from(*Borrow::<crate::SlotMapKeyData>::borrow(k)))
+                .collect::<Vec<_>>();
+            let count = key_data.len();
+
+            key_data.sort_unstable();
+            key_data.dedup();
+
+            prop_assert_eq!(count, key_data.len());
+        }
+
+        #[test]
+        fn test_key_strategies_pick_matching_keys(
+            (churned, live, stale) in test_maps()
+                .prop_filter("needs live and stale keys", |c| {
+                    !c.live_keys.is_empty() && !c.stale_keys.is_empty()
+                })
+                .prop_flat_map(|c| {
+                    let live = c.live_key();
+                    let stale = c.stale_key();
+                    (Just(c), live, stale)
+                })
+        ) {
+            prop_assert!(churned.map.get(&live).is_some());
+            prop_assert_eq!(None, churned.map.get(&stale));
+        }
+    }
+}
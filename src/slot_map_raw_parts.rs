@@ -0,0 +1,112 @@
+use super::slot_map::Slots;
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+
+/// Complete internal state of a [`SlotMap`], produced by
+/// [`SlotMap::into_raw_parts`]. Slots are listed in order of position, which
+/// is their chunk index times the chunk size plus their index in the chunk.
+/// Filled slots hold their own key data and their item, and vacant slots
+/// hold an odd generation, the coordinates of the next open slot, and
+/// whatever value was left behind
+#[derive(Debug, Clone)]
+pub struct SlotMapRawParts<T> {
+    /// Every slot that has been written, in order of position
+    pub slots: Vec<(SlotMapKeyData, T)>,
+
+    /// Coordinates of the first open slot, which is either a vacant slot or
+    /// the first slot past the end of `slots`
+    pub next_open_slot: SlotMapKeyData,
+
+    /// Number of filled slots
+    pub len: usize,
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Take this map apart into its internal state. Settings like the
+    /// generation exhaustion policy are not part of the state
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey, (), &str>::new();
+    /// let key = map.insert((), "kept");
+    ///
+    /// let parts = map.into_raw_parts();
+    /// assert_eq!(1, parts.len);
+    ///
+    /// // Safety - The parts came from a map and weren't changed
+    /// let map = unsafe { SlotMap::<TestKey, (), &str>::from_raw_parts(parts) };
+    /// assert_eq!(Some(&"kept"), map.get(&key));
+    /// ```
+    pub fn into_raw_parts(self) -> SlotMapRawParts<T> {
+        let next_open_slot = self.next_open_slot();
+        let len = self.len();
+
+        SlotMapRawParts {
+            slots: self.into_slots().collect(),
+            next_open_slot,
+            len,
+        }
+    }
+
+    /// Put a map back together from its internal state. Keys that were valid
+    /// for the map the parts came from are valid for the new map
+    ///
+    /// # Safety
+    /// The parts must describe a consistent map, as they do when they come
+    /// from [`SlotMap::into_raw_parts`] unchanged: filled slots must hold
+    /// their own coordinates, the chain of open slots starting at
+    /// `next_open_slot` must visit every vacant slot that isn't retired and
+    /// end at the first slot past the end of `slots`, and `len` must be the
+    /// number of filled slots. Maps built from inconsistent parts can read
+    /// uninitialized memory. [`SnapshotBuilder`](crate::SnapshotBuilder)
+    /// checks all of this for state from an untrusted source
+    pub unsafe fn from_raw_parts(
+        parts: SlotMapRawParts<T>,
+    ) -> SlotMap<K, P, T> {
+        let mut slots = Slots::new();
+
+        for slot in parts.slots {
+            slots.push_slot(slot);
+        }
+
+        SlotMap::from_raw_state(slots, parts.next_open_slot, parts.len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::{churned_map, TestKey};
+
+    #[test]
+    fn test_raw_parts_round_trip() {
+        let (map, keys, _) = churned_map(9, |i| i.to_string());
+
+        let mut dump = Vec::new();
+        map.dump_with_values(&mut dump).unwrap();
+
+        let parts = map.into_raw_parts();
+        assert_eq!(keys.len(), parts.len);
+        assert_eq!(
+            parts.len,
+            parts.slots.iter().filter(|(k, _)| k.is_filled()).count()
+        );
+
+        let mut map =
+            unsafe { SlotMap::<TestKey, usize, String>::from_raw_parts(parts) };
+
+        let mut rebuilt_dump = Vec::new();
+        map.dump_with_values(&mut rebuilt_dump).unwrap();
+        assert_eq!(String::from_utf8(dump), String::from_utf8(rebuilt_dump));
+
+        for key in &keys {
+            assert_eq!(Some(&key.pointer.to_string()), map.get(key));
+        }
+
+        let key = map.insert(0, "new".to_owned());
+        assert_eq!(Some(&"new".to_owned()), map.get(&key));
+    }
+}
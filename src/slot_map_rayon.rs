@@ -0,0 +1,388 @@
+use super::slot_map::{new_filled_chunk, FilledChunk};
+use super::{SlotMap, SlotMapKey, SlotMapKeyData, SLOT_MAP_CHUNK_SIZE};
+use rayon::prelude::*;
+
+/// Chunk that was just built and hasn't been shared yet
+struct UnsharedChunk<T>(FilledChunk<T>);
+
+// Safety - Nothing else refers to the chunk, so sending it to another thread
+// is the same as sending a Box of its values
+unsafe impl<T: Send> Send for UnsharedChunk<T> {}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P> + Send,
+    P: Send,
+    T: Send,
+{
+    /// Insert all of the given items, returning their keys in the order the
+    /// items were produced. Open slots and the partially written last chunk
+    /// are filled first, one item at a time, and the remaining items are
+    /// built into whole chunks on rayon's worker threads and then added to
+    /// the end of the map. The keys are the same as if the items had been
+    /// inserted one at a time
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # use rayon::prelude::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey,(),usize>::new();
+    ///
+    /// let keys = map.par_extend((0..10_000).into_par_iter().map(|i| ((), i)));
+    ///
+    /// assert_eq!(10_000, map.len());
+    /// assert_eq!(Some(&1234), map.get(&keys[1234]));
+    /// ```
+    pub fn par_extend<I>(&mut self, items: I) -> Vec<K>
+    where
+        I: IntoParallelIterator<Item = (P, T)>,
+    {
+        let mut items = items.into_par_iter().collect::<Vec<_>>().into_iter();
+        let mut keys = Vec::with_capacity(items.len());
+
+        // Items go into open slots and the current chunk one at a time until
+        // a new chunk can be started
+        while !self.is_at_chunk_boundary() {
+            match items.next() {
+                Some((pointer, value)) => {
+                    keys.push(self.insert(pointer, value))
+                }
+                None => return keys,
+            }
+        }
+
+        let mut chunked = items.collect::<Vec<_>>();
+        let tail = chunked.split_off(
+            chunked.len() / SLOT_MAP_CHUNK_SIZE * SLOT_MAP_CHUNK_SIZE,
+        );
+
+        if !chunked.is_empty() {
+            let first_key_data = self.next_key_data();
+
+            let chunks = chunked
+                .into_par_iter()
+                .chunks(SLOT_MAP_CHUNK_SIZE)
+                .enumerate()
+                .map(|(i, items)| {
+                    let chunk_index = first_key_data.chunk_index + i as u32;
+                    let (pointers, values): (Vec<_>, Vec<_>) =
+                        items.into_iter().unzip();
+
+                    let chunk_keys = pointers
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index_in_chunk, pointer)| {
+                            K::from((
+                                pointer,
+                                SlotMapKeyData {
+                                    chunk_index,
+                                    index_in_chunk: index_in_chunk as u16,
+                                    ..first_key_data
+                                },
+                            ))
+                        })
+                        .collect::<Vec<_>>();
+
+                    (
+                        UnsharedChunk(new_filled_chunk(chunk_index, values)),
+                        chunk_keys,
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            for (chunk, chunk_keys) in chunks {
+                self.push_filled_chunk(chunk.0);
+                keys.extend(chunk_keys);
+            }
+        }
+
+        for (pointer, value) in tail {
+            keys.push(self.insert(pointer, value));
+        }
+
+        keys
+    }
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Default + Send,
+{
+    /// Remove all items from this map, producing them as a parallel iterator.
+    /// Items are moved out of their slots one chunk per task on rayon's
+    /// worker threads, leaving default values behind, and only marking the
+    /// slots as open is done on the calling thread. Values left behind in
+    /// slots that were already open are not produced
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # use rayon::prelude::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey,(),usize>::new();
+    /// let _ = map.par_extend((0..10_000).into_par_iter().map(|i| ((), i)));
+    ///
+    /// let sum: usize = map.par_drain().sum();
+    ///
+    /// assert_eq!((0..10_000).sum::<usize>(), sum);
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn par_drain(&mut self) -> impl ParallelIterator<Item = T> {
+        let values = self
+            .chunks_mut()
+            .into_par_iter()
+            .flat_map_iter(|chunk| {
+                chunk
+                    .into_iter()
+                    .filter(|(key_data, _)| key_data.is_filled())
+                    .map(|(_, value)| std::mem::take(value))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        self.clear();
+
+        values.into_par_iter()
+    }
+
+    /// Remove all items from this map, dropping their values on rayon's
+    /// worker threads. Unlike [`SlotMap::clear`], this also drops the values
+    /// left behind in slots that were already open, replacing every value
+    /// with a default one
+    pub fn par_clear(&mut self) {
+        self.chunks_mut().into_par_iter().for_each(|chunk| {
+            for (_, value) in chunk {
+                *value = T::default();
+            }
+        });
+
+        self.clear();
+    }
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Send,
+{
+    /// Same as [`SlotMap::compact`], but with items moved on rayon's worker
+    /// threads. Moves are planned on the calling thread, and the given
+    /// function is called there too, once every item has moved, in the same
+    /// order as the single threaded version
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # use rayon::prelude::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey,(),usize>::new();
+    /// let keys = map.par_extend((0..10_000).into_par_iter().map(|i| ((), i)));
+    ///
+    /// for key in keys.iter().step_by(2) {
+    ///     let _ = map.remove(key);
+    /// }
+    ///
+    /// let mut moved = 0;
+    /// map.par_compact(|_, _| moved += 1);
+    ///
+    /// assert_eq!(2_500, moved);
+    /// assert_eq!(5_000, map.len());
+    /// ```
+    pub fn par_compact(
+        &mut self,
+        mut remap: impl FnMut(SlotMapKeyData, SlotMapKeyData),
+    ) {
+        let moves = self.plan_compaction();
+        let slots = self.compaction_slots();
+
+        moves.par_iter().with_min_len(SLOT_MAP_CHUNK_SIZE).for_each(
+            |(from, to)| {
+                // Safety - Every slot is part of at most one planned move
+                unsafe { slots.move_item(from, to) }
+            },
+        );
+
+        self.vacate_compacted(&moves);
+
+        for (from, to) in moves {
+            remap(from, to);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::slot_map_key_data::MAX_GENERATION;
+    use crate::test_support::TestKey;
+    use crate::GenerationExhaustion;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn insert_sequentially(
+        map: &mut SlotMap<TestKey, usize, String>,
+        count: usize,
+    ) -> Vec<TestKey> {
+        (0..count)
+            .map(|i| map.insert(i, format!("{}", i)))
+            .collect()
+    }
+
+    #[test]
+    fn test_par_extend_matches_sequential_inserts() {
+        for (existing, removed, count) in [
+            (0, 0, 0),
+            (0, 0, SLOT_MAP_CHUNK_SIZE * 5),
+            (3, 0, SLOT_MAP_CHUNK_SIZE * 5 + 17),
+            (SLOT_MAP_CHUNK_SIZE + 40, 30, SLOT_MAP_CHUNK_SIZE * 3 + 1),
+            (SLOT_MAP_CHUNK_SIZE * 2, 10, 8),
+        ] {
+            let mut parallel = SlotMap::<TestKey, usize, String>::new();
+            let existing_keys = insert_sequentially(&mut parallel, existing);
+
+            for key in existing_keys.iter().step_by(3).take(removed) {
+                let _ = parallel.remove(key);
+            }
+
+            let mut sequential = parallel.clone();
+
+            let expected = insert_sequentially(&mut sequential, count);
+            let keys = parallel.par_extend(
+                (0..count).into_par_iter().map(|i| (i, format!("{}", i))),
+            );
+
+            assert_eq!(expected, keys);
+            assert_eq!(sequential.state_digest(), parallel.state_digest());
+
+            // Both maps behave the same afterwards
+            assert_eq!(
+                sequential.insert(0, "after".to_owned()),
+                parallel.insert(0, "after".to_owned())
+            );
+            if let Some(key) = keys.get(count / 2) {
+                let _ = sequential.remove(key);
+                let _ = parallel.remove(key);
+            }
+            assert_eq!(sequential.state_digest(), parallel.state_digest());
+        }
+    }
+
+    #[test]
+    fn test_par_extend_around_retired_slots() {
+        let mut parallel = SlotMap::<TestKey, usize, String>::new();
+        parallel.set_generation_exhaustion(GenerationExhaustion::Retire);
+        let keys = insert_sequentially(&mut parallel, 10);
+
+        // Wear out the first slot so removing its item retires it
+        for chunk in parallel.chunks_mut() {
+            if let Some((key_data, _)) = chunk.into_iter().next() {
+                key_data.generation = MAX_GENERATION - 1;
+            }
+        }
+        let worn = parallel.iter_raw().next().unwrap().0;
+        assert!(parallel.remove_raw(&worn).is_some());
+        assert!(parallel.remove(&keys[5]).is_some());
+        assert_eq!(1, parallel.retired_slots());
+
+        let mut sequential = parallel.clone();
+        let count = SLOT_MAP_CHUNK_SIZE * 2 + 88;
+
+        let expected = insert_sequentially(&mut sequential, count);
+        let keys = parallel.par_extend(
+            (0..count).into_par_iter().map(|i| (i, format!("{}", i))),
+        );
+
+        assert_eq!(expected, keys);
+        assert_eq!(sequential.state_digest(), parallel.state_digest());
+        assert_eq!(1, parallel.retired_slots());
+    }
+
+    /// Value that counts how many times a non-default value was dropped
+    #[derive(Default)]
+    struct DropCounter(Option<Arc<AtomicUsize>>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            if let Some(count) = &self.0 {
+                let _ = count.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    #[test]
+    fn test_par_clear_drops_everything() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let mut map = SlotMap::<TestKey, usize, DropCounter>::new();
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 3 + 11)
+            .map(|i| map.insert(i, DropCounter(Some(drops.clone()))))
+            .collect::<Vec<_>>();
+        let _ = map.remove(&keys[5]);
+
+        map.par_clear();
+
+        // The value left behind by the removal is dropped too
+        assert_eq!(keys.len(), drops.load(Ordering::SeqCst));
+        assert!(map.is_empty());
+        assert!(keys.iter().all(|k| !map.contains_key(k)));
+
+        let mut sequential = SlotMap::<TestKey, usize, DropCounter>::new();
+        for _ in &keys {
+            let _ = sequential.insert(0, DropCounter::default());
+        }
+        let _ = sequential.remove(&keys[5]);
+        sequential.clear();
+
+        assert_eq!(
+            sequential.insert(0, DropCounter::default()),
+            map.insert(0, DropCounter::default())
+        );
+    }
+
+    #[test]
+    fn test_par_drain_produces_live_values() {
+        let mut map = SlotMap::<TestKey, usize, usize>::new();
+        let keys = map.par_extend(
+            (0..SLOT_MAP_CHUNK_SIZE * 4 + 3)
+                .into_par_iter()
+                .map(|i| (i, i)),
+        );
+
+        for key in keys.iter().step_by(7) {
+            let _ = map.remove(key);
+        }
+
+        let mut drained = map.par_drain().collect::<Vec<_>>();
+        drained.sort_unstable();
+
+        let expected =
+            (0..keys.len()).filter(|i| i % 7 != 0).collect::<Vec<_>>();
+
+        assert_eq!(expected, drained);
+        assert!(map.is_empty());
+        assert!(keys.iter().all(|k| !map.contains_key(k)));
+    }
+
+    #[test]
+    fn test_par_compact_matches_compact() {
+        let mut parallel = SlotMap::<TestKey, usize, String>::new();
+        let keys = insert_sequentially(&mut parallel, SLOT_MAP_CHUNK_SIZE * 6);
+
+        for (i, key) in keys.iter().enumerate() {
+            if i % 3 == 0 || (i / SLOT_MAP_CHUNK_SIZE == 4 && i % 5 != 0) {
+                let _ = parallel.remove(key);
+            }
+        }
+
+        let mut sequential = parallel.clone();
+
+        let mut expected = Vec::new();
+        sequential.compact(|old, new| expected.push((old, new)));
+
+        let mut moves = Vec::new();
+        parallel.par_compact(|old, new| moves.push((old, new)));
+
+        assert!(!moves.is_empty());
+        assert_eq!(expected, moves);
+        assert_eq!(sequential.state_digest(), parallel.state_digest());
+    }
+}
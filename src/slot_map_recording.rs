@@ -0,0 +1,313 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+use std::io::{Error, ErrorKind};
+
+/// A single operation captured by a [`RecordingSlotMap`], along with the key
+/// data it applied to and any value it wrote
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RecordedOp<T> {
+    /// The given value was inserted and given the given key data
+    Insert(SlotMapKeyData, T),
+
+    /// The item for the given key data was removed
+    Remove(SlotMapKeyData),
+
+    /// The item for the given key data was changed to the given value
+    Write(SlotMapKeyData, T),
+
+    /// All items were removed
+    Clear,
+}
+
+/// Every operation that changed a [`RecordingSlotMap`] in the order they were
+/// applied. Replaying a recording reproduces the recorded map exactly,
+/// including which slots are open and the order they will be reused in, so
+/// bugs that depend on slot reuse can be reproduced from a saved recording
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpRecording<T> {
+    ops: Vec<RecordedOp<T>>,
+}
+
+impl<T> Default for OpRecording<T> {
+    fn default() -> Self {
+        OpRecording { ops: Vec::new() }
+    }
+}
+
+impl<T> OpRecording<T> {
+    /// Get the recorded operations in the order they were applied
+    pub fn ops(&self) -> &[RecordedOp<T>] {
+        &self.ops
+    }
+
+    /// Build a new map by applying the recorded operations to an empty map.
+    /// Every operation is checked against the key data it was recorded with,
+    /// and one that doesn't apply the same way is reported as invalid data,
+    /// which happens if the recording was altered or the map changed behavior
+    pub fn replay<K, P>(&self) -> Result<SlotMap<K, P, T>, Error>
+    where
+        K: SlotMapKey<P>,
+        T: Clone,
+    {
+        let mut map = SlotMap::new();
+
+        for (position, op) in self.ops.iter().enumerate() {
+            let applied = match op {
+                RecordedOp::Insert(key_data, value) => {
+                    map.insert_raw(value.clone()) == *key_data
+                }
+                RecordedOp::Remove(key_data) => {
+                    map.remove_raw(key_data).is_some()
+                }
+                RecordedOp::Write(key_data, value) => map
+                    .get_mut_raw(key_data)
+                    .map(|existing| *existing = value.clone())
+                    .is_some(),
+                RecordedOp::Clear => {
+                    map.clear();
+                    true
+                }
+            };
+
+            if !applied {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "recorded operation {} does not apply to the \
+                        replayed map",
+                        position
+                    ),
+                ));
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+/// Slot map wrapper that records every operation that changes the map, with
+/// copies of the values written, so the exact state of the map can be
+/// reproduced later with [`OpRecording::replay`]. Values can only be changed
+/// through [`RecordingSlotMap::update`], so the changed value can be
+/// recorded. Unlike [`LoggedSlotMap`](crate::LoggedSlotMap), the recording is
+/// kept in memory and the values only need to be cloneable, or serializable
+/// to save the recording with the `serde` feature
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()>);
+/// let mut map = RecordingSlotMap::<TestKey, (), &str>::new();
+///
+/// let first = map.insert((), "first");
+/// let second = map.insert((), "second");
+/// let _ = map.remove(&first);
+/// map.update(&second, |value| *value = "changed");
+///
+/// let replayed = map.recording().replay::<TestKey, ()>().unwrap();
+///
+/// assert_eq!(None, replayed.get(&first));
+/// assert_eq!(Some(&"changed"), replayed.get(&second));
+/// ```
+pub struct RecordingSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, T>,
+    recording: OpRecording<T>,
+}
+
+impl<K, P, T> std::fmt::Debug for RecordingSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordingSlotMap")
+            .field("map", &self.map)
+            .field("recording", &self.recording)
+            .finish()
+    }
+}
+
+impl<K, P, T> Default for RecordingSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        RecordingSlotMap {
+            map: SlotMap::new(),
+            recording: OpRecording::default(),
+        }
+    }
+}
+
+impl<K, P, T> RecordingSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Clone,
+{
+    /// Create an empty map with an empty recording
+    pub fn new() -> RecordingSlotMap<K, P, T> {
+        RecordingSlotMap::default()
+    }
+
+    /// Get read access to the underlying map
+    pub fn map(&self) -> &SlotMap<K, P, T> {
+        &self.map
+    }
+
+    /// Get the operations recorded so far
+    pub fn recording(&self) -> &OpRecording<T> {
+        &self.recording
+    }
+
+    /// Split this wrapper into the map and its recording
+    pub fn into_parts(self) -> (SlotMap<K, P, T>, OpRecording<T>) {
+        (self.map, self.recording)
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Get a reference to the item for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.map.get(key)
+    }
+
+    /// Check to see if the given key is still valid in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Insert the given value and record a copy of it
+    pub fn insert(&mut self, pointer: P, value: T) -> K {
+        let key = self.map.insert(pointer, value.clone());
+
+        self.recording
+            .ops
+            .push(RecordedOp::Insert(*key.borrow(), value));
+
+        key
+    }
+
+    /// Remove the item for the given key. Nothing is recorded if the key is
+    /// not present
+    pub fn remove(&mut self, key: &K) -> Option<&mut T> {
+        let key_data = *key.borrow();
+        let removed = self.map.remove_raw(&key_data);
+
+        if removed.is_some() {
+            self.recording.ops.push(RecordedOp::Remove(key_data));
+        }
+
+        removed
+    }
+
+    /// Change the item for the given key with the given function and record
+    /// a copy of the result. Tells whether there was an item to change
+    pub fn update(&mut self, key: &K, change: impl FnOnce(&mut T)) -> bool {
+        let key_data = *key.borrow();
+
+        let Some(value) = self.map.get_mut_raw(&key_data) else {
+            return false;
+        };
+
+        change(value);
+
+        self.recording
+            .ops
+            .push(RecordedOp::Write(key_data, value.clone()));
+
+        true
+    }
+
+    /// Remove all items from the map
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.recording.ops.push(RecordedOp::Clear);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use rand::{thread_rng, Rng};
+    use std::borrow::Borrow;
+
+    fn dump<T>(map: &SlotMap<TestKey, usize, T>) -> String {
+        let mut out = Vec::new();
+        map.dump(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_replay_reproduces_exact_state() {
+        let mut rng = thread_rng();
+        let mut map = RecordingSlotMap::<TestKey, usize, usize>::new();
+        let mut keys = Vec::<TestKey>::new();
+
+        for i in 0..3000 {
+            let target =
+                (!keys.is_empty()).then(|| rng.gen_range(0..keys.len()));
+
+            match (rng.gen_range(0..10), target) {
+                (0..=4, _) | (_, None) => keys.push(map.insert(i, i)),
+                (5..=7, Some(t)) => {
+                    let _ = map.remove(&keys.swap_remove(t));
+                }
+                (8, Some(t)) => {
+                    assert!(map.update(&keys[t], |value| *value *= 2));
+                }
+                (_, Some(_)) if i > 1500 => {
+                    map.clear();
+                    keys.clear();
+                }
+                (_, Some(_)) => {}
+            }
+        }
+
+        let (original, recording) = map.into_parts();
+        let replayed = recording.replay::<TestKey, usize>().unwrap();
+
+        // The dump covers every slot's generation and the chain of open slots
+        assert_eq!(dump(&original), dump(&replayed));
+        assert_eq!(
+            original.iter_raw().collect::<Vec<_>>(),
+            replayed.iter_raw().collect::<Vec<_>>()
+        );
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string(&recording).unwrap();
+            let loaded: OpRecording<usize> =
+                serde_json::from_str(&json).unwrap();
+            assert_eq!(recording, loaded);
+        }
+    }
+
+    #[test]
+    fn test_altered_recording_is_rejected() {
+        let mut map = RecordingSlotMap::<TestKey, usize, usize>::new();
+        let key = map.insert(0, 0);
+        let _ = map.remove(&key);
+
+        let (_, mut recording) = map.into_parts();
+        recording
+            .ops
+            .push(RecordedOp::Remove(*Borrow::<SlotMapKeyData>::borrow(&key)));
+
+        assert_eq!(
+            ErrorKind::InvalidData,
+            recording.replay::<TestKey, usize>().unwrap_err().kind()
+        );
+    }
+}
@@ -0,0 +1,564 @@
+//! Serde support for slot maps. A map is serialized as its snapshot header
+//! followed by its chunks, and deserialization feeds each slot straight into a
+//! [`SnapshotBuilder`] so no intermediate collection of the whole map is ever
+//! built in either direction
+//!
+//! Output depends only on the state of the map, including the order of its
+//! chain of open slots and the values left in vacant slots. Maps that should
+//! serialize identically regardless of their histories can be put in a
+//! canonical form first with [`SlotMap::canonicalize`]
+//!
+//! Values are deserialized with the lifetime of the input, so values that
+//! borrow from it, like `&'de str` or structs with `#[serde(borrow)]` fields,
+//! are rebuilt without copying when the format allows it
+
+use super::{SlotMap, SlotMapKey, SlotMapKeyData, SnapshotBuilder};
+use serde::de::{DeserializeSeed, Error, MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::Formatter;
+use std::marker::PhantomData;
+
+const FIELDS: &[&str] = &["header", "chunks"];
+
+impl<K, P, T> Serialize for SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_snapshot(self.snapshot_writer(), serializer)
+    }
+}
+
+/// Serialize the header and chunks of the given writer as a slot map
+fn serialize_snapshot<T, S>(
+    writer: crate::SnapshotWriter<'_, T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    let mut state = serializer.serialize_struct("SlotMap", 2)?;
+    state.serialize_field("header", &writer.header())?;
+    state.serialize_field("chunks", &SerializeChunks(writer))?;
+    state.end()
+}
+
+/// Serialization of a slot map that records the version of the value
+/// representation in the snapshot header. Created with
+/// [`SlotMap::with_value_version`]
+pub struct VersionedSlotMap<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    map: &'a SlotMap<K, P, T>,
+    value_version: u32,
+}
+
+impl<'a, K, P, T> std::fmt::Debug for VersionedSlotMap<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VersionedSlotMap")
+            .field("value_version", &self.value_version)
+            .finish()
+    }
+}
+
+impl<'a, K, P, T> Serialize for VersionedSlotMap<'a, K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_snapshot(
+            self.map
+                .snapshot_writer()
+                .with_value_version(self.value_version),
+            serializer,
+        )
+    }
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Serialize this map with the given version of the value representation
+    /// recorded alongside it, so that future versions of the value type can
+    /// migrate it with [`SlotMapMigration`] when it is loaded
+    pub fn with_value_version(
+        &self,
+        value_version: u32,
+    ) -> VersionedSlotMap<'_, K, P, T> {
+        VersionedSlotMap {
+            map: self,
+            value_version,
+        }
+    }
+}
+
+/// Adapter that serializes the chunks of a snapshot writer as a sequence
+struct SerializeChunks<'a, T>(crate::SnapshotWriter<'a, T>);
+
+impl<'a, T> Serialize for SerializeChunks<'a, T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.0.clone())
+    }
+}
+
+impl<'de, K, P, T> Deserialize<'de> for SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        SlotMapMigration::new(|_, value: T| value).deserialize(deserializer)
+    }
+}
+
+/// Deserialization of a slot map whose values may have been written with an
+/// older representation. Every value (including values left in vacant slots)
+/// is deserialized as `Old` and converted with the migration, which also
+/// receives the value version recorded when the map was serialized
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # use serde::de::DeserializeSeed;
+/// # define_key_type!(TestKey<()>);
+/// let mut old_map = SlotMap::<TestKey, (), u32>::new();
+/// let key = old_map.insert((), 42);
+///
+/// let json = serde_json::to_string(&old_map.with_value_version(1)).unwrap();
+///
+/// let new_map: SlotMap<TestKey, (), String> =
+///     SlotMapMigration::new(|version, old: u32| format!("v{}: {}", version, old))
+///         .deserialize(&mut serde_json::Deserializer::from_str(&json))
+///         .unwrap();
+///
+/// assert_eq!(Some(&"v1: 42".to_owned()), new_map.get(&key));
+/// ```
+pub struct SlotMapMigration<K, P, T, Old, F>
+where
+    K: SlotMapKey<P>,
+    F: FnMut(u32, Old) -> T,
+{
+    migrate: F,
+
+    _phantom: PhantomData<SlotMap<K, P, T>>,
+    _old: PhantomData<fn() -> Old>,
+}
+
+impl<K, P, T, Old, F> std::fmt::Debug for SlotMapMigration<K, P, T, Old, F>
+where
+    K: SlotMapKey<P>,
+    F: FnMut(u32, Old) -> T,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SlotMapMigration").finish_non_exhaustive()
+    }
+}
+
+impl<K, P, T, Old, F> SlotMapMigration<K, P, T, Old, F>
+where
+    K: SlotMapKey<P>,
+    F: FnMut(u32, Old) -> T,
+{
+    /// Create a migration that converts values with the given function
+    pub fn new(migrate: F) -> Self {
+        SlotMapMigration {
+            migrate,
+            _phantom: PhantomData,
+            _old: PhantomData,
+        }
+    }
+}
+
+impl<'de, K, P, T, Old, F> DeserializeSeed<'de>
+    for SlotMapMigration<K, P, T, Old, F>
+where
+    K: SlotMapKey<P>,
+    Old: Deserialize<'de>,
+    F: FnMut(u32, Old) -> T,
+{
+    type Value = SlotMap<K, P, T>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("SlotMap", FIELDS, self)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "lowercase")]
+enum Field {
+    Header,
+    Chunks,
+}
+
+impl<'de, K, P, T, Old, F> Visitor<'de> for SlotMapMigration<K, P, T, Old, F>
+where
+    K: SlotMapKey<P>,
+    Old: Deserialize<'de>,
+    F: FnMut(u32, Old) -> T,
+{
+    type Value = SlotMap<K, P, T>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("a slot map snapshot")
+    }
+
+    fn visit_seq<A>(mut self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let header = seq
+            .next_element()?
+            .ok_or_else(|| Error::invalid_length(0, &"header and chunks"))?;
+
+        let mut builder = SnapshotBuilder::new(header);
+
+        seq.next_element_seed(ChunksSeed::new(
+            &mut builder,
+            &mut self.migrate,
+        ))?
+        .ok_or_else(|| Error::invalid_length(1, &"header and chunks"))?;
+
+        builder.build().map_err(Error::custom)
+    }
+
+    fn visit_map<A>(mut self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut builder = None;
+        let mut saw_chunks = false;
+
+        while let Some(field) = map.next_key()? {
+            match field {
+                Field::Header => {
+                    if builder.is_some() {
+                        return Err(Error::duplicate_field("header"));
+                    }
+                    builder = Some(SnapshotBuilder::new(map.next_value()?));
+                }
+                Field::Chunks => {
+                    if saw_chunks {
+                        return Err(Error::duplicate_field("chunks"));
+                    }
+                    // Chunks are fed directly into the builder, so the header
+                    // has to have been read already
+                    let builder = builder.as_mut().ok_or_else(|| {
+                        Error::custom("snapshot header must precede chunks")
+                    })?;
+                    map.next_value_seed(ChunksSeed::new(
+                        builder,
+                        &mut self.migrate,
+                    ))?;
+                    saw_chunks = true;
+                }
+            }
+        }
+
+        let builder = builder.ok_or_else(|| Error::missing_field("header"))?;
+
+        if !saw_chunks {
+            return Err(Error::missing_field("chunks"));
+        }
+
+        builder.build().map_err(Error::custom)
+    }
+}
+
+/// Seed for the sequence of chunks in a serialized map
+struct ChunksSeed<'b, T, Old, F> {
+    builder: &'b mut SnapshotBuilder<T>,
+    migrate: &'b mut F,
+
+    _old: PhantomData<fn() -> Old>,
+}
+
+impl<'b, T, Old, F> ChunksSeed<'b, T, Old, F> {
+    fn new(builder: &'b mut SnapshotBuilder<T>, migrate: &'b mut F) -> Self {
+        ChunksSeed {
+            builder,
+            migrate,
+            _old: PhantomData,
+        }
+    }
+
+    /// Reborrow this seed so it can be used for more than one element
+    fn reborrow(&mut self) -> ChunksSeed<'_, T, Old, F> {
+        ChunksSeed::new(self.builder, self.migrate)
+    }
+}
+
+impl<'de, 'b, T, Old, F> DeserializeSeed<'de> for ChunksSeed<'b, T, Old, F>
+where
+    Old: Deserialize<'de>,
+    F: FnMut(u32, Old) -> T,
+{
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<(), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'b, T, Old, F> Visitor<'de> for ChunksSeed<'b, T, Old, F>
+where
+    Old: Deserialize<'de>,
+    F: FnMut(u32, Old) -> T,
+{
+    type Value = ();
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("a sequence of slot map chunks")
+    }
+
+    fn visit_seq<A>(mut self, mut seq: A) -> Result<(), A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        while seq.next_element_seed(ChunkSeed(self.reborrow()))?.is_some() {}
+
+        Ok(())
+    }
+}
+
+/// Seed for a single chunk in a serialized map
+struct ChunkSeed<'b, T, Old, F>(ChunksSeed<'b, T, Old, F>);
+
+impl<'de, 'b, T, Old, F> DeserializeSeed<'de> for ChunkSeed<'b, T, Old, F>
+where
+    Old: Deserialize<'de>,
+    F: FnMut(u32, Old) -> T,
+{
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<(), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'b, T, Old, F> Visitor<'de> for ChunkSeed<'b, T, Old, F>
+where
+    Old: Deserialize<'de>,
+    F: FnMut(u32, Old) -> T,
+{
+    type Value = ();
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("a sequence of slots")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<(), A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let ChunksSeed {
+            builder, migrate, ..
+        } = self.0;
+        let value_version = builder.header().value_version;
+
+        builder.begin_chunk().map_err(Error::custom)?;
+
+        while let Some((key_data, old)) =
+            seq.next_element::<(SlotMapKeyData, Old)>()?
+        {
+            builder
+                .push_slot((key_data, migrate(value_version, old)))
+                .map_err(Error::custom)?;
+        }
+
+        builder.end_chunk().map_err(Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut map = SlotMap::<TestKey, usize, String>::new();
+
+        let keys = (0..1000)
+            .map(|i| map.insert(i, format!("{}", i)))
+            .collect::<Vec<_>>();
+
+        for k in keys.iter().step_by(7) {
+            let _ = map.remove(k);
+        }
+
+        let json = serde_json::to_string(&map).unwrap();
+        let copy: SlotMap<TestKey, usize, String> =
+            serde_json::from_str(&json).unwrap();
+
+        assert_eq!(map.len(), copy.len());
+
+        for k in keys.iter() {
+            assert_eq!(map.get(k), copy.get(k));
+        }
+
+        assert_eq!(json, serde_json::to_string(&copy).unwrap());
+    }
+
+    #[test]
+    fn test_migration_sees_recorded_version() {
+        let mut map = SlotMap::<TestKey, usize, u8>::new();
+
+        let keys = (0..300).map(|i| map.insert(i, i as u8)).collect::<Vec<_>>();
+        let _ = map.remove(&keys[0]);
+
+        for (version, json) in [
+            (0, serde_json::to_string(&map).unwrap()),
+            (
+                7,
+                serde_json::to_string(&map.with_value_version(7)).unwrap(),
+            ),
+        ] {
+            let mut migrated = 0usize;
+
+            let new_map: SlotMap<TestKey, usize, (u32, u16)> =
+                SlotMapMigration::new(|v, old: u8| {
+                    migrated += 1;
+                    (v, old as u16 * 2)
+                })
+                .deserialize(&mut serde_json::Deserializer::from_str(&json))
+                .unwrap();
+
+            // Values in vacant slots are migrated too
+            assert_eq!(300, migrated);
+            assert_eq!(None, new_map.get(&keys[0]));
+
+            for k in keys.iter().skip(1) {
+                assert_eq!(
+                    Some(&(version, (k.pointer as u8) as u16 * 2)),
+                    new_map.get(k)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_canonical_output_is_deterministic() {
+        let mut left = SlotMap::<TestKey, usize, String>::new();
+        let keys = (0..300)
+            .map(|i| left.insert(i, format!("{}", i)))
+            .collect::<Vec<_>>();
+        let mut right = left.clone();
+
+        let _ = left.remove(&keys[1]);
+        let _ = left.remove(&keys[299]);
+        let _ = right.remove(&keys[299]);
+        let _ = right.remove(&keys[1]);
+
+        assert_ne!(
+            serde_json::to_string(&left).unwrap(),
+            serde_json::to_string(&right).unwrap()
+        );
+
+        left.canonicalize();
+        right.canonicalize();
+
+        let json = serde_json::to_string(&left).unwrap();
+        assert_eq!(json, serde_json::to_string(&right).unwrap());
+        assert_eq!(json, serde_json::to_string(&left.clone()).unwrap());
+
+        // Loading canonical output and writing it again is byte-for-byte
+        // stable
+        let loaded: SlotMap<TestKey, usize, String> =
+            serde_json::from_str(&json).unwrap();
+        assert_eq!(json, serde_json::to_string(&loaded).unwrap());
+    }
+
+    #[test]
+    fn test_borrowed_values() {
+        use std::borrow::Cow;
+
+        #[derive(Serialize, Deserialize)]
+        struct Named<'a> {
+            #[serde(borrow)]
+            name: Cow<'a, str>,
+        }
+
+        let mut map = SlotMap::<TestKey, usize, Named<'static>>::new();
+        let keys = (0..300)
+            .map(|i| {
+                map.insert(
+                    i,
+                    Named {
+                        name: Cow::Owned(format!("name {}", i)),
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+        let json = serde_json::to_string(&map).unwrap();
+
+        let borrowed: SlotMap<TestKey, usize, Named<'_>> =
+            serde_json::from_str(&json).unwrap();
+
+        for k in &keys {
+            let name = &borrowed.get(k).unwrap().name;
+
+            assert!(matches!(name, Cow::Borrowed(_)));
+            assert_eq!(&map.get(k).unwrap().name, name);
+        }
+
+        let mut strs = SlotMap::<TestKey, usize, &'static str>::new();
+        let key = strs.insert(0, "Hello");
+        let json = serde_json::to_string(&strs).unwrap();
+
+        let borrowed: SlotMap<TestKey, usize, &str> =
+            serde_json::from_str(&json).unwrap();
+        assert_eq!(Some(&"Hello"), borrowed.get(&key));
+    }
+
+    #[test]
+    fn test_chunks_before_header_rejected() {
+        let json = r#"{"chunks":[],"header":{"len":0,"next_open_slot":0,"chunk_count":0}}"#;
+
+        assert!(
+            serde_json::from_str::<SlotMap<TestKey, usize, String>>(json)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_structural_error_surfaces() {
+        let json = r#"{"header":{"len":0,"next_open_slot":0,"chunk_count":0},"chunks":[[[0,"a"]]]}"#;
+
+        let err = serde_json::from_str::<SlotMap<TestKey, usize, String>>(json)
+            .unwrap_err();
+
+        assert!(err.to_string().contains("more than 0 chunks"));
+    }
+}
@@ -0,0 +1,160 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+
+/// Allocator of keys with no values attached, for systems that only need
+/// stable IDs and a way to tell if they're still live. It hands out keys
+/// exactly like a [`SlotMap`] would, with the same key data, generations,
+/// and slot reuse, but the slots only hold key data
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(EntityId<()>);
+/// let mut entities = SlotSet::<EntityId, ()>::new();
+///
+/// let player = entities.insert(());
+/// assert!(entities.contains_key(&player));
+///
+/// assert!(entities.remove(&player));
+/// assert!(!entities.contains_key(&player));
+///
+/// // The slot is reused with a new generation, so the old ID stays dead
+/// let enemy = entities.insert(());
+/// assert!(entities.contains_key(&enemy));
+/// assert!(!entities.contains_key(&player));
+/// ```
+pub struct SlotSet<K, P>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, ()>,
+}
+
+impl<K, P> std::fmt::Debug for SlotSet<K, P>
+where
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter_raw()).finish()
+    }
+}
+
+impl<K, P> Default for SlotSet<K, P>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        SlotSet::new()
+    }
+}
+
+impl<K, P> Clone for SlotSet<K, P>
+where
+    K: SlotMapKey<P>,
+{
+    fn clone(&self) -> Self {
+        SlotSet {
+            map: self.map.clone(),
+        }
+    }
+}
+
+impl<K, P> SlotSet<K, P>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty set
+    pub fn new() -> SlotSet<K, P> {
+        SlotSet {
+            map: SlotMap::new(),
+        }
+    }
+
+    /// Get the number of live keys in the set
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the set has no live keys
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Allocate a new key with the given pointer data
+    pub fn insert(&mut self, pointer: P) -> K {
+        self.map.insert(pointer, ())
+    }
+
+    /// Same as insert, but only returns slot map key data
+    pub fn insert_raw(&mut self) -> SlotMapKeyData {
+        self.map.insert_raw(())
+    }
+
+    /// Tells if the given key is live
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Same as contains_key, but only requires slot map key data
+    pub fn contains_key_raw(&self, key_data: &SlotMapKeyData) -> bool {
+        self.map.contains_key_raw(key_data)
+    }
+
+    /// Release the given key so its slot can be reused. Returns whether the
+    /// key was live
+    pub fn remove(&mut self, key: &K) -> bool {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> bool {
+        self.map.remove_raw(key_data).is_some()
+    }
+
+    /// Release all keys
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Create an iterator over the key data of all live keys
+    pub fn iter_raw(&self) -> impl Iterator<Item = SlotMapKeyData> + '_ {
+        self.map.iter_raw().map(|(key_data, _)| key_data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+
+    #[test]
+    fn test_keys_match_slot_map() {
+        let mut set = SlotSet::<TestKey, usize>::new();
+        let mut map = SlotMap::<TestKey, usize, ()>::new();
+
+        let mut live = Vec::new();
+
+        for i in 0..3000 {
+            if i % 3 == 2 {
+                let key: TestKey = live.remove((i * 7) % live.len());
+                assert!(set.remove(&key));
+                assert!(!set.remove(&key));
+                assert!(map.remove(&key).is_some());
+                assert!(!set.contains_key(&key));
+            } else {
+                let key = set.insert(i);
+                assert_eq!(key, map.insert(i, ()));
+                live.push(key);
+            }
+        }
+
+        assert_eq!(live.len(), set.len());
+        assert!(live.iter().all(|key| set.contains_key(key)));
+        assert_eq!(
+            map.iter_raw().map(|(k, _)| k).collect::<Vec<_>>(),
+            set.iter_raw().collect::<Vec<_>>()
+        );
+
+        set.clear();
+        assert!(set.is_empty());
+        assert!(live.iter().all(|key| !set.contains_key(key)));
+    }
+}
@@ -0,0 +1,195 @@
+use std::alloc::{alloc, dealloc, handle_alloc_error, Layout};
+use std::mem::MaybeUninit;
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::sync::atomic::{fence, AtomicUsize, Ordering};
+
+/// Reference counted pointer to a chunk of slots. It works like an `Arc`
+/// without weak references, but its memory is allocated directly, so a chunk
+/// can be allocated without aborting when the allocator runs out of memory,
+/// which `Arc` can't do on stable Rust
+pub(crate) struct SharedChunk<C> {
+    ptr: NonNull<SharedChunkInner<C>>,
+}
+
+/// The allocation behind a shared chunk, with the count of references in
+/// front of the chunk. It is repr(C) so chunks with the same layout can share
+/// one allocation when a pointer is cast from one to the other
+#[repr(C)]
+struct SharedChunkInner<C> {
+    references: AtomicUsize,
+    chunk: C,
+}
+
+// Safety - Like an Arc, a shared chunk gives shared access to its chunk from
+// every thread holding a reference, and the last of them drops it
+unsafe impl<C: Send + Sync> Send for SharedChunk<C> {}
+unsafe impl<C: Send + Sync> Sync for SharedChunk<C> {}
+
+impl<C> SharedChunk<MaybeUninit<C>> {
+    /// Allocate a chunk without initializing it, or return None if the
+    /// memory isn't available
+    pub(crate) fn try_new_uninit() -> Option<SharedChunk<MaybeUninit<C>>> {
+        let layout = Self::layout();
+
+        // Safety - The reference count keeps the layout from having a size of
+        // zero, and it is written before the pointer is used
+        unsafe {
+            let ptr = NonNull::new(
+                alloc(layout) as *mut SharedChunkInner<MaybeUninit<C>>
+            )?;
+
+            std::ptr::addr_of_mut!((*ptr.as_ptr()).references)
+                .write(AtomicUsize::new(1));
+
+            Some(SharedChunk { ptr })
+        }
+    }
+
+    /// Allocate a chunk without initializing it, handling running out of
+    /// memory like the rest of the standard library does
+    pub(crate) fn new_uninit() -> SharedChunk<MaybeUninit<C>> {
+        Self::try_new_uninit()
+            .unwrap_or_else(|| handle_alloc_error(Self::layout()))
+    }
+
+    /// Treat the chunk as initialized
+    ///
+    /// # Safety
+    /// The chunk must be valid for the initialized type
+    pub(crate) unsafe fn assume_init(self) -> SharedChunk<C> {
+        self.cast()
+    }
+}
+
+impl<C> SharedChunk<C> {
+    /// Get the layout of the allocation behind a chunk
+    pub(crate) fn layout() -> Layout {
+        Layout::new::<SharedChunkInner<C>>()
+    }
+
+    /// Get a pointer to the chunk
+    pub(crate) fn as_ptr(this: &Self) -> *const C {
+        // Safety - The allocation lives as long as this reference to it
+        unsafe { std::ptr::addr_of!((*this.ptr.as_ptr()).chunk) }
+    }
+
+    /// Get mutable access to the chunk if no other reference to it exists
+    pub(crate) fn get_mut(this: &mut Self) -> Option<&mut C> {
+        // Acquire pairs with the release when other references are dropped,
+        // so their accesses to the chunk happen before this one
+        if this.inner().references.load(Ordering::Acquire) == 1 {
+            // Safety - This is the only reference to the chunk
+            Some(unsafe { &mut (*this.ptr.as_ptr()).chunk })
+        } else {
+            None
+        }
+    }
+
+    /// Reinterpret the chunk as another type without moving it
+    ///
+    /// # Safety
+    /// The other type must have the same layout as the chunk, and the chunk
+    /// must be valid for it
+    pub(crate) unsafe fn cast<D>(self) -> SharedChunk<D> {
+        let this = std::mem::ManuallyDrop::new(self);
+
+        SharedChunk {
+            ptr: this.ptr.cast(),
+        }
+    }
+
+    fn inner(&self) -> &SharedChunkInner<C> {
+        // Safety - The allocation lives as long as this reference to it
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<C> Clone for SharedChunk<C> {
+    fn clone(&self) -> Self {
+        // Like Arc, new references only need to be counted, and running the
+        // count up to where it could overflow is treated as a fatal bug
+        if self.inner().references.fetch_add(1, Ordering::Relaxed)
+            > isize::MAX as usize
+        {
+            std::process::abort();
+        }
+
+        SharedChunk { ptr: self.ptr }
+    }
+}
+
+impl<C> Deref for SharedChunk<C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        &self.inner().chunk
+    }
+}
+
+impl<C> Drop for SharedChunk<C> {
+    fn drop(&mut self) {
+        if self.inner().references.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+
+        // Every other reference's accesses happen before the chunk is dropped
+        fence(Ordering::Acquire);
+
+        // Safety - This was the last reference, and the memory is freed with
+        // the layout it was allocated with
+        unsafe {
+            std::ptr::drop_in_place(std::ptr::addr_of_mut!(
+                (*self.ptr.as_ptr()).chunk
+            ));
+            dealloc(self.ptr.as_ptr() as *mut u8, Self::layout());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct DropCounter<'a>(&'a AtomicUsize);
+
+    impl Drop for DropCounter<'_> {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_shared_chunk_is_dropped_with_last_reference() {
+        let drops = AtomicUsize::new(0);
+
+        let mut chunk =
+            SharedChunk::<MaybeUninit<DropCounter<'_>>>::new_uninit();
+        SharedChunk::get_mut(&mut chunk)
+            .expect("new chunk is not shared")
+            .write(DropCounter(&drops));
+
+        // Safety - The chunk was just written
+        let mut chunk = unsafe { chunk.assume_init() };
+        let other = chunk.clone();
+
+        assert!(SharedChunk::get_mut(&mut chunk).is_none());
+        assert_eq!(SharedChunk::as_ptr(&chunk), SharedChunk::as_ptr(&other));
+
+        drop(other);
+        assert!(SharedChunk::get_mut(&mut chunk).is_some());
+        assert_eq!(0, drops.load(Ordering::Relaxed));
+
+        drop(chunk);
+        assert_eq!(1, drops.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_uninit_shared_chunk_drops_nothing() {
+        let chunk =
+            SharedChunk::<MaybeUninit<DropCounter<'_>>>::try_new_uninit()
+                .expect("allocation failed");
+
+        drop(chunk);
+    }
+}
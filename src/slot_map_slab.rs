@@ -0,0 +1,248 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+
+/// Key data with only the coordinates of the given key data, which is all a
+/// slab key carries
+fn coordinates(key_data: &SlotMapKeyData) -> SlotMapKeyData {
+    SlotMapKeyData {
+        generation: 0,
+        ..*key_data
+    }
+}
+
+/// Slot map that doesn't check generations, so keys are just the coordinates
+/// of their slots like the keys of a slab. Lookups skip the generation
+/// comparison, but a key that outlives its item will find whatever item is
+/// put in its slot next, so this is only for code that never holds on to
+/// keys of removed items. The storage is the same as a [`SlotMap`], and keys
+/// use the same key data, so a map can be switched into slab mode with
+/// `From` and keys it already handed out keep working
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()>);
+/// let mut slab = SlabSlotMap::<TestKey, (), &'static str>::new();
+///
+/// let first = slab.insert((), "first");
+/// assert_eq!(Some(&mut "first"), slab.remove(&first));
+///
+/// // The first key finds the item that took its slot
+/// let second = slab.insert((), "second");
+/// assert_eq!(Some(&"second"), slab.get(&first));
+/// assert_eq!(Some(&"second"), slab.get(&second));
+/// ```
+pub struct SlabSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, T>,
+}
+
+impl<K, P, T> std::fmt::Debug for SlabSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.values()).finish()
+    }
+}
+
+impl<K, P, T> Default for SlabSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        SlabSlotMap::new()
+    }
+}
+
+impl<K, P, T> Clone for SlabSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        SlabSlotMap {
+            map: self.map.clone(),
+        }
+    }
+}
+
+impl<K, P, T> From<SlotMap<K, P, T>> for SlabSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn from(map: SlotMap<K, P, T>) -> Self {
+        SlabSlotMap { map }
+    }
+}
+
+impl<K, P, T> SlabSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> SlabSlotMap<K, P, T> {
+        SlabSlotMap {
+            map: SlotMap::new(),
+        }
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Insert the given item into the map and return its key, which holds
+    /// only the coordinates of the item's slot
+    pub fn insert(&mut self, pointer: P, value: T) -> K {
+        let key_data = self.map.insert_raw(value);
+
+        K::from((pointer, coordinates(&key_data)))
+    }
+
+    /// Get a reference to the item in the given key's slot if there is one
+    #[inline]
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    #[inline]
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.map.get_ignoring_generation(key_data)
+    }
+
+    /// Get a mutable reference to the item in the given key's slot if there
+    /// is one
+    #[inline]
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Same as get_mut, but only requires slot map key data
+    #[inline]
+    pub fn get_mut_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        self.map.get_mut_ignoring_generation(key_data)
+    }
+
+    /// Tells if there is an item in the given key's slot
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Remove the item in the given key's slot and return a mutable ref to it
+    /// if there was one
+    pub fn remove(&mut self, key: &K) -> Option<&mut T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        self.map.remove_ignoring_generation(key_data)
+    }
+
+    /// Remove all items from the map
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Create an iterator over the raw key data and values of all items in
+    /// the map. The key data holds only coordinates, like the keys from
+    /// [`insert`](Self::insert)
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.map
+            .iter_raw()
+            .map(|(key_data, value)| (coordinates(&key_data), value))
+    }
+
+    /// Create an iterator over the raw key data and mutable values of all
+    /// items in the map
+    pub fn iter_mut_raw(
+        &mut self,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &mut T)> {
+        self.map
+            .iter_mut_raw()
+            .map(|(key_data, value)| (coordinates(&key_data), value))
+    }
+
+    /// Create an iterator over the values of all items in the map
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.map.values()
+    }
+
+    /// Create an iterator over the mutable values of all items in the map
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.map.values_mut()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use crate::SLOT_MAP_CHUNK_SIZE;
+    use std::borrow::Borrow;
+
+    #[test]
+    fn test_slab_uses_slot_map_coordinates() {
+        let mut slab = SlabSlotMap::<TestKey, usize, usize>::new();
+        let mut expected = SlotMap::<TestKey, usize, usize>::new();
+
+        // Slab keys paired with the slot map keys for the same items
+        let mut keys = Vec::new();
+
+        for i in 0..SLOT_MAP_CHUNK_SIZE * 3 {
+            let key = slab.insert(i, i);
+            let expected_key = expected.insert(i, i);
+
+            assert_eq!(
+                coordinates(expected_key.borrow()),
+                *Borrow::<SlotMapKeyData>::borrow(&key)
+            );
+            keys.push((key, expected_key));
+
+            if i % 3 == 0 {
+                let (key, expected_key) = keys.swap_remove(i * 7 % keys.len());
+                assert_eq!(Some(&mut key.pointer.clone()), slab.remove(&key));
+                assert!(expected.remove(&expected_key).is_some());
+                assert!(!slab.contains_key(&key));
+                assert_eq!(None, slab.remove(&key));
+            }
+        }
+
+        let keys = keys.into_iter().map(|(k, _)| k).collect::<Vec<_>>();
+
+        assert_eq!(keys.len(), slab.len());
+
+        for key in &keys {
+            *slab.get_mut(key).unwrap() += 1;
+            assert_eq!(Some(&(key.pointer + 1)), slab.get(key));
+        }
+
+        for (key_data, value) in slab.iter_raw() {
+            assert_eq!(0, key_data.generation);
+            assert_eq!(Some(value), slab.get_raw(&key_data));
+        }
+
+        // Keys from a slot map keep working after switching to slab mode
+        let expected_keys = expected
+            .iter_raw()
+            .map(|(k, v)| TestKey::from((*v, k)))
+            .collect::<Vec<_>>();
+        let converted = SlabSlotMap::from(expected);
+
+        for key in &expected_keys {
+            assert_eq!(Some(&key.pointer), converted.get(key));
+        }
+
+        slab.clear();
+        assert!(slab.is_empty());
+        assert!(keys.iter().all(|k| !slab.contains_key(k)));
+    }
+}
@@ -0,0 +1,260 @@
+//! Loading of maps written by the serde support of the `slotmap` crate. Its
+//! `SlotMap`, `HopSlotMap`, and `DenseSlotMap` all serialize as a sequence of
+//! slots, each holding an optional value and a version, where odd versions
+//! are occupied and the first slot is an unoccupied sentinel
+
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+use serde::de::{Error, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt::Formatter;
+use std::marker::PhantomData;
+
+/// Key data in the format used by the `slotmap` crate, both for serde and for
+/// `KeyData::as_ffi`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SlotmapKeyData {
+    /// Index of the slot the key refers to
+    pub idx: u32,
+
+    /// Version of the slot the key refers to. Keys to occupied slots have odd
+    /// versions
+    pub version: u32,
+}
+
+impl SlotmapKeyData {
+    /// Unpack key data produced by `slotmap::KeyData::as_ffi`
+    pub fn from_ffi(value: u64) -> SlotmapKeyData {
+        SlotmapKeyData {
+            idx: value as u32,
+            version: (value >> 32) as u32,
+        }
+    }
+
+    /// Pack this key data the same way as `slotmap::KeyData::as_ffi`
+    pub fn as_ffi(&self) -> u64 {
+        ((self.version as u64) << 32) | self.idx as u64
+    }
+}
+
+/// Table translating keys of a map written by the `slotmap` crate into keys
+/// of the map it was loaded into
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotmapKeyTranslation {
+    /// Version and new key data for every occupied slot, by slot index
+    slots: Vec<Option<(u32, SlotMapKeyData)>>,
+}
+
+impl SlotmapKeyTranslation {
+    /// Find the key data for the value that the given key referred to in the
+    /// original map. Keys that were not live in the original map produce
+    /// `None`
+    pub fn translate(&self, key: SlotmapKeyData) -> Option<SlotMapKeyData> {
+        self.slots
+            .get(key.idx as usize)
+            .copied()
+            .flatten()
+            .filter(|(version, _)| *version == key.version)
+            .map(|(_, key_data)| key_data)
+    }
+
+    /// Get the number of keys that can be translated
+    pub fn len(&self) -> usize {
+        self.slots.iter().filter(|s| s.is_some()).count()
+    }
+
+    /// Tells if no keys can be translated
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(|s| s.is_none())
+    }
+}
+
+/// A single slot as serialized by the `slotmap` crate
+#[derive(Deserialize)]
+struct SlotmapSlot<T> {
+    value: Option<T>,
+    version: u32,
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Load a map serialized by the `slotmap` crate's `SlotMap`,
+    /// `HopSlotMap`, or `DenseSlotMap`. The live values are stored compactly
+    /// in order of their original slots, and the returned table translates
+    /// the original keys into keys of the new map
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let saved = r#"[
+    ///     { "value": null, "version": 0 },
+    ///     { "value": "Hello", "version": 1 },
+    ///     { "value": null, "version": 2 },
+    ///     { "value": "World", "version": 3 }
+    /// ]"#;
+    ///
+    /// let (map, translation) =
+    ///     SlotMap::<TestKey, (), String>::deserialize_slotmap_format(
+    ///         &mut serde_json::Deserializer::from_str(saved),
+    ///     )
+    ///     .unwrap();
+    ///
+    /// let old_key = SlotmapKeyData { idx: 3, version: 3 };
+    /// let new_key_data = translation.translate(old_key).unwrap();
+    ///
+    /// assert_eq!(2, map.len());
+    /// assert_eq!(Some(&"World".to_owned()), map.get_raw(&new_key_data));
+    /// ```
+    pub fn deserialize_slotmap_format<'de, D>(
+        deserializer: D,
+    ) -> Result<(Self, SlotmapKeyTranslation), D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+    {
+        deserializer.deserialize_seq(SlotmapVisitor(PhantomData))
+    }
+}
+
+struct SlotmapVisitor<K, P, T>(PhantomData<SlotMap<K, P, T>>)
+where
+    K: SlotMapKey<P>;
+
+impl<'de, K, P, T> Visitor<'de> for SlotmapVisitor<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Deserialize<'de>,
+{
+    type Value = (SlotMap<K, P, T>, SlotmapKeyTranslation);
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("a sequence of slotmap slots")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut map = SlotMap::new();
+        let mut slots = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+
+        while let Some(slot) = seq.next_element::<SlotmapSlot<T>>()? {
+            let occupied = slot.version % 2 == 1;
+
+            if occupied != slot.value.is_some() {
+                return Err(Error::custom("inconsistent occupation in slot"));
+            }
+
+            if slots.is_empty() && occupied {
+                return Err(Error::custom("first slot not empty"));
+            }
+
+            if slots.len() == u32::MAX as usize {
+                return Err(Error::custom("too many slots"));
+            }
+
+            slots.push(
+                slot.value
+                    .map(|value| (slot.version, map.insert_raw(value))),
+            );
+        }
+
+        if slots.is_empty() {
+            return Err(Error::custom("first slot not empty"));
+        }
+
+        Ok((map, SlotmapKeyTranslation { slots }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use slotmap::Key;
+
+    fn load(
+        json: &str,
+    ) -> (SlotMap<TestKey, usize, String>, SlotmapKeyTranslation) {
+        SlotMap::deserialize_slotmap_format(
+            &mut serde_json::Deserializer::from_str(json),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_load_slotmap_output() {
+        let mut original = slotmap::SlotMap::new();
+
+        let keys = (0..600)
+            .map(|i| original.insert(format!("{}", i)))
+            .collect::<Vec<_>>();
+        let _ = original.remove(keys[7]);
+        let _ = original.remove(keys[300]);
+        let reused = original.insert("reused".to_owned());
+
+        let (map, translation) =
+            load(&serde_json::to_string(&original).unwrap());
+
+        assert_eq!(original.len(), map.len());
+        assert_eq!(original.len(), translation.len());
+
+        for k in keys.iter().chain(Some(&reused)) {
+            let old = SlotmapKeyData::from_ffi(k.data().as_ffi());
+            let new = translation.translate(old);
+
+            assert_eq!(original.get(*k), new.and_then(|n| map.get_raw(&n)));
+        }
+    }
+
+    #[test]
+    fn test_load_dense_slotmap_output() {
+        let mut original = slotmap::DenseSlotMap::new();
+
+        let keys = (0..50)
+            .map(|i| original.insert(format!("{}", i)))
+            .collect::<Vec<_>>();
+        let _ = original.remove(keys[0]);
+        let _ = original.remove(keys[25]);
+
+        let (map, translation) =
+            load(&serde_json::to_string(&original).unwrap());
+
+        assert_eq!(original.len(), map.len());
+
+        for k in &keys {
+            let old = SlotmapKeyData::from_ffi(k.data().as_ffi());
+            let new = translation.translate(old);
+
+            assert_eq!(original.get(*k), new.and_then(|n| map.get_raw(&n)));
+        }
+
+        // Keys are also serialized in the slotmap crate's format
+        let old: SlotmapKeyData =
+            serde_json::from_str(&serde_json::to_string(&keys[1]).unwrap())
+                .unwrap();
+        assert_eq!(keys[1].data().as_ffi(), old.as_ffi());
+    }
+
+    #[test]
+    fn test_malformed_input_rejected() {
+        let load = |json: &str| {
+            SlotMap::<TestKey, usize, String>::deserialize_slotmap_format(
+                &mut serde_json::Deserializer::from_str(json),
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+        };
+
+        assert!(load("[]").unwrap_err().contains("first slot not empty"));
+        assert!(load(r#"[{"value":"a","version":1}]"#)
+            .unwrap_err()
+            .contains("first slot not empty"));
+        assert!(load(
+            r#"[{"value":null,"version":0},{"value":"a","version":2}]"#
+        )
+        .unwrap_err()
+        .contains("inconsistent occupation"));
+    }
+}
@@ -0,0 +1,159 @@
+//! Conversions between the keys of the `slotmap` crate and this crate, so
+//! both can address the same items while a project moves from one to the
+//! other. A `slotmap` key's slot index is one more than the position of the
+//! corresponding slot here, because `slotmap` reserves its first slot, and
+//! its odd versions map to the even generations of filled slots
+
+use super::slot_map_key_data::MAX_GENERATION;
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+
+impl From<slotmap::KeyData> for SlotMapKeyData {
+    /// Translate the key data of an item in a `slotmap` map. Generations only
+    /// have 24 bits, so versions past that wrap around
+    fn from(key_data: slotmap::KeyData) -> Self {
+        let packed = key_data.as_ffi();
+        let idx = packed as u32;
+        let version = (packed >> 32) as u32;
+
+        SlotMapKeyData {
+            generation: (version - 1) & MAX_GENERATION,
+            ..SlotMapKeyData::from(idx.wrapping_sub(1) as u64)
+        }
+    }
+}
+
+impl TryFrom<SlotMapKeyData> for slotmap::KeyData {
+    type Error = SlotMapKeyData;
+
+    /// Translate the key data of an item into the `slotmap` form. Key data
+    /// of vacant slots, and of slots further along than a `slotmap` map can
+    /// reach, can't be translated and is given back
+    fn try_from(key_data: SlotMapKeyData) -> Result<Self, Self::Error> {
+        let idx = key_data.position() + 1;
+
+        if !key_data.is_filled() || idx >= u32::MAX as usize {
+            return Err(key_data);
+        }
+
+        let version = key_data.generation as u64 + 1;
+
+        Ok(slotmap::KeyData::from_ffi((version << 32) | idx as u64))
+    }
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Default,
+{
+    /// Build a map holding the given items of a `slotmap` map, such as a
+    /// `DenseSlotMap`, `SlotMap`, or `HopSlotMap`, each in the slot its key
+    /// translates to, so the translated keys of the live items keep working.
+    /// Slots without an item are left vacant holding default values. Which
+    /// slots were vacant in the original map, and their versions, aren't
+    /// visible from its items, so keys to items removed before the conversion
+    /// could match items inserted afterward
+    ///
+    /// # Panics
+    /// Panics if an item's slot is further along than
+    /// [`SLOT_MAP_CHUNK_SIZE`](crate::SLOT_MAP_CHUNK_SIZE) times the number
+    /// of items, which only a map with almost all of its items removed has
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut original = slotmap::DenseSlotMap::new();
+    ///
+    /// let hello = original.insert("Hello");
+    /// let removed = original.insert("Removed");
+    /// let world = original.insert("World");
+    /// original.remove(removed);
+    ///
+    /// let map = SlotMap::<TestKey, (), &str>::from_slotmap(original);
+    ///
+    /// let key_data = SlotMapKeyData::from(slotmap::Key::data(&world));
+    /// assert_eq!(Some(&"World"), map.get_raw(&key_data));
+    /// assert_eq!(2, map.len());
+    /// ```
+    pub fn from_slotmap<SK: slotmap::Key>(
+        items: impl IntoIterator<Item = (SK, T)>,
+    ) -> SlotMap<K, P, T> {
+        SlotMap::from_filled_slots(
+            items
+                .into_iter()
+                .map(|(key, value)| (SlotMapKeyData::from(key.data()), value))
+                .collect(),
+        )
+        .expect("Items of a slotmap map must not be too sparse")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::seeded_rng;
+    use crate::test_support::TestKey;
+    use rand::Rng;
+
+    #[test]
+    fn test_translated_keys_stay_valid() {
+        let mut rng = seeded_rng(12);
+        let mut original =
+            slotmap::DenseSlotMap::<slotmap::DefaultKey, usize>::new();
+        let mut live = Vec::new();
+        let mut removed = Vec::new();
+
+        for i in 0..2000 {
+            if live.is_empty() || rng.gen_bool(0.6) {
+                live.push(original.insert(i));
+            } else {
+                let key = live.swap_remove(rng.gen_range(0..live.len()));
+                let _ = original.remove(key);
+                removed.push(key);
+            }
+        }
+
+        let expected =
+            live.iter().map(|key| original[*key]).collect::<Vec<_>>();
+        let mut map = SlotMap::<TestKey, usize, usize>::from_slotmap(original);
+
+        assert_eq!(live.len(), map.len());
+
+        for (key, value) in live.iter().zip(&expected) {
+            let key_data = SlotMapKeyData::from(slotmap::Key::data(key));
+
+            assert_eq!(Some(value), map.get_raw(&key_data));
+            assert_eq!(
+                Ok(slotmap::Key::data(key)),
+                slotmap::KeyData::try_from(key_data)
+            );
+        }
+
+        for key in &removed {
+            let key_data = SlotMapKeyData::from(slotmap::Key::data(key));
+            assert_eq!(None, map.get_raw(&key_data));
+        }
+
+        // The map keeps working as usual, filling the vacant slots first
+        let slot_count = map.slots().slot_count();
+        let vacant = slot_count - map.len();
+
+        for i in 0..vacant {
+            let _ = map.insert(i, i);
+        }
+
+        assert_eq!(slot_count, map.slots().slot_count());
+
+        let _ = map.insert(0, 0);
+        assert_eq!(slot_count + 1, map.slots().slot_count());
+
+        let vacant_key_data = SlotMapKeyData {
+            generation: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            Err(vacant_key_data),
+            slotmap::KeyData::try_from(vacant_key_data)
+        );
+    }
+}
@@ -0,0 +1,750 @@
+use super::slot_map::{ChunkRef, Slots};
+use super::SLOT_MAP_CHUNK_SIZE;
+use super::{SlotMap, SlotMapKey, SlotMapKeyData, SnapshotError};
+
+/// Bookkeeping information that precedes the chunks of a slot map snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SnapshotHeader {
+    /// Number of filled slots in the map
+    pub len: usize,
+
+    /// Head of the chain of vacant slots threaded through the map
+    pub next_open_slot: SlotMapKeyData,
+
+    /// Number of chunks that follow this header
+    pub chunk_count: usize,
+
+    /// Version of the representation the values were written with. This is
+    /// not interpreted by this crate, but is handed to migrations when the
+    /// snapshot is loaded. Defaults to 0
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub value_version: u32,
+}
+
+/// A single chunk of slots borrowed from a map being written. Vacant slots are
+/// included because the values they hold are still owned by the map, and
+/// because their key data forms the chain of open slots
+pub struct SnapshotChunk<'a, T> {
+    chunk_index: usize,
+    slots: ChunkRef<'a, T>,
+}
+
+impl<'a, T> std::fmt::Debug for SnapshotChunk<'a, T>
+where
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SnapshotChunk")
+            .field("chunk_index", &self.chunk_index)
+            .field("slots", &self.slots().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl<'a, T> SnapshotChunk<'a, T> {
+    /// Index of this chunk within the map
+    pub fn chunk_index(&self) -> usize {
+        self.chunk_index
+    }
+
+    /// Create an iterator over the raw key data and value of every
+    /// initialized slot in this chunk
+    pub fn slots(
+        &self,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &'a T)> + Clone {
+        self.slots
+            .iter()
+            .map(|(key_data, value)| (*key_data, value))
+    }
+
+    /// The raw key data of every initialized slot in this chunk, which is
+    /// stored apart from the values
+    pub fn keys(&self) -> &'a [SlotMapKeyData] {
+        self.slots.keys()
+    }
+
+    /// The value of every initialized slot in this chunk, in the same order
+    /// as the key data
+    pub fn values(&self) -> &'a [T] {
+        self.slots.values()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'a, T> serde::Serialize for SnapshotChunk<'a, T>
+where
+    T: serde::Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.slots())
+    }
+}
+
+/// Iterator over the chunks of a slot map that allows the map to be written
+/// out incrementally. Created with
+/// [`SlotMap::snapshot_writer`](crate::SlotMap::snapshot_writer)
+pub struct SnapshotWriter<'a, T> {
+    slots: &'a Slots<T>,
+    header: SnapshotHeader,
+    next_chunk_index: usize,
+}
+
+impl<'a, T> SnapshotWriter<'a, T> {
+    pub(crate) fn new(
+        slots: &'a Slots<T>,
+        header: SnapshotHeader,
+    ) -> SnapshotWriter<'a, T> {
+        SnapshotWriter {
+            slots,
+            header,
+            next_chunk_index: 0,
+        }
+    }
+
+    /// Get the header that must be written before the chunks
+    pub fn header(&self) -> SnapshotHeader {
+        self.header
+    }
+
+    /// Record the given version of the value representation in the header
+    pub fn with_value_version(mut self, value_version: u32) -> Self {
+        self.header.value_version = value_version;
+        self
+    }
+}
+
+impl<'a, T> std::fmt::Debug for SnapshotWriter<'a, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SnapshotWriter")
+            .field("header", &self.header)
+            .field("next_chunk_index", &self.next_chunk_index)
+            .finish()
+    }
+}
+
+impl<'a, T> Clone for SnapshotWriter<'a, T> {
+    fn clone(&self) -> Self {
+        SnapshotWriter {
+            slots: self.slots,
+            header: self.header,
+            next_chunk_index: self.next_chunk_index,
+        }
+    }
+}
+
+impl<'a, T> Iterator for SnapshotWriter<'a, T> {
+    type Item = SnapshotChunk<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk_index = self.next_chunk_index;
+        let slots = self.slots.chunk(chunk_index)?;
+
+        self.next_chunk_index += 1;
+
+        Some(SnapshotChunk { chunk_index, slots })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.header.chunk_count - self.next_chunk_index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for SnapshotWriter<'a, T> {}
+
+/// Incremental reconstruction of a slot map from a header and a sequence of
+/// chunks produced by a [`SnapshotWriter`]. Slots are moved directly into
+/// the new map's storage as they are supplied, so at most one chunk needs to
+/// be decoded at a time
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()>);
+/// let mut map = SlotMap::<TestKey,(),String>::new();
+/// let key = map.insert((), "Hello".to_owned());
+///
+/// let writer = map.snapshot_writer();
+/// let mut builder = SnapshotBuilder::new(writer.header());
+///
+/// for chunk in writer {
+///     builder.push_chunk(chunk.slots().map(|(k, v)| (k, v.clone()))).unwrap();
+/// }
+///
+/// let copy: SlotMap<TestKey,(),String> = builder.build().unwrap();
+///
+/// assert_eq!(Some(&"Hello".to_owned()), copy.get(&key));
+/// ```
+pub struct SnapshotBuilder<T> {
+    header: SnapshotHeader,
+    slots: Slots<T>,
+    chunks_received: usize,
+    slots_in_chunk: usize,
+    saw_partial_chunk: bool,
+
+    /// Index of the chunk that overflowed, after which the slots received no
+    /// longer line up with their chunks and nothing more is accepted
+    overflowed_chunk: Option<usize>,
+}
+
+impl<T> std::fmt::Debug for SnapshotBuilder<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SnapshotBuilder")
+            .field("header", &self.header)
+            .field("chunks_received", &self.chunks_received)
+            .finish()
+    }
+}
+
+impl<T> SnapshotBuilder<T> {
+    /// Create a builder for the snapshot described by the given header
+    pub fn new(header: SnapshotHeader) -> SnapshotBuilder<T> {
+        SnapshotBuilder {
+            header,
+            slots: Slots::new(),
+            chunks_received: 0,
+            slots_in_chunk: 0,
+            saw_partial_chunk: false,
+            overflowed_chunk: None,
+        }
+    }
+
+    /// Get the header this builder was created with
+    pub fn header(&self) -> SnapshotHeader {
+        self.header
+    }
+
+    /// Add the next chunk of slots to the map being built. Every chunk except
+    /// the last must contain exactly `SLOT_MAP_CHUNK_SIZE` slots. Once a chunk
+    /// has been rejected for containing too many slots, every later call
+    /// fails with the same error
+    pub fn push_chunk(
+        &mut self,
+        slots: impl IntoIterator<Item = (SlotMapKeyData, T)>,
+    ) -> Result<(), SnapshotError> {
+        self.begin_chunk()?;
+
+        for slot in slots {
+            self.push_slot(slot)?;
+        }
+
+        self.end_chunk()
+    }
+
+    /// Finish building the map. Before the map is created, the supplied slots
+    /// are checked against the header to make sure they describe a consistent
+    /// map: filled slots must sit at the coordinates in their key data, the
+    /// header's length must match the number of filled slots, and every vacant
+    /// slot that hasn't been retired must be reachable exactly once by
+    /// following the chain of open slots from the header
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey,(),usize>::new();
+    /// let _ = map.insert((), 42);
+    ///
+    /// let mut header = map.snapshot_writer().header();
+    /// header.len = 2;
+    ///
+    /// let mut builder = SnapshotBuilder::new(header);
+    ///
+    /// for chunk in map.snapshot_writer() {
+    ///     builder.push_chunk(chunk.slots().map(|(k, v)| (k, *v))).unwrap();
+    /// }
+    ///
+    /// assert_eq!(
+    ///     SnapshotError::LengthMismatch { declared: 2, found: 1 },
+    ///     builder.build::<TestKey, ()>().unwrap_err()
+    /// );
+    /// ```
+    pub fn build<K, P>(self) -> Result<SlotMap<K, P, T>, SnapshotError>
+    where
+        K: SlotMapKey<P>,
+    {
+        self.check_overflow()?;
+
+        if self.chunks_received != self.header.chunk_count {
+            return Err(SnapshotError::MissingChunks {
+                expected: self.header.chunk_count,
+                found: self.chunks_received,
+            });
+        }
+
+        validate(&self.slots, &self.header)?;
+
+        Ok(SlotMap::from_raw_state(
+            self.slots,
+            self.header.next_open_slot,
+            self.header.len,
+        ))
+    }
+
+    /// Check that another chunk can be accepted and prepare to receive its
+    /// slots
+    pub(crate) fn begin_chunk(&mut self) -> Result<(), SnapshotError> {
+        self.check_overflow()?;
+
+        if self.chunks_received == self.header.chunk_count {
+            return Err(SnapshotError::TooManyChunks {
+                expected: self.header.chunk_count,
+            });
+        }
+
+        if self.saw_partial_chunk {
+            return Err(SnapshotError::ChunkAfterPartial {
+                chunk_index: self.chunks_received,
+            });
+        }
+
+        self.slots_in_chunk = 0;
+
+        Ok(())
+    }
+
+    /// Move a single slot into the chunk currently being received
+    pub(crate) fn push_slot(
+        &mut self,
+        slot: (SlotMapKeyData, T),
+    ) -> Result<(), SnapshotError> {
+        self.check_overflow()?;
+
+        if self.slots_in_chunk == SLOT_MAP_CHUNK_SIZE {
+            self.overflowed_chunk = Some(self.chunks_received);
+            return self.check_overflow();
+        }
+
+        self.slots.push_slot(slot);
+        self.slots_in_chunk += 1;
+
+        Ok(())
+    }
+
+    /// Finish receiving the current chunk
+    pub(crate) fn end_chunk(&mut self) -> Result<(), SnapshotError> {
+        self.check_overflow()?;
+
+        if self.slots_in_chunk == 0 {
+            return Err(SnapshotError::EmptyChunk {
+                chunk_index: self.chunks_received,
+            });
+        }
+
+        if self.slots_in_chunk < SLOT_MAP_CHUNK_SIZE {
+            self.saw_partial_chunk = true;
+        }
+
+        self.chunks_received += 1;
+
+        Ok(())
+    }
+
+    /// Fail if a chunk has overflowed
+    fn check_overflow(&self) -> Result<(), SnapshotError> {
+        match self.overflowed_chunk {
+            Some(chunk_index) => {
+                Err(SnapshotError::ChunkOverflow { chunk_index })
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Position of the given coordinates if all slots were laid out end to end
+fn linear_index(key: &SlotMapKeyData) -> usize {
+    key.chunk_index as usize * SLOT_MAP_CHUNK_SIZE + key.index_in_chunk as usize
+}
+
+/// Verify that the given storage and header describe a map that could have
+/// been produced by a sequence of map operations
+pub(crate) fn validate<T>(
+    slots: &Slots<T>,
+    header: &SnapshotHeader,
+) -> Result<(), SnapshotError> {
+    let chunks = (0..slots.chunk_count())
+        .filter_map(|chunk_index| slots.chunk(chunk_index))
+        .collect::<Vec<_>>();
+
+    // Index of the first slot that has never been written. Because only the
+    // last chunk can be partially filled, coordinates can be compared to
+    // this boundary through their linear index
+    let slot_count = slots.slot_count();
+
+    let in_range = |key: &SlotMapKeyData| {
+        (key.index_in_chunk as usize) < SLOT_MAP_CHUNK_SIZE
+            && linear_index(key) <= slot_count
+    };
+
+    let mut filled = 0usize;
+    let mut vacant = 0usize;
+
+    // Retired slots are vacant, but they are kept out of the chain of open
+    // slots, so they aren't counted
+    for (chunk_index, chunk) in chunks.iter().enumerate() {
+        for (index_in_chunk, (key, _)) in chunk.iter().enumerate() {
+            if key.is_filled() {
+                if key.chunk_index as usize != chunk_index
+                    || key.index_in_chunk as usize != index_in_chunk
+                {
+                    return Err(SnapshotError::MisplacedFilledSlot {
+                        chunk_index,
+                        index_in_chunk,
+                    });
+                }
+                filled += 1;
+            } else if !key.is_retired_at(&SlotMapKeyData::from(
+                (chunk_index * SLOT_MAP_CHUNK_SIZE + index_in_chunk) as u64,
+            )) {
+                if !in_range(key) {
+                    return Err(SnapshotError::CoordinatesOutOfRange {
+                        chunk_index,
+                        index_in_chunk,
+                    });
+                }
+                vacant += 1;
+            }
+        }
+    }
+
+    if filled != header.len {
+        return Err(SnapshotError::LengthMismatch {
+            declared: header.len,
+            found: filled,
+        });
+    }
+
+    // Fresh slots take their generation from the head of the chain, so it
+    // must describe a filled slot
+    if !in_range(&header.next_open_slot) || !header.next_open_slot.is_filled() {
+        return Err(SnapshotError::InvalidNextOpenSlot);
+    }
+
+    let mut visited = vec![false; slot_count];
+    let mut cursor = header.next_open_slot;
+    let mut steps = 0usize;
+
+    while linear_index(&cursor) != slot_count {
+        let chunk_index = cursor.chunk_index as usize;
+        let index_in_chunk = cursor.index_in_chunk as usize;
+        let next = &chunks[chunk_index].keys()[index_in_chunk];
+
+        if next.is_filled() {
+            return Err(SnapshotError::FreeListEntersFilledSlot {
+                chunk_index,
+                index_in_chunk,
+            });
+        }
+
+        let seen = &mut visited[linear_index(&cursor)];
+
+        if *seen {
+            return Err(SnapshotError::FreeListCycle {
+                chunk_index,
+                index_in_chunk,
+            });
+        }
+
+        *seen = true;
+        steps += 1;
+        cursor = *next;
+    }
+
+    if steps != vacant {
+        let unreachable = chunks
+            .iter()
+            .flat_map(|chunk| chunk.iter())
+            .zip(visited.iter())
+            .enumerate()
+            .position(|(position, ((key, _), seen))| {
+                !key.is_filled()
+                    && !seen
+                    && !key
+                        .is_retired_at(&SlotMapKeyData::from(position as u64))
+            })
+            .unwrap_or_default();
+
+        return Err(SnapshotError::UnreachableVacantSlot {
+            chunk_index: unreachable / SLOT_MAP_CHUNK_SIZE,
+            index_in_chunk: unreachable % SLOT_MAP_CHUNK_SIZE,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::{churned_map, TestKey};
+    use std::borrow::Borrow;
+
+    fn rebuild(
+        map: &SlotMap<TestKey, usize, String>,
+    ) -> SlotMap<TestKey, usize, String> {
+        let writer = map.snapshot_writer();
+        let mut builder = SnapshotBuilder::new(writer.header());
+
+        for chunk in writer {
+            builder
+                .push_chunk(chunk.slots().map(|(k, v)| (k, v.clone())))
+                .unwrap();
+        }
+
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_with_churn() {
+        let (mut map, live, removed) = churned_map(13, |i| format!("{}", i));
+
+        let mut copy = rebuild(&map);
+
+        assert_eq!(map.len(), copy.len());
+
+        for k in live.iter().chain(removed.iter()) {
+            assert_eq!(map.get(k), copy.get(k));
+        }
+
+        // Both maps must hand out the same slots for new insertions
+        for i in 0..removed.len() + SLOT_MAP_CHUNK_SIZE {
+            let left = map.insert(i, format!("{}", i));
+            let right = copy.insert(i, format!("{}", i));
+            assert_eq!(left, right);
+        }
+    }
+
+    #[test]
+    fn test_chunk_boundaries() {
+        for insertions in [0, 1, SLOT_MAP_CHUNK_SIZE, SLOT_MAP_CHUNK_SIZE + 1] {
+            let mut map = SlotMap::<TestKey, usize, String>::new();
+
+            for i in 0..insertions {
+                let _ = map.insert(i, format!("{}", i));
+            }
+
+            let writer = map.snapshot_writer();
+
+            assert_eq!(insertions.div_ceil(SLOT_MAP_CHUNK_SIZE), writer.len());
+
+            assert_eq!(insertions, rebuild(&map).len());
+        }
+    }
+
+    #[test]
+    fn test_structural_errors() {
+        let mut map = SlotMap::<TestKey, usize, String>::new();
+
+        for i in 0..=SLOT_MAP_CHUNK_SIZE {
+            let _ = map.insert(i, format!("{}", i));
+        }
+
+        let header = map.snapshot_writer().header();
+        let chunks = map
+            .snapshot_writer()
+            .map(|c| c.slots().map(|(k, v)| (k, v.clone())).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let mut builder = SnapshotBuilder::<String>::new(header);
+        builder.push_chunk(chunks[0].clone()).unwrap();
+        assert_eq!(
+            SnapshotError::MissingChunks {
+                expected: 2,
+                found: 1
+            },
+            builder.build::<TestKey, usize>().unwrap_err()
+        );
+
+        let mut builder = SnapshotBuilder::<String>::new(header);
+        assert_eq!(
+            Err(SnapshotError::ChunkOverflow { chunk_index: 0 }),
+            builder
+                .push_chunk(chunks[0].iter().chain(chunks[1].iter()).cloned())
+        );
+
+        // The overflowing slots were partly taken, so the builder refuses
+        // anything more rather than misplacing them
+        assert_eq!(
+            Err(SnapshotError::ChunkOverflow { chunk_index: 0 }),
+            builder.push_chunk(chunks[0].clone())
+        );
+        assert_eq!(
+            SnapshotError::ChunkOverflow { chunk_index: 0 },
+            builder.build::<TestKey, usize>().unwrap_err()
+        );
+
+        let mut builder = SnapshotBuilder::<String>::new(header);
+        builder.push_chunk(chunks[1].clone()).unwrap();
+        assert_eq!(
+            Err(SnapshotError::ChunkAfterPartial { chunk_index: 1 }),
+            builder.push_chunk(chunks[0].clone())
+        );
+
+        let mut builder = SnapshotBuilder::<String>::new(header);
+        assert_eq!(
+            Err(SnapshotError::EmptyChunk { chunk_index: 0 }),
+            builder.push_chunk(Vec::new())
+        );
+
+        let mut builder = SnapshotBuilder::<String>::new(header);
+        builder.push_chunk(chunks[0].clone()).unwrap();
+        builder.push_chunk(chunks[1].clone()).unwrap();
+        assert_eq!(
+            Err(SnapshotError::TooManyChunks { expected: 2 }),
+            builder.push_chunk(chunks[1].clone())
+        );
+    }
+
+    /// Rebuild the given map after letting the given closure tamper with the
+    /// snapshot header and the raw slot key data
+    fn rebuild_tampered(
+        map: &SlotMap<TestKey, usize, String>,
+        tamper: impl FnOnce(&mut SnapshotHeader, &mut Vec<SlotMapKeyData>),
+    ) -> Result<SlotMap<TestKey, usize, String>, SnapshotError> {
+        let mut header = map.snapshot_writer().header();
+        let mut keys = map
+            .snapshot_writer()
+            .flat_map(|c| c.keys().iter().copied())
+            .collect::<Vec<_>>();
+
+        tamper(&mut header, &mut keys);
+
+        let mut builder = SnapshotBuilder::new(header);
+        let mut keys = keys.into_iter();
+
+        for chunk in map.snapshot_writer() {
+            builder.push_chunk(
+                chunk
+                    .values()
+                    .iter()
+                    .map(|v| (keys.next().unwrap(), v.clone())),
+            )?;
+        }
+
+        builder.build()
+    }
+
+    #[test]
+    fn test_untampered_snapshot_is_valid() {
+        let (map, _, _) = churned_map(13, |i| format!("{}", i));
+
+        assert!(rebuild_tampered(&map, |_, _| {}).is_ok());
+        assert!(rebuild_tampered(&SlotMap::new(), |_, _| {}).is_ok());
+    }
+
+    #[test]
+    fn test_misplaced_filled_slot() {
+        let (map, live, _) = churned_map(13, |i| format!("{}", i));
+        let filled: &SlotMapKeyData = live[0].borrow();
+
+        assert_eq!(
+            Err(SnapshotError::MisplacedFilledSlot {
+                chunk_index: filled.chunk_index as usize,
+                index_in_chunk: filled.index_in_chunk as usize
+            }),
+            rebuild_tampered(&map, |_, keys| {
+                keys[filled.position()].index_in_chunk ^= 1;
+            })
+            .map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_length_mismatch() {
+        let (map, _, _) = churned_map(13, |i| format!("{}", i));
+        let len = map.len();
+
+        assert_eq!(
+            Err(SnapshotError::LengthMismatch {
+                declared: len + 1,
+                found: len
+            }),
+            rebuild_tampered(&map, |header, _| header.len += 1).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_generation_parity_mismatch() {
+        let (map, live, _) = churned_map(13, |i| format!("{}", i));
+        let filled: &SlotMapKeyData = live[0].borrow();
+
+        // Marking a filled slot as vacant leaves it outside the open chain
+        assert_eq!(
+            Err(SnapshotError::LengthMismatch {
+                declared: map.len(),
+                found: map.len() - 1
+            }),
+            rebuild_tampered(&map, |_, keys| {
+                keys[filled.position()].generation += 1;
+            })
+            .map(|_| ())
+        );
+
+        assert_eq!(
+            Err(SnapshotError::UnreachableVacantSlot {
+                chunk_index: filled.chunk_index as usize,
+                index_in_chunk: filled.index_in_chunk as usize
+            }),
+            rebuild_tampered(&map, |header, keys| {
+                keys[filled.position()].generation += 1;
+                header.len -= 1;
+            })
+            .map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_broken_free_list() {
+        let (map, live, _) = churned_map(13, |i| format!("{}", i));
+        let filled: &SlotMapKeyData = live[0].borrow();
+        let header = map.snapshot_writer().header();
+        let head = header.next_open_slot;
+        let out_of_range = header.chunk_count as u32 + 1;
+
+        assert_eq!(
+            Err(SnapshotError::FreeListCycle {
+                chunk_index: head.chunk_index as usize,
+                index_in_chunk: head.index_in_chunk as usize
+            }),
+            rebuild_tampered(&map, |_, keys| {
+                // Point the head of the open chain back at itself
+                keys[head.position()].chunk_index = head.chunk_index;
+                keys[head.position()].index_in_chunk = head.index_in_chunk;
+            })
+            .map(|_| ())
+        );
+
+        assert_eq!(
+            Err(SnapshotError::FreeListEntersFilledSlot {
+                chunk_index: filled.chunk_index as usize,
+                index_in_chunk: filled.index_in_chunk as usize
+            }),
+            rebuild_tampered(&map, |header, _| {
+                header.next_open_slot =
+                    SlotMapKeyData::from(filled.position() as u64);
+            })
+            .map(|_| ())
+        );
+
+        assert_eq!(
+            Err(SnapshotError::CoordinatesOutOfRange {
+                chunk_index: head.chunk_index as usize,
+                index_in_chunk: head.index_in_chunk as usize
+            }),
+            rebuild_tampered(&map, |_, keys| {
+                keys[head.position()].chunk_index = out_of_range;
+            })
+            .map(|_| ())
+        );
+
+        assert_eq!(
+            Err(SnapshotError::InvalidNextOpenSlot),
+            rebuild_tampered(&map, |header, _| {
+                header.next_open_slot.chunk_index = out_of_range;
+            })
+            .map(|_| ())
+        );
+    }
+}
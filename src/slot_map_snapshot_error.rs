@@ -0,0 +1,325 @@
+use std::fmt::{Display, Formatter};
+
+/// Reasons a snapshot can be rejected while rebuilding a slot map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// More chunks were supplied than the snapshot header declared
+    TooManyChunks {
+        /// Number of chunks declared by the header
+        expected: usize,
+    },
+
+    /// Fewer chunks were supplied than the snapshot header declared
+    MissingChunks {
+        /// Number of chunks declared by the header
+        expected: usize,
+
+        /// Number of chunks actually supplied
+        found: usize,
+    },
+
+    /// A chunk contained more slots than fit in a single chunk
+    ChunkOverflow {
+        /// Index of the offending chunk
+        chunk_index: usize,
+    },
+
+    /// A chunk contained no slots at all
+    EmptyChunk {
+        /// Index of the offending chunk
+        chunk_index: usize,
+    },
+
+    /// A chunk was supplied after a chunk that was not completely filled.
+    /// Only the last chunk of a map may be partially filled
+    ChunkAfterPartial {
+        /// Index of the offending chunk
+        chunk_index: usize,
+    },
+
+    /// A filled slot's key data names coordinates other than its own
+    MisplacedFilledSlot {
+        /// Index of the chunk containing the slot
+        chunk_index: usize,
+
+        /// Index of the slot within its chunk
+        index_in_chunk: usize,
+    },
+
+    /// A vacant slot links to coordinates outside of the map
+    CoordinatesOutOfRange {
+        /// Index of the chunk containing the slot
+        chunk_index: usize,
+
+        /// Index of the slot within its chunk
+        index_in_chunk: usize,
+    },
+
+    /// The length in the header does not match the number of filled slots
+    LengthMismatch {
+        /// Length declared by the header
+        declared: usize,
+
+        /// Number of slots with a filled generation
+        found: usize,
+    },
+
+    /// The header's next open slot is outside of the map or has a generation
+    /// that would produce a vacant slot on insertion
+    InvalidNextOpenSlot,
+
+    /// The chain of open slots leads into a filled slot
+    FreeListEntersFilledSlot {
+        /// Index of the chunk containing the slot
+        chunk_index: usize,
+
+        /// Index of the slot within its chunk
+        index_in_chunk: usize,
+    },
+
+    /// The chain of open slots visits the same slot twice
+    FreeListCycle {
+        /// Index of the chunk containing the slot
+        chunk_index: usize,
+
+        /// Index of the slot within its chunk
+        index_in_chunk: usize,
+    },
+
+    /// A vacant slot cannot be reached from the chain of open slots, so it
+    /// could never be reused
+    UnreachableVacantSlot {
+        /// Index of the chunk containing the slot
+        chunk_index: usize,
+
+        /// Index of the slot within its chunk
+        index_in_chunk: usize,
+    },
+
+    /// An entry of a subset snapshot has key data with a vacant generation,
+    /// so it could never be retrieved once merged
+    VacantSubsetEntry {
+        /// Index of the chunk named by the entry
+        chunk_index: usize,
+
+        /// Index of the slot within its chunk named by the entry
+        index_in_chunk: usize,
+    },
+
+    /// An entry names a slot further past the end of the map it is merged
+    /// into than a chunk's worth of slots per merged entry
+    EntryOutOfRange {
+        /// Index of the chunk named by the entry
+        chunk_index: usize,
+
+        /// Index of the slot within its chunk named by the entry
+        index_in_chunk: usize,
+    },
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::TooManyChunks { expected } => {
+                write!(f, "snapshot contains more than {} chunks", expected)
+            }
+            SnapshotError::MissingChunks { expected, found } => write!(
+                f,
+                "snapshot declared {} chunks but only {} were supplied",
+                expected, found
+            ),
+            SnapshotError::ChunkOverflow { chunk_index } => write!(
+                f,
+                "chunk {} contains more than {} slots",
+                chunk_index,
+                crate::SLOT_MAP_CHUNK_SIZE
+            ),
+            SnapshotError::EmptyChunk { chunk_index } => {
+                write!(f, "chunk {} contains no slots", chunk_index)
+            }
+            SnapshotError::ChunkAfterPartial { chunk_index } => write!(
+                f,
+                "chunk {} follows a chunk that is only partially filled",
+                chunk_index
+            ),
+            SnapshotError::MisplacedFilledSlot {
+                chunk_index,
+                index_in_chunk,
+            } => write!(
+                f,
+                "filled slot {}:{} has key data for a different slot",
+                chunk_index, index_in_chunk
+            ),
+            SnapshotError::CoordinatesOutOfRange {
+                chunk_index,
+                index_in_chunk,
+            } => write!(
+                f,
+                "vacant slot {}:{} links to a slot outside of the map",
+                chunk_index, index_in_chunk
+            ),
+            SnapshotError::LengthMismatch { declared, found } => write!(
+                f,
+                "snapshot declared {} filled slots but contains {}",
+                declared, found
+            ),
+            SnapshotError::InvalidNextOpenSlot => {
+                write!(f, "snapshot's next open slot is invalid")
+            }
+            SnapshotError::FreeListEntersFilledSlot {
+                chunk_index,
+                index_in_chunk,
+            } => write!(
+                f,
+                "chain of open slots leads into filled slot {}:{}",
+                chunk_index, index_in_chunk
+            ),
+            SnapshotError::FreeListCycle {
+                chunk_index,
+                index_in_chunk,
+            } => write!(
+                f,
+                "chain of open slots visits slot {}:{} more than once",
+                chunk_index, index_in_chunk
+            ),
+            SnapshotError::UnreachableVacantSlot {
+                chunk_index,
+                index_in_chunk,
+            } => write!(
+                f,
+                "vacant slot {}:{} is not in the chain of open slots",
+                chunk_index, index_in_chunk
+            ),
+            SnapshotError::VacantSubsetEntry {
+                chunk_index,
+                index_in_chunk,
+            } => write!(
+                f,
+                "subset entry for slot {}:{} has a vacant generation",
+                chunk_index, index_in_chunk
+            ),
+            SnapshotError::EntryOutOfRange {
+                chunk_index,
+                index_in_chunk,
+            } => write!(
+                f,
+                "entry for slot {}:{} is too far past the end of the map",
+                chunk_index, index_in_chunk
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SLOT_MAP_CHUNK_SIZE;
+
+    #[test]
+    fn test_errors_describe_themselves() {
+        let (chunk_index, index_in_chunk) = (3, 17);
+
+        let cases = [
+            (
+                SnapshotError::TooManyChunks { expected: 2 },
+                "snapshot contains more than 2 chunks".to_owned(),
+            ),
+            (
+                SnapshotError::MissingChunks {
+                    expected: 2,
+                    found: 1,
+                },
+                "snapshot declared 2 chunks but only 1 were supplied"
+                    .to_owned(),
+            ),
+            (
+                SnapshotError::ChunkOverflow { chunk_index },
+                format!(
+                    "chunk 3 contains more than {} slots",
+                    SLOT_MAP_CHUNK_SIZE
+                ),
+            ),
+            (
+                SnapshotError::EmptyChunk { chunk_index },
+                "chunk 3 contains no slots".to_owned(),
+            ),
+            (
+                SnapshotError::ChunkAfterPartial { chunk_index },
+                "chunk 3 follows a chunk that is only partially filled"
+                    .to_owned(),
+            ),
+            (
+                SnapshotError::MisplacedFilledSlot {
+                    chunk_index,
+                    index_in_chunk,
+                },
+                "filled slot 3:17 has key data for a different slot".to_owned(),
+            ),
+            (
+                SnapshotError::CoordinatesOutOfRange {
+                    chunk_index,
+                    index_in_chunk,
+                },
+                "vacant slot 3:17 links to a slot outside of the map"
+                    .to_owned(),
+            ),
+            (
+                SnapshotError::LengthMismatch {
+                    declared: 5,
+                    found: 4,
+                },
+                "snapshot declared 5 filled slots but contains 4".to_owned(),
+            ),
+            (
+                SnapshotError::InvalidNextOpenSlot,
+                "snapshot's next open slot is invalid".to_owned(),
+            ),
+            (
+                SnapshotError::FreeListEntersFilledSlot {
+                    chunk_index,
+                    index_in_chunk,
+                },
+                "chain of open slots leads into filled slot 3:17".to_owned(),
+            ),
+            (
+                SnapshotError::FreeListCycle {
+                    chunk_index,
+                    index_in_chunk,
+                },
+                "chain of open slots visits slot 3:17 more than once"
+                    .to_owned(),
+            ),
+            (
+                SnapshotError::UnreachableVacantSlot {
+                    chunk_index,
+                    index_in_chunk,
+                },
+                "vacant slot 3:17 is not in the chain of open slots".to_owned(),
+            ),
+            (
+                SnapshotError::VacantSubsetEntry {
+                    chunk_index,
+                    index_in_chunk,
+                },
+                "subset entry for slot 3:17 has a vacant generation".to_owned(),
+            ),
+            (
+                SnapshotError::EntryOutOfRange {
+                    chunk_index,
+                    index_in_chunk,
+                },
+                "entry for slot 3:17 is too far past the end of the map"
+                    .to_owned(),
+            ),
+        ];
+
+        for (error, expected) in cases {
+            assert_eq!(expected, error.to_string());
+
+            let error: Box<dyn std::error::Error> = Box::new(error);
+            assert_eq!(expected, error.to_string());
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use super::{SlotMapKey, SlotMapKeyData, SLOT_MAP_CHUNK_SIZE};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Map from the keys of a slot map to extra data that only some of its items
+/// have. Entries are kept in a hash map by slot, so memory use follows the
+/// number of entries rather than the size of the slot map, at the cost of
+/// hashing on every lookup. Each slot holds at most one entry, and an entry
+/// is only found with the key it was inserted under, so entries for removed
+/// items are never returned for items that reuse their slots
+pub struct SparseSecondaryMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    entries: HashMap<u64, (u32, T)>,
+
+    _phantom: PhantomData<fn(P, K)>,
+}
+
+impl<K, P, T> std::fmt::Debug for SparseSecondaryMap<K, P, T>
+where
+    T: std::fmt::Debug,
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter_raw()).finish()
+    }
+}
+
+impl<K, P, T> Default for SparseSecondaryMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        SparseSecondaryMap::new()
+    }
+}
+
+impl<K, P, T> Clone for SparseSecondaryMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        SparseSecondaryMap {
+            entries: self.entries.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<K, P, T> SparseSecondaryMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> SparseSecondaryMap<K, P, T> {
+        SparseSecondaryMap {
+            entries: HashMap::new(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Create a new empty map with room for the given number of entries
+    pub fn with_capacity(capacity: usize) -> SparseSecondaryMap<K, P, T> {
+        SparseSecondaryMap {
+            entries: HashMap::with_capacity(capacity),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Get the number of entries in the map
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Tells if the map has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Attach the given value to the given key, returning the value that was
+    /// attached to the same key before. A value attached to an older key for
+    /// the same slot is dropped, and `None` is returned
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey,(),&'static str>::new();
+    /// let mut labels = SparseSecondaryMap::<TestKey,(),&'static str>::new();
+    ///
+    /// let first = map.insert((), "first");
+    /// let second = map.insert((), "second");
+    ///
+    /// assert_eq!(None, labels.insert(&second, "important"));
+    /// assert_eq!(Some("important"), labels.insert(&second, "urgent"));
+    ///
+    /// assert_eq!(None, labels.get(&first));
+    /// assert_eq!(Some(&"urgent"), labels.get(&second));
+    ///
+    /// // The slot is reused with a new key, which has nothing attached
+    /// let _ = map.remove(&second);
+    /// let third = map.insert((), "third");
+    ///
+    /// assert_eq!(None, labels.get(&third));
+    /// assert_eq!(None, labels.insert(&third, "new"));
+    /// assert_eq!(None, labels.get(&second));
+    /// ```
+    pub fn insert(&mut self, key: &K, value: T) -> Option<T> {
+        self.insert_raw(key.borrow(), value)
+    }
+
+    /// Same as insert, but only requires slot map key data
+    pub fn insert_raw(
+        &mut self,
+        key_data: &SlotMapKeyData,
+        value: T,
+    ) -> Option<T> {
+        self.entries
+            .insert(position(key_data), (key_data.generation, value))
+            .filter(|(generation, _)| *generation == key_data.generation)
+            .map(|(_, old)| old)
+    }
+
+    /// Get a reference to the value attached to the given key
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.entries
+            .get(&position(key_data))
+            .filter(|(generation, _)| *generation == key_data.generation)
+            .map(|(_, value)| value)
+    }
+
+    /// Get a mutable reference to the value attached to the given key
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Same as get_mut, but only requires slot map key data
+    pub fn get_mut_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        self.entries
+            .get_mut(&position(key_data))
+            .filter(|(generation, _)| *generation == key_data.generation)
+            .map(|(_, value)| value)
+    }
+
+    /// Tells if a value is attached to the given key
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Detach and return the value attached to the given key
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<T> {
+        let position = position(key_data);
+
+        match self.entries.get(&position) {
+            Some((generation, _)) if *generation == key_data.generation => {
+                self.entries.remove(&position).map(|(_, value)| value)
+            }
+            _ => None,
+        }
+    }
+
+    /// Keep only the entries for which the given function returns true
+    pub fn retain(
+        &mut self,
+        mut f: impl FnMut(SlotMapKeyData, &mut T) -> bool,
+    ) {
+        self.entries.retain(|position, (generation, value)| {
+            f(key_data(*position, *generation), value)
+        });
+    }
+
+    /// Remove every entry
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Create an iterator over the key data and values of the entries, in no
+    /// particular order
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.entries.iter().map(|(position, (generation, value))| {
+            (key_data(*position, *generation), value)
+        })
+    }
+
+    /// Create an iterator over the key data and mutable values of the
+    /// entries, in no particular order
+    pub fn iter_mut_raw(
+        &mut self,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &mut T)> {
+        self.entries
+            .iter_mut()
+            .map(|(position, (generation, value))| {
+                (key_data(*position, *generation), value)
+            })
+    }
+
+    /// Create an iterator over the values of the entries, in no particular
+    /// order
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.entries.values().map(|(_, value)| value)
+    }
+
+    /// Create an iterator over the mutable values of the entries, in no
+    /// particular order
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.entries.values_mut().map(|(_, value)| value)
+    }
+}
+
+/// Get the position of the slot for the given key data in order of slots
+fn position(key_data: &SlotMapKeyData) -> u64 {
+    key_data.chunk_index as u64 * SLOT_MAP_CHUNK_SIZE as u64
+        + key_data.index_in_chunk as u64
+}
+
+/// Rebuild key data from the position of its slot and its generation
+fn key_data(position: u64, generation: u32) -> SlotMapKeyData {
+    SlotMapKeyData {
+        generation,
+        ..SlotMapKeyData::from(position)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use crate::SlotMap;
+    use std::borrow::Borrow;
+
+    #[test]
+    fn test_entries_follow_keys() {
+        let mut map = SlotMap::<TestKey, usize, usize>::new();
+        let mut sparse = SparseSecondaryMap::<TestKey, usize, String>::new();
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 3)
+            .map(|i| map.insert(i, i))
+            .collect::<Vec<_>>();
+
+        for key in keys.iter().step_by(100) {
+            assert_eq!(None, sparse.insert(key, format!("{}", key.pointer)));
+        }
+
+        assert_eq!(8, sparse.len());
+
+        for key in &keys {
+            assert_eq!(
+                (key.pointer % 100 == 0).then(|| format!("{}", key.pointer)),
+                sparse.get(key).cloned()
+            );
+        }
+
+        sparse.get_mut(&keys[100]).unwrap().push('!');
+        assert_eq!(Some("100!".to_owned()), sparse.remove(&keys[100]));
+        assert_eq!(None, sparse.remove(&keys[100]));
+
+        // Keys for reused slots don't see entries of the removed items, and
+        // stale keys can't remove entries of new ones
+        let _ = map.remove(&keys[200]);
+        let reused = map.insert(200, 200);
+        assert_eq!(None, sparse.remove(&reused));
+        assert!(sparse.contains_key(&keys[200]));
+        assert_eq!(None, sparse.insert(&reused, "reused".to_owned()));
+        assert!(!sparse.contains_key(&keys[200]));
+        assert_eq!(None, sparse.remove(&keys[200]));
+
+        sparse.retain(|key_data, _| key_data.chunk_index == 0);
+
+        let mut remaining = sparse.iter_raw().collect::<Vec<_>>();
+        remaining.sort_by_key(|(key_data, _)| position(key_data));
+
+        assert_eq!(
+            vec![
+                (*Borrow::<SlotMapKeyData>::borrow(&keys[0]), &"0".to_owned()),
+                (*reused.borrow(), &"reused".to_owned()),
+            ],
+            remaining
+        );
+
+        sparse.clear();
+        assert!(sparse.is_empty());
+    }
+}
@@ -0,0 +1,252 @@
+use super::slot_map_concurrent::lock;
+use super::{SlotMap, SlotMapKey};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+/// Batch of items handed over by a buffer, along with the way back to it
+struct Batch<K, P, T> {
+    items: Vec<(P, T)>,
+    keys: Sender<Vec<K>>,
+}
+
+/// Collection point for items that worker threads want to insert into a slot
+/// map. Each worker stages items in its own [`InsertBuffer`] without any
+/// synchronization, hands them over a batch at a time, and gets their keys
+/// back through its buffer once the owner of the map calls
+/// [`publish`](InsertStaging::publish)
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<usize>);
+/// let staging = InsertStaging::<TestKey, usize, String>::new();
+/// let mut map = SlotMap::new();
+///
+/// let buffers = std::thread::scope(|s| {
+///     let handles = (0..4)
+///         .map(|t| {
+///             let mut buffer = staging.buffer();
+///
+///             s.spawn(move || {
+///                 for i in 0..100 {
+///                     buffer.push(t * 100 + i, format!("{}", t * 100 + i));
+///                 }
+///
+///                 buffer.flush();
+///                 buffer
+///             })
+///         })
+///         .collect::<Vec<_>>();
+///
+///     handles
+///         .into_iter()
+///         .map(|h| h.join().unwrap())
+///         .collect::<Vec<_>>()
+/// });
+///
+/// assert_eq!(400, staging.publish(&mut map));
+///
+/// for buffer in &buffers {
+///     for key in buffer.recv_keys() {
+///         assert_eq!(Some(&format!("{}", key.pointer)), map.get(&key));
+///     }
+/// }
+/// ```
+pub struct InsertStaging<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    batches: Arc<Mutex<Vec<Batch<K, P, T>>>>,
+}
+
+impl<K, P, T> std::fmt::Debug for InsertStaging<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InsertStaging")
+            .field("pending_batches", &lock(&self.batches).len())
+            .finish()
+    }
+}
+
+impl<K, P, T> Default for InsertStaging<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        InsertStaging::new()
+    }
+}
+
+impl<K, P, T> InsertStaging<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a staging area with nothing staged
+    pub fn new() -> InsertStaging<K, P, T> {
+        InsertStaging {
+            batches: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Create a buffer for a worker to stage items in
+    pub fn buffer(&self) -> InsertBuffer<K, P, T> {
+        let (sender, receiver) = channel();
+
+        InsertBuffer {
+            batches: self.batches.clone(),
+            items: Vec::new(),
+            sender,
+            receiver,
+        }
+    }
+
+    /// Insert every batch handed over so far into the given map, in the order
+    /// the batches were handed over, and send the keys of each batch back to
+    /// the buffer it came from. Returns the number of items inserted
+    pub fn publish(&self, map: &mut SlotMap<K, P, T>) -> usize {
+        let batches = std::mem::take(&mut *lock(&self.batches));
+        let mut count = 0;
+
+        for Batch { items, keys } in batches {
+            count += items.len();
+
+            let batch_keys = items
+                .into_iter()
+                .map(|(pointer, value)| map.insert(pointer, value))
+                .collect();
+
+            // The buffer may be gone, in which case nobody wants the keys
+            let _ = keys.send(batch_keys);
+        }
+
+        count
+    }
+}
+
+/// Buffer owned by a single worker for staging inserts. Pushing an item
+/// doesn't synchronize with anything, and items are handed over to the
+/// [`InsertStaging`] the buffer came from when it is flushed or dropped
+pub struct InsertBuffer<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    batches: Arc<Mutex<Vec<Batch<K, P, T>>>>,
+    items: Vec<(P, T)>,
+    sender: Sender<Vec<K>>,
+    receiver: Receiver<Vec<K>>,
+}
+
+impl<K, P, T> std::fmt::Debug for InsertBuffer<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InsertBuffer")
+            .field("staged", &self.items.len())
+            .finish()
+    }
+}
+
+impl<K, P, T> InsertBuffer<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Stage the given item for insertion
+    pub fn push(&mut self, pointer: P, value: T) {
+        self.items.push((pointer, value));
+    }
+
+    /// Get the number of items staged since the last flush
+    pub fn staged(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Hand the staged items over as one batch. Their keys can be received
+    /// after the next publish
+    pub fn flush(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+
+        let batch = Batch {
+            items: std::mem::take(&mut self.items),
+            keys: self.sender.clone(),
+        };
+
+        lock(&self.batches).push(batch);
+    }
+
+    /// Wait for the keys of the next batch flushed from this buffer, in the
+    /// order the items were pushed. This blocks until that batch is
+    /// published, so it should only be called after flushing
+    pub fn recv_keys(&self) -> Vec<K> {
+        self.receiver
+            .recv()
+            .expect("Buffer holds a sender for its own keys")
+    }
+
+    /// Get the keys of every batch that has been published, without waiting
+    pub fn try_recv_keys(&self) -> impl Iterator<Item = Vec<K>> + '_ {
+        self.receiver.try_iter()
+    }
+}
+
+impl<K, P, T> Drop for InsertBuffer<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+
+    #[test]
+    fn test_batches_keep_their_order() {
+        let staging = InsertStaging::<TestKey, usize, usize>::new();
+        let mut map = SlotMap::new();
+
+        let mut first = staging.buffer();
+        let mut second = staging.buffer();
+
+        first.push(0, 0);
+        second.push(10, 10);
+        first.push(1, 1);
+        assert_eq!(2, first.staged());
+
+        first.flush();
+        second.flush();
+        first.push(2, 2);
+        first.flush();
+
+        assert_eq!(0, first.try_recv_keys().count());
+        assert_eq!(4, staging.publish(&mut map));
+
+        let batches = first.try_recv_keys().collect::<Vec<_>>();
+        assert_eq!(2, batches.len());
+        assert_eq!(
+            vec![0, 1],
+            batches[0].iter().map(|k| k.pointer).collect::<Vec<_>>()
+        );
+        assert_eq!(2, batches[1][0].pointer);
+
+        let second_keys = second.recv_keys();
+        assert_eq!(Some(&10), map.get(&second_keys[0]));
+
+        for key in batches.iter().flatten() {
+            assert_eq!(Some(&key.pointer), map.get(key));
+        }
+
+        // Items still staged when a buffer is dropped are handed over
+        second.push(11, 11);
+        drop(second);
+        assert_eq!(1, staging.publish(&mut map));
+        assert_eq!(0, staging.publish(&mut map));
+        assert_eq!(5, map.len());
+    }
+}
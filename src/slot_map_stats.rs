@@ -0,0 +1,177 @@
+use super::slot_map_key_data::MAX_GENERATION;
+use super::{SlotMap, SlotMapKey};
+
+/// Statistics about the slots of a [`SlotMap`], from [`SlotMap::stats`].
+/// Chunks with low occupancy suggest the map would benefit from
+/// [`SlotMap::compact`], and a maximum generation approaching
+/// [`SlotMapStats::generation_limit`] means keys of long removed items are
+/// close to becoming valid again when generations wrap
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SlotMapStats {
+    /// Number of items in the map
+    pub len: usize,
+
+    /// Number of slots that have ever been written, filled or vacant
+    pub slot_count: usize,
+
+    /// Number of vacant slots waiting to be reused, which leaves out slots
+    /// retired by
+    /// [`GenerationExhaustion::Retire`](crate::GenerationExhaustion::Retire)
+    pub free_slots: usize,
+
+    /// Number of filled slots in each chunk, in chunk order
+    pub chunk_occupancy: Vec<usize>,
+
+    /// Average generation of all written slots
+    pub average_generation: f64,
+
+    /// Highest generation of any written slot
+    pub max_generation: u32,
+
+    /// Highest generation a slot can reach before it wraps back to 0
+    pub generation_limit: u32,
+
+    /// Number of written slots by the number of bits their generation needs,
+    /// so entry 0 counts slots at generation 0 and the last entry counts
+    /// slots in the top half of the generation range
+    pub generation_histogram: Vec<usize>,
+
+    /// Total number of times an item was put in a slot that had held an item
+    /// before, since the slot's generation last wrapped
+    pub reuses: u64,
+}
+
+impl SlotMapStats {
+    /// Get the fraction of written slots that are filled, or 1 for a map
+    /// that hasn't written any slots
+    pub fn occupancy(&self) -> f64 {
+        match self.slot_count {
+            0 => 1.0,
+            slot_count => self.len as f64 / slot_count as f64,
+        }
+    }
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Collect statistics about the slots of this map. This visits every
+    /// written slot, so it takes time proportional to the size of the map
+    /// rather than the number of items
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = SlotMap::<TestKey,(),usize>::new();
+    ///
+    /// let keys = (0..300).map(|i| map.insert((), i)).collect::<Vec<_>>();
+    /// let _ = map.remove(&keys[0]);
+    /// let _ = map.insert((), 300);
+    /// let _ = map.remove(&keys[1]);
+    ///
+    /// let stats = map.stats();
+    ///
+    /// assert_eq!(vec![255, 44], stats.chunk_occupancy);
+    /// assert_eq!(1, stats.free_slots);
+    /// assert_eq!(1, stats.reuses);
+    /// assert_eq!(2, stats.max_generation);
+    /// ```
+    pub fn stats(&self) -> SlotMapStats {
+        let slots = self.slots();
+        let generation_bits =
+            (u32::BITS - MAX_GENERATION.leading_zeros()) as usize;
+
+        let mut stats = SlotMapStats {
+            len: self.len(),
+            slot_count: slots.slot_count(),
+            free_slots: slots.slot_count() - self.len() - self.retired_slots(),
+            generation_limit: MAX_GENERATION,
+            generation_histogram: vec![0; generation_bits + 1],
+            ..Default::default()
+        };
+
+        let mut generation_total = 0u64;
+
+        for chunk_index in 0..slots.chunk_count() {
+            let chunk = slots.chunk(chunk_index).unwrap_or_default();
+            let mut occupancy = 0;
+
+            for (key_data, _) in chunk {
+                let generation = key_data.generation;
+
+                occupancy += key_data.is_filled() as usize;
+                generation_total += generation as u64;
+                stats.max_generation = stats.max_generation.max(generation);
+                stats.reuses += generation as u64 / 2;
+                stats.generation_histogram
+                    [(u32::BITS - generation.leading_zeros()) as usize] += 1;
+            }
+
+            stats.chunk_occupancy.push(occupancy);
+        }
+
+        if stats.slot_count > 0 {
+            stats.average_generation =
+                generation_total as f64 / stats.slot_count as f64;
+        }
+
+        stats
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use crate::SLOT_MAP_CHUNK_SIZE;
+
+    #[test]
+    fn test_stats_follow_churn() {
+        let mut map = SlotMap::<TestKey, usize, usize>::new();
+
+        let empty = map.stats();
+        assert_eq!(0, empty.slot_count);
+        assert_eq!(1.0, empty.occupancy());
+        assert_eq!(0, empty.generation_histogram.iter().sum::<usize>());
+
+        let mut keys = (0..SLOT_MAP_CHUNK_SIZE * 2)
+            .map(|i| map.insert(i, i))
+            .collect::<Vec<_>>();
+
+        // Churn the first 10 slots 5 times each, leaving them filled
+        for _ in 0..5 {
+            for key in keys.iter_mut().take(10) {
+                let _ = map.remove(key);
+                *key = map.insert(key.pointer, key.pointer);
+            }
+        }
+
+        // Leave the last 6 slots of the second chunk vacant
+        for key in keys.iter().rev().take(6) {
+            let _ = map.remove(key);
+        }
+
+        let stats = map.stats();
+
+        assert_eq!(SLOT_MAP_CHUNK_SIZE * 2 - 6, stats.len);
+        assert_eq!(SLOT_MAP_CHUNK_SIZE * 2, stats.slot_count);
+        assert_eq!(6, stats.free_slots);
+        assert_eq!(
+            vec![SLOT_MAP_CHUNK_SIZE, SLOT_MAP_CHUNK_SIZE - 6],
+            stats.chunk_occupancy
+        );
+        assert_eq!(50, stats.reuses);
+        assert_eq!(10, stats.max_generation);
+        assert_eq!(
+            (10 * 10 + 6) as f64 / (SLOT_MAP_CHUNK_SIZE * 2) as f64,
+            stats.average_generation
+        );
+
+        // Generation 10 needs 4 bits and generation 1 needs 1
+        assert_eq!(10, stats.generation_histogram[4]);
+        assert_eq!(6, stats.generation_histogram[1]);
+        assert_eq!(SLOT_MAP_CHUNK_SIZE * 2 - 16, stats.generation_histogram[0]);
+        assert_eq!(25, stats.generation_histogram.len());
+    }
+}
@@ -0,0 +1,263 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData, SnapshotError};
+
+/// Selected entries of a slot map stored with their exact key data, so they
+/// can be merged into another map under the same keys. Created with
+/// [`SlotMap::snapshot_subset`](crate::SlotMap::snapshot_subset) and applied
+/// with [`SlotMap::merge_snapshot`](crate::SlotMap::merge_snapshot)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubsetSnapshot<T> {
+    entries: Vec<(SlotMapKeyData, T)>,
+}
+
+impl<T> SubsetSnapshot<T> {
+    /// The selected entries in the order they were selected
+    pub fn entries(&self) -> &[(SlotMapKeyData, T)] {
+        &self.entries
+    }
+
+    /// Take the selected entries
+    pub fn into_entries(self) -> Vec<(SlotMapKeyData, T)> {
+        self.entries
+    }
+
+    /// Get the number of entries in this snapshot
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Tells if this snapshot has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<T> SubsetSnapshot<&T>
+where
+    T: Clone,
+{
+    /// Create a snapshot that owns copies of the borrowed values
+    pub fn cloned(&self) -> SubsetSnapshot<T> {
+        SubsetSnapshot {
+            entries: self
+                .entries
+                .iter()
+                .map(|(key_data, value)| (*key_data, (*value).clone()))
+                .collect(),
+        }
+    }
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Take a snapshot of the entries for the given keys, borrowing their
+    /// values. Keys that are not in the map are skipped
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut server = SlotMap::<TestKey, (), usize>::new();
+    /// let mut client = SlotMap::<TestKey, (), usize>::new();
+    ///
+    /// let _ = server.insert((), 1);
+    /// let dirty = server.insert((), 2);
+    ///
+    /// let subset = server.snapshot_subset([&dirty]).cloned();
+    /// client.merge_snapshot(subset).unwrap();
+    ///
+    /// assert_eq!(1, client.len());
+    /// assert_eq!(Some(&2), client.get(&dirty));
+    /// ```
+    pub fn snapshot_subset<'k>(
+        &self,
+        keys: impl IntoIterator<Item = &'k K>,
+    ) -> SubsetSnapshot<&T>
+    where
+        K: 'k,
+    {
+        SubsetSnapshot {
+            entries: keys
+                .into_iter()
+                .filter_map(|key| {
+                    let key_data = *key.borrow();
+                    self.get_raw(&key_data).map(|value| (key_data, value))
+                })
+                .collect(),
+        }
+    }
+
+    /// Write the entries of the given snapshot into this map under their
+    /// exact key data. Entries replace whatever occupies their slots, and
+    /// slots between the end of this map and an entry are created as vacant
+    /// slots holding default values, up to a chunk's worth of slots per entry.
+    /// The snapshot is rejected without changing the map if any entry has a
+    /// vacant generation or names a slot further past the end than that
+    pub fn merge_snapshot(
+        &mut self,
+        snapshot: SubsetSnapshot<T>,
+    ) -> Result<(), SnapshotError>
+    where
+        T: Default,
+    {
+        if let Some((key_data, _)) =
+            snapshot.entries.iter().find(|(k, _)| !k.is_filled())
+        {
+            return Err(SnapshotError::VacantSubsetEntry {
+                chunk_index: key_data.chunk_index as usize,
+                index_in_chunk: key_data.index_in_chunk as usize,
+            });
+        }
+
+        if let Some(key_data) = self.find_out_of_reach(&snapshot.entries) {
+            return Err(SnapshotError::EntryOutOfRange {
+                chunk_index: key_data.chunk_index as usize,
+                index_in_chunk: key_data.index_in_chunk as usize,
+            });
+        }
+
+        self.insert_all_at(snapshot.entries);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use crate::SLOT_MAP_CHUNK_SIZE;
+
+    #[test]
+    fn test_merge_into_matching_replica() {
+        let mut server = SlotMap::<TestKey, usize, String>::new();
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE + 10)
+            .map(|i| server.insert(i, format!("{}", i)))
+            .collect::<Vec<_>>();
+        let _ = server.remove(&keys[3]);
+
+        let mut client = SlotMap::new();
+        client
+            .merge_snapshot(server.snapshot_subset(&keys).cloned())
+            .unwrap();
+
+        assert_eq!(server.len(), client.len());
+        assert_eq!(server.state_digest(), client.state_digest());
+
+        *server.get_mut(&keys[20]).unwrap() = "dirty".to_owned();
+        let _ = server.remove(&keys[30]);
+        let reused = server.insert(30, "reused".to_owned());
+
+        client
+            .merge_snapshot(
+                server.snapshot_subset([&keys[20], &reused]).cloned(),
+            )
+            .unwrap();
+
+        assert_eq!(Some(&"dirty".to_owned()), client.get(&keys[20]));
+        assert_eq!(Some(&"reused".to_owned()), client.get(&reused));
+        assert_eq!(None, client.get(&keys[30]));
+        assert_eq!(server.len(), client.len());
+    }
+
+    #[test]
+    fn test_merge_past_end_keeps_free_list_valid() {
+        let mut server = SlotMap::<TestKey, usize, String>::new();
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 2)
+            .map(|i| server.insert(i, format!("{}", i)))
+            .collect::<Vec<_>>();
+
+        let mut client = SlotMap::<TestKey, usize, String>::new();
+        let local = client.insert(0, "local".to_owned());
+
+        let selected = [&keys[5], &keys[SLOT_MAP_CHUNK_SIZE + 7], &keys[1]];
+        client
+            .merge_snapshot(server.snapshot_subset(selected).cloned())
+            .unwrap();
+
+        assert_eq!(4, client.len());
+        assert_eq!(Some(&"local".to_owned()), client.get(&local));
+
+        for k in selected {
+            assert_eq!(server.get(k), client.get(k));
+        }
+
+        // Rebuilding from a snapshot checks every free list invariant
+        let mut builder =
+            crate::SnapshotBuilder::new(client.snapshot_writer().header());
+        for chunk in client.snapshot_writer() {
+            builder
+                .push_chunk(chunk.slots().map(|(k, v)| (k, v.clone())))
+                .unwrap();
+        }
+        let _ = builder.build::<TestKey, usize>().unwrap();
+
+        // Inserting fills every vacant slot before growing the map
+        let slot_count = client.slots().slot_count();
+        for i in 0..slot_count - client.len() {
+            let _ = client.insert(i, "new".to_owned());
+        }
+        assert_eq!(slot_count, client.slots().slot_count());
+        let _ = client.insert(0, "new".to_owned());
+        assert_eq!(slot_count + 1, client.slots().slot_count());
+    }
+
+    #[test]
+    fn test_vacant_entry_rejected() {
+        let mut server = SlotMap::<TestKey, usize, usize>::new();
+        let key = server.insert(0, 42);
+
+        let mut entries =
+            server.snapshot_subset([&key]).cloned().into_entries();
+        entries[0].0.generation = 1;
+
+        let mut client = SlotMap::<TestKey, usize, usize>::new();
+
+        assert_eq!(
+            Err(SnapshotError::VacantSubsetEntry {
+                chunk_index: 0,
+                index_in_chunk: 0
+            }),
+            client.merge_snapshot(SubsetSnapshot { entries })
+        );
+        assert!(client.is_empty());
+    }
+
+    #[test]
+    fn test_entry_far_past_end_rejected() {
+        let mut client = SlotMap::<TestKey, usize, usize>::new();
+        let local = client.insert(0, 1);
+
+        // Two entries may reach two chunks past the single slot of the client
+        let reachable = SlotMapKeyData::from((SLOT_MAP_CHUNK_SIZE * 2) as u64);
+        let far = SlotMapKeyData {
+            chunk_index: u32::MAX - 1,
+            ..SlotMapKeyData::from(0u64)
+        };
+
+        assert_eq!(
+            Err(SnapshotError::EntryOutOfRange {
+                chunk_index: far.chunk_index as usize,
+                index_in_chunk: 0
+            }),
+            client.merge_snapshot(SubsetSnapshot {
+                entries: vec![(reachable, 2), (far, 3)]
+            })
+        );
+        assert_eq!(1, client.len());
+        assert_eq!(1, client.slots().slot_count());
+        assert_eq!(Some(&1), client.get(&local));
+
+        client
+            .merge_snapshot(SubsetSnapshot {
+                entries: vec![(reachable, 2), (SlotMapKeyData::from(5u64), 3)],
+            })
+            .unwrap();
+        assert_eq!(Some(&2), client.get_raw(&reachable));
+        assert_eq!(3, client.len());
+        assert_eq!(Ok(()), client.check_invariants());
+    }
+}
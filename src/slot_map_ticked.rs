@@ -0,0 +1,299 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+
+/// Item of a [`TickedSlotMap`] along with the tick it last changed at
+#[derive(Clone)]
+struct Entry<T> {
+    value: T,
+    changed: u64,
+}
+
+/// Slot map that stamps every item with a change tick whenever it's
+/// inserted, replaced, or mutably accessed, so the items that changed after
+/// some point can be found without comparing every item against a copy.
+/// Ticks count up from 1 with every change, so the current tick can be
+/// saved as a watermark and passed to [`TickedSlotMap::iter_changed_since`]
+/// later. Created with [`SlotMap::with_change_ticks`]
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()>);
+/// let mut map = SlotMap::<TestKey, (), &str>::with_change_ticks();
+///
+/// let first = map.insert((), "first");
+/// let _ = map.insert((), "second");
+///
+/// let watermark = map.current_tick();
+///
+/// *map.get_mut(&first).unwrap() = "changed";
+///
+/// assert_eq!(
+///     vec!["changed"],
+///     map.iter_changed_since(watermark)
+///         .map(|(_, value)| *value)
+///         .collect::<Vec<_>>()
+/// );
+/// ```
+pub struct TickedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, Entry<T>>,
+    tick: u64,
+}
+
+impl<K, P, T> std::fmt::Debug for TickedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(
+                self.map.values().map(|entry| (&entry.value, entry.changed)),
+            )
+            .finish()
+    }
+}
+
+impl<K, P, T> Default for TickedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        TickedSlotMap::new()
+    }
+}
+
+impl<K, P, T> Clone for TickedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        TickedSlotMap {
+            map: self.map.clone(),
+            tick: self.tick,
+        }
+    }
+}
+
+impl<K, P, T> SlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map that stamps every item with the tick it last
+    /// changed at
+    pub fn with_change_ticks() -> TickedSlotMap<K, P, T> {
+        TickedSlotMap::new()
+    }
+}
+
+impl<K, P, T> TickedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> TickedSlotMap<K, P, T> {
+        TickedSlotMap {
+            map: SlotMap::new(),
+            tick: 0,
+        }
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Get the tick of the latest change. Items that change after this call
+    /// are stamped with a later tick
+    pub fn current_tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Insert the given item into the map and return its key
+    pub fn insert(&mut self, pointer: P, value: T) -> K {
+        let changed = self.next_tick();
+        self.map.insert(pointer, Entry { value, changed })
+    }
+
+    /// Get a reference to the item for the given key if it exists. This
+    /// doesn't count as a change
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.map.get_raw(key_data).map(|entry| &entry.value)
+    }
+
+    /// Get a mutable reference to the item for the given key if it exists,
+    /// stamping the item as changed whether or not it's written to
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Same as get_mut, but only requires slot map key data
+    pub fn get_mut_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        if !self.map.contains_key_raw(key_data) {
+            return None;
+        }
+
+        let changed = self.next_tick();
+        let entry = self.map.get_mut_raw(key_data)?;
+        entry.changed = changed;
+
+        Some(&mut entry.value)
+    }
+
+    /// Replace the item for the given key and return the old item, or give
+    /// the new item back if the key isn't in the map
+    pub fn replace(&mut self, key: &K, value: T) -> Result<T, T> {
+        match self.get_mut(key) {
+            Some(existing) => Ok(std::mem::replace(existing, value)),
+            None => Err(value),
+        }
+    }
+
+    /// Get the tick the item for the given key last changed at
+    pub fn changed_tick(&self, key: &K) -> Option<u64> {
+        self.map.get(key).map(|entry| entry.changed)
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Remove the item for the given key and return a mutable ref to it if
+    /// there was one
+    pub fn remove(&mut self, key: &K) -> Option<&mut T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        self.map.remove_raw(key_data).map(|entry| &mut entry.value)
+    }
+
+    /// Remove all items from the map. The tick keeps counting from where it
+    /// was, so watermarks taken before stay meaningful
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Create an iterator over the raw key data and values of the items that
+    /// changed after the given tick, in slot order. This visits every item,
+    /// but only the changed ones are produced
+    pub fn iter_changed_since(
+        &self,
+        tick: u64,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.map
+            .iter_raw()
+            .filter(move |(_, entry)| entry.changed > tick)
+            .map(|(key_data, entry)| (key_data, &entry.value))
+    }
+
+    /// Create an iterator over the raw key data and values of all items
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.map
+            .iter_raw()
+            .map(|(key_data, entry)| (key_data, &entry.value))
+    }
+
+    /// Create an iterator over the values of all items
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.map.values().map(|entry| &entry.value)
+    }
+
+    /// Create an iterator over the mutable values of all items, stamping
+    /// every item as changed
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        let changed = self.next_tick();
+
+        self.map.values_mut().map(move |entry| {
+            entry.changed = changed;
+            &mut entry.value
+        })
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use rand::{thread_rng, Rng};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_changes_since_match_model() {
+        let mut rng = thread_rng();
+        let mut map = TickedSlotMap::<TestKey, usize, usize>::new();
+        let mut keys = Vec::<TestKey>::new();
+
+        // Tick each live key last changed at
+        let mut model = HashMap::<usize, u64>::new();
+        let mut watermarks = vec![0];
+
+        for i in 0..5000 {
+            let target =
+                (!keys.is_empty()).then(|| rng.gen_range(0..keys.len()));
+
+            match (rng.gen_range(0..5), target) {
+                (0, _) | (_, None) => {
+                    keys.push(map.insert(i, i));
+                    let _ = model.insert(i, map.current_tick());
+                }
+                (1, Some(t)) => {
+                    assert_eq!(Some(&keys[t].pointer), map.get(&keys[t]));
+                }
+                (2, Some(t)) => {
+                    assert!(map.get_mut(&keys[t]).is_some());
+                    let _ = model.insert(keys[t].pointer, map.current_tick());
+                }
+                (3, Some(t)) => {
+                    let key = keys.swap_remove(t);
+                    assert!(map.remove(&key).is_some());
+                    let _ = model.remove(&key.pointer);
+                }
+                (_, Some(_)) => watermarks.push(map.current_tick()),
+            }
+        }
+
+        for watermark in watermarks {
+            let mut expected = model
+                .iter()
+                .filter(|(_, changed)| **changed > watermark)
+                .map(|(pointer, _)| *pointer)
+                .collect::<Vec<_>>();
+            let mut changed = map
+                .iter_changed_since(watermark)
+                .map(|(_, value)| *value)
+                .collect::<Vec<_>>();
+
+            expected.sort_unstable();
+            changed.sort_unstable();
+            assert_eq!(expected, changed);
+        }
+
+        let watermark = map.current_tick();
+        assert_eq!(Ok(keys[0].pointer), map.replace(&keys[0], 0));
+        assert_eq!(Some(watermark + 1), map.changed_tick(&keys[0]));
+        assert_eq!(1, map.iter_changed_since(watermark).count());
+
+        map.values_mut().for_each(|_| {});
+        assert_eq!(map.len(), map.iter_changed_since(watermark + 1).count());
+    }
+}
@@ -0,0 +1,362 @@
+use super::slot_map_append_only::{bucket_len, locate, BUCKET_COUNT};
+use super::{SlotMap, SlotMapKey, SlotMapKeyData, SLOT_MAP_CHUNK_SIZE};
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicPtr, AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Copy of the generation of every slot of a map in atomics, so it can be
+/// read while the map is being modified. Storage is split into buckets like
+/// the append-only map's, so it never moves as the map grows
+struct GenerationTable {
+    buckets: [AtomicPtr<AtomicU32>; BUCKET_COUNT],
+
+    /// Number of slots whose generation has been written
+    slot_count: AtomicUsize,
+}
+
+impl GenerationTable {
+    /// Get the generation of the slot at the given linear index if it has
+    /// been written
+    fn load(&self, linear_index: usize) -> Option<u32> {
+        if linear_index >= self.slot_count.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let (bucket, index) = locate(linear_index);
+        let ptr = self.buckets[bucket].load(Ordering::Acquire);
+
+        // Safety - The bucket of every counted slot has been installed, and
+        // buckets are never freed while the table is alive
+        Some(unsafe { &*ptr.add(index) }.load(Ordering::Acquire))
+    }
+
+    /// Write the generation of the slot at the given linear index. Only the
+    /// map that owns the table writes it
+    fn store(&self, linear_index: usize, generation: u32) {
+        let (bucket, index) = locate(linear_index);
+        let mut ptr = self.buckets[bucket].load(Ordering::Acquire);
+
+        if ptr.is_null() {
+            ptr = Box::into_raw(
+                (0..bucket_len(bucket))
+                    .map(|_| AtomicU32::new(0))
+                    .collect::<Box<[AtomicU32]>>(),
+            ) as *mut AtomicU32;
+            self.buckets[bucket].store(ptr, Ordering::Release);
+        }
+
+        // Safety - The bucket was installed above if it didn't exist
+        unsafe { &*ptr.add(index) }.store(generation, Ordering::Release);
+
+        if linear_index >= self.slot_count.load(Ordering::Relaxed) {
+            self.slot_count.store(linear_index + 1, Ordering::Release);
+        }
+    }
+}
+
+impl Drop for GenerationTable {
+    fn drop(&mut self) {
+        for (bucket, ptr) in self.buckets.iter_mut().enumerate() {
+            let ptr = *ptr.get_mut();
+
+            if !ptr.is_null() {
+                // Safety - The pointer came from a boxed slice of this length
+                // and nothing else can be using it during the drop
+                drop(unsafe {
+                    Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+                        ptr,
+                        bucket_len(bucket),
+                    ))
+                });
+            }
+        }
+    }
+}
+
+/// Slot map that publishes the generation of each of its slots in atomics,
+/// so other threads can check whether keys are still valid through a
+/// [`LivenessView`] while this map is being modified. All of the read-only
+/// methods of [`SlotMap`] are available through `Deref`
+pub struct TrackedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, T>,
+    generations: Arc<GenerationTable>,
+}
+
+impl<K, P, T> std::fmt::Debug for TrackedSlotMap<K, P, T>
+where
+    T: std::fmt::Debug,
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.map.fmt(f)
+    }
+}
+
+impl<K, P, T> Default for TrackedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        TrackedSlotMap::new()
+    }
+}
+
+impl<K, P, T> Deref for TrackedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    type Target = SlotMap<K, P, T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
+
+impl<K, P, T> From<SlotMap<K, P, T>> for TrackedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn from(map: SlotMap<K, P, T>) -> Self {
+        let result = TrackedSlotMap {
+            map,
+            generations: Arc::new(GenerationTable {
+                buckets: std::array::from_fn(|_| AtomicPtr::new(null_mut())),
+                slot_count: AtomicUsize::new(0),
+            }),
+        };
+
+        result.publish_all();
+        result
+    }
+}
+
+impl<K, P, T> TrackedSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> TrackedSlotMap<K, P, T> {
+        SlotMap::new().into()
+    }
+
+    /// Get a view that checks keys against this map from any thread
+    pub fn liveness(&self) -> LivenessView<K, P> {
+        LivenessView {
+            generations: self.generations.clone(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Insert the given item into the map and return its key
+    pub fn insert(&mut self, pointer: P, value: T) -> K {
+        let key_data = self.map.insert_raw(value);
+        self.publish(&key_data);
+
+        K::from((pointer, key_data))
+    }
+
+    /// Get a mutable reference to the item for the given key
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.map.get_mut(key)
+    }
+
+    /// Remove the item for the given key, returning a mutable reference to
+    /// it if it was present. The key stops being live for every view before
+    /// this returns
+    pub fn remove(&mut self, key: &K) -> Option<&mut T> {
+        let mut key_data = *key.borrow();
+
+        if !self.map.contains_key_raw(&key_data) {
+            return None;
+        }
+
+        key_data.increment_generation();
+        self.publish(&key_data);
+
+        self.map.remove_raw(key.borrow())
+    }
+
+    /// Remove every item from the map
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.publish_all();
+    }
+
+    /// Stop tracking generations and get the underlying map. Existing views
+    /// keep reporting the state at the time this was called
+    pub fn into_inner(self) -> SlotMap<K, P, T> {
+        self.map
+    }
+
+    /// Publish the generation in the given key data for its slot
+    fn publish(&self, key_data: &SlotMapKeyData) {
+        let linear_index = key_data.chunk_index as usize * SLOT_MAP_CHUNK_SIZE
+            + key_data.index_in_chunk as usize;
+
+        self.generations.store(linear_index, key_data.generation);
+    }
+
+    /// Publish the generation of every slot
+    fn publish_all(&self) {
+        for (linear_index, (key_data, _)) in
+            self.map.slots().values().enumerate()
+        {
+            self.generations.store(linear_index, key_data.generation);
+        }
+    }
+}
+
+/// Handle for checking from any thread whether keys are live in a
+/// [`TrackedSlotMap`] without locking. Views are cheap to clone.
+///
+/// A removal made by the map's owner is visible to a view once the removal
+/// has returned and the thread using the view has synchronized with the
+/// owner afterwards, and an answer reflects some state the map was in while
+/// the check ran. When a view reports a key as not live, it stays that way
+/// until the slot's generation wraps around, which takes millions of
+/// reuses of the same slot
+pub struct LivenessView<K, P>
+where
+    K: SlotMapKey<P>,
+{
+    generations: Arc<GenerationTable>,
+
+    _phantom: PhantomData<fn(P, K)>,
+}
+
+impl<K, P> std::fmt::Debug for LivenessView<K, P>
+where
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LivenessView").finish_non_exhaustive()
+    }
+}
+
+impl<K, P> Clone for LivenessView<K, P>
+where
+    K: SlotMapKey<P>,
+{
+    fn clone(&self) -> Self {
+        LivenessView {
+            generations: self.generations.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<K, P> LivenessView<K, P>
+where
+    K: SlotMapKey<P>,
+{
+    /// Tells if the given key is in the map
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()>);
+    /// let mut map = TrackedSlotMap::<TestKey,(),usize>::new();
+    /// let key = map.insert((), 42);
+    /// let view = map.liveness();
+    ///
+    /// std::thread::scope(|s| {
+    ///     s.spawn(|| assert!(view.contains_key(&key))).join().unwrap();
+    ///     let _ = map.remove(&key);
+    ///     s.spawn(|| assert!(!view.contains_key(&key))).join().unwrap();
+    /// });
+    /// ```
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.contains_key_raw(key.borrow())
+    }
+
+    /// Same as contains_key, but only requires slot map key data
+    pub fn contains_key_raw(&self, key_data: &SlotMapKeyData) -> bool {
+        let linear_index = key_data.chunk_index as usize * SLOT_MAP_CHUNK_SIZE
+            + key_data.index_in_chunk as usize;
+
+        key_data.is_filled()
+            && self.generations.load(linear_index) == Some(key_data.generation)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use std::sync::atomic::AtomicBool;
+
+    #[test]
+    fn test_view_follows_map() {
+        let mut map = SlotMap::<TestKey, usize, usize>::new();
+        let early = (0..10).map(|i| map.insert(i, i)).collect::<Vec<_>>();
+        let _ = map.remove(&early[0]);
+
+        let mut map = TrackedSlotMap::from(map);
+        let view = map.liveness();
+
+        assert!(!view.contains_key(&early[0]));
+        assert!(view.contains_key(&early[1]));
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE * 3)
+            .map(|i| map.insert(i, i))
+            .collect::<Vec<_>>();
+
+        for key in &keys {
+            assert!(view.contains_key(key));
+            assert_eq!(map.contains_key(key), view.contains_key(key));
+        }
+
+        let _ = map.remove(&keys[7]);
+        assert!(!view.contains_key(&keys[7]));
+
+        let reused = map.insert(0, 0);
+        assert!(view.contains_key(&reused));
+        assert!(!view.contains_key(&keys[7]));
+
+        map.clear();
+        assert!(keys.iter().all(|k| !view.contains_key(k)));
+        assert!(!view.contains_key(&reused));
+    }
+
+    #[test]
+    fn test_removals_seen_across_threads() {
+        let mut map = TrackedSlotMap::<TestKey, usize, usize>::new();
+        let keys = (0..5000).map(|i| map.insert(i, i)).collect::<Vec<_>>();
+        let view = map.liveness();
+        let done = AtomicBool::new(false);
+
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                let (view, keys, done) = (view.clone(), &keys, &done);
+
+                let _ = s.spawn(move || {
+                    while !done.load(Ordering::Acquire) {
+                        // Keys are removed in order, so once a key is dead
+                        // every earlier one must be as well
+                        let first_live =
+                            keys.iter().position(|k| view.contains_key(k));
+
+                        if let Some(first_live) = first_live {
+                            assert!(keys[..first_live]
+                                .iter()
+                                .all(|k| !view.contains_key(k)));
+                        }
+                    }
+                });
+            }
+
+            for key in &keys {
+                let _ = map.remove(key);
+                let _ = map.insert(0, 0);
+            }
+
+            done.store(true, Ordering::Release);
+        });
+
+        assert!(keys.iter().all(|k| !view.contains_key(k)));
+    }
+}
@@ -0,0 +1,458 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+
+/// Item of a [`TreeSlotMap`] along with its links to the items around it
+struct Node<T> {
+    value: T,
+    parent: Option<SlotMapKeyData>,
+    first_child: Option<SlotMapKeyData>,
+    last_child: Option<SlotMapKeyData>,
+    previous_sibling: Option<SlotMapKeyData>,
+    next_sibling: Option<SlotMapKeyData>,
+}
+
+impl<T> Node<T> {
+    fn new(value: T) -> Node<T> {
+        Node {
+            value,
+            parent: None,
+            first_child: None,
+            last_child: None,
+            previous_sibling: None,
+            next_sibling: None,
+        }
+    }
+}
+
+/// Slot map whose items form a forest. Each item stores its parent and links
+/// to its siblings, so children can be listed in the order they were added,
+/// and items can be moved to a new parent in O(1) after a walk up the new
+/// parent's ancestors to refuse cycles. Removing an item removes its whole
+/// subtree
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(WidgetKey<()>);
+/// let mut ui = TreeSlotMap::<WidgetKey, (), &str>::new();
+///
+/// let window = ui.insert((), "window");
+/// let panel = ui.insert_child(&window, (), "panel").unwrap();
+/// let button = ui.insert_child(&panel, (), "button").unwrap();
+///
+/// assert!(ui.reparent(&button, Some(&window)));
+/// assert_eq!(2, ui.children(&window).count());
+///
+/// // A node can't be moved under its own descendant
+/// assert!(!ui.reparent(&window, Some(&panel)));
+///
+/// assert_eq!(3, ui.remove_subtree(&window));
+/// assert!(ui.is_empty());
+/// ```
+pub struct TreeSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, Node<T>>,
+}
+
+impl<K, P, T> std::fmt::Debug for TreeSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.map.values().map(|node| &node.value))
+            .finish()
+    }
+}
+
+impl<K, P, T> Default for TreeSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        TreeSlotMap::new()
+    }
+}
+
+impl<K, P, T> TreeSlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> TreeSlotMap<K, P, T> {
+        TreeSlotMap {
+            map: SlotMap::new(),
+        }
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Insert the given item as a new root and return its key
+    pub fn insert(&mut self, pointer: P, value: T) -> K {
+        self.map.insert(pointer, Node::new(value))
+    }
+
+    /// Insert the given item as the last child of the item for the given
+    /// parent key and return its key, or give the item back if the parent
+    /// isn't in the map
+    pub fn insert_child(
+        &mut self,
+        parent: &K,
+        pointer: P,
+        value: T,
+    ) -> Result<K, T> {
+        let parent = parent.borrow();
+
+        if !self.map.contains_key_raw(parent) {
+            return Err(value);
+        }
+
+        let key_data = self.map.insert_raw(Node::new(value));
+        self.attach(key_data, *parent);
+
+        Ok(K::from((pointer, key_data)))
+    }
+
+    /// Get a reference to the item for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.map.get_raw(key_data).map(|node| &node.value)
+    }
+
+    /// Get a mutable reference to the item for the given key if it exists
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Same as get_mut, but only requires slot map key data
+    pub fn get_mut_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        self.map.get_mut_raw(key_data).map(|node| &mut node.value)
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Get the key data of the parent of the item for the given key, or
+    /// `None` if the item is a root or isn't in the map
+    pub fn parent(&self, key: &K) -> Option<SlotMapKeyData> {
+        self.parent_raw(key.borrow())
+    }
+
+    /// Same as parent, but only requires slot map key data
+    pub fn parent_raw(
+        &self,
+        key_data: &SlotMapKeyData,
+    ) -> Option<SlotMapKeyData> {
+        self.map.get_raw(key_data)?.parent
+    }
+
+    /// Create an iterator over the key data of the children of the item for
+    /// the given key, in the order they were added
+    pub fn children(
+        &self,
+        key: &K,
+    ) -> impl Iterator<Item = SlotMapKeyData> + '_ {
+        self.children_raw(key.borrow())
+    }
+
+    /// Same as children, but only requires slot map key data
+    pub fn children_raw(
+        &self,
+        key_data: &SlotMapKeyData,
+    ) -> impl Iterator<Item = SlotMapKeyData> + '_ {
+        let first = self.map.get_raw(key_data).and_then(|n| n.first_child);
+
+        std::iter::successors(first, |child| self.node(child).next_sibling)
+    }
+
+    /// Create an iterator over the key data of the ancestors of the item for
+    /// the given key, starting with its parent
+    pub fn ancestors(
+        &self,
+        key: &K,
+    ) -> impl Iterator<Item = SlotMapKeyData> + '_ {
+        self.ancestors_raw(key.borrow())
+    }
+
+    /// Same as ancestors, but only requires slot map key data
+    pub fn ancestors_raw(
+        &self,
+        key_data: &SlotMapKeyData,
+    ) -> impl Iterator<Item = SlotMapKeyData> + '_ {
+        std::iter::successors(self.parent_raw(key_data), |ancestor| {
+            self.node(ancestor).parent
+        })
+    }
+
+    /// Create an iterator over the key data of all items without a parent
+    pub fn roots(&self) -> impl Iterator<Item = SlotMapKeyData> + '_ {
+        self.map
+            .iter_raw()
+            .filter(|(_, node)| node.parent.is_none())
+            .map(|(key_data, _)| key_data)
+    }
+
+    /// Move the item for the given key, with its subtree, to be the last
+    /// child of the given new parent, or a root if there is no new parent.
+    /// Returns false and changes nothing if either item isn't in the map or
+    /// the new parent is the item itself or one of its descendants
+    pub fn reparent(&mut self, key: &K, new_parent: Option<&K>) -> bool {
+        self.reparent_raw(key.borrow(), new_parent.map(|p| *p.borrow()))
+    }
+
+    /// Same as reparent, but only requires slot map key data
+    pub fn reparent_raw(
+        &mut self,
+        key_data: &SlotMapKeyData,
+        new_parent: Option<SlotMapKeyData>,
+    ) -> bool {
+        if !self.map.contains_key_raw(key_data) {
+            return false;
+        }
+
+        if let Some(new_parent) = new_parent {
+            if !self.map.contains_key_raw(&new_parent)
+                || new_parent == *key_data
+                || self.ancestors_raw(&new_parent).any(|a| a == *key_data)
+            {
+                return false;
+            }
+        }
+
+        self.detach(*key_data);
+
+        if let Some(new_parent) = new_parent {
+            self.attach(*key_data, new_parent);
+        }
+
+        true
+    }
+
+    /// Remove the item for the given key and all of its descendants, and
+    /// return how many items were removed
+    pub fn remove_subtree(&mut self, key: &K) -> usize {
+        self.remove_subtree_with(key, |_, _| {})
+    }
+
+    /// Same as remove_subtree, but the given function is called with the key
+    /// data and value of each removed item, parents before their children
+    pub fn remove_subtree_with(
+        &mut self,
+        key: &K,
+        mut f: impl FnMut(SlotMapKeyData, &mut T),
+    ) -> usize {
+        let key_data = *key.borrow();
+
+        if !self.map.contains_key_raw(&key_data) {
+            return 0;
+        }
+
+        self.detach(key_data);
+
+        let mut pending = vec![key_data];
+        let mut removed = 0;
+
+        while let Some(key_data) = pending.pop() {
+            pending.extend(self.children_raw(&key_data));
+
+            let node = self.map.remove_raw(&key_data).expect("item exists");
+            f(key_data, &mut node.value);
+            removed += 1;
+        }
+
+        removed
+    }
+
+    /// Remove all items from the map
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Create an iterator over the raw key data and values of all items in
+    /// the map
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.map
+            .iter_raw()
+            .map(|(key_data, node)| (key_data, &node.value))
+    }
+
+    /// Create an iterator over the values of all items in the map
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.map.values().map(|node| &node.value)
+    }
+
+    /// Create an iterator over the mutable values of all items in the map
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.map.values_mut().map(|node| &mut node.value)
+    }
+
+    /// Unlink the given item from its parent and siblings, making it a root
+    fn detach(&mut self, key_data: SlotMapKeyData) {
+        let node = self.node_mut(&key_data);
+        let parent = node.parent.take();
+        let previous = node.previous_sibling.take();
+        let next = node.next_sibling.take();
+
+        match (previous, parent) {
+            (Some(previous), _) => self.node_mut(&previous).next_sibling = next,
+            (None, Some(parent)) => self.node_mut(&parent).first_child = next,
+            (None, None) => {}
+        }
+
+        match (next, parent) {
+            (Some(next), _) => self.node_mut(&next).previous_sibling = previous,
+            (None, Some(parent)) => {
+                self.node_mut(&parent).last_child = previous
+            }
+            (None, None) => {}
+        }
+    }
+
+    /// Link the given root item in as the last child of the given parent
+    fn attach(&mut self, key_data: SlotMapKeyData, parent: SlotMapKeyData) {
+        let previous = self.node_mut(&parent).last_child.replace(key_data);
+
+        match previous {
+            Some(previous) => {
+                self.node_mut(&previous).next_sibling = Some(key_data)
+            }
+            None => self.node_mut(&parent).first_child = Some(key_data),
+        }
+
+        let node = self.node_mut(&key_data);
+        node.parent = Some(parent);
+        node.previous_sibling = previous;
+    }
+
+    /// Get the node for key data that a link refers to, which always exists
+    fn node(&self, key_data: &SlotMapKeyData) -> &Node<T> {
+        self.map
+            .get_raw(key_data)
+            .expect("links only refer to items in the map")
+    }
+
+    fn node_mut(&mut self, key_data: &SlotMapKeyData) -> &mut Node<T> {
+        self.map
+            .get_mut_raw(key_data)
+            .expect("links only refer to items in the map")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use rand::{thread_rng, Rng};
+    use std::borrow::Borrow;
+
+    fn key_data(key: &TestKey) -> SlotMapKeyData {
+        *key.borrow()
+    }
+
+    #[test]
+    fn test_random_reparenting_matches_model() {
+        let mut rng = thread_rng();
+        let mut map = TreeSlotMap::<TestKey, usize, usize>::new();
+
+        // Parent of each item by its index in keys
+        let mut model = Vec::<Option<usize>>::new();
+        let mut keys = Vec::<TestKey>::new();
+
+        for i in 0..300 {
+            if i == 0 || rng.gen_bool(0.1) {
+                keys.push(map.insert(i, i));
+                model.push(None);
+            } else {
+                let parent = rng.gen_range(0..keys.len());
+                keys.push(map.insert_child(&keys[parent], i, i).unwrap());
+                model.push(Some(parent));
+            }
+        }
+
+        let is_ancestor = |model: &[Option<usize>], a: usize, mut b: usize| {
+            while let Some(parent) = model[b] {
+                if parent == a {
+                    return true;
+                }
+                b = parent;
+            }
+            false
+        };
+
+        for _ in 0..1000 {
+            let child = rng.gen_range(0..keys.len());
+            let parent = rng.gen_range(0..keys.len() + 20);
+            let parent = (parent < keys.len()).then_some(parent);
+
+            let allowed = match parent {
+                Some(p) => p != child && !is_ancestor(&model, child, p),
+                None => true,
+            };
+
+            assert_eq!(
+                allowed,
+                map.reparent(&keys[child], parent.map(|p| &keys[p]))
+            );
+
+            if allowed {
+                model[child] = parent;
+            }
+        }
+
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(model[i].map(|p| key_data(&keys[p])), map.parent(key));
+
+            let mut children = map.children(key).collect::<Vec<_>>();
+            let mut expected = (0..keys.len())
+                .filter(|c| model[*c] == Some(i))
+                .map(|c| key_data(&keys[c]))
+                .collect::<Vec<_>>();
+
+            children.sort_by_key(|k| u64::from(*k));
+            expected.sort_by_key(|k| u64::from(*k));
+            assert_eq!(expected, children);
+        }
+
+        assert_eq!(
+            model.iter().filter(|p| p.is_none()).count(),
+            map.roots().count()
+        );
+
+        // Removing a subtree removes exactly the item and its descendants
+        let target = rng.gen_range(0..keys.len());
+        let mut removed = Vec::new();
+        let count = map.remove_subtree_with(&keys[target], |_, v| {
+            removed.push(*v);
+        });
+        assert_eq!(count, removed.len());
+
+        for (i, key) in keys.iter().enumerate() {
+            let in_subtree = i == target || is_ancestor(&model, target, i);
+            assert_eq!(in_subtree, removed.contains(&i));
+            assert_eq!(!in_subtree, map.contains_key(key));
+        }
+
+        if let Some(parent) = model[target] {
+            assert!(map
+                .children(&keys[parent])
+                .all(|c| c != key_data(&keys[target])));
+        }
+
+        assert_eq!(0, map.remove_subtree(&keys[target]));
+    }
+}
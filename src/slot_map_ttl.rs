@@ -0,0 +1,255 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::time::Instant;
+
+/// Slot map whose items expire at a deadline given when they are inserted.
+/// Expired items can't be read, and are removed when
+/// [`TtlSlotMap::purge_expired`] is called with a time at or after their
+/// deadline. The current time is always passed in, so deadlines can be
+/// instants, ticks of a simulation, or anything else that is ordered
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()>);
+/// let mut sessions = TtlSlotMap::<TestKey, (), &'static str, u64>::new();
+///
+/// let short = sessions.insert((), "short", 10);
+/// let long = sessions.insert((), "long", 100);
+///
+/// assert_eq!(Some(&"short"), sessions.get(&short, 5));
+/// assert_eq!(None, sessions.get(&short, 10));
+///
+/// assert_eq!(1, sessions.purge_expired(50));
+/// assert_eq!(1, sessions.len());
+/// assert_eq!(Some(&"long"), sessions.get(&long, 50));
+/// ```
+pub struct TtlSlotMap<K, P, T, D = Instant>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, (D, T)>,
+
+    /// Deadlines of items along with their packed key data, soonest first.
+    /// Entries for items that were removed, or whose deadline changed, are
+    /// skipped when they come up
+    deadlines: BinaryHeap<Reverse<(D, u64)>>,
+}
+
+impl<K, P, T, D> std::fmt::Debug for TtlSlotMap<K, P, T, D>
+where
+    K: SlotMapKey<P>,
+    T: std::fmt::Debug,
+    D: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.map.values()).finish()
+    }
+}
+
+impl<K, P, T, D> Default for TtlSlotMap<K, P, T, D>
+where
+    K: SlotMapKey<P>,
+    D: Ord + Copy,
+{
+    fn default() -> Self {
+        TtlSlotMap::new()
+    }
+}
+
+impl<K, P, T, D> TtlSlotMap<K, P, T, D>
+where
+    K: SlotMapKey<P>,
+    D: Ord + Copy,
+{
+    /// Create a new empty map
+    pub fn new() -> TtlSlotMap<K, P, T, D> {
+        TtlSlotMap {
+            map: SlotMap::new(),
+            deadlines: BinaryHeap::new(),
+        }
+    }
+
+    /// Get the number of items in the map, including expired items that
+    /// haven't been purged yet
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty, including expired items that haven't been
+    /// purged yet
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Insert the given item, which expires at the given deadline, and return
+    /// its key
+    pub fn insert(&mut self, pointer: P, value: T, deadline: D) -> K {
+        let key = self.map.insert(pointer, (deadline, value));
+
+        self.deadlines
+            .push(Reverse((deadline, u64::from(*key.borrow()))));
+
+        key
+    }
+
+    /// Get a reference to the item for the given key if it exists and hasn't
+    /// expired by the given time
+    pub fn get(&self, key: &K, now: D) -> Option<&T> {
+        self.get_raw(key.borrow(), now)
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData, now: D) -> Option<&T> {
+        self.map
+            .get_raw(key_data)
+            .filter(|(deadline, _)| now < *deadline)
+            .map(|(_, value)| value)
+    }
+
+    /// Get a mutable reference to the item for the given key if it exists
+    /// and hasn't expired by the given time
+    pub fn get_mut(&mut self, key: &K, now: D) -> Option<&mut T> {
+        self.get_mut_raw(key.borrow(), now)
+    }
+
+    /// Same as get_mut, but only requires slot map key data
+    pub fn get_mut_raw(
+        &mut self,
+        key_data: &SlotMapKeyData,
+        now: D,
+    ) -> Option<&mut T> {
+        self.map
+            .get_mut_raw(key_data)
+            .filter(|(deadline, _)| now < *deadline)
+            .map(|(_, value)| value)
+    }
+
+    /// Tells if the given key is in the map and hasn't expired by the given
+    /// time
+    pub fn contains_key(&self, key: &K, now: D) -> bool {
+        self.get(key, now).is_some()
+    }
+
+    /// Get the deadline of the item for the given key, even if it has expired
+    pub fn deadline(&self, key: &K) -> Option<D> {
+        self.map.get(key).map(|(deadline, _)| *deadline)
+    }
+
+    /// Change the deadline of the item for the given key, returning whether
+    /// the item exists. Expired items that haven't been purged can be given a
+    /// new deadline too
+    pub fn set_deadline(&mut self, key: &K, deadline: D) -> bool {
+        let Some(entry) = self.map.get_mut(key) else {
+            return false;
+        };
+
+        entry.0 = deadline;
+        self.deadlines
+            .push(Reverse((deadline, u64::from(*key.borrow()))));
+
+        true
+    }
+
+    /// Remove the item for the given key whether or not it has expired. Like
+    /// a slot map, the item stays in its slot until the slot is reused
+    pub fn remove(&mut self, key: &K) -> Option<&mut T> {
+        self.map.remove(key).map(|(_, value)| value)
+    }
+
+    /// Remove every item that has expired by the given time, returning how
+    /// many were removed
+    pub fn purge_expired(&mut self, now: D) -> usize {
+        let mut count = 0;
+        self.purge_expired_with(now, |_, _| count += 1);
+        count
+    }
+
+    /// Remove every item that has expired by the given time, calling the
+    /// given function with the key data and value of each, soonest deadline
+    /// first
+    pub fn purge_expired_with(
+        &mut self,
+        now: D,
+        mut f: impl FnMut(SlotMapKeyData, &mut T),
+    ) {
+        while let Some(Reverse((deadline, packed))) = self.deadlines.peek() {
+            if now < *deadline {
+                break;
+            }
+
+            let (deadline, key_data) =
+                (*deadline, SlotMapKeyData::from(*packed));
+            let _ = self.deadlines.pop();
+
+            // Skip entries for items that are gone or have a new deadline
+            if self.map.get_raw(&key_data).map(|(d, _)| *d) != Some(deadline) {
+                continue;
+            }
+
+            if let Some((_, value)) = self.map.remove_raw(&key_data) {
+                f(key_data, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use std::time::Duration;
+
+    #[test]
+    fn test_purge_respects_new_deadlines() {
+        let start = Instant::now();
+        let at = |seconds| start + Duration::from_secs(seconds);
+
+        let mut map = TtlSlotMap::<TestKey, usize, String>::new();
+
+        let keys = (0..100)
+            .map(|i| map.insert(i, format!("{}", i), at(i as u64)))
+            .collect::<Vec<_>>();
+
+        // Some items live longer, some are removed early, and some are
+        // brought forward
+        for key in keys.iter().filter(|k| k.pointer % 10 == 0) {
+            assert!(map.set_deadline(key, at(1000)));
+        }
+        for key in keys.iter().filter(|k| k.pointer % 10 == 1) {
+            assert!(map.remove(key).is_some());
+        }
+        assert!(map.set_deadline(&keys[99], at(0)));
+
+        assert_eq!(None, map.get(&keys[99], at(2)));
+        assert_eq!(Some(&"50".to_owned()), map.get(&keys[50], at(70)));
+        assert_eq!(Some(at(1000)), map.deadline(&keys[50]));
+
+        let mut purged = Vec::new();
+        map.purge_expired_with(at(50), |_, value| purged.push(value.clone()));
+
+        let expected = (0..=50)
+            .filter(|i| i % 10 != 0 && i % 10 != 1)
+            .map(|i| format!("{}", i))
+            .collect::<Vec<_>>();
+
+        assert_eq!("99", purged.remove(0));
+        assert_eq!(expected, purged);
+
+        for key in &keys {
+            let expected = key.pointer % 10 == 0
+                || (key.pointer > 50
+                    && key.pointer % 10 != 1
+                    && key.pointer != 99);
+            assert_eq!(expected, map.get(key, at(50)).is_some());
+        }
+
+        // A purged item's key stays invalid once its slot is reused
+        let reused = map.insert(0, "new".to_owned(), at(5000));
+        assert!(!map.contains_key(&keys[2], at(0)));
+        assert!(map.contains_key(&reused, at(4000)));
+
+        assert_eq!(10 + 39, map.purge_expired(at(1000)));
+        assert_eq!(1, map.len());
+    }
+}
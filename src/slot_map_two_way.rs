@@ -0,0 +1,214 @@
+use super::{SlotMap, SlotMapKey, SlotMapKeyData};
+
+/// Slot map that gives removed values back to the caller instead of leaving
+/// them in their slots until the slots are reused. It is a [`SlotMap`] of
+/// optional values underneath, so it hands out exactly the same keys as a
+/// slot map would, and its keys can be used with any type that accepts slot
+/// map keys
+pub struct TwoWaySlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    map: SlotMap<K, P, Option<T>>,
+}
+
+impl<K, P, T> std::fmt::Debug for TwoWaySlotMap<K, P, T>
+where
+    T: std::fmt::Debug,
+    K: SlotMapKey<P>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.values()).finish()
+    }
+}
+
+impl<K, P, T> Default for TwoWaySlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    fn default() -> Self {
+        TwoWaySlotMap::new()
+    }
+}
+
+impl<K, P, T> Clone for TwoWaySlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        TwoWaySlotMap {
+            map: self.map.clone(),
+        }
+    }
+}
+
+impl<K, P, T> TwoWaySlotMap<K, P, T>
+where
+    K: SlotMapKey<P>,
+{
+    /// Create a new empty map
+    pub fn new() -> TwoWaySlotMap<K, P, T> {
+        TwoWaySlotMap {
+            map: SlotMap::new(),
+        }
+    }
+
+    /// Get the number of items in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Tells if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Insert the given item into the map and return its key
+    pub fn insert(&mut self, pointer: P, value: T) -> K {
+        self.map.insert(pointer, Some(value))
+    }
+
+    /// Get a reference to the item for the given key if it exists
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.get_raw(key.borrow())
+    }
+
+    /// Same as get, but only requires slot map key data
+    pub fn get_raw(&self, key_data: &SlotMapKeyData) -> Option<&T> {
+        self.map.get_raw(key_data).and_then(Option::as_ref)
+    }
+
+    /// Get a mutable reference to the item for the given key if it exists
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.get_mut_raw(key.borrow())
+    }
+
+    /// Same as get_mut, but only requires slot map key data
+    pub fn get_mut_raw(&mut self, key_data: &SlotMapKeyData) -> Option<&mut T> {
+        self.map.get_mut_raw(key_data).and_then(Option::as_mut)
+    }
+
+    /// Tells if the given key is in the map
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Remove the item for the given key and return it
+    ///
+    /// ```
+    /// # use one_way_slot_map::*;
+    /// # define_key_type!(TestKey<()> : Debug + PartialEq);
+    /// let mut map = TwoWaySlotMap::<TestKey,(),String>::new();
+    /// let mut one_way = SlotMap::<TestKey,(),String>::new();
+    ///
+    /// let key = map.insert((), "Hello".to_owned());
+    ///
+    /// // Keys are the same as a one-way map would hand out
+    /// assert_eq!(key, one_way.insert((), "Hello".to_owned()));
+    ///
+    /// assert_eq!(Some("Hello".to_owned()), map.remove(&key));
+    /// assert_eq!(None, map.remove(&key));
+    /// ```
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        self.remove_raw(key.borrow())
+    }
+
+    /// Same as remove, but only requires slot map key data
+    pub fn remove_raw(&mut self, key_data: &SlotMapKeyData) -> Option<T> {
+        self.map.remove_raw(key_data).and_then(Option::take)
+    }
+
+    /// Remove all items from the map, producing them as an iterator. Every
+    /// item is taken out of the map before the first one is produced
+    pub fn drain(&mut self) -> impl Iterator<Item = T> {
+        self.map
+            .drain()
+            .filter_map(Option::take)
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Remove all items from the map
+    pub fn clear(&mut self) {
+        let _ = self.drain();
+    }
+
+    /// Create an iterator over the raw key data and values of all items in
+    /// the map
+    pub fn iter_raw(&self) -> impl Iterator<Item = (SlotMapKeyData, &T)> {
+        self.map
+            .iter_raw()
+            .filter_map(|(key_data, value)| Some((key_data, value.as_ref()?)))
+    }
+
+    /// Create an iterator over the raw key data and mutable values of all
+    /// items in the map
+    pub fn iter_mut_raw(
+        &mut self,
+    ) -> impl Iterator<Item = (SlotMapKeyData, &mut T)> {
+        self.map
+            .iter_mut_raw()
+            .filter_map(|(key_data, value)| Some((key_data, value.as_mut()?)))
+    }
+
+    /// Create an iterator over the values of all items in the map
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.map.values().filter_map(Option::as_ref)
+    }
+
+    /// Create an iterator over the mutable values of all items in the map
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.map.values_mut().filter_map(Option::as_mut)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TestKey;
+    use crate::SLOT_MAP_CHUNK_SIZE;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_removed_values_are_returned() {
+        let tracker = Rc::new(());
+        let mut map = TwoWaySlotMap::<TestKey, usize, Rc<()>>::new();
+        let mut one_way = SlotMap::<TestKey, usize, ()>::new();
+
+        let keys = (0..SLOT_MAP_CHUNK_SIZE + 10)
+            .map(|i| {
+                let key = map.insert(i, tracker.clone());
+                assert_eq!(one_way.insert(i, ()), key);
+                key
+            })
+            .collect::<Vec<_>>();
+
+        let removed = keys
+            .iter()
+            .step_by(2)
+            .map(|k| map.remove(k).unwrap())
+            .collect::<Vec<_>>();
+
+        // Nothing is left behind in the open slots
+        assert_eq!(keys.len() + 1, Rc::strong_count(&tracker));
+        drop(removed);
+        assert_eq!(keys.len() / 2 + 1, Rc::strong_count(&tracker));
+
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(i % 2 == 1, map.contains_key(key));
+            assert_eq!(i % 2 == 1, map.get(key).is_some());
+        }
+
+        assert_eq!(keys.len() / 2, map.iter_raw().count());
+
+        let drained = map.drain();
+        assert!(map.is_empty());
+        assert_eq!(keys.len() / 2, drained.count());
+        assert_eq!(1, Rc::strong_count(&tracker));
+
+        let _ = map.insert(0, tracker.clone());
+        map.clear();
+        assert_eq!(1, Rc::strong_count(&tracker));
+    }
+}
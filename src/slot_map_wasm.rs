@@ -0,0 +1,145 @@
+use super::SlotMapKeyData;
+use std::str::FromStr;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+impl SlotMapKeyData {
+    /// Split the packed `u64` form of this key data into its high and low 32
+    /// bits, which can each be passed to JavaScript as a plain number
+    pub fn to_u32_pair(&self) -> (u32, u32) {
+        let packed = u64::from(*self);
+        ((packed >> 32) as u32, packed as u32)
+    }
+
+    /// Rebuild key data from the high and low 32 bits of its packed form
+    pub fn from_u32_pair(high: u32, low: u32) -> SlotMapKeyData {
+        SlotMapKeyData::from(((high as u64) << 32) | low as u64)
+    }
+}
+
+/// Key data in a form that crosses the JavaScript boundary without losing
+/// precision. A packed `u64` key doesn't fit in a JavaScript number, so this
+/// holds its two halves, each of which does. Its decimal string form can
+/// also be passed to `BigInt()` on the JavaScript side and parsed back with
+/// [`str::parse`], or converted to and from a [`js_sys::BigInt`] directly.
+/// It's exported to JavaScript as a `JsKey` class with `high` and `low`
+/// properties
+///
+/// ```
+/// # use one_way_slot_map::*;
+/// # define_key_type!(TestKey<()>);
+/// # use std::borrow::Borrow;
+/// let mut map = SlotMap::<TestKey, (), &str>::new();
+/// let key = map.insert((), "handle");
+///
+/// let js_key = JsKey::from(*Borrow::<SlotMapKeyData>::borrow(&key));
+/// let (high, low) = (js_key.high(), js_key.low());
+///
+/// // ... round trip through JavaScript ...
+///
+/// let key_data = SlotMapKeyData::from(JsKey::new(high, low));
+/// assert_eq!(Some(&"handle"), map.get_raw(&key_data));
+///
+/// let from_big_int = js_key.to_string().parse::<JsKey>().unwrap();
+/// assert_eq!(js_key, from_big_int);
+/// ```
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(C)]
+pub struct JsKey {
+    high: u32,
+    low: u32,
+}
+
+#[wasm_bindgen]
+impl JsKey {
+    /// Create a key from the high and low 32 bits of packed key data
+    #[wasm_bindgen(constructor)]
+    pub fn new(high: u32, low: u32) -> JsKey {
+        JsKey { high, low }
+    }
+
+    /// Get the high 32 bits of the packed key data
+    #[wasm_bindgen(getter)]
+    pub fn high(&self) -> u32 {
+        self.high
+    }
+
+    /// Get the low 32 bits of the packed key data
+    #[wasm_bindgen(getter)]
+    pub fn low(&self) -> u32 {
+        self.low
+    }
+}
+
+impl From<SlotMapKeyData> for JsKey {
+    fn from(key_data: SlotMapKeyData) -> Self {
+        let (high, low) = key_data.to_u32_pair();
+        JsKey { high, low }
+    }
+}
+
+impl From<JsKey> for SlotMapKeyData {
+    fn from(key: JsKey) -> Self {
+        SlotMapKeyData::from_u32_pair(key.high, key.low)
+    }
+}
+
+impl From<JsKey> for js_sys::BigInt {
+    fn from(key: JsKey) -> Self {
+        js_sys::BigInt::from(u64::from(SlotMapKeyData::from(key)))
+    }
+}
+
+impl TryFrom<js_sys::BigInt> for JsKey {
+    type Error = js_sys::BigInt;
+
+    /// Convert a `BigInt` holding packed key data. A `BigInt` that is
+    /// negative or doesn't fit in 64 bits is handed back as the error
+    fn try_from(big_int: js_sys::BigInt) -> Result<Self, Self::Error> {
+        let packed = u64::try_from(big_int)?;
+        Ok(JsKey::new((packed >> 32) as u32, packed as u32))
+    }
+}
+
+impl std::fmt::Display for JsKey {
+    /// Write the packed key data as a decimal number, the form `BigInt()`
+    /// accepts
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", ((self.high as u64) << 32) | self.low as u64)
+    }
+}
+
+impl FromStr for JsKey {
+    type Err = std::num::ParseIntError;
+
+    /// Parse the decimal form of packed key data, as produced by calling
+    /// `toString()` on a JavaScript `BigInt`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let packed = s.parse::<u64>()?;
+        Ok(JsKey::new((packed >> 32) as u32, packed as u32))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::churned_map;
+    use std::borrow::Borrow;
+
+    #[test]
+    fn test_keys_round_trip() {
+        let (map, keys, _) = churned_map(10, |i| i);
+
+        for key in &keys {
+            let key_data = *Borrow::<SlotMapKeyData>::borrow(key);
+            let js_key = JsKey::from(key_data);
+
+            assert_eq!(key_data, SlotMapKeyData::from(js_key));
+            assert_eq!(js_key, js_key.to_string().parse().unwrap());
+            assert_eq!(u64::from(key_data).to_string(), js_key.to_string());
+            assert_eq!(Some(&key.pointer), map.get_raw(&js_key.into()));
+        }
+
+        assert!("-1".parse::<JsKey>().is_err());
+    }
+}
@@ -0,0 +1,38 @@
+use crate::SlotMap;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Key type shared by the unit tests of the modules in this crate
+define_key_type!(pub(crate) TestKey<usize> : Clone + Copy + Debug + Hash + PartialEq + Eq);
+
+/// Get a random number generator for tests that always produces the same
+/// numbers for the same seed, so failures can be reproduced
+pub(crate) fn seeded_rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+/// Build a map with 2000 random inserts and removals, so it has open slots
+/// spread across several chunks at a variety of generations. Returns the map
+/// along with the keys of the items still in it and the keys of the items
+/// that were removed, in the order they were removed
+pub(crate) fn churned_map<T>(
+    seed: u64,
+    mut value: impl FnMut(usize) -> T,
+) -> (SlotMap<TestKey, usize, T>, Vec<TestKey>, Vec<TestKey>) {
+    let mut rng = seeded_rng(seed);
+    let mut map = SlotMap::new();
+    let mut live = Vec::<TestKey>::new();
+    let mut removed = Vec::<TestKey>::new();
+
+    for i in 0..2000 {
+        if live.is_empty() || rng.gen_bool(0.6) {
+            live.push(map.insert(i, value(i)));
+        } else {
+            let key = live.swap_remove(rng.gen_range(0..live.len()));
+            assert!(map.remove(&key).is_some());
+            removed.push(key);
+        }
+    }
+
+    (map, live, removed)
+}
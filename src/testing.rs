@@ -0,0 +1,4 @@
+//! Tools for testing code built on top of [`SlotMap`](crate::SlotMap)
+
+pub use super::slot_map_consistency::KeyDiscrepancies;
+pub use super::slot_map_oracle::OracleMap;